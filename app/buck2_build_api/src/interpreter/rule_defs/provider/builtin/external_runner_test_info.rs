@@ -106,6 +106,12 @@ pub struct ExternalRunnerTestInfoGen<V: ValueLifetimeless> {
     /// Configuration needed to spawn a new worker. This worker will be used to run every single
     /// command related to test execution, including listing.
     worker: ValueOfUncheckedGeneric<V, FrozenWorkerInfo>,
+
+    /// Whether this test is hermetic, i.e. its result only depends on its command, environment
+    /// and inputs. The results of hermetic tests that pass are cached like action results.
+    ///
+    /// Defaults to `False`.
+    hermetic: ValueOfUncheckedGeneric<V, bool>,
 }
 
 // NOTE: All the methods here unwrap because we validate at freeze time.
@@ -149,6 +155,14 @@ impl FrozenExternalRunnerTestInfo {
             .unwrap_or(true)
     }
 
+    pub fn hermetic(&self) -> bool {
+        NoneOr::<bool>::unpack_value(self.hermetic.get().to_value())
+            .unwrap()
+            .unwrap()
+            .into_option()
+            .unwrap_or(false)
+    }
+
     pub fn default_executor(&self) -> Option<&StarlarkCommandExecutorConfig> {
         unpack_opt_executor(self.default_executor.get().to_value()).unwrap()
     }
@@ -495,6 +509,9 @@ where
     NoneOr::<bool>::unpack_value(info.run_from_project_root.get().to_value())
         .map_err(from_starlark)?
         .buck_error_context("`run_from_project_root` must be a bool if provided")?;
+    NoneOr::<bool>::unpack_value(info.hermetic.get().to_value())
+        .map_err(from_starlark)?
+        .buck_error_context("`hermetic` must be a bool if provided")?;
     unpack_opt_executor(info.default_executor.get().to_value())
         .buck_error_context("Invalid `default_executor`")?;
     unpack_opt_worker(info.worker.get().to_value()).buck_error_context("Invalid `worker`")?;
@@ -524,6 +541,7 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(default = NoneType)] local_resources: Value<'v>,
         #[starlark(default = NoneType)] required_local_resources: Value<'v>,
         #[starlark(default = NoneType)] worker: Value<'v>,
+        #[starlark(default = NoneType)] hermetic: Value<'v>,
     ) -> starlark::Result<ExternalRunnerTestInfo<'v>> {
         let res = ExternalRunnerTestInfo {
             test_type: ValueOfUnchecked::new(r#type),
@@ -538,6 +556,7 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
             local_resources: ValueOfUnchecked::new(local_resources),
             required_local_resources: ValueOfUnchecked::new(required_local_resources),
             worker: ValueOfUnchecked::new(worker),
+            hermetic: ValueOfUnchecked::new(hermetic),
         };
         validate_external_runner_test_info(&res)?;
         Ok(res)
@@ -36,6 +36,7 @@ fn test_construction() -> anyhow::Result<()> {
             ExternalRunnerTestInfo(type = "foo", labels = ("foo",))
             ExternalRunnerTestInfo(type = "foo", use_project_relative_paths = True)
             ExternalRunnerTestInfo(type = "foo", run_from_project_root = True)
+            ExternalRunnerTestInfo(type = "foo", hermetic = True)
             ExternalRunnerTestInfo(type = "foo", local_resources = {"bar": None}, required_local_resources = [RequiredTestLocalResource("bar", listing=False)])
         "#
     );
@@ -167,6 +168,16 @@ fn test_validation() -> anyhow::Result<()> {
         "`run_from_project_root`",
     );
 
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+        def test():
+            ExternalRunnerTestInfo(type = "foo", hermetic = "foo")
+        "#
+        ),
+        "`hermetic`",
+    );
+
     tester.run_starlark_bzl_test_expecting_error(
         indoc!(
             r#"
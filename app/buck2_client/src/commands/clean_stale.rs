@@ -80,6 +80,13 @@ fn format_result_stats(stats: buck2_data::CleanStaleStats) -> String {
         stats.untracked_artifact_count,
         bytesize::to_string(stats.untracked_bytes, true),
     );
+    if stats.unreferenced_object_count > 0 {
+        output += &format!(
+            "Found {} unreferenced objects ({})\n",
+            stats.unreferenced_object_count,
+            bytesize::to_string(stats.unreferenced_object_bytes, true),
+        );
+    }
     if stats.cleaned_artifact_count > 0 || stats.cleaned_bytes > 0 {
         output += &format!("Cleaned {} paths\n", stats.cleaned_artifact_count,);
         output += &format!(
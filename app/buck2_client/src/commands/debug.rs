@@ -27,6 +27,7 @@ use crate::commands::debug::allocative::AllocativeCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
 use crate::commands::debug::eval::EvalCommand;
 use crate::commands::debug::exe::ExeCommand;
+use crate::commands::debug::local_re_server::LocalReServerCommand;
 use crate::commands::debug::log_perf::LogPerfCommand;
 use crate::commands::debug::paranoid::ParanoidCommand;
 use crate::commands::debug::persist_event_logs::PersistEventLogsCommand;
@@ -49,6 +50,7 @@ mod file_status;
 mod flush_dep_files;
 mod heap_dump;
 mod internal_version;
+mod local_re_server;
 mod log_perf;
 mod materialize;
 mod paranoid;
@@ -101,6 +103,7 @@ pub enum DebugCommand {
     Paranoid(ParanoidCommand),
     Eval(EvalCommand),
     ThreadDump(ThreadDumpCommand),
+    LocalReServer(LocalReServerCommand),
 }
 
 impl DebugCommand {
@@ -129,6 +132,7 @@ impl DebugCommand {
             DebugCommand::Paranoid(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Eval(cmd) => cmd.exec(matches, ctx),
             DebugCommand::ThreadDump(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::LocalReServer(cmd) => cmd.exec(matches, ctx),
         }
     }
 
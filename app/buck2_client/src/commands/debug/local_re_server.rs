@@ -0,0 +1,83 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use re_grpc_server::DigestAlgorithm;
+use re_grpc_server::LocalReServer;
+use re_grpc_server::LocalReServerOptions;
+
+/// Runs a remote execution server on this machine, to test remote execution without a remote
+/// execution service.
+///
+/// The server keeps blobs and action results in memory, and runs actions in temporary directories
+/// on this machine. Point the addresses in `[buck2_re_client]` to the address it prints. It runs
+/// until interrupted, and forgets everything when it exits.
+#[derive(Debug, clap::Parser)]
+pub struct LocalReServerCommand {
+    /// Port to listen on, on localhost. By default, a free port is picked.
+    #[clap(long, default_value = "0")]
+    port: u16,
+
+    /// Number of actions to run at once. Defaults to the number of CPUs.
+    #[clap(long)]
+    workers: Option<usize>,
+
+    /// Directory in which to run actions. Defaults to a directory in the system temporary
+    /// directory.
+    #[clap(long, value_name = "PATH")]
+    dir: Option<PathArg>,
+
+    /// Digest algorithm to use, `SHA1` or `SHA256`. It must match `buck2.digest_algorithms`.
+    #[clap(long, default_value = "SHA256")]
+    digest_algorithm: DigestAlgorithm,
+}
+
+impl LocalReServerCommand {
+    pub fn exec(self, _matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let work_dir = match &self.dir {
+            Some(dir) => dir.resolve(&ctx.working_dir).into_path_buf(),
+            None => {
+                std::env::temp_dir().join(format!("buck2-local-re-server-{}", std::process::id()))
+            }
+        };
+        let options = LocalReServerOptions {
+            addr: SocketAddr::from((Ipv4Addr::LOCALHOST, self.port)),
+            workers: self.workers.unwrap_or_else(num_cpus::get),
+            work_dir,
+            digest_algorithm: self.digest_algorithm,
+        };
+
+        ctx.instant_command_no_log("debug-local-re-server", |_ctx| async move {
+            let server = LocalReServer::bind(options).await?;
+            let address = format!("grpc://{}", server.local_addr()?);
+            buck2_client_ctx::println!(
+                "Listening on {address}. To use it, add to your .buckconfig:\n\
+                 \n\
+                 [buck2_re_client]\n\
+                 engine_address = {address}\n\
+                 action_cache_address = {address}\n\
+                 cas_address = {address}\n\
+                 tls = false\n\
+                 \n\
+                 [buck2]\n\
+                 digest_algorithms = {}",
+                self.digest_algorithm
+            )?;
+            server.serve().await?;
+            buck2_error::Ok(())
+        })
+        .into()
+    }
+}
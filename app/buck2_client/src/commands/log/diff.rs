@@ -12,6 +12,7 @@ use buck2_client_ctx::common::BuckArgMatches;
 use buck2_client_ctx::exit_result::ExitResult;
 
 mod action_divergence;
+mod actions;
 mod diff_options;
 mod external_config_diff;
 
@@ -19,6 +20,7 @@ mod external_config_diff;
 #[clap(about = "Subcommands for diff'ing two buck2 commands")]
 pub enum DiffCommand {
     ActionDivergence(action_divergence::ActionDivergenceCommand),
+    Actions(actions::ActionsDiffCommand),
     ExternalConfigs(external_config_diff::ExternalConfigDiffCommand),
 }
 
@@ -27,6 +29,7 @@ impl DiffCommand {
         match self {
             Self::ExternalConfigs(cmd) => cmd.exec(matches, ctx),
             Self::ActionDivergence(cmd) => cmd.exec(matches, ctx),
+            Self::Actions(cmd) => cmd.exec(matches, ctx),
         }
     }
 }
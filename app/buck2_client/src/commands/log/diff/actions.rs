@@ -0,0 +1,381 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_data::ActionKey;
+use buck2_data::ActionName;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::action_util::get_action_digest;
+use buck2_event_observer::display::display_action_identity;
+use buck2_event_observer::display::TargetDisplayOptions;
+use futures::Stream;
+use futures::TryStreamExt;
+use linked_hash_map::LinkedHashMap;
+
+use crate::commands::log::diff::diff_options::DiffEventLogOptions;
+
+/// Compares the actions executed by two builds, matching them by action key.
+///
+/// Reports actions which only ran in one of the builds, and for actions which ran in both, whether
+/// their command line, environment, inputs or outputs changed. Useful for finding out why the
+/// second build did not get cache hits.
+///
+/// Command lines and environments are only recorded for actions which ran locally. For other
+/// actions, a change to either is reported as a change of inputs.
+#[derive(Debug, clap::Parser)]
+pub struct ActionsDiffCommand {
+    #[clap(flatten)]
+    diff_event_log: DiffEventLogOptions,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ActionData {
+    name: Option<ActionName>,
+    action_digest: Option<String>,
+    /// The command line and environment, if the action was executed locally.
+    command: Option<LocalCommandData>,
+    output_tiny_digests: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct LocalCommandData {
+    argv: Vec<String>,
+    env: BTreeMap<String, String>,
+}
+
+/// A difference in an action between the two builds.
+#[derive(Debug, PartialEq)]
+enum ActionChange<'a> {
+    CommandLine {
+        first: &'a [String],
+        second: &'a [String],
+    },
+    Environment {
+        key: &'a str,
+        first: Option<&'a str>,
+        second: Option<&'a str>,
+    },
+    /// The action digest changed for a reason other than the command line or environment.
+    Inputs {
+        first: Option<&'a str>,
+        second: Option<&'a str>,
+    },
+    Outputs {
+        first: &'a [String],
+        second: &'a [String],
+    },
+}
+
+fn get_local_command(commands: &[buck2_data::CommandExecution]) -> Option<LocalCommandData> {
+    let command = commands.last()?.details.as_ref()?.command_kind.as_ref()?;
+    let (argv, env) = match command.command.as_ref()? {
+        buck2_data::command_execution_kind::Command::LocalCommand(command) => {
+            (&command.argv, &command.env)
+        }
+        buck2_data::command_execution_kind::Command::WorkerCommand(command) => {
+            (&command.argv, &command.env)
+        }
+        _ => return None,
+    };
+    Some(LocalCommandData {
+        argv: argv.clone(),
+        env: env
+            .iter()
+            .map(|entry| (entry.key.clone(), entry.value.clone()))
+            .collect(),
+    })
+}
+
+fn get_action_data(event: &buck2_data::BuckEvent) -> Option<(ActionKey, ActionData)> {
+    match event.data.as_ref()? {
+        buck2_data::buck_event::Data::SpanEnd(end) => match end.data.as_ref()? {
+            buck2_data::span_end_event::Data::ActionExecution(data) => Some((
+                data.key.clone()?,
+                ActionData {
+                    name: data.name.clone(),
+                    action_digest: get_action_digest(&data.commands),
+                    command: get_local_command(&data.commands),
+                    output_tiny_digests: data
+                        .outputs
+                        .iter()
+                        .map(|output| output.tiny_digest.clone())
+                        .collect(),
+                },
+            )),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+async fn get_action_map(
+    mut events: impl Stream<Item = buck2_error::Result<StreamValue>> + Unpin + Send,
+) -> buck2_error::Result<LinkedHashMap<ActionKey, ActionData>> {
+    let mut out = LinkedHashMap::new();
+
+    while let Some(event) = events.try_next().await? {
+        if let StreamValue::Event(event) = event {
+            if let Some((key, action_data)) = get_action_data(&event) {
+                out.insert(key, action_data);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn diff_action<'a>(first: &'a ActionData, second: &'a ActionData) -> Vec<ActionChange<'a>> {
+    let mut changes = Vec::new();
+
+    if let (Some(c1), Some(c2)) = (&first.command, &second.command) {
+        if c1.argv != c2.argv {
+            changes.push(ActionChange::CommandLine {
+                first: &c1.argv,
+                second: &c2.argv,
+            });
+        }
+        for (key, value) in &c1.env {
+            let other = c2.env.get(key);
+            if other != Some(value) {
+                changes.push(ActionChange::Environment {
+                    key,
+                    first: Some(value),
+                    second: other.map(|v| v.as_str()),
+                });
+            }
+        }
+        for (key, value) in &c2.env {
+            if !c1.env.contains_key(key) {
+                changes.push(ActionChange::Environment {
+                    key,
+                    first: None,
+                    second: Some(value),
+                });
+            }
+        }
+    }
+
+    // The action digest covers the command line and environment too, so only attribute the
+    // change to the inputs if nothing else explains it.
+    if first.action_digest != second.action_digest && changes.is_empty() {
+        changes.push(ActionChange::Inputs {
+            first: first.action_digest.as_deref(),
+            second: second.action_digest.as_deref(),
+        });
+    }
+
+    if first.output_tiny_digests != second.output_tiny_digests {
+        changes.push(ActionChange::Outputs {
+            first: &first.output_tiny_digests,
+            second: &second.output_tiny_digests,
+        });
+    }
+
+    changes
+}
+
+fn display_identity(key: &ActionKey, data: &ActionData) -> buck2_error::Result<String> {
+    display_action_identity(
+        Some(key),
+        data.name.as_ref(),
+        TargetDisplayOptions::for_log(),
+    )
+}
+
+fn display_command_line(argv: &[String]) -> String {
+    shlex::try_join(argv.iter().map(|arg| arg.as_str())).expect("Null byte unexpected")
+}
+
+fn display_change(change: &ActionChange) -> String {
+    let or_none = |value: Option<&str>| value.unwrap_or("<none>").to_owned();
+    match change {
+        ActionChange::CommandLine { first, second } => format!(
+            "  Command line:\n    first:  {}\n    second: {}",
+            display_command_line(first),
+            display_command_line(second)
+        ),
+        ActionChange::Environment { key, first, second } => format!(
+            "  Environment variable {}:\n    first:  {}\n    second: {}",
+            key,
+            or_none(*first),
+            or_none(*second)
+        ),
+        ActionChange::Inputs { first, second } => format!(
+            "  Inputs (action digest):\n    first:  {}\n    second: {}",
+            or_none(*first),
+            or_none(*second)
+        ),
+        ActionChange::Outputs { first, second } => format!(
+            "  Tiny output digest(s):\n    first:  {}\n    second: {}",
+            first.join(" "),
+            second.join(" ")
+        ),
+    }
+}
+
+impl ActionsDiffCommand {
+    pub fn exec(self, _matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
+        ctx.instant_command_no_log("log-diff-actions", |ctx| async move {
+            let (log_path1, log_path2) = self.diff_event_log.get(&ctx).await?;
+
+            let (invocation1, events1) = log_path1.unpack_stream().await?;
+            let (invocation2, events2) = log_path2.unpack_stream().await?;
+
+            buck2_client_ctx::println!(
+                "Comparing actions between: \n{} and \n{}",
+                invocation1.display_command_line(),
+                invocation2.display_command_line()
+            )?;
+
+            let actions1 = get_action_map(events1).await?;
+            let actions2 = get_action_map(events2).await?;
+
+            let mut removed = 0;
+            let mut added = 0;
+            let mut changed = 0;
+
+            for (key, data1) in &actions1 {
+                if !actions2.contains_key(key) {
+                    removed += 1;
+                    buck2_client_ctx::println!(
+                        "Only in the first build: {}",
+                        display_identity(key, data1)?
+                    )?;
+                }
+            }
+
+            for (key, data2) in &actions2 {
+                let Some(data1) = actions1.get(key) else {
+                    added += 1;
+                    buck2_client_ctx::println!(
+                        "Only in the second build: {}",
+                        display_identity(key, data2)?
+                    )?;
+                    continue;
+                };
+                let changes = diff_action(data1, data2);
+                if changes.is_empty() {
+                    continue;
+                }
+                changed += 1;
+                buck2_client_ctx::println!("Changed: {}", display_identity(key, data2)?)?;
+                for change in &changes {
+                    buck2_client_ctx::println!("{}", display_change(change))?;
+                }
+            }
+
+            buck2_client_ctx::println!(
+                "{} action(s) only in the first build, {} only in the second build, {} changed.",
+                removed,
+                added,
+                changed
+            )?;
+            buck2_error::Ok(())
+        })
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(digest: &str, argv: &[&str], env: &[(&str, &str)], outputs: &[&str]) -> ActionData {
+        ActionData {
+            name: None,
+            action_digest: Some(digest.to_owned()),
+            command: Some(LocalCommandData {
+                argv: argv.iter().map(|s| (*s).to_owned()).collect(),
+                env: env
+                    .iter()
+                    .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+                    .collect(),
+            }),
+            output_tiny_digests: outputs.iter().map(|s| (*s).to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let a = local("d1", &["cc", "a.c"], &[("PATH", "/bin")], &["o1"]);
+        assert_eq!(Vec::<ActionChange>::new(), diff_action(&a, &a.clone()));
+    }
+
+    #[test]
+    fn test_command_and_environment() {
+        let a = local("d1", &["cc", "a.c"], &[("A", "1"), ("B", "2")], &["o1"]);
+        let b = local(
+            "d2",
+            &["cc", "-O2", "a.c"],
+            &[("A", "3"), ("C", "4")],
+            &["o1"],
+        );
+        let argv1 = ["cc".to_owned(), "a.c".to_owned()];
+        let argv2 = ["cc".to_owned(), "-O2".to_owned(), "a.c".to_owned()];
+        assert_eq!(
+            vec![
+                ActionChange::CommandLine {
+                    first: &argv1,
+                    second: &argv2,
+                },
+                ActionChange::Environment {
+                    key: "A",
+                    first: Some("1"),
+                    second: Some("3"),
+                },
+                ActionChange::Environment {
+                    key: "B",
+                    first: Some("2"),
+                    second: None,
+                },
+                ActionChange::Environment {
+                    key: "C",
+                    first: None,
+                    second: Some("4"),
+                },
+            ],
+            diff_action(&a, &b)
+        );
+    }
+
+    #[test]
+    fn test_inputs_and_outputs() {
+        let a = local("d1", &["cc", "a.c"], &[], &["o1"]);
+        let b = local("d2", &["cc", "a.c"], &[], &["o2"]);
+        assert_eq!(
+            vec![
+                ActionChange::Inputs {
+                    first: Some("d1"),
+                    second: Some("d2"),
+                },
+                ActionChange::Outputs {
+                    first: &["o1".to_owned()],
+                    second: &["o2".to_owned()],
+                },
+            ],
+            diff_action(&a, &b)
+        );
+
+        // Without a recorded command, any digest change is attributed to the inputs.
+        let remote = |digest: &str| ActionData {
+            action_digest: Some(digest.to_owned()),
+            ..ActionData::default()
+        };
+        assert_eq!(
+            vec![ActionChange::Inputs {
+                first: Some("d1"),
+                second: Some("d2"),
+            }],
+            diff_action(&remote("d1"), &remote("d2"))
+        );
+    }
+}
@@ -0,0 +1,403 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::io::BufWriter;
+use std::time::SystemTime;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::BuckArgMatches;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_log::utils::Invocation;
+use buck2_event_observer::display::display_action_key;
+use buck2_event_observer::display::display_event;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_events::span::SpanId;
+use buck2_events::BuckEvent;
+use dupe::Dupe;
+use futures::TryStreamExt;
+use gazebo::variants::VariantName;
+use serde::Serialize;
+
+use crate::commands::log::options::EventLogOptions;
+
+#[derive(Debug, Clone, Dupe, clap::ValueEnum)]
+#[clap(rename_all = "kebab-case")]
+enum ExportFormat {
+    /// OpenTelemetry traces, in the JSON encoding of the OTLP protocol.
+    OtlpJson,
+}
+
+/// Export an event log to a format that other tools can ingest.
+///
+/// With `--format otlp-json`, every span in the log becomes an OpenTelemetry span, and the file
+/// written is an `ExportTraceServiceRequest` that can be sent as is to an OTLP/HTTP collector.
+#[derive(Debug, clap::Parser)]
+pub struct ExportCommand {
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    /// The format to export to.
+    #[clap(long, value_enum)]
+    format: ExportFormat,
+
+    /// Where to write the exported log.
+    #[clap(long, value_name = "PATH")]
+    output: PathArg,
+}
+
+/// `SPAN_KIND_INTERNAL`: buck2 spans are not requests between services.
+const OTLP_SPAN_KIND_INTERNAL: u32 = 1;
+/// `STATUS_CODE_ERROR`.
+const OTLP_STATUS_CODE_ERROR: u32 = 2;
+
+#[derive(Serialize, Debug, PartialEq)]
+enum OtlpValue {
+    #[serde(rename = "stringValue")]
+    String(String),
+    #[serde(rename = "boolValue")]
+    Bool(bool),
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct OtlpAttribute {
+    key: &'static str,
+    value: OtlpValue,
+}
+
+impl OtlpAttribute {
+    fn string(key: &'static str, value: impl Into<String>) -> Self {
+        OtlpAttribute {
+            key,
+            value: OtlpValue::String(value.into()),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct OtlpStatus {
+    code: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OtlpSpan {
+    trace_id: String,
+    span_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u32,
+    /// A 64 bit integer, which the JSON encoding of protobuf represents as a string.
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+    attributes: Vec<OtlpAttribute>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<OtlpStatus>,
+}
+
+/// Converts the spans of an event log into OpenTelemetry spans.
+struct OtlpTraceWriter {
+    /// The trace id, as the 32 hex digits OTLP expects.
+    trace_id: String,
+    resource_attributes: Vec<OtlpAttribute>,
+    /// Spans which have started but not yet ended.
+    open_spans: HashMap<SpanId, OtlpSpan>,
+    spans: Vec<OtlpSpan>,
+    last_timestamp: SystemTime,
+}
+
+fn unix_nanos(timestamp: SystemTime) -> buck2_error::Result<String> {
+    Ok(timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_nanos()
+        .to_string())
+}
+
+fn otlp_span_id(span_id: SpanId) -> String {
+    format!("{:016x}", u64::from(span_id))
+}
+
+fn span_start_attributes(start: &buck2_data::span_start_event::Data) -> Vec<OtlpAttribute> {
+    let mut attributes = vec![OtlpAttribute::string(
+        "buck2.span_type",
+        start.variant_name(),
+    )];
+    if let buck2_data::span_start_event::Data::ActionExecution(action) = start {
+        if let Some(name) = &action.name {
+            attributes.push(OtlpAttribute::string(
+                "buck2.action.category",
+                &name.category,
+            ));
+            attributes.push(OtlpAttribute::string(
+                "buck2.action.identifier",
+                &name.identifier,
+            ));
+        }
+        if let Some(owner) = action
+            .key
+            .as_ref()
+            .and_then(|key| display_action_key(key, TargetDisplayOptions::for_log()).ok())
+        {
+            attributes.push(OtlpAttribute::string("buck2.action.owner", owner));
+        }
+        if let Some(kind) = buck2_data::ActionKind::from_i32(action.kind) {
+            attributes.push(OtlpAttribute::string(
+                "buck2.action.kind",
+                kind.as_str_name(),
+            ));
+        }
+    }
+    attributes
+}
+
+fn span_end_attributes(end: &buck2_data::span_end_event::Data) -> (Vec<OtlpAttribute>, bool) {
+    match end {
+        buck2_data::span_end_event::Data::ActionExecution(action) => {
+            let mut attributes = vec![OtlpAttribute {
+                key: "buck2.action.failed",
+                value: OtlpValue::Bool(action.failed),
+            }];
+            if let Some(kind) = buck2_data::ActionExecutionKind::from_i32(action.execution_kind) {
+                attributes.push(OtlpAttribute::string(
+                    "buck2.action.execution_kind",
+                    kind.as_str_name(),
+                ));
+            }
+            (attributes, action.failed)
+        }
+        _ => (Vec::new(), false),
+    }
+}
+
+impl OtlpTraceWriter {
+    fn new(invocation: &Invocation) -> Self {
+        OtlpTraceWriter {
+            trace_id: invocation.trace_id.to_string().replace('-', ""),
+            resource_attributes: vec![
+                OtlpAttribute::string("service.name", "buck2"),
+                OtlpAttribute::string("buck2.command_line", invocation.display_command_line()),
+                OtlpAttribute::string("buck2.trace_id", invocation.trace_id.to_string()),
+            ],
+            open_spans: HashMap::new(),
+            spans: Vec::new(),
+            last_timestamp: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    fn handle_event(&mut self, event: &BuckEvent) -> buck2_error::Result<()> {
+        self.last_timestamp = self.last_timestamp.max(event.timestamp());
+        let Some(span_id) = event.span_id() else {
+            return Ok(());
+        };
+        match event.data() {
+            buck2_data::buck_event::Data::SpanStart(start) => {
+                let Some(data) = &start.data else {
+                    return Ok(());
+                };
+                let name = display_event(event, TargetDisplayOptions::for_log())
+                    .unwrap_or_else(|_| data.variant_name().to_owned());
+                let time = unix_nanos(event.timestamp())?;
+                self.open_spans.insert(
+                    span_id,
+                    OtlpSpan {
+                        trace_id: self.trace_id.clone(),
+                        span_id: otlp_span_id(span_id),
+                        parent_span_id: event.parent_id().map(otlp_span_id),
+                        name,
+                        kind: OTLP_SPAN_KIND_INTERNAL,
+                        start_time_unix_nano: time.clone(),
+                        end_time_unix_nano: time,
+                        attributes: span_start_attributes(data),
+                        status: None,
+                    },
+                );
+            }
+            buck2_data::buck_event::Data::SpanEnd(end) => {
+                let Some(mut span) = self.open_spans.remove(&span_id) else {
+                    return Ok(());
+                };
+                span.end_time_unix_nano = unix_nanos(event.timestamp())?;
+                if let Some(data) = &end.data {
+                    let (attributes, failed) = span_end_attributes(data);
+                    span.attributes.extend(attributes);
+                    if failed {
+                        span.status = Some(OtlpStatus {
+                            code: OTLP_STATUS_CODE_ERROR,
+                        });
+                    }
+                }
+                self.spans.push(span);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Produce the `ExportTraceServiceRequest`. Spans which never ended (e.g. because buck2 was
+    /// killed) end at the last event in the log.
+    fn finish(mut self) -> buck2_error::Result<serde_json::Value> {
+        let end = unix_nanos(self.last_timestamp)?;
+        let mut unfinished: Vec<_> = self.open_spans.into_values().collect();
+        unfinished.sort_by(|a, b| a.start_time_unix_nano.cmp(&b.start_time_unix_nano));
+        for mut span in unfinished {
+            span.end_time_unix_nano = end.clone();
+            span.attributes.push(OtlpAttribute {
+                key: "buck2.unfinished",
+                value: OtlpValue::Bool(true),
+            });
+            self.spans.push(span);
+        }
+
+        Ok(serde_json::json!({
+            "resourceSpans": [{
+                "resource": { "attributes": self.resource_attributes },
+                "scopeSpans": [{
+                    "scope": { "name": "buck2" },
+                    "spans": self.spans,
+                }],
+            }],
+        }))
+    }
+}
+
+impl ExportCommand {
+    pub fn exec(self, _matches: BuckArgMatches<'_>, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let Self {
+            event_log,
+            format,
+            output,
+        } = self;
+        let output = output.resolve(&ctx.working_dir);
+        ctx.instant_command_no_log("log-export", |ctx| async move {
+            let log_path = event_log.get(&ctx).await?;
+            let (invocation, mut events) = log_path.unpack_stream().await?;
+
+            let trace = match format {
+                ExportFormat::OtlpJson => {
+                    let mut writer = OtlpTraceWriter::new(&invocation);
+                    while let Some(event) = events.try_next().await? {
+                        if let StreamValue::Event(event) = event {
+                            writer.handle_event(&BuckEvent::try_from(event)?)?;
+                        }
+                    }
+                    writer.finish()?
+                }
+            };
+
+            let file = std::fs::File::create(&output)?;
+            serde_json::to_writer(BufWriter::new(file), &trace)?;
+            buck2_error::Ok(())
+        })
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use buck2_wrapper_common::invocation_id::TraceId;
+
+    use super::*;
+
+    fn event(
+        trace_id: &TraceId,
+        millis: u64,
+        span_id: u64,
+        parent_id: Option<u64>,
+        data: impl Into<buck2_data::buck_event::Data>,
+    ) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            trace_id.dupe(),
+            Some(SpanId::from_u64(span_id).unwrap()),
+            parent_id.map(|id| SpanId::from_u64(id).unwrap()),
+            data.into(),
+        )
+    }
+
+    fn action_start() -> buck2_data::SpanStartEvent {
+        buck2_data::SpanStartEvent {
+            data: Some(
+                buck2_data::ActionExecutionStart {
+                    key: None,
+                    kind: buck2_data::ActionKind::Run as i32,
+                    name: Some(buck2_data::ActionName {
+                        category: "cxx_compile".to_owned(),
+                        identifier: "a.cpp".to_owned(),
+                    }),
+                }
+                .into(),
+            ),
+        }
+    }
+
+    fn action_end(failed: bool) -> buck2_data::SpanEndEvent {
+        buck2_data::SpanEndEvent {
+            data: Some(
+                buck2_data::ActionExecutionEnd {
+                    failed,
+                    execution_kind: buck2_data::ActionExecutionKind::Local as i32,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_action_spans() -> buck2_error::Result<()> {
+        let trace_id = TraceId::new();
+        let invocation = Invocation {
+            command_line_args: vec!["buck2".to_owned(), "build".to_owned()],
+            expanded_command_line_args: vec!["buck2".to_owned(), "build".to_owned()],
+            working_dir: "/repo".to_owned(),
+            trace_id: trace_id.dupe(),
+        };
+        let mut writer = OtlpTraceWriter::new(&invocation);
+        writer.handle_event(&event(&trace_id, 1, 1, None, action_start()))?;
+        writer.handle_event(&event(&trace_id, 2, 2, Some(1), action_start()))?;
+        writer.handle_event(&event(&trace_id, 3, 2, Some(1), action_end(true)))?;
+
+        let trace = writer.finish()?;
+        let spans = &trace["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(2, spans.as_array().unwrap().len());
+
+        let ended = &spans[0];
+        assert_eq!(trace_id.to_string().replace('-', ""), ended["traceId"]);
+        assert_eq!("0000000000000002", ended["spanId"]);
+        assert_eq!("0000000000000001", ended["parentSpanId"]);
+        assert_eq!("2000000", ended["startTimeUnixNano"]);
+        assert_eq!("3000000", ended["endTimeUnixNano"]);
+        assert_eq!(OTLP_STATUS_CODE_ERROR, ended["status"]["code"]);
+        let attributes = serde_json::json!([
+            { "key": "buck2.span_type", "value": { "stringValue": "ActionExecution" } },
+            { "key": "buck2.action.category", "value": { "stringValue": "cxx_compile" } },
+            { "key": "buck2.action.identifier", "value": { "stringValue": "a.cpp" } },
+            { "key": "buck2.action.kind", "value": { "stringValue": "RUN" } },
+            { "key": "buck2.action.failed", "value": { "boolValue": true } },
+            {
+                "key": "buck2.action.execution_kind",
+                "value": { "stringValue": "ACTION_EXECUTION_KIND_LOCAL" }
+            },
+        ]);
+        assert_eq!(attributes, ended["attributes"]);
+
+        // The outer span never ended, so it ends with the last event.
+        let unfinished = &spans[1];
+        assert_eq!("0000000000000001", unfinished["spanId"]);
+        assert!(unfinished.get("parentSpanId").is_none());
+        assert_eq!("3000000", unfinished["endTimeUnixNano"]);
+        Ok(())
+    }
+}
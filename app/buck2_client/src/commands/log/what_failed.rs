@@ -31,6 +31,7 @@ impl WhatFailedCommand {
             incomplete: false,
             show_std_err: false,
             omit_empty_std_err: false,
+            sort_by: None,
         }
         .exec(matches, ctx)
     }
@@ -8,6 +8,7 @@
  */
 
 use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::io::Write;
 
@@ -19,6 +20,7 @@ use buck2_data::re_platform::Property;
 use buck2_error::BuckErrorContext;
 use buck2_event_log::stream_value::StreamValue;
 use buck2_event_observer::fmt_duration;
+use buck2_event_observer::humanized::HumanizedBytes;
 use buck2_event_observer::what_ran;
 use buck2_event_observer::what_ran::CommandReproducer;
 use buck2_event_observer::what_ran::WhatRanOptions;
@@ -28,6 +30,7 @@ use buck2_event_observer::what_ran::WhatRanOutputWriter;
 use buck2_event_observer::what_ran::WhatRanRelevantAction;
 use buck2_event_observer::what_ran::WhatRanState;
 use buck2_events::span::SpanId;
+use dupe::Dupe;
 use futures::stream::Stream;
 use futures::TryStreamExt;
 use indexmap::IndexMap;
@@ -49,6 +52,10 @@ use crate::commands::log::OutputFormatWithWriter;
 ///
 /// Details to reproduce it. For RE, that's the action digest. For local, the command.
 ///
+/// The peak memory usage of the command, or `-` if it was not measured.
+///
+/// The user and system CPU time of the command, or `-` if it was not measured.
+///
 ///
 /// To reproduce an action that ran on RE, use the following command then follow the instructions.
 /// The DIGEST is of the form `hash:size`.
@@ -84,6 +91,36 @@ pub struct WhatRanCommand {
     /// Omit commands if their std_err is empty
     #[clap(long, conflicts_with = "incomplete", requires = "show_std_err")]
     pub omit_empty_std_err: bool,
+
+    /// Sort finished commands by the given resource, largest first, instead of printing them
+    /// as they finished. Commands for which it was not measured come last.
+    #[clap(long, value_enum, conflicts_with = "incomplete")]
+    pub sort_by: Option<WhatRanSortKey>,
+}
+
+#[derive(Debug, Clone, Copy, Dupe, clap::ValueEnum)]
+#[clap(rename_all = "kebab-case")]
+pub enum WhatRanSortKey {
+    /// The wall time of the action.
+    Duration,
+    /// The peak memory usage of the command.
+    MemoryPeak,
+    /// The user and system CPU time of the command.
+    CpuTime,
+}
+
+impl WhatRanSortKey {
+    fn value(self, data: &Option<buck2_data::span_end_event::Data>) -> Option<u128> {
+        match self {
+            Self::Duration => what_ran::action_wall_time(data).map(|d| d.as_nanos()),
+            Self::MemoryPeak => {
+                what_ran::action_execution_stats(data).and_then(|s| s.memory_peak.map(u128::from))
+            }
+            Self::CpuTime => what_ran::action_execution_stats(data)
+                .and_then(what_ran::cpu_time)
+                .map(|d| d.as_nanos()),
+        }
+    }
 }
 
 #[derive(Debug, clap::Parser)]
@@ -112,6 +149,9 @@ struct WhatRanCommandOptions {
 
     /// Print commands only if they did not finish.
     incomplete: bool,
+
+    /// Print finished commands at the end, sorted by this key.
+    sort_by: Option<WhatRanSortKey>,
 }
 
 impl WhatRanCommand {
@@ -127,6 +167,7 @@ impl WhatRanCommand {
             incomplete,
             show_std_err,
             omit_empty_std_err,
+            sort_by,
         } = self;
         buck2_client_ctx::stdio::print_with_writer::<buck2_error::Error, _>(|w| {
             let mut output = OutputFormatWithWriter {
@@ -153,6 +194,7 @@ impl WhatRanCommand {
                     options,
                     failed,
                     incomplete,
+                    sort_by,
                 };
                 WhatRanCommandState::execute(events, &mut output, &options).await?;
                 buck2_error::Ok(())
@@ -208,6 +250,8 @@ impl WhatRanEntry {
 pub struct WhatRanCommandState {
     /// Maps action spans to their details.
     known_actions: HashMap<SpanId, WhatRanEntry>,
+    /// Finished actions held back to be sorted, with their span end data.
+    finished_actions: Vec<(WhatRanEntry, Option<buck2_data::span_end_event::Data>)>,
 }
 
 impl WhatRanState for WhatRanCommandState {
@@ -234,6 +278,14 @@ impl WhatRanCommandState {
             }
         }
 
+        if let Some(sort_by) = options.sort_by {
+            cmd.finished_actions
+                .sort_by_cached_key(|(_, data)| Reverse(sort_by.value(data)));
+            for (entry, data) in cmd.finished_actions.iter() {
+                entry.emit_what_ran_entry(output, data, options)?;
+            }
+        }
+
         // emit remaining
         for (_, entry) in cmd.known_actions.iter() {
             if should_emit_unfinished_action(options) {
@@ -279,7 +331,11 @@ impl WhatRanCommandState {
                         self.known_actions.remove(&SpanId::from_u64(event.span_id)?)
                     {
                         if should_emit_finished_action(&span.data, options) {
-                            entry.emit_what_ran_entry(output, &span.data, options)?;
+                            if options.sort_by.is_some() {
+                                self.finished_actions.push((entry, span.data.clone()));
+                            } else {
+                                entry.emit_what_ran_entry(output, &span.data, options)?;
+                            }
                         }
                     }
                 }
@@ -338,7 +394,15 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
 
         match &mut self.format {
             LogCommandOutputFormatWithWriter::Tabulated(w) => {
-                w.write_all(format!("{}\n", command.as_tabulated_reproducer()).as_bytes())?;
+                w.write_all(
+                    format!(
+                        "{}\t{}\t{}\n",
+                        command.as_tabulated_reproducer(),
+                        fmt_memory_peak(command.memory_peak()),
+                        fmt_cpu_time(command.cpu_time()),
+                    )
+                    .as_bytes(),
+                )?;
                 if let Some(std_err) = std_err_formatted {
                     write!(
                         w,
@@ -426,6 +490,10 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
                     duration: command
                         .duration
                         .map(|duration| fmt_duration::fmt_duration(duration, 1.0)),
+                    memory_peak: command.memory_peak(),
+                    cpu_time: command
+                        .cpu_time()
+                        .map(|cpu_time| fmt_duration::fmt_duration(cpu_time, 1.0)),
                     extra: command.extra.map(Into::into),
                     std_err,
                 };
@@ -440,6 +508,8 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
                     identity: &'a str,
                     executor: String,
                     reproducer: String,
+                    memory_peak: Option<u64>,
+                    cpu_time: Option<String>,
                     #[serde(skip_serializing_if = "Option::is_none")]
                     std_err: Option<&'a str>,
                 }
@@ -448,6 +518,10 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
                     identity: command.identity,
                     executor: command.repro.executor(),
                     reproducer: command.repro.as_human_readable().to_string(),
+                    memory_peak: command.memory_peak(),
+                    cpu_time: command
+                        .cpu_time()
+                        .map(|cpu_time| fmt_duration::fmt_duration(cpu_time, 1.0)),
                     std_err: std_err_formatted,
                 })?;
                 Ok(())
@@ -456,6 +530,20 @@ impl WhatRanOutputWriter for OutputFormatWithWriter<'_> {
     }
 }
 
+fn fmt_memory_peak(memory_peak: Option<u64>) -> String {
+    memory_peak.map_or_else(
+        || "-".to_owned(),
+        |bytes| HumanizedBytes::new(bytes).to_string(),
+    )
+}
+
+fn fmt_cpu_time(cpu_time: Option<std::time::Duration>) -> String {
+    cpu_time.map_or_else(
+        || "-".to_owned(),
+        |cpu_time| fmt_duration::fmt_duration(cpu_time, 1.0),
+    )
+}
+
 fn into_index_map(platform: &Option<buck2_data::RePlatform>) -> IndexMap<&str, &str> {
     platform.as_ref().map_or_else(IndexMap::new, |p| {
         p.properties
@@ -472,6 +560,12 @@ struct JsonCommand<'a> {
     reproducer: JsonReproducer<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
     duration: Option<String>,
+    /// Peak memory usage in bytes, if measured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_peak: Option<u64>,
+    /// User and system CPU time, if measured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_time: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     extra: Option<JsonExtra<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -550,6 +644,8 @@ mod tests {
             identity: "some/target",
             reproducer: JsonReproducer::Local { command, env },
             duration: Some("1".to_owned()),
+            memory_peak: None,
+            cpu_time: None,
             extra: None,
             std_err: None,
         }
@@ -567,6 +663,8 @@ mod tests {
                 action_key: None,
             },
             duration: Some("1".to_owned()),
+            memory_peak: None,
+            cpu_time: None,
             extra: None,
             std_err: None,
         }
@@ -629,6 +727,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn serialize_what_ran_command_with_resource_usage() -> buck2_error::Result<()> {
+        let mut command = make_base_command();
+        command.memory_peak = Some(1024);
+        command.cpu_time = Some("2.5".to_owned());
+
+        let expected = r#"{
+  "reason": "test.run",
+  "identity": "some/target",
+  "reproducer": {
+    "executor": "Local",
+    "details": {
+      "command": [
+        "some",
+        "command"
+      ],
+      "env": {
+        "KEY": "val"
+      }
+    }
+  },
+  "duration": "1",
+  "memory_peak": 1024,
+  "cpu_time": "2.5"
+}"#;
+        assert_eq!(expected, serde_json::to_string_pretty(&command)?);
+        Ok(())
+    }
+
+    fn action_end(
+        wall_time_secs: i64,
+        execution_stats: Option<buck2_data::CommandExecutionStats>,
+    ) -> Option<buck2_data::span_end_event::Data> {
+        Some(buck2_data::span_end_event::Data::ActionExecution(Box::new(
+            buck2_data::ActionExecutionEnd {
+                wall_time: Some(prost_types::Duration {
+                    seconds: wall_time_secs,
+                    nanos: 0,
+                }),
+                commands: vec![buck2_data::CommandExecution {
+                    details: Some(buck2_data::CommandExecutionDetails {
+                        metadata: Some(buck2_data::CommandExecutionMetadata {
+                            execution_stats,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )))
+    }
+
+    #[test]
+    fn sort_key_value() {
+        let stats = buck2_data::CommandExecutionStats {
+            memory_peak: Some(2048),
+            cpu_time_user_us: Some(1500),
+            cpu_time_system_us: Some(500),
+            ..Default::default()
+        };
+        let data = action_end(3, Some(stats));
+        assert_eq!(Some(3_000_000_000), WhatRanSortKey::Duration.value(&data));
+        assert_eq!(Some(2048), WhatRanSortKey::MemoryPeak.value(&data));
+        assert_eq!(Some(2_000_000), WhatRanSortKey::CpuTime.value(&data));
+
+        let unmeasured = action_end(1, None);
+        assert_eq!(None, WhatRanSortKey::MemoryPeak.value(&unmeasured));
+        assert_eq!(None, WhatRanSortKey::CpuTime.value(&unmeasured));
+        assert_eq!(None, WhatRanSortKey::Duration.value(&None));
+    }
+
+    #[test]
+    fn format_resource_columns() {
+        assert_eq!("-", fmt_memory_peak(None));
+        assert_eq!("2.0KiB", fmt_memory_peak(Some(2048)));
+        assert_eq!("-", fmt_cpu_time(None));
+        assert_eq!(
+            "2.5s",
+            fmt_cpu_time(Some(std::time::Duration::from_millis(2500)))
+        );
+    }
+
     #[test]
     fn serialize_what_ran_command_in_re() -> buck2_error::Result<()> {
         let command = make_base_command_in_re();
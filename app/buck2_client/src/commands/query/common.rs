@@ -26,6 +26,9 @@ enum QueryOutputFormatArg {
     Json,
     DotCompact,
     Starlark,
+    Ndjson,
+    Graphml,
+    Protobuf,
 }
 
 /// Args common to all the query commands
@@ -55,9 +58,12 @@ pub(crate) struct CommonQueryOptions {
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
            json - JSON format. \n
-           starlark - targets are printed like starlark code that would produce them.
+           starlark - targets are printed like starlark code that would produce them. \n
+           ndjson - newline-delimited JSON, one object per target. \n
+           graphml - GraphML graph format. \n
+           protobuf - length-delimited `QueryGraphNode` protobuf messages, one per target.
          ",
-        value_name = "dot|dot_compact|json|starlark",
+        value_name = "dot|dot_compact|json|starlark|ndjson|graphml|protobuf",
         value_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
@@ -90,6 +96,9 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
             Some(QueryOutputFormatArg::Starlark) => QueryOutputFormat::Starlark,
+            Some(QueryOutputFormatArg::Ndjson) => QueryOutputFormat::Ndjson,
+            Some(QueryOutputFormatArg::Graphml) => QueryOutputFormat::Graphml,
+            Some(QueryOutputFormatArg::Protobuf) => QueryOutputFormat::Protobuf,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
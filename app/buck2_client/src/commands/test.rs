@@ -7,8 +7,14 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
 use async_trait::async_trait;
+use buck2_cli_proto::test_request::TestSharding;
 use buck2_cli_proto::CounterWithExamples;
+use buck2_cli_proto::TestReportFormat;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -31,11 +37,17 @@ use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_client_ctx::subscribers::superconsole::test::span_from_build_failure_count;
 use buck2_client_ctx::subscribers::superconsole::test::TestCounterColumn;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_core::fs::working_dir::AbsWorkingDir;
 use buck2_error::BuckErrorContext;
 use buck2_error::ErrorTag;
+use buck2_event_log::read::EventLogPathBuf;
+use buck2_event_log::stream_value::StreamValue;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
 use superconsole::Line;
 use superconsole::Span;
+use tokio_stream::StreamExt;
 
 use crate::commands::build::print_build_result;
 
@@ -68,6 +80,85 @@ fn print_error_counter(
     }
     Ok(())
 }
+
+/// The total duration of the tests of each target in the event log of a prior test run, keyed by
+/// target label.
+async fn target_durations_from_event_log(
+    path: AbsPathBuf,
+) -> buck2_error::Result<HashMap<String, u64>> {
+    let (_invocation, mut events) = EventLogPathBuf::infer(path)?.unpack_stream().await?;
+    let mut durations = HashMap::new();
+    while let Some(event) = events.try_next().await? {
+        let StreamValue::Event(event) = event else {
+            continue;
+        };
+        let Some(buck2_data::buck_event::Data::Instant(instant)) = event.data else {
+            continue;
+        };
+        let Some(buck2_data::instant_event::Data::TestResult(result)) = instant.data else {
+            continue;
+        };
+        let (Some(target_label), Some(duration)) = (result.target_label, result.duration) else {
+            continue;
+        };
+        let target = display_configured_target_label(
+            &target_label,
+            TargetDisplayOptions::for_console(false),
+        )?;
+        let duration: std::time::Duration = duration
+            .try_into()
+            .buck_error_context("Invalid test duration")?;
+        *durations.entry(target).or_default() += duration.as_millis() as u64;
+    }
+    Ok(durations)
+}
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum TestCommandError {
+    #[error(
+        "`--list-affected` requires `--affected-by`, `--affected-by-file` or `--affected-by-mergebase`"
+    )]
+    ListAffectedWithoutAffectedBy,
+}
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum TestReportArgError {
+    #[error(
+        "Invalid test report `{0}`. Expected `FORMAT=PATH`, where FORMAT is `junit` or `tap`."
+    )]
+    InvalidFormat(String),
+}
+
+/// A report of the individual test results, and where to write it.
+#[derive(Debug, Clone)]
+struct TestReportArg {
+    format: TestReportFormat,
+    path: PathArg,
+}
+
+impl FromStr for TestReportArg {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || TestReportArgError::InvalidFormat(value.to_owned());
+        let (format, path) = value.split_once('=').ok_or_else(invalid)?;
+        let format = match format {
+            "junit" => TestReportFormat::Junit,
+            "tap" => TestReportFormat::Tap,
+            _ => return Err(invalid().into()),
+        };
+        if path.is_empty() {
+            return Err(invalid().into());
+        }
+        Ok(Self {
+            format,
+            path: PathArg::from_str(path)?,
+        })
+    }
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -159,12 +250,65 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long)]
     test_executor_stderr: Option<OutputDestinationArg>,
 
+    /// Writes a report of the individual test results to a file
+    ///
+    /// --report=junit=FILEPATH writes a JUnit XML report, and --report=tap=FILEPATH writes a TAP
+    /// (version 13) report. The reports include each test's target, status, duration and output.
+    /// Can be passed more than once.
+    #[clap(long = "report", value_name = "FORMAT=PATH")]
+    reports: Vec<TestReportArg>,
+
+    /// Split the test targets into this many shards, and only build and test the targets of the
+    /// shard selected by `--shard-index`
+    ///
+    /// Every shard of a run must be given the same target patterns and options.
+    #[clap(long, requires = "shard_index", value_name = "COUNT")]
+    shard_count: Option<u32>,
+
+    /// Which shard of the test targets to build and test, from 0 to `--shard-count` - 1
+    #[clap(long, requires = "shard_count", value_name = "INDEX")]
+    shard_index: Option<u32>,
+
+    /// Balance the shards using the test durations in the event log of a prior test run
+    ///
+    /// Targets which are not in the event log are assigned by a hash of their label. Every shard
+    /// of a run must be given the same event log.
+    #[clap(long, requires = "shard_count", value_name = "EVENT_LOG")]
+    shard_durations_from: Option<PathArg>,
+
+    /// Only test the targets matched by the target patterns that are affected by changes to this
+    /// file. Can be repeated
+    ///
+    /// A test is affected if it depends on a changed file, directly or transitively, or is in the
+    /// `tests` attribute of a target which does.
+    #[clap(long, value_name = "FILE")]
+    affected_by: Vec<PathArg>,
+
+    /// Like `--affected-by`, with the changed files read from this file, one per line
+    #[clap(long, value_name = "FILE")]
+    affected_by_file: Option<PathArg>,
+
+    /// Like `--affected-by`, with the files changed since the mergebase reported by the file
+    /// watcher
+    #[clap(long)]
+    affected_by_mergebase: bool,
+
+    /// Print the tests selected by `--affected-by` or `--affected-by-mergebase`, without building
+    /// or running anything
+    #[clap(long)]
+    list_affected: bool,
+
     /// Additional arguments passed to the test executor.
     ///
     /// Test executor is expected to have `--env` flag to pass environment variables.
     /// Can be used like this:
     ///
     /// buck2 test //foo:bar -- --env PRIVATE_KEY=123
+    ///
+    /// The built-in test runner also accepts `--retries N`, to rerun tests that fail up to N more
+    /// times. Tests that pass when rerun are reported as flaky:
+    ///
+    /// buck2 test //foo:bar -- --retries 2
     #[clap(name = "TEST_EXECUTOR_ARGS", raw = true)]
     test_executor_args: Vec<String>,
 
@@ -196,7 +340,49 @@ impl StreamingCommand for TestCommand {
         matches: BuckArgMatches<'_>,
         ctx: &mut ClientCommandContext<'_>,
     ) -> ExitResult {
+        if self.list_affected
+            && self.affected_by.is_empty()
+            && self.affected_by_file.is_none()
+            && !self.affected_by_mergebase
+        {
+            return ExitResult::err(TestCommandError::ListAffectedWithoutAffectedBy.into());
+        }
+
+        let mut affected_by: Vec<String> = self
+            .affected_by
+            .iter()
+            .map(|path| path.resolve(&ctx.working_dir).to_string())
+            .collect();
+        if let Some(path) = &self.affected_by_file {
+            let contents = fs_util::read_to_string(path.resolve(&ctx.working_dir))
+                .with_buck_error_context(|| {
+                    format!("Failed to read changed files from `{}`", path.display())
+                })?;
+            affected_by.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(|line| ctx.working_dir.resolve(Path::new(line)).to_string()),
+            );
+        }
+
         let context = ctx.client_context(matches, &self)?;
+        let sharding = match (self.shard_index, self.shard_count) {
+            (Some(index), Some(count)) => Some(TestSharding {
+                index,
+                count,
+                target_durations_ms: match &self.shard_durations_from {
+                    Some(path) => target_durations_from_event_log(path.resolve(&ctx.working_dir))
+                        .await
+                        .with_buck_error_context(|| {
+                            format!("Failed to read test durations from `{}`", path.display())
+                        })?,
+                    None => HashMap::new(),
+                },
+            }),
+            _ => None,
+        };
         let response = buckd
             .with_flushing()
             .test(
@@ -227,12 +413,25 @@ impl StreamingCommand for TestCommand {
                         .transpose()
                         .buck_error_context("Invalid `timeout`")?,
                     ignore_tests_attribute: self.ignore_tests_attribute,
+                    report_formats: self.reports.iter().map(|r| r.format as i32).collect(),
+                    sharding,
+                    affected_by,
+                    affected_by_file: self.affected_by_file.is_some(),
+                    affected_by_mergebase: self.affected_by_mergebase,
+                    list_affected: self.list_affected,
                 },
                 ctx.console_interaction_stream(&self.common_opts.console_opts),
                 &mut NoPartialResultHandler,
             )
             .await??;
 
+        if self.list_affected {
+            for test in &response.affected_tests {
+                buck2_client_ctx::println!("{}", test)?;
+            }
+            return ExitResult::success();
+        }
+
         let statuses = response
             .test_statuses
             .as_ref()
@@ -258,6 +457,10 @@ impl StreamingCommand for TestCommand {
             .skipped
             .as_ref()
             .buck_error_context("Missing `skipped`")?;
+        let flaky = statuses
+            .flaky
+            .as_ref()
+            .buck_error_context("Missing `flaky`")?;
 
         let console = self.common_opts.console_opts.final_console();
         print_build_result(&console, &response.errors)?;
@@ -276,18 +479,28 @@ impl StreamingCommand for TestCommand {
             console.print_error(&format!("{} BUILDS FAILED", build_errors.len()))?;
         }
 
+        if let Some(shard) = &response.shard {
+            console.print_stderr(&format!(
+                "Shard {} (of {} shards): tested {} targets, left {} to other shards",
+                shard.index, shard.count, shard.targets, shard.targets_in_other_shards
+            ))?;
+        }
+
         let mut line = Line::default();
         line.push(Span::new_unstyled_lossy("Tests finished: "));
         if listing_failed.count > 0 {
             line.push(TestCounterColumn::LISTING_FAIL.to_span_from_test_statuses(statuses)?);
             line.push(Span::new_unstyled_lossy(". "));
         }
-        let columns = [
-            TestCounterColumn::PASS,
+        let mut columns = vec![TestCounterColumn::PASS];
+        if flaky.count > 0 {
+            columns.push(TestCounterColumn::FLAKY);
+        }
+        columns.extend([
             TestCounterColumn::FAIL,
             TestCounterColumn::FATAL,
             TestCounterColumn::SKIP,
-        ];
+        ]);
         for column in columns {
             line.push(column.to_span_from_test_statuses(statuses)?);
             line.push(Span::new_unstyled_lossy(". "));
@@ -298,7 +511,8 @@ impl StreamingCommand for TestCommand {
         print_error_counter(&console, listing_failed, "LISTINGS FAILED", "⚠")?;
         print_error_counter(&console, failed, "TESTS FAILED", "✗")?;
         print_error_counter(&console, fatals, "TESTS FATALS", "⚠")?;
-        if passed.count + failed.count + fatals.count + skipped.count == 0 {
+        print_error_counter(&console, flaky, "TESTS FLAKY", "≈")?;
+        if passed.count + flaky.count + failed.count + fatals.count + skipped.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
 
@@ -317,6 +531,13 @@ impl StreamingCommand for TestCommand {
             None => {}
         }
 
+        for (report, arg) in response.reports.iter().zip(&self.reports) {
+            fs_util::write(arg.path.resolve(&ctx.working_dir), &report.contents)
+                .with_buck_error_context(|| {
+                    format!("Failed to write test report to `{}`", arg.path.display())
+                })?;
+        }
+
         if let Some(build_report) = response.serialized_build_report {
             buck2_client_ctx::println!("{}", build_report)?;
         }
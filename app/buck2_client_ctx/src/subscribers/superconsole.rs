@@ -910,7 +910,9 @@ fn lines_for_command_details(
                     help_message.with(Color::DarkRed),
                 )]));
             }
-            Some(Command::OmittedLocalCommand(..)) | None => {
+            Some(Command::OmittedLocalCommand(..))
+            | Some(Command::LocalActionCacheHit(..))
+            | None => {
                 // Nothing to show in this case.
             }
             Some(Command::WorkerInitCommand(worker_init_command)) => {
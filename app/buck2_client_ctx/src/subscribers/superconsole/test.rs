@@ -68,6 +68,12 @@ impl TestCounterColumn {
         get_from_test_state: |test_state| test_state.skipped,
         get_from_test_statues: |test_statuses| &test_statuses.skipped,
     };
+    pub const FLAKY: TestCounterColumn = TestCounterColumn {
+        label: "Flaky",
+        color: Some(Color::Yellow),
+        get_from_test_state: |test_state| test_state.flaky,
+        get_from_test_statues: |test_statuses| &test_statuses.flaky,
+    };
     const TIMEOUT: TestCounterColumn = TestCounterColumn {
         label: "Timeout",
         color: Some(Color::Yellow),
@@ -122,6 +128,10 @@ impl TestCounterComponent {
         spans.push(". ".try_into()?);
         spans.push(TestCounterColumn::PASS.to_span_from_test_state(test_state)?);
         spans.push(". ".try_into()?);
+        if test_state.flaky > 0 {
+            spans.push(TestCounterColumn::FLAKY.to_span_from_test_state(test_state)?);
+            spans.push(". ".try_into()?);
+        }
         spans.push(TestCounterColumn::FAIL.to_span_from_test_state(test_state)?);
         spans.push(". ".try_into()?);
         spans.push(TestCounterColumn::FATAL.to_span_from_test_state(test_state)?);
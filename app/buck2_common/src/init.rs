@@ -234,6 +234,14 @@ pub struct ResourceControlConfig {
     pub memory_max: Option<String>,
     /// A memory threshold that any action is allowed to allocate.
     pub memory_max_per_action: Option<String>,
+    /// A cgroup v2 directory under which buck2 creates a cgroup for each local action, to limit
+    /// its resources and measure its peak memory and CPU time, without systemd.
+    /// The corresponding buckconfig is `buck2_resource_control.action_cgroup_root`.
+    pub action_cgroup_root: Option<String>,
+    /// The number of CPUs (possibly fractional, e.g. `1.5`) that any action is allowed to use when
+    /// `action_cgroup_root` is set.
+    /// The corresponding buckconfig is `buck2_resource_control.cpu_max_per_action`.
+    pub cpu_max_per_action: Option<String>,
     /// If provided and above the threshold, hybrid executor will stop scheduling local actions.
     /// The corresponding buckconfig is `buck2_resource_control.hybrid_execution_memory_limit_gibibytes`.
     pub hybrid_execution_memory_limit_gibibytes: Option<u64>,
@@ -302,10 +310,20 @@ impl ResourceControlConfig {
                 section: "buck2_resource_control",
                 property: "hybrid_execution_memory_limit_gibibytes",
             })?;
+            let action_cgroup_root = config.parse(BuckconfigKeyRef {
+                section: "buck2_resource_control",
+                property: "action_cgroup_root",
+            })?;
+            let cpu_max_per_action = config.parse(BuckconfigKeyRef {
+                section: "buck2_resource_control",
+                property: "cpu_max_per_action",
+            })?;
             Ok(Self {
                 status,
                 memory_max,
                 memory_max_per_action,
+                action_cgroup_root,
+                cpu_max_per_action,
                 hybrid_execution_memory_limit_gibibytes,
             })
         }
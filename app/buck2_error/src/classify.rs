@@ -139,9 +139,11 @@ pub(crate) fn category_and_rank(tag: ErrorTag) -> (Option<Tier>, u32) {
         ErrorTag::ActionMissingOutputs => rank!(input),
         ErrorTag::ActionWrongOutputType => rank!(input),
         ErrorTag::ActionCommandFailure => rank!(input),
+        ErrorTag::ActionSandboxViolation => rank!(input),
         ErrorTag::ProjectMissingPath => rank!(input),
         ErrorTag::StarlarkFail => rank!(input),
         ErrorTag::StarlarkStackOverflow => rank!(input),
+        ErrorTag::StarlarkBudgetExceeded => rank!(input),
         ErrorTag::StarlarkValue => rank!(input),
         ErrorTag::StarlarkFunction => rank!(input),
         ErrorTag::StarlarkScope => rank!(input),
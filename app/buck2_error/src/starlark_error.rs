@@ -130,6 +130,7 @@ fn from_starlark_impl(
     let tag = match e.kind() {
         starlark_syntax::ErrorKind::Fail(_) => crate::ErrorTag::StarlarkFail,
         starlark_syntax::ErrorKind::StackOverflow(_) => crate::ErrorTag::StarlarkStackOverflow,
+        starlark_syntax::ErrorKind::BudgetExceeded(_) => crate::ErrorTag::StarlarkBudgetExceeded,
         starlark_syntax::ErrorKind::Value(_) => crate::ErrorTag::StarlarkValue,
         starlark_syntax::ErrorKind::Function(_) => crate::ErrorTag::StarlarkFunction,
         starlark_syntax::ErrorKind::Scope(_) => crate::ErrorTag::StarlarkScope,
@@ -146,6 +147,7 @@ fn from_starlark_impl(
     let variant_name = match e.kind() {
         starlark_syntax::ErrorKind::Fail(_) => "StarlarkError::Fail",
         starlark_syntax::ErrorKind::StackOverflow(_) => "StarlarkError::StackOverflow",
+        starlark_syntax::ErrorKind::BudgetExceeded(_) => "StarlarkError::BudgetExceeded",
         starlark_syntax::ErrorKind::Internal(_) => "StarlarkError::Internal",
         starlark_syntax::ErrorKind::Value(_) => "StarlarkError::Value",
         starlark_syntax::ErrorKind::Function(_) => "StarlarkError::Function",
@@ -164,6 +166,7 @@ fn from_starlark_impl(
     match e.into_kind() {
         starlark_syntax::ErrorKind::Fail(e)
         | starlark_syntax::ErrorKind::StackOverflow(e)
+        | starlark_syntax::ErrorKind::BudgetExceeded(e)
         | starlark_syntax::ErrorKind::Internal(e)
         | starlark_syntax::ErrorKind::Value(e)
         | starlark_syntax::ErrorKind::Function(e)
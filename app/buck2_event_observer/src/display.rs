@@ -556,6 +556,7 @@ pub fn format_test_result(
         status,
         duration,
         details,
+        cached,
         ..
     } = test_result;
     let status = TestStatus::try_from(*status)?;
@@ -578,8 +579,12 @@ pub fn format_test_result(
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
         TestStatus::LISTING_FAILED => Span::new_styled("⚠ Listing failed".to_owned().red()),
+        TestStatus::FLAKY => Span::new_styled("≈ Flaky".to_owned().yellow()),
     }?;
     let mut base = Line::from_iter([prefix, Span::new_unstyled(format!(": {}", name,))?]);
+    if *cached {
+        base.push(Span::new_unstyled(" (cached)")?);
+    }
     if let Some(duration) = duration {
         if let Ok(duration) = Duration::try_from(duration.clone()) {
             base.push(Span::new_unstyled(format!(
@@ -686,7 +691,9 @@ impl<'a> ActionErrorDisplay<'a> {
                         );
                     }
                 }
-                Some(Command::OmittedLocalCommand(..)) | None => {
+                Some(Command::OmittedLocalCommand(..))
+                | Some(Command::LocalActionCacheHit(..))
+                | None => {
                     // Nothing to show in this case.
                 }
             };
@@ -811,6 +818,7 @@ fn failure_reason_for_command_execution(
             Some(Command::LocalCommand(..)) | Some(Command::OmittedLocalCommand(..)) => "Local ",
             Some(Command::WorkerInitCommand(..)) => "Local Worker Initialization ",
             Some(Command::WorkerCommand(..)) => "Local Worker ",
+            Some(Command::LocalActionCacheHit(..)) => "Local Action Cache ",
             None => "",
         }
     } else {
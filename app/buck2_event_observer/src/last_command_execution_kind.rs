@@ -37,6 +37,7 @@ pub fn get_last_command_execution_kind(
             Some(Command::WorkerCommand(_)) | Some(Command::WorkerInitCommand(_)) => {
                 LastCommandExecutionKind::LocalWorker
             }
+            Some(Command::LocalActionCacheHit(..)) => LastCommandExecutionKind::Cached,
             Some(Command::RemoteCommand(buck2_data::RemoteCommand {
                 cache_hit: true,
                 cache_hit_type,
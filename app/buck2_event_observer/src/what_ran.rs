@@ -100,12 +100,21 @@ pub struct WhatRanOutputCommand<'a> {
     pub extra: Option<WhatRanOutputCommandExtra<'a>>,
     pub std_err: Option<&'a str>,
     pub duration: Option<std::time::Duration>,
+    pub execution_stats: Option<&'a buck2_data::CommandExecutionStats>,
 }
 
 impl<'a> WhatRanOutputCommand<'a> {
     pub fn as_tabulated_reproducer(&self) -> impl fmt::Display + '_ {
         WhatRanOutputCommandHeader { cmd: self }
     }
+
+    pub fn memory_peak(&self) -> Option<u64> {
+        self.execution_stats.and_then(|stats| stats.memory_peak)
+    }
+
+    pub fn cpu_time(&self) -> Option<std::time::Duration> {
+        self.execution_stats.and_then(cpu_time)
+    }
 }
 
 struct WhatRanOutputCommandHeader<'r, 'a> {
@@ -235,28 +244,64 @@ pub fn emit_what_ran_entry(
             .and_then(|cmd| cmd.details.as_ref().map(|d| d.stderr.as_ref())),
         _ => None,
     };
-    let duration = match data {
-        Some(buck2_data::span_end_event::Data::ActionExecution(action_exec)) => action_exec
-            .wall_time
-            .as_ref()
-            .map(|prost_types::Duration { seconds, nanos }| {
-                std::time::Duration::new(*seconds as u64, *nanos as u32)
-            }),
-
-        _ => None,
-    };
     output.emit_command(WhatRanOutputCommand {
         reason,
         identity: &identity,
         repro,
         extra,
         std_err,
-        duration,
+        duration: action_wall_time(data),
+        execution_stats: action_execution_stats(data),
     })?;
 
     Ok(())
 }
 
+/// The resource usage of the last command an action ran, if it was measured.
+pub fn action_execution_stats(
+    data: &Option<buck2_data::span_end_event::Data>,
+) -> Option<&buck2_data::CommandExecutionStats> {
+    match data {
+        Some(buck2_data::span_end_event::Data::ActionExecution(action_exec)) => {
+            action_exec.commands.iter().last().and_then(|cmd| {
+                cmd.details
+                    .as_ref()?
+                    .metadata
+                    .as_ref()?
+                    .execution_stats
+                    .as_ref()
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The wall time an action took to execute.
+pub fn action_wall_time(
+    data: &Option<buck2_data::span_end_event::Data>,
+) -> Option<std::time::Duration> {
+    match data {
+        Some(buck2_data::span_end_event::Data::ActionExecution(action_exec)) => action_exec
+            .wall_time
+            .as_ref()
+            .map(|prost_types::Duration { seconds, nanos }| {
+                std::time::Duration::new(*seconds as u64, *nanos as u32)
+            }),
+
+        _ => None,
+    }
+}
+
+/// The user and system CPU time a command used, if either was measured.
+pub fn cpu_time(stats: &buck2_data::CommandExecutionStats) -> Option<std::time::Duration> {
+    match (stats.cpu_time_user_us, stats.cpu_time_system_us) {
+        (None, None) => None,
+        (user, system) => Some(std::time::Duration::from_micros(
+            user.unwrap_or_default() + system.unwrap_or_default(),
+        )),
+    }
+}
+
 /// The reproduction details for this command.
 #[derive(Clone, Copy, Dupe)]
 pub enum CommandReproducer<'a> {
@@ -69,6 +69,9 @@ pub enum CommandExecutionKind {
         env: SortedVectorMap<String, String>,
         fallback_exe: Vec<String>,
     },
+    /// This action was served by the local action cache and not executed.
+    #[display("local_action_cache")]
+    LocalActionCache { digest: ActionDigest },
 }
 
 impl CommandExecutionKind {
@@ -81,6 +84,7 @@ impl CommandExecutionKind {
             Self::Remote { .. } => buck2_data::ActionExecutionKind::Remote,
             Self::ActionCache { .. } => buck2_data::ActionExecutionKind::ActionCache,
             Self::RemoteDepFileCache { .. } => buck2_data::ActionExecutionKind::RemoteDepFileCache,
+            Self::LocalActionCache { .. } => buck2_data::ActionExecutionKind::LocalActionCache,
         }
     }
 
@@ -183,6 +187,12 @@ impl CommandExecutionKind {
                     .collect(),
                 fallback_exe: fallback_exe.to_owned(),
             }),
+
+            Self::LocalActionCache { digest } => {
+                Command::LocalActionCacheHit(buck2_data::LocalActionCacheHit {
+                    action_digest: digest.to_string(),
+                })
+            }
         });
 
         buck2_data::CommandExecutionKind { command }
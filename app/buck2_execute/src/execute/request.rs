@@ -306,6 +306,9 @@ pub struct CommandExecutionRequest {
     prefetch_lossy_stderr: bool,
     /// Whether to cleanup outputs
     pub outputs_cleanup: bool,
+    /// Whether the result of this command may be served from, and stored in, the local action
+    /// cache.
+    cacheable: bool,
     /// What environment variables to inherit from the Buck2 daemon.
     local_environment_inheritance: Option<EnvironmentInheritance>,
     /// Whether this command should override the fallback-only behavior on an hybrid executor and
@@ -347,6 +350,7 @@ impl CommandExecutionRequest {
             working_directory: ProjectRelativePathBuf::default(),
             prefetch_lossy_stderr: false,
             outputs_cleanup: true,
+            cacheable: true,
             local_environment_inheritance: None,
             force_full_hybrid_if_capable: false,
             disable_miniperf: false,
@@ -401,6 +405,11 @@ impl CommandExecutionRequest {
         self
     }
 
+    pub fn with_cacheable(mut self, cacheable: bool) -> Self {
+        self.cacheable = cacheable;
+        self
+    }
+
     pub fn with_remote_dep_file_key(mut self, remote_dep_file_key: &DepFileDigest) -> Self {
         self.remote_dep_file_key = Some(remote_dep_file_key.dupe());
         self
@@ -418,6 +427,10 @@ impl CommandExecutionRequest {
         self.outputs_cleanup
     }
 
+    pub fn cacheable(&self) -> bool {
+        self.cacheable
+    }
+
     pub fn all_args(&self) -> impl Iterator<Item = &String> {
         self.exe.iter().chain(self.args.iter())
     }
@@ -17,4 +17,18 @@ pub struct ExecutorGlobalKnobs {
     /// Whether to emit action keys to execution logs (thos are pretty verbose and omitted by
     /// default).
     pub log_action_keys: bool,
+
+    /// Whether to run local actions in a sandbox that only exposes their declared inputs and
+    /// outputs, and no network (Linux only, requires the forkserver).
+    pub sandbox_local_actions: bool,
+
+    /// Whether to log what local actions are denied in the sandbox, to report it when they fail.
+    /// This makes their path lookups and connections slower, since each of them is reported to the
+    /// forkserver.
+    pub sandbox_log_denials: bool,
+
+    /// Whether to run local actions which fail in the sandbox without being denied anything there
+    /// again outside of it, to report whether the sandbox is what made them fail. This is a
+    /// debugging aid: it runs those failing actions twice.
+    pub sandbox_rerun_failed_actions: bool,
 }
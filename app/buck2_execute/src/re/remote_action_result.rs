@@ -223,6 +223,9 @@ fn convert_perf_counts(
             userspace_events: userspace_counter.map(|p| p.to_proto()),
             kernel_events: kernel_counter.map(|p| p.to_proto()),
             memory_peak: memory_stat.map(|m| m.max_used_mem as u64),
+            cpu_time_user_us: None,
+            cpu_time_system_us: None,
+            oom_killed: None,
         }
     })
 }
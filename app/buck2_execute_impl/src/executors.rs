@@ -13,6 +13,7 @@ pub mod caching;
 pub(crate) mod empty_action_result;
 pub mod hybrid;
 pub mod local;
+pub mod local_action_cache;
 pub mod local_actions_throttle;
 pub mod re;
 pub mod stacked;
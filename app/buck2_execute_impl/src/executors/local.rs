@@ -94,13 +94,34 @@ enum LocalExecutionError {
 
     #[error("Trying to execute a remote-only action on a local executor")]
     RemoteOnlyAction,
+
+    #[error("Sandboxing local actions requires the forkserver")]
+    SandboxWithoutForkserver,
+
+    #[error(
+        "Command failed with exit code {exit_code} in the sandbox, but succeeded outside of it: it \
+        most likely reads undeclared inputs or accesses the network.\nStderr in the sandbox:\n{stderr}"
+    )]
+    #[buck2(tag = ActionSandboxViolation)]
+    SandboxViolation { exit_code: i32, stderr: String },
+
+    #[error(
+        "Command failed with exit code {exit_code} in the sandbox, which denied it:\n{denials}It \
+        most likely reads undeclared inputs or accesses the network.\nStderr in the sandbox:\n{stderr}"
+    )]
+    #[buck2(tag = ActionSandboxViolation)]
+    SandboxDenied {
+        exit_code: i32,
+        denials: String,
+        stderr: String,
+    },
 }
 
 #[derive(Clone)]
 pub struct LocalExecutor {
-    artifact_fs: ArtifactFs,
-    materializer: Arc<dyn Materializer>,
-    blocking_executor: Arc<dyn BlockingExecutor>,
+    pub(crate) artifact_fs: ArtifactFs,
+    pub(crate) materializer: Arc<dyn Materializer>,
+    pub(crate) blocking_executor: Arc<dyn BlockingExecutor>,
     pub(crate) host_sharing_broker: Arc<HostSharingBroker>,
     root: AbsNormPathBuf,
     #[cfg_attr(not(unix), allow(unused))]
@@ -147,6 +168,7 @@ impl LocalExecutor {
         liveliness_observer: impl LivelinessObserver + 'static,
         disable_miniperf: bool,
         action_digest: &'a str,
+        sandbox: Option<&'a CommandExecutionRequest>,
     ) -> impl futures::future::Future<
         Output = buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)>,
     > + Send
@@ -169,18 +191,30 @@ impl LocalExecutor {
                             liveliness_observer,
                             self.knobs.enable_miniperf && !disable_miniperf,
                             action_digest,
+                            sandbox
+                                .map(|request| {
+                                    unix::sandbox(
+                                        &self.artifact_fs,
+                                        request,
+                                        self.knobs.sandbox_log_denials,
+                                    )
+                                })
+                                .transpose()?,
                         )
                         .await
                     }
 
                     #[cfg(not(unix))]
                     {
-                        let _unused = (forkserver, disable_miniperf, action_digest);
+                        let _unused = (forkserver, disable_miniperf, action_digest, sandbox);
                         Err(buck2_error!([], "Forkserver is not supported off-UNIX"))
                     }
                 }
 
                 None => {
+                    if sandbox.is_some() {
+                        return Err(LocalExecutionError::SandboxWithoutForkserver.into());
+                    }
                     let exe = maybe_absolutize_exe(exe, &working_directory)?;
                     let mut cmd = background_command(exe.as_ref());
                     cmd.current_dir(working_directory.as_path());
@@ -374,13 +408,19 @@ impl LocalExecutor {
                     StrOrOsStr::from(build_id),
                 )))
         };
-        let liveliness_observer = manager.inner.liveliness_observer.dupe().and(cancellation);
+        let liveliness_observer = manager
+            .inner
+            .liveliness_observer
+            .dupe()
+            .and(cancellation.dupe());
 
         let (worker, manager) = self
             .initialize_worker(request, manager, dispatcher)
             .boxed()
             .await?;
 
+        let sandboxed = self.knobs.sandbox_local_actions && worker.is_none();
+
         let execution_kind = match worker {
             None => CommandExecutionKind::Local {
                 digest: action_digest.dupe(),
@@ -448,6 +488,7 @@ impl LocalExecutor {
                         liveliness_observer,
                         request.disable_miniperf(),
                         &action_digest.to_string(),
+                        sandboxed.then_some(request),
                     )
                     .await
                 };
@@ -478,12 +519,14 @@ impl LocalExecutor {
             }
         };
 
+        let sandbox_stderr = sandboxed.then(|| String::from_utf8_lossy(&stderr).into_owned());
         let std_streams = CommandStdStreams::Local { stdout, stderr };
 
         match status {
             GatherOutputStatus::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_denials,
             } => {
                 let (outputs, hashing_time) = match self
                     .calculate_and_declare_output_values(request, digest_config)
@@ -496,6 +539,9 @@ impl LocalExecutor {
                     }
                 };
 
+                let oom_killed = execution_stats
+                    .as_ref()
+                    .is_some_and(|stats| stats.oom_killed == Some(true));
                 timing.execution_stats = execution_stats;
                 timing.hashing_duration = hashing_time.hashing_duration;
                 timing.hashed_artifacts_count = hashing_time.hashed_artifacts_count;
@@ -503,6 +549,47 @@ impl LocalExecutor {
                 if exit_code == 0 {
                     manager.success(execution_kind, outputs, std_streams, *timing)
                 } else {
+                    match sandbox_stderr {
+                        Some(stderr) if !sandbox_denials.is_empty() => {
+                            return manager.error(
+                                "sandbox_violation",
+                                LocalExecutionError::SandboxDenied {
+                                    exit_code,
+                                    denials: format_sandbox_denials(&sandbox_denials),
+                                    stderr,
+                                },
+                            );
+                        }
+                        // Not everything the sandbox prevents is recorded (e.g. writing to inputs),
+                        // so running the command again outside of it can still tell.
+                        Some(stderr) if self.knobs.sandbox_rerun_failed_actions => {
+                            let liveliness_observer =
+                                manager.inner.liveliness_observer.dupe().and(cancellation);
+                            match self
+                                .succeeds_outside_sandbox(
+                                    request,
+                                    args,
+                                    iter_env().map(|(k, v)| (k, v.into_os_str())),
+                                    liveliness_observer,
+                                    cancellations,
+                                    &action_digest.to_string(),
+                                )
+                                .boxed()
+                                .await
+                            {
+                                Ok(true) => {
+                                    return manager.error(
+                                        "sandbox_violation",
+                                        LocalExecutionError::SandboxViolation { exit_code, stderr },
+                                    );
+                                }
+                                Ok(false) => {}
+                                Err(e) => return manager.error("sandbox_rerun_failed", e),
+                            }
+                        }
+                        _ => {}
+                    }
+
                     let manager = check_inputs(
                         manager,
                         &self.artifact_fs,
@@ -518,7 +605,35 @@ impl LocalExecutor {
                         std_streams,
                         Some(exit_code),
                         *timing,
-                        None,
+                        if oom_killed {
+                            Some(
+                                "Command was killed for exceeding its memory limit \
+                                (`buck2_resource_control.memory_max_per_action`)"
+                                    .to_owned(),
+                            )
+                        } else if sandboxed
+                            && !self.knobs.sandbox_rerun_failed_actions
+                            && self.knobs.sandbox_log_denials
+                        {
+                            Some(
+                                "Command ran in the local action sandbox, which hides undeclared \
+                                inputs and the network (`buck2.sandbox_local_actions`), but was \
+                                not denied either. Set `buck2.sandbox_rerun_failed_actions = true` \
+                                to check whether it only fails in the sandbox"
+                                    .to_owned(),
+                            )
+                        } else if sandboxed && !self.knobs.sandbox_rerun_failed_actions {
+                            Some(
+                                "Command ran in the local action sandbox, which hides undeclared \
+                                inputs and the network (`buck2.sandbox_local_actions`). Set \
+                                `buck2.sandbox_log_denials = true` to log what it is denied there, \
+                                or `buck2.sandbox_rerun_failed_actions = true` to check whether it \
+                                only fails in the sandbox"
+                                    .to_owned(),
+                            )
+                        } else {
+                            None
+                        },
                     )
                 }
             }
@@ -555,7 +670,50 @@ impl LocalExecutor {
         }
     }
 
-    async fn calculate_and_declare_output_values(
+    /// Runs a command which failed in the sandbox again outside of it, and returns whether it
+    /// succeeded there, meaning that the sandbox is what made it fail. Only used when
+    /// `sandbox_rerun_failed_actions` is set, since it runs the command a second time.
+    async fn succeeds_outside_sandbox(
+        &self,
+        request: &CommandExecutionRequest,
+        args: &[String],
+        env: impl IntoIterator<Item = (impl AsRef<OsStr> + Send, impl AsRef<OsStr> + Send)> + Send,
+        liveliness_observer: impl LivelinessObserver + 'static,
+        cancellations: &CancellationContext,
+        action_digest: &str,
+    ) -> buck2_error::Result<bool> {
+        create_output_dirs(
+            &self.artifact_fs,
+            request,
+            self.materializer.dupe(),
+            self.blocking_executor.dupe(),
+            cancellations,
+        )
+        .await
+        .buck_error_context("Error creating output directories")?;
+
+        let (status, _stdout, _stderr) = self
+            .exec(
+                &args[0],
+                &args[1..],
+                env,
+                request.working_directory(),
+                request.timeout(),
+                request.local_environment_inheritance(),
+                liveliness_observer,
+                request.disable_miniperf(),
+                action_digest,
+                None,
+            )
+            .await?;
+
+        Ok(matches!(
+            status,
+            GatherOutputStatus::Finished { exit_code: 0, .. }
+        ))
+    }
+
+    pub(crate) async fn calculate_and_declare_output_values(
         &self,
         request: &CommandExecutionRequest,
         digest_config: DigestConfig,
@@ -900,6 +1058,21 @@ pub async fn materialize_inputs(
 /// A scratch path discovered during `materialize_inputs`.
 pub struct ScratchPath(Option<ProjectRelativePathBuf>);
 
+/// Lists what a command was denied in the sandbox, one per line, for error messages.
+fn format_sandbox_denials(denials: &[String]) -> String {
+    const MAX_LISTED: usize = 10;
+
+    let mut listed: String = denials
+        .iter()
+        .take(MAX_LISTED)
+        .map(|denial| format!("  {}\n", denial))
+        .collect();
+    if denials.len() > MAX_LISTED {
+        listed.push_str(&format!("  ({} more)\n", denials.len() - MAX_LISTED));
+    }
+    listed
+}
+
 async fn check_inputs(
     manager: CommandExecutionManagerWithClaim,
     artifact_fs: &ArtifactFs,
@@ -1096,6 +1269,7 @@ mod unix {
         liveliness_observer: impl LivelinessObserver + 'static,
         enable_miniperf: bool,
         action_digest: &str,
+        sandbox: Option<buck2_forkserver_proto::Sandbox>,
     ) -> buck2_error::Result<(GatherOutputStatus, Vec<u8>, Vec<u8>)> {
         let exe = exe.as_ref();
 
@@ -1114,6 +1288,7 @@ mod unix {
             std_redirects: None,
             graceful_shutdown_timeout_s: None,
             action_digest: Some(action_digest.to_owned()),
+            sandbox,
         };
         apply_local_execution_environment(&mut req, working_directory, env, env_inheritance);
         forkserver
@@ -1121,6 +1296,52 @@ mod unix {
             .await
     }
 
+    /// The paths a sandboxed command can access: its inputs, read-only, and its outputs and
+    /// scratch directory, writable.
+    pub fn sandbox(
+        artifact_fs: &ArtifactFs,
+        request: &CommandExecutionRequest,
+        log_denials: bool,
+    ) -> buck2_error::Result<buck2_forkserver_proto::Sandbox> {
+        let fs = artifact_fs.fs();
+        let resolver = artifact_fs.buck_out_path_resolver();
+        let abs =
+            |path: &ProjectRelativePath| fs.resolve(path).as_path().as_os_str().as_bytes().to_vec();
+
+        let mut read_only_paths = Vec::new();
+        let mut writable_paths = Vec::new();
+
+        for input in request.inputs() {
+            match input {
+                CommandExecutionInput::Artifact(group) => {
+                    for (artifact, _) in group.iter() {
+                        read_only_paths.push(abs(&artifact.resolve_path(artifact_fs)?));
+                    }
+                }
+                CommandExecutionInput::ActionMetadata(metadata) => {
+                    read_only_paths.push(abs(&resolver.resolve_gen(&metadata.path)));
+                }
+                CommandExecutionInput::ScratchPath(path) => {
+                    writable_paths.push(abs(&resolver.resolve_scratch(path)));
+                }
+            }
+        }
+
+        for output in request.outputs() {
+            let output = output.resolve(artifact_fs);
+            if let Some(path) = output.path_to_create() {
+                writable_paths.push(abs(path));
+            }
+        }
+
+        Ok(buck2_forkserver_proto::Sandbox {
+            root: fs.root().as_path().as_os_str().as_bytes().to_vec(),
+            read_only_paths,
+            writable_paths,
+            log_denials,
+        })
+    }
+
     trait CommandRequestExt {
         fn push_env_directive<D>(&mut self, directive: D)
         where
@@ -1234,6 +1455,7 @@ mod tests {
                 NoopLivelinessObserver::create(),
                 false,
                 "",
+                None,
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
@@ -1271,6 +1493,7 @@ mod tests {
                 NoopLivelinessObserver::create(),
                 false,
                 "",
+                None,
             )
             .await?;
         assert!(matches!(status, GatherOutputStatus::Finished { exit_code, .. } if exit_code == 0));
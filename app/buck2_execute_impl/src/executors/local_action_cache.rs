@@ -0,0 +1,636 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A cache of local action results on disk, keyed by action digest.
+//!
+//! Each entry is a directory named after the action digest, holding copies of the action's
+//! outputs along with their digests and the action's stdout and stderr. Restored outputs whose
+//! digests don't match are treated as a cache miss. Entries are written to a temporary directory and
+//! renamed into place, so a reader never sees a partial entry. Once the cache grows past its size
+//! limit, the least recently used entries are deleted.
+//!
+//! Because the cache lives outside of `buck-out`, it survives `buck2 clean` and is shared by all
+//! the checkouts configured to use the same directory.
+
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_error::BuckErrorContext;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::manager::CommandExecutionManagerExt;
+use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionMetadata;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_futures::cancellation::CancellationContext;
+use dupe::Dupe;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+
+use crate::executors::local::create_output_dirs;
+use crate::executors::local::LocalExecutor;
+
+/// Where entries are assembled before being renamed into place.
+const TEMP_DIR: &str = "tmp";
+const OUTPUTS_DIR: &str = "outputs";
+const STDOUT_FILE: &str = "stdout";
+const STDERR_FILE: &str = "stderr";
+/// The digests of the outputs when they were stored, as written by `output_digests`.
+const DIGESTS_FILE: &str = "digests";
+/// Empty file whose modification time records when the entry was last used.
+const LAST_USED_FILE: &str = "last_used";
+
+/// When evicting, shrink the cache to this fraction of its limit so that the next few stores
+/// don't need to evict again.
+const EVICTION_TARGET_PERCENT: u64 = 90;
+
+#[derive(Allocative)]
+pub struct LocalActionCache {
+    root: AbsNormPathBuf,
+    max_bytes: u64,
+    /// Whether to also cache `local_only` actions and actions which use local resources.
+    cache_local_only: bool,
+    /// Total size of the entries, computed when the first entry is stored.
+    #[allocative(skip)]
+    size_bytes: Mutex<Option<u64>>,
+    #[allocative(skip)]
+    next_temp_id: AtomicU64,
+}
+
+struct CacheEntry {
+    path: AbsNormPathBuf,
+    size_bytes: u64,
+    last_used: SystemTime,
+}
+
+fn name(name: &str) -> &FileName {
+    FileName::unchecked_new(name)
+}
+
+/// Copy a file, directory or symlink, returning the number of bytes of file contents copied.
+fn copy_recursive(src: &AbsNormPath, dst: &AbsNormPath) -> buck2_error::Result<u64> {
+    let metadata = fs_util::symlink_metadata(src)?;
+    if metadata.is_symlink() {
+        fs_util::symlink(fs_util::read_link(src)?, dst)?;
+        Ok(0)
+    } else if metadata.is_dir() {
+        fs_util::create_dir(dst)?;
+        let mut size = 0;
+        for entry in fs_util::read_dir(src)? {
+            let entry = entry?;
+            let filename = entry.file_name();
+            let filename = filename
+                .to_str()
+                .buck_error_context("Filename is not UTF-8")
+                .and_then(|f| FileNameBuf::try_from(f.to_owned()))?;
+            size += copy_recursive(&entry.path(), &dst.join(filename))?;
+        }
+        Ok(size)
+    } else {
+        Ok(fs_util::copy(src, dst)?)
+    }
+}
+
+fn disk_usage(path: &AbsNormPath) -> buck2_error::Result<u64> {
+    let metadata = fs_util::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        let mut size = 0;
+        for entry in fs_util::read_dir(path)? {
+            size += disk_usage(&entry?.path())?;
+        }
+        Ok(size)
+    } else if metadata.is_symlink() {
+        Ok(0)
+    } else {
+        Ok(metadata.len())
+    }
+}
+
+impl LocalActionCache {
+    pub fn new(root: AbsNormPathBuf, max_bytes: u64, cache_local_only: bool) -> Self {
+        Self {
+            root,
+            max_bytes,
+            cache_local_only,
+            size_bytes: Mutex::new(None),
+            next_temp_id: AtomicU64::new(0),
+        }
+    }
+
+    fn entry_path(&self, digest: &ActionDigest) -> AbsNormPathBuf {
+        let name = format!("{}_{}", digest.raw_digest(), digest.size());
+        self.root.join(FileName::unchecked_new(&name))
+    }
+
+    fn output_path(entry: &AbsNormPath, index: usize) -> AbsNormPathBuf {
+        entry
+            .join(name(OUTPUTS_DIR))
+            .join(FileNameBuf::unchecked_new(index.to_string()))
+    }
+
+    pub fn contains(&self, digest: &ActionDigest) -> buck2_error::Result<bool> {
+        Ok(fs_util::try_exists(self.entry_path(digest))?)
+    }
+
+    /// The digests of the outputs stored for an action, or `None` if the entry has none.
+    pub fn stored_digests(&self, digest: &ActionDigest) -> buck2_error::Result<Option<String>> {
+        Ok(fs_util::read_to_string_if_exists(
+            self.entry_path(digest).join(name(DIGESTS_FILE)),
+        )?)
+    }
+
+    /// Delete the entry for an action, e.g. because its outputs don't match their digests.
+    pub fn remove(&self, digest: &ActionDigest) -> buck2_error::Result<()> {
+        let entry = self.entry_path(digest);
+        let size = disk_usage(&entry)?;
+        fs_util::remove_all(&entry)?;
+        if let Some(total) = &mut *self.size_bytes.lock() {
+            *total = total.saturating_sub(size);
+        }
+        Ok(())
+    }
+
+    /// Copy the cached outputs of an action to `outputs`, returning its stdout and stderr, or
+    /// `None` if the action is not in the cache.
+    ///
+    /// `outputs` must be in the same order as when the entry was stored, and must not exist.
+    pub fn restore(
+        &self,
+        digest: &ActionDigest,
+        outputs: &[AbsNormPathBuf],
+    ) -> buck2_error::Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let entry = self.entry_path(digest);
+
+        // Mark the entry as used before copying from it, so that it isn't the next to be evicted.
+        // If that fails, the entry is missing, was evicted since, or is incomplete.
+        let touched = std::fs::File::options()
+            .write(true)
+            .open(entry.join(name(LAST_USED_FILE)))
+            .and_then(|last_used| last_used.set_modified(SystemTime::now()));
+        if touched.is_err() {
+            return Ok(None);
+        }
+
+        for (index, output) in outputs.iter().enumerate() {
+            let cached = Self::output_path(&entry, index);
+            if fs_util::symlink_metadata_if_exists(&cached)?.is_some() {
+                copy_recursive(&cached, output)
+                    .with_buck_error_context(|| format!("Error restoring `{}`", output))?;
+            }
+        }
+        let stdout = fs_util::read(entry.join(name(STDOUT_FILE)))?;
+        let stderr = fs_util::read(entry.join(name(STDERR_FILE)))?;
+        Ok(Some((stdout, stderr)))
+    }
+
+    /// Store the outputs of a successful action and their digests, evicting old entries if the
+    /// cache is over its size limit. Outputs which don't exist are skipped.
+    pub fn store(
+        &self,
+        digest: &ActionDigest,
+        outputs: &[AbsNormPathBuf],
+        output_digests: &str,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) -> buck2_error::Result<()> {
+        let entry = self.entry_path(digest);
+        if fs_util::try_exists(&entry)? {
+            return Ok(());
+        }
+
+        let temp = self
+            .root
+            .join(name(TEMP_DIR))
+            .join(FileNameBuf::unchecked_new(format!(
+                "{}_{}",
+                std::process::id(),
+                self.next_temp_id.fetch_add(1, Ordering::Relaxed)
+            )));
+        if fs_util::try_exists(&temp)? {
+            fs_util::remove_all(&temp)?;
+        }
+        fs_util::create_dir_all(temp.join(name(OUTPUTS_DIR)))?;
+
+        let mut size = (stdout.len() + stderr.len()) as u64;
+        for (index, output) in outputs.iter().enumerate() {
+            if fs_util::symlink_metadata_if_exists(output)?.is_some() {
+                size += copy_recursive(output, &Self::output_path(&temp, index))
+                    .with_buck_error_context(|| format!("Error storing `{}`", output))?;
+            }
+        }
+        fs_util::write(temp.join(name(STDOUT_FILE)), stdout)?;
+        fs_util::write(temp.join(name(STDERR_FILE)), stderr)?;
+        fs_util::write(temp.join(name(DIGESTS_FILE)), output_digests)?;
+        fs_util::write(temp.join(name(LAST_USED_FILE)), b"")?;
+
+        if let Err(e) = fs_util::rename(&temp, &entry) {
+            fs_util::remove_all(&temp)?;
+            // Another daemon may have stored the same action concurrently.
+            if fs_util::try_exists(&entry)? {
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+
+        self.record_stored(size)
+    }
+
+    fn record_stored(&self, size: u64) -> buck2_error::Result<()> {
+        let mut size_bytes = self.size_bytes.lock();
+        let total = match *size_bytes {
+            Some(total) => total + size,
+            // The entry we just stored is included in the scan.
+            None => self.entries()?.iter().map(|e| e.size_bytes).sum(),
+        };
+        let total = if total > self.max_bytes {
+            self.evict()?
+        } else {
+            total
+        };
+        *size_bytes = Some(total);
+        Ok(())
+    }
+
+    fn entries(&self) -> buck2_error::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        let Some(read_dir) = fs_util::read_dir_if_exists(&self.root)? else {
+            return Ok(entries);
+        };
+        for entry in read_dir {
+            let entry = entry?;
+            if entry.file_name() == TEMP_DIR {
+                continue;
+            }
+            let path = entry.path();
+            let last_used = fs_util::metadata(path.join(name(LAST_USED_FILE)))
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push(CacheEntry {
+                size_bytes: disk_usage(&path)?,
+                path,
+                last_used,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Delete the least recently used entries until the cache is comfortably under its limit,
+    /// returning the remaining size.
+    fn evict(&self) -> buck2_error::Result<u64> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|e| e.last_used);
+
+        let target = self.max_bytes / 100 * EVICTION_TARGET_PERCENT;
+        let mut total: u64 = entries.iter().map(|e| e.size_bytes).sum();
+        for entry in entries {
+            if total <= target {
+                break;
+            }
+            fs_util::remove_all(&entry.path)?;
+            total -= entry.size_bytes;
+        }
+        Ok(total)
+    }
+}
+
+/// One line per output the action produced, with its digest, or `-` for symlinks.
+fn output_digests(outputs: &IndexMap<CommandExecutionOutput, ArtifactValue>) -> String {
+    outputs
+        .values()
+        .map(|value| match value.digest() {
+            Some(digest) => format!("{}\n", digest),
+            None => "-\n".to_owned(),
+        })
+        .collect()
+}
+
+/// Wraps the local executor, serving actions from the local action cache when possible and
+/// storing the outputs of successful local executions in it.
+pub struct LocalActionCacheExecutor {
+    pub inner: LocalExecutor,
+    pub cache: Arc<LocalActionCache>,
+}
+
+impl LocalActionCacheExecutor {
+    fn output_paths(&self, command: &PreparedCommand<'_, '_>) -> Vec<AbsNormPathBuf> {
+        let artifact_fs = &self.inner.artifact_fs;
+        command
+            .request
+            .outputs()
+            .map(|output| {
+                artifact_fs
+                    .fs()
+                    .resolve(output.resolve(artifact_fs).into_path())
+            })
+            .collect()
+    }
+
+    /// Actions which don't clean up their outputs may read them, and persistent workers keep
+    /// state between actions, so what they produce depends on more than their action digest.
+    /// `local_only` actions and actions which use local resources usually depend on the machine
+    /// they run on, so are only cached when configured to.
+    fn is_cacheable(&self, command: &PreparedCommand<'_, '_>) -> bool {
+        let request = command.request;
+        if !request.cacheable() || !request.outputs_cleanup || request.worker().is_some() {
+            return false;
+        }
+        self.cache.cache_local_only
+            || (!request.executor_preference().requires_local()
+                && request.required_local_resources().is_empty())
+    }
+
+    async fn maybe_restore(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let digest = command.prepared_action.digest();
+        match self.cache.contains(&digest) {
+            Ok(true) => {}
+            Ok(false) => return ControlFlow::Continue(manager),
+            Err(e) => {
+                tracing::warn!("Error checking the local action cache: {:#}", e);
+                return ControlFlow::Continue(manager);
+            }
+        }
+
+        let start = Instant::now();
+        let mut timing = CommandExecutionMetadata::default();
+        let manager = manager.with_execution_kind(CommandExecutionKind::LocalActionCache {
+            digest: digest.dupe(),
+        });
+
+        if let Err(e) = create_output_dirs(
+            &self.inner.artifact_fs,
+            command.request,
+            self.inner.materializer.dupe(),
+            self.inner.blocking_executor.dupe(),
+            cancellations,
+        )
+        .await
+        {
+            return ControlFlow::Break(manager.error("prepare_output_dirs_failed", e));
+        }
+
+        let outputs = self.output_paths(command);
+        let restored = self
+            .inner
+            .blocking_executor
+            .execute_io_inline(|| self.cache.restore(&digest, &outputs))
+            .await;
+        let (stdout, stderr) = match restored {
+            Ok(Some(std_streams)) => std_streams,
+            // The entry may have been evicted since we checked, in which case we run the action.
+            Ok(None) => return ControlFlow::Continue(manager),
+            Err(e) => {
+                tracing::warn!(
+                    "Error restoring action `{}` from the local action cache: {:#}",
+                    digest,
+                    e
+                );
+                return ControlFlow::Continue(manager);
+            }
+        };
+
+        let (outputs, hashing_info) = match self
+            .inner
+            .calculate_and_declare_output_values(command.request, command.digest_config)
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                return ControlFlow::Break(manager.error("calculate_output_values_failed", e));
+            }
+        };
+
+        // The entry may have been corrupted since it was stored, in which case we delete it and
+        // run the action, which replaces the restored outputs.
+        let expected = self
+            .inner
+            .blocking_executor
+            .execute_io_inline(|| self.cache.stored_digests(&digest))
+            .await;
+        if !matches!(&expected, Ok(Some(expected)) if *expected == output_digests(&outputs)) {
+            tracing::warn!(
+                "Outputs of action `{}` restored from the local action cache don't match their digests, running it instead",
+                digest
+            );
+            if let Err(e) = self
+                .inner
+                .blocking_executor
+                .execute_io_inline(|| self.cache.remove(&digest))
+                .await
+            {
+                tracing::warn!(
+                    "Error removing action `{}` from the local action cache: {:#}",
+                    digest,
+                    e
+                );
+            }
+            return ControlFlow::Continue(manager);
+        }
+
+        timing.wall_time = start.elapsed();
+        timing.hashing_duration = hashing_info.hashing_duration;
+        timing.hashed_artifacts_count = hashing_info.hashed_artifacts_count;
+
+        let manager = manager.claim().await;
+        ControlFlow::Break(manager.success(
+            CommandExecutionKind::LocalActionCache { digest },
+            outputs,
+            CommandStdStreams::Local { stdout, stderr },
+            timing,
+        ))
+    }
+
+    async fn store(&self, command: &PreparedCommand<'_, '_>, result: &CommandExecutionResult) {
+        let CommandStdStreams::Local { stdout, stderr } = &result.report.std_streams else {
+            return;
+        };
+        let digest = command.prepared_action.digest();
+        let outputs = self.output_paths(command);
+        let output_digests = output_digests(&result.outputs);
+        if let Err(e) = self
+            .inner
+            .blocking_executor
+            .execute_io_inline(|| {
+                self.cache
+                    .store(&digest, &outputs, &output_digests, stdout, stderr)
+            })
+            .await
+        {
+            tracing::warn!(
+                "Error storing action `{}` in the local action cache: {:#}",
+                digest,
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for LocalActionCacheExecutor {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        if !self.is_cacheable(command) {
+            return self.inner.exec_cmd(command, manager, cancellations).await;
+        }
+
+        let manager = match self.maybe_restore(command, manager, cancellations).await {
+            ControlFlow::Break(result) => return result,
+            ControlFlow::Continue(manager) => manager,
+        };
+
+        let result = self.inner.exec_cmd(command, manager, cancellations).await;
+        if result.was_success() && result.was_locally_executed() {
+            self.store(command, &result).await;
+        }
+        result
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
+    fn digest(n: u64) -> ActionDigest {
+        ActionDigest::new_sha1([n as u8; 20], n)
+    }
+
+    fn write(path: &AbsNormPath, contents: &str) {
+        fs_util::create_dir_all(path.parent().unwrap()).unwrap();
+        fs_util::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_store_and_restore() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let cache = LocalActionCache::new(root.join(name("cache")), 1 << 20, false);
+
+        let out_file = root.join(name("out_file"));
+        let out_dir = root.join(name("out_dir"));
+        let missing = root.join(name("missing"));
+        write(&out_file, "file");
+        write(&out_dir.join(name("nested")), "nested");
+
+        let outputs = vec![out_file.clone(), missing.clone(), out_dir.clone()];
+        assert_eq!(None, cache.restore(&digest(1), &outputs)?);
+        cache.store(&digest(1), &outputs, "a\n-\n", b"out", b"err")?;
+        assert!(cache.contains(&digest(1))?);
+        assert!(!cache.contains(&digest(2))?);
+
+        fs_util::remove_all(&out_file)?;
+        fs_util::remove_all(&out_dir)?;
+        assert_eq!(
+            Some((b"out".to_vec(), b"err".to_vec())),
+            cache.restore(&digest(1), &outputs)?
+        );
+        assert_eq!("file", fs_util::read_to_string(&out_file)?);
+        assert_eq!(
+            "nested",
+            fs_util::read_to_string(out_dir.join(name("nested")))?
+        );
+        assert!(!fs_util::try_exists(&missing)?);
+        assert_eq!(Some("a\n-\n".to_owned()), cache.stored_digests(&digest(1))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_without_last_used_is_a_miss() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let cache = LocalActionCache::new(root.join(name("cache")), 1 << 20, false);
+
+        let output = root.join(name("out"));
+        write(&output, "out");
+        cache.store(&digest(1), &[output.clone()], "a\n", b"", b"")?;
+        fs_util::remove_file(cache.entry_path(&digest(1)).join(name(LAST_USED_FILE)))?;
+        fs_util::remove_all(&output)?;
+
+        assert_eq!(None, cache.restore(&digest(1), &[output.clone()])?);
+        assert!(!fs_util::try_exists(&output)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        let cache = LocalActionCache::new(root.join(name("cache")), 1 << 20, false);
+
+        let output = root.join(name("out"));
+        write(&output, "out");
+        cache.store(&digest(1), &[output], "a\n", b"", b"")?;
+        cache.remove(&digest(1))?;
+        assert!(!cache.contains(&digest(1))?);
+        assert_eq!(None, cache.stored_digests(&digest(1))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() -> buck2_error::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let root = temp.path().root();
+        // Room for two entries of 100 bytes of output each.
+        let cache = LocalActionCache::new(root.join(name("cache")), 250, false);
+
+        let output = root.join(name("out"));
+        let outputs = vec![output.clone()];
+        let store = |n| -> buck2_error::Result<()> {
+            write(&output, &"x".repeat(100));
+            cache.store(&digest(n), &outputs, "", b"", b"")?;
+            fs_util::remove_all(&output)?;
+            Ok(())
+        };
+
+        store(1)?;
+        store(2)?;
+        // Use the first entry, so that the second one is the least recently used. Make sure the
+        // timestamps differ even on filesystems with coarse ones.
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.restore(&digest(1), &outputs)?;
+        fs_util::remove_all(&output)?;
+        store(3)?;
+
+        assert!(cache.contains(&digest(1))?);
+        assert!(!cache.contains(&digest(2))?);
+        assert!(cache.contains(&digest(3))?);
+        Ok(())
+    }
+}
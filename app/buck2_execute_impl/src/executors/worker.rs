@@ -155,6 +155,7 @@ fn spawn_via_forkserver(
             }),
             graceful_shutdown_timeout_s,
             action_digest: None,
+            sandbox: None,
         };
         apply_local_execution_environment(&mut req, &working_directory, env, None);
         let res = forkserver
@@ -468,6 +469,7 @@ impl WorkerHandle {
                                 GatherOutputStatus::Finished {
                                     exit_code: exec_response.exit_code,
                                     execution_stats: None,
+                                    sandbox_denials: Vec::new(),
                                 },
                                 vec![],
                                 exec_response.stderr.into(),
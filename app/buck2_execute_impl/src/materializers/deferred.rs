@@ -11,6 +11,7 @@ pub mod clean_stale;
 mod extension;
 mod file_tree;
 mod io_handler;
+pub mod object_store;
 mod subscriptions;
 
 #[cfg(test)]
@@ -111,10 +112,13 @@ use tracing::instrument;
 use crate::materializers::deferred::clean_stale::CleanResult;
 use crate::materializers::deferred::clean_stale::CleanStaleArtifactsCommand;
 use crate::materializers::deferred::clean_stale::CleanStaleConfig;
+use crate::materializers::deferred::clean_stale::EvictionConfig;
 use crate::materializers::deferred::extension::ExtensionCommand;
 use crate::materializers::deferred::file_tree::FileTree;
 use crate::materializers::deferred::io_handler::DefaultIoHandler;
 use crate::materializers::deferred::io_handler::IoHandler;
+use crate::materializers::deferred::object_store::ObjectStore;
+use crate::materializers::deferred::object_store::ObjectStoreMode;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptionOperation;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptions;
 use crate::materializers::sqlite::MaterializerState;
@@ -194,6 +198,8 @@ pub struct DeferredMaterializerConfigs {
     pub update_access_times: AccessTimesUpdates,
     pub verbose_materializer_log: bool,
     pub clean_stale_config: Option<CleanStaleConfig>,
+    pub eviction_config: Option<EvictionConfig>,
+    pub object_store: Option<ObjectStoreMode>,
     pub disable_eager_write_dispatch: bool,
 }
 
@@ -658,9 +664,49 @@ enum ArtifactMaterializationStage {
         /// Should not be deleted without invalidating DICE nodes, which currently
         /// means killing the daemon.
         active: bool,
+        /// Set for active artifacts that were downloaded from the CAS. Those can be deleted
+        /// without invalidating DICE nodes by returning them to `Declared`.
+        cas_source: Option<CasSource>,
     },
 }
 
+/// How to download an active artifact from the CAS again. DICE still holds the artifact's value,
+/// so keeping its entry here doesn't cost much memory.
+#[derive(Clone, Dupe)]
+struct CasSource {
+    entry: ActionDirectoryEntry<ActionSharedDirectory>,
+    info: Arc<CasDownloadInfo>,
+}
+
+impl CasSource {
+    fn new(
+        entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+        method: &ArtifactMaterializationMethod,
+    ) -> Option<Self> {
+        match method {
+            ArtifactMaterializationMethod::CasDownload { info } => Some(Self {
+                entry: entry.dupe(),
+                info: info.dupe(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether the CAS is still expected to have the artifact, so it can be downloaded again.
+    fn is_available(&self) -> bool {
+        self.info.origin.guaranteed_by_action_cache()
+    }
+
+    fn redeclare(&self) -> ArtifactMaterializationStage {
+        ArtifactMaterializationStage::Declared {
+            entry: self.entry.dupe(),
+            method: Arc::new(ArtifactMaterializationMethod::CasDownload {
+                info: self.info.dupe(),
+            }),
+        }
+    }
+}
+
 /// Different ways to materialize the files of an artifact. Some artifacts need
 /// to be fetched from the CAS, others copied locally.
 #[derive(Debug, Display)]
@@ -1020,6 +1066,15 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
 
         let tree = ArtifactTree::initialize(sqlite_state);
 
+        let object_store = match configs.object_store {
+            Some(mode) => {
+                let object_store = ObjectStore::new(&buck_out_path, mode);
+                object_store.check_links(&fs)?;
+                Some(object_store)
+            }
+            None => None,
+        };
+
         let io = Arc::new(DefaultIoHandler::new(
             fs,
             digest_config,
@@ -1027,6 +1082,7 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
             re_client_manager,
             io_executor,
             http_client,
+            object_store,
         ));
 
         let command_processor = {
@@ -1072,6 +1128,7 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
                     access_time_update_max_buffer_size,
                     configs.update_access_times,
                     configs.clean_stale_config,
+                    configs.eviction_config,
                 ));
             }
         })
@@ -1126,6 +1183,8 @@ struct CommandStream<T: 'static> {
     refresh_ttl_ticker: Option<Interval>,
     io_buffer_ticker: Interval,
     clean_stale_ticker: Option<Interval>,
+    eviction_ticker: Option<Interval>,
+    /// Either a clean stale or an eviction, which never run concurrently.
     clean_stale_fut: Option<BoxFuture<'static, buck2_error::Result<CleanResult>>>,
 }
 
@@ -1135,6 +1194,7 @@ enum Op<T: 'static> {
     RefreshTtls,
     Tick,
     CleanStaleRequest,
+    EvictionRequest,
 }
 
 impl<T: 'static> Stream for CommandStream<T> {
@@ -1169,9 +1229,16 @@ impl<T: 'static> Stream for CommandStream<T> {
             if std::pin::pin!(fut).poll(cx).is_ready() {
                 *this.clean_stale_fut = None;
             }
-        } else if let Some(ticker) = this.clean_stale_ticker.as_mut() {
-            if ticker.poll_tick(cx).is_ready() {
-                return Poll::Ready(Some(Op::CleanStaleRequest));
+        } else {
+            if let Some(ticker) = this.clean_stale_ticker.as_mut() {
+                if ticker.poll_tick(cx).is_ready() {
+                    return Poll::Ready(Some(Op::CleanStaleRequest));
+                }
+            }
+            if let Some(ticker) = this.eviction_ticker.as_mut() {
+                if ticker.poll_tick(cx).is_ready() {
+                    return Poll::Ready(Some(Op::EvictionRequest));
+                }
             }
         }
 
@@ -1236,6 +1303,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
         access_time_update_max_buffer_size: usize,
         access_time_updates: AccessTimesUpdates,
         clean_stale_config: Option<CleanStaleConfig>,
+        eviction_config: Option<EvictionConfig>,
     ) {
         let MaterializerReceiver {
             high_priority,
@@ -1259,6 +1327,16 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
             )
         });
 
+        let eviction_ticker = eviction_config.as_ref().map(|eviction_config| {
+            let mut ticker = tokio::time::interval_at(
+                tokio::time::Instant::now() + eviction_config.check_period,
+                eviction_config.check_period,
+            );
+            // A check skipped because a clean is running can happen as soon as it finishes.
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker
+        });
+
         let io_buffer_ticker = tokio::time::interval(std::time::Duration::from_secs(5));
 
         let mut stream = CommandStream {
@@ -1267,6 +1345,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
             refresh_ttl_ticker,
             io_buffer_ticker,
             clean_stale_ticker,
+            eviction_ticker,
             clean_stale_fut: None,
         };
 
@@ -1333,6 +1412,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                             keep_since_time: chrono::Utc::now() - config.artifact_ttl,
                             dry_run: config.dry_run,
                             tracked_only: false,
+                            evict_to_bytes: None,
                             dispatcher,
                         };
                         stream.clean_stale_fut = Some(cmd.create_clean_fut(&mut self, None));
@@ -1346,6 +1426,31 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                         .unwrap();
                     }
                 }
+                Op::EvictionRequest => {
+                    if let Some(config) = eviction_config.as_ref() {
+                        if clean_stale::materialized_bytes(&self.tree) > config.max_bytes {
+                            let dispatcher = self.daemon_dispatcher.dupe();
+                            // Evicted artifacts are re-materialized when they are next declared.
+                            let cmd = CleanStaleArtifactsCommand {
+                                keep_since_time: chrono::Utc::now(),
+                                dry_run: config.dry_run,
+                                tracked_only: true,
+                                evict_to_bytes: Some(config.target_bytes),
+                                dispatcher,
+                            };
+                            stream.clean_stale_fut = Some(cmd.create_clean_fut(&mut self, None));
+                        }
+                    } else {
+                        // This should never happen
+                        soft_error!(
+                            "eviction_no_config",
+                            buck2_error!([], "eviction scheduled without being configured")
+                                .into(),
+                            quiet: true
+                        )
+                        .unwrap();
+                    }
+                }
             }
         }
     }
@@ -1569,6 +1674,37 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
             "materializer_declare_existing_error",
         );
 
+        let version = self.version_tracker.next();
+        // Files produced locally are deduplicated against the object store. Until that's done,
+        // anything touching the artifact waits for it, like for a materialization.
+        let processing = match (self.io.object_store(), self.sqlite_db.as_mut()) {
+            (Some(_), Some(sqlite_db)) => {
+                if let Err(e) = sqlite_db
+                    .object_refs_table()
+                    .insert(path, ObjectStore::entry_objects(value.entry()))
+                {
+                    soft_error!(
+                        "materializer_object_refs_error",
+                        e.context(format!("{}", self.log_buffer)).into(),
+                        quiet: true
+                    )
+                    .unwrap();
+                }
+                let future = self.io.ingest_objects(
+                    path.to_owned(),
+                    value.entry().dupe(),
+                    version,
+                    self.command_sender.dupe(),
+                    self.cancellations,
+                );
+                Processing::Active {
+                    future: ProcessingFuture::Materializing(future.shared()),
+                    version,
+                }
+            }
+            _ => Processing::Done(version),
+        };
+
         self.tree.insert(
             path.iter().map(|f| f.to_owned()),
             Box::new(ArtifactMaterializationData {
@@ -1577,8 +1713,9 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                     metadata,
                     last_access_time: Utc::now(),
                     active: true,
+                    cas_source: None,
                 },
-                processing: Processing::Done(self.version_tracker.next()),
+                processing,
             }),
         );
     }
@@ -1625,6 +1762,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                             metadata: metadata.dupe(),
                             last_access_time: *last_access_time,
                             active: true,
+                            cas_source: CasSource::new(value.entry(), &method),
                         };
                         data.deps = deps;
 
@@ -1762,9 +1900,9 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
 
         match &mut data.stage {
             ArtifactMaterializationStage::Materialized {
-                metadata: _,
                 last_access_time,
                 active,
+                ..
             } => {
                 // Treat this case much like a `declare_existing`
                 *active = true;
@@ -2087,10 +2225,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                             tracing::debug!("artifact is already materialized");
                             None
                         }
-                        ArtifactMaterializationStage::Declared {
-                            entry,
-                            method: _method,
-                        } => {
+                        ArtifactMaterializationStage::Declared { entry, method } => {
                             let metadata = ArtifactMetadata::new(entry);
                             // NOTE: We only insert this artifact if there isn't an in-progress cleanup
                             // future on this path.
@@ -2104,10 +2239,34 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                                 "materializer_finished_error",
                             );
 
+                            // Files downloaded from CAS were linked from the object store.
+                            if let (
+                                Some(_),
+                                Some(sqlite_db),
+                                ArtifactMaterializationMethod::CasDownload { .. },
+                            ) = (
+                                self.io.object_store(),
+                                self.sqlite_db.as_mut(),
+                                method.as_ref(),
+                            ) {
+                                if let Err(e) = sqlite_db
+                                    .object_refs_table()
+                                    .insert(&artifact_path, ObjectStore::entry_objects(entry))
+                                {
+                                    soft_error!(
+                                        "materializer_object_refs_error",
+                                        e.context(format!("{}", self.log_buffer)).into(),
+                                        quiet: true
+                                    )
+                                    .unwrap();
+                                }
+                            }
+
                             Some(ArtifactMaterializationStage::Materialized {
                                 metadata,
                                 last_access_time: timestamp,
                                 active: true,
+                                cas_source: CasSource::new(entry, method),
                             })
                         }
                     };
@@ -2173,6 +2332,7 @@ impl ArtifactTree {
                             metadata,
                             last_access_time,
                             active: false,
+                            cas_source: None,
                         },
                         processing: Processing::Done(Version(0)),
                     }),
@@ -2335,6 +2495,10 @@ impl ArtifactTree {
         // the underlying nodes, because when materialization finishes we'll check the version
         // number.
         if let Some(sqlite_db) = sqlite_db {
+            sqlite_db
+                .object_refs_table()
+                .delete(&invalidated_paths)
+                .buck_error_context("Error invalidating paths in materializer state")?;
             sqlite_db
                 .materializer_state_table()
                 .delete(invalidated_paths)
@@ -8,6 +8,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -27,6 +28,7 @@ use buck2_core::soft_error;
 use buck2_data::CleanStaleResultKind;
 use buck2_data::CleanStaleStats;
 use buck2_error::buck2_error;
+use buck2_error::internal_error;
 use buck2_error::BuckErrorContext;
 use buck2_error::ErrorTag;
 use buck2_events::dispatch::EventDispatcher;
@@ -48,7 +50,9 @@ use tracing::error;
 use crate::materializers::deferred::extension::ExtensionCommand;
 use crate::materializers::deferred::io_handler::IoHandler;
 use crate::materializers::deferred::join_all_existing_futs;
+use crate::materializers::deferred::object_store::ObjectStore;
 use crate::materializers::deferred::ArtifactMaterializationData;
+use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
 use crate::materializers::deferred::ArtifactTree;
 use crate::materializers::deferred::DeferredMaterializerCommandProcessor;
@@ -59,6 +63,10 @@ pub struct CleanStaleArtifactsCommand {
     pub keep_since_time: DateTime<Utc>,
     pub dry_run: bool,
     pub tracked_only: bool,
+    /// If set, instead of cleaning everything not accessed since `keep_since_time`, evict the
+    /// least recently used artifacts until the materialized artifacts take at most this many
+    /// bytes. Only tracked artifacts not accessed since `keep_since_time` are evicted.
+    pub evict_to_bytes: Option<u64>,
     pub dispatcher: EventDispatcher,
 }
 
@@ -121,6 +129,7 @@ impl From<CleanResult> for buck2_cli_proto::CleanStaleResponse {
 fn create_result(
     result: Result<CleanResult, buck2_error::Error>,
     trace_id: Option<TraceId>,
+    evict_to_bytes: Option<u64>,
     total_duration_s: u64,
 ) -> buck2_data::CleanStaleResult {
     let (kind, mut stats, error) = match result {
@@ -132,12 +141,29 @@ fn create_result(
         ),
     };
     stats.total_duration_s = total_duration_s;
+    let evict_to_bytes_unmet = match evict_to_bytes {
+        Some(evict_to_bytes)
+            if kind == CleanStaleResultKind::Finished && stats.retained_bytes > evict_to_bytes =>
+        {
+            tracing::warn!(
+                "Materialized artifacts still take {} bytes after eviction, more than the {} \
+                bytes evicted down to, because the rest are in use by the daemon and can't be \
+                downloaded again",
+                stats.retained_bytes,
+                evict_to_bytes,
+            );
+            true
+        }
+        _ => false,
+    };
     buck2_data::CleanStaleResult {
         kind: kind.into(),
         stats: Some(stats),
         metadata: metadata::collect(),
         error,
         command_uuid: trace_id.map(|id| id.to_string()),
+        evict_to_bytes,
+        evict_to_bytes_unmet,
     }
 }
 
@@ -158,6 +184,7 @@ impl CleanStaleArtifactsCommand {
         let start_time = Instant::now();
         let pending_result = self.create_pending_clean_result(processor);
         let dispatcher_dup = self.dispatcher.dupe();
+        let evict_to_bytes = self.evict_to_bytes;
         async move {
             let result = match pending_result {
                 Ok(res) => match res {
@@ -170,6 +197,7 @@ impl CleanStaleArtifactsCommand {
             let result_event: buck2_data::CleanStaleResult = create_result(
                 result.clone(),
                 trace_id,
+                evict_to_bytes,
                 (Instant::now() - start_time).as_secs(),
             );
             dispatcher_dup.instant_event(result_event);
@@ -185,32 +213,23 @@ impl CleanStaleArtifactsCommand {
         let (liveliness_observer, liveliness_guard) = LivelinessGuard::create_sync();
         *processor.command_sender.clean_guard.lock() = Some(liveliness_guard);
 
-        if let Some(sqlite_db) = processor.sqlite_db.as_mut() {
-            if !processor.defer_write_actions {
-                Ok(CleanStaleResultKind::SkippedDeferWriteDisabled.into())
-            } else {
-                self.scan_and_create_clean_fut(
-                    &mut processor.tree,
-                    sqlite_db,
-                    &processor.io,
-                    processor.cancellations,
-                    liveliness_observer.clone(),
-                )
-            }
-        } else {
+        if processor.sqlite_db.is_none() {
             Ok(CleanStaleResultKind::SkippedSqliteDisabled.into())
+        } else if !processor.defer_write_actions {
+            Ok(CleanStaleResultKind::SkippedDeferWriteDisabled.into())
+        } else {
+            self.scan_and_create_clean_fut(processor, liveliness_observer.clone())
         }
     }
 
     fn scan_and_create_clean_fut<T: IoHandler>(
         &self,
-        tree: &mut ArtifactTree,
-        sqlite_db: &mut MaterializerStateSqliteDb,
-        io: &Arc<T>,
-        cancellations: &'static CancellationContext,
+        processor: &mut DeferredMaterializerCommandProcessor<T>,
         liveliness_observer: Arc<dyn LivelinessObserverSync>,
     ) -> buck2_error::Result<PendingCleanResult> {
         let start_time = Instant::now();
+        let io = &processor.io;
+        let tree = &processor.tree;
         let gen_path = io
             .buck_out_path()
             .join(ProjectRelativePathBuf::unchecked_new("gen".to_owned()));
@@ -221,7 +240,9 @@ impl CleanStaleArtifactsCommand {
         tracing::trace!(gen_dir = %gen_dir, "Scanning");
 
         let mut found_paths = Vec::new();
-        if self.tracked_only {
+        if let Some(evict_to_bytes) = self.evict_to_bytes {
+            find_lru_evictable(tree, self.keep_since_time, evict_to_bytes, &mut found_paths)
+        } else if self.tracked_only {
             find_stale_tracked_only(tree, self.keep_since_time, &mut found_paths)?
         } else {
             let gen_subtree = tree
@@ -276,9 +297,9 @@ impl CleanStaleArtifactsCommand {
         if stats.stale_artifact_count + stats.retained_artifact_count == 0 {
             // Just need to know if any entries exist, could be a simpler query.
             // Checking the db directly in case tree is somehow not in sync.
-            let materializer_state = sqlite_db
+            let materializer_state = sqlite_db_mut(&mut processor.sqlite_db)?
                 .materializer_state_table()
-                .read_all(io.digest_config())?;
+                .read_all(processor.io.digest_config())?;
 
             // Entries in the db should have been found in buck-out, return error and skip cleaning untracked artifacts.
             if !materializer_state.is_empty() {
@@ -301,10 +322,7 @@ impl CleanStaleArtifactsCommand {
             Ok(PendingCleanResult::Pending(create_clean_fut(
                 found_paths,
                 stats,
-                tree,
-                sqlite_db,
-                io,
-                cancellations,
+                processor,
                 liveliness_observer,
             )?))
         }
@@ -326,7 +344,7 @@ fn stats_for_paths(paths: &Vec<FoundPath>) -> buck2_data::CleanStaleStats {
                 stats.untracked_artifact_count += 1;
                 stats.untracked_bytes += *size;
             }
-            FoundPath::Stale(_, size) => {
+            FoundPath::Stale(_, size) | FoundPath::Evicted(_, size) => {
                 stats.stale_artifact_count += 1;
                 stats.stale_bytes += *size;
             }
@@ -339,16 +357,24 @@ fn stats_for_paths(paths: &Vec<FoundPath>) -> buck2_data::CleanStaleStats {
     stats
 }
 
+fn sqlite_db_mut(
+    sqlite_db: &mut Option<MaterializerStateSqliteDb>,
+) -> buck2_error::Result<&mut MaterializerStateSqliteDb> {
+    sqlite_db
+        .as_mut()
+        .internal_error("clean stale requires the sqlite materializer state")
+}
+
 fn create_clean_fut<T: IoHandler>(
     found_paths: Vec<FoundPath>,
     mut stats: CleanStaleStats,
-    tree: &mut ArtifactTree,
-    sqlite_db: &mut MaterializerStateSqliteDb,
-    io: &Arc<T>,
-    cancellations: &'static CancellationContext,
+    processor: &mut DeferredMaterializerCommandProcessor<T>,
     liveliness_observer: Arc<dyn LivelinessObserverSync>,
 ) -> buck2_error::Result<BoxFuture<'static, buck2_error::Result<CleanResult>>> {
-    let io = io.dupe();
+    let io = processor.io.dupe();
+    let cancellations = processor.cancellations;
+    let tree = &mut processor.tree;
+    let sqlite_db = sqlite_db_mut(&mut processor.sqlite_db)?;
 
     let paths_to_invalidate: Vec<ProjectRelativePathBuf> = found_paths
         .iter()
@@ -359,18 +385,89 @@ fn create_clean_fut<T: IoHandler>(
         .collect();
 
     let existing_clean_futs =
-        tree.invalidate_paths_and_collect_futures(paths_to_invalidate, Some(sqlite_db))?;
+        tree.invalidate_paths_and_collect_futures(paths_to_invalidate, Some(&mut *sqlite_db))?;
+
+    // Evicted artifacts are still used by DICE, so they are declared again rather than removed
+    // from the tree, and downloaded again when they are needed. Their declaration's cleaning
+    // future deletes them.
+    let mut evicted_paths = Vec::new();
+    let mut evicted_futs = Vec::new();
+    for (path, size) in found_paths.iter().filter_map(|x| match x {
+        FoundPath::Evicted(p, size) => Some((p, *size)),
+        _ => None,
+    }) {
+        let data = tree
+            .prefix_get_mut(&mut path.iter())
+            .internal_error("Evicted artifact is not in the tree")?;
+        let stage = match &data.stage {
+            ArtifactMaterializationStage::Materialized {
+                cas_source: Some(cas_source),
+                ..
+            } => cas_source.redeclare(),
+            _ => return Err(internal_error!("Evicted artifact has no CAS source")),
+        };
+        let version = processor.version_tracker.next();
+        let future = io
+            .clean_path(
+                path.clone(),
+                version,
+                processor.command_sender.dupe(),
+                cancellations,
+            )
+            .shared();
+        tracing::trace!(path = %path, "redeclaring evicted artifact");
+        data.stage = stage;
+        data.processing = super::Processing::Active {
+            future: super::ProcessingFuture::Cleaning(future.clone()),
+            version,
+        };
+        evicted_paths.push(path.clone());
+        evicted_futs.push((future, size));
+    }
+    if !evicted_paths.is_empty() {
+        sqlite_db
+            .object_refs_table()
+            .delete(&evicted_paths)
+            .buck_error_context("Error invalidating paths in materializer state")?;
+        sqlite_db
+            .materializer_state_table()
+            .delete(evicted_paths)
+            .buck_error_context("Error invalidating paths in materializer state")?;
+    }
+
     let mut existing_materialization_futs = vec![];
+    // Objects being downloaded have no references until their materialization finishes.
+    let mut materializing_objects = HashSet::new();
     for data in tree.iter_without_paths() {
         match &data.processing {
             super::Processing::Active {
                 future: super::ProcessingFuture::Materializing(future),
                 ..
-            } => existing_materialization_futs.push(future.clone()),
+            } => {
+                existing_materialization_futs.push(future.clone());
+                if let ArtifactMaterializationStage::Declared { entry, method } = &data.stage {
+                    if let ArtifactMaterializationMethod::CasDownload { .. } = method.as_ref() {
+                        materializing_objects.extend(ObjectStore::entry_objects(entry));
+                    }
+                }
+            }
             _ => (),
         };
     }
 
+    // Now that the stale artifacts no longer reference their objects, find the objects which
+    // aren't referenced anymore.
+    let unreferenced_objects = match io.object_store() {
+        Some(object_store) => {
+            let mut referenced = sqlite_db.object_refs_table().referenced_objects()?;
+            referenced.extend(materializing_objects);
+            object_store.find_unreferenced(io.fs(), &referenced)?
+        }
+        None => Vec::new(),
+    };
+    stats.unreferenced_object_count = unreferenced_objects.len() as u64;
+    stats.unreferenced_object_bytes = unreferenced_objects.iter().map(|(_, size)| size).sum();
+
     let fut = async move {
         let start_time = Instant::now();
         // Wait for all in-progress operations to finish on the paths we are about to
@@ -398,7 +495,27 @@ fn create_clean_fut<T: IoHandler>(
         )
         .await?;
 
-        let cleaned_sizes: Vec<u64> = res.iter().filter_map(|x| *x).collect();
+        let mut evicted_sizes = Vec::with_capacity(evicted_futs.len());
+        for (future, size) in evicted_futs {
+            future.await?;
+            evicted_sizes.push(size);
+        }
+
+        // Objects may have been pinned by a materialization since they were found unreferenced,
+        // so they are deleted separately.
+        let cleaned_object_sizes = if unreferenced_objects.is_empty() {
+            Vec::new()
+        } else {
+            io.clean_unreferenced_objects(unreferenced_objects, liveliness_observer.dupe())
+                .await?
+        };
+
+        let cleaned_sizes: Vec<u64> = res
+            .iter()
+            .filter_map(|x| *x)
+            .chain(evicted_sizes)
+            .chain(cleaned_object_sizes)
+            .collect();
         stats.cleaned_artifact_count += cleaned_sizes.len() as u64;
         stats.cleaned_bytes = cleaned_sizes.iter().sum();
         stats.clean_duration_s = (Instant::now() - start_time).as_secs();
@@ -482,6 +599,9 @@ enum FoundPath {
     Untracked(ProjectRelativePathBuf, FileType, u64),
     /// These will be invalidated in the materiaizer.
     Stale(ProjectRelativePathBuf, u64),
+    /// Active artifacts downloaded from the CAS. These will be declared again in the
+    /// materializer.
+    Evicted(ProjectRelativePathBuf, u64),
     Retained(u64),
 }
 
@@ -559,6 +679,7 @@ impl<'a, T: IoHandler> StaleFinder<'a, T> {
                             active: false,
                             last_access_time,
                             metadata,
+                            ..
                         },
                     ..
                 }) if *last_access_time < self.keep_since_time => {
@@ -611,6 +732,73 @@ fn find_stale_tracked_only(
     Ok(())
 }
 
+/// Total size of the materialized artifacts.
+pub(crate) fn materialized_bytes(tree: &ArtifactTree) -> u64 {
+    tree.iter_without_paths()
+        .map(|data| match &data.stage {
+            ArtifactMaterializationStage::Materialized { metadata, .. } => metadata.size(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Find the least recently used artifacts to evict so that materialized artifacts take at most
+/// `max_bytes`. Artifacts accessed since `keep_since_time` are never evicted, and neither are
+/// artifacts declared by this daemon (active) unless they can be downloaded from the CAS again, so
+/// the result may still exceed `max_bytes`, in which case the clean's result is reported with
+/// `evict_to_bytes_unmet`.
+fn find_lru_evictable(
+    tree: &ArtifactTree,
+    keep_since_time: DateTime<Utc>,
+    max_bytes: u64,
+    found_paths: &mut Vec<FoundPath>,
+) {
+    let mut total_bytes = 0;
+    let mut evictable = Vec::new();
+    for (f_path, v) in tree.iter_with_paths() {
+        if let ArtifactMaterializationStage::Materialized {
+            last_access_time,
+            active,
+            metadata,
+            cas_source,
+        } = &v.stage
+        {
+            let size = metadata.size();
+            total_bytes += size;
+            // Active artifacts can only be evicted by declaring them again, which can't be done
+            // while they are being processed.
+            let can_evict = !active
+                || (cas_source.as_ref().is_some_and(|s| s.is_available())
+                    && matches!(v.processing, super::Processing::Done(..)));
+            if *last_access_time < keep_since_time && can_evict {
+                evictable.push((
+                    *last_access_time,
+                    ProjectRelativePathBuf::from(f_path),
+                    size,
+                    *active,
+                ));
+            } else {
+                found_paths.push(FoundPath::Retained(size));
+            }
+        }
+    }
+
+    evictable.sort_by_key(|(last_access_time, ..)| *last_access_time);
+    for (_, path, size, redeclare) in evictable {
+        if total_bytes > max_bytes {
+            tracing::trace!(path = %path, size = size, redeclare, "evicting artifact");
+            total_bytes -= size;
+            found_paths.push(if redeclare {
+                FoundPath::Evicted(path, size)
+            } else {
+                FoundPath::Stale(path, size)
+            });
+        } else {
+            found_paths.push(FoundPath::Retained(size));
+        }
+    }
+}
+
 pub struct CleanStaleConfig {
     // Time before running first clean, after daemon start
     pub start_offset: std::time::Duration,
@@ -672,3 +860,51 @@ impl CleanStaleConfig {
         Ok(clean_stale_config)
     }
 }
+
+/// Evicting least recently used artifacts when buck-out grows past a size budget.
+pub struct EvictionConfig {
+    /// Size of materialized artifacts above which artifacts are evicted.
+    pub max_bytes: u64,
+    /// Size of materialized artifacts to evict down to, to avoid evicting on every check.
+    pub target_bytes: u64,
+    pub check_period: std::time::Duration,
+    pub dry_run: bool,
+}
+
+impl EvictionConfig {
+    pub fn from_buck_config(root_config: &LegacyBuckConfig) -> buck2_error::Result<Option<Self>> {
+        let Some(max_bytes) = root_config.parse::<u64>(BuckconfigKeyRef {
+            section: "buck2",
+            property: "materializer_max_bytes",
+        })?
+        else {
+            return Ok(None);
+        };
+        let target_ratio = root_config
+            .parse::<f64>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "materializer_evict_to_ratio",
+            })?
+            .unwrap_or(0.8)
+            .clamp(0.0, 1.0);
+        let check_period_secs = root_config
+            .parse(BuckconfigKeyRef {
+                section: "buck2",
+                property: "materializer_eviction_period_secs",
+            })?
+            .unwrap_or(600);
+        let dry_run = root_config
+            .parse(BuckconfigKeyRef {
+                section: "buck2",
+                property: "materializer_eviction_dry_run",
+            })?
+            .unwrap_or(false);
+
+        Ok(Some(Self {
+            max_bytes,
+            target_bytes: (max_bytes as f64 * target_ratio) as u64,
+            check_period: std::time::Duration::from_secs(check_period_secs),
+            dry_run,
+        }))
+    }
+}
@@ -431,6 +431,7 @@ impl<T: IoHandler> DeferredMaterializerExtensions for DeferredMaterializerAccess
                         keep_since_time,
                         dry_run,
                         tracked_only,
+                        evict_to_bytes: None,
                         dispatcher,
                     },
                     sender,
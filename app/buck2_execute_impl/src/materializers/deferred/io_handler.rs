@@ -15,6 +15,7 @@ use std::sync::Arc;
 use allocative::Allocative;
 use async_trait::async_trait;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::liveliness_observer::LivelinessObserverSync;
 use buck2_core::buck2_env;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::fs_util::IoError;
@@ -63,6 +64,7 @@ use remote_execution::TDigest;
 use tracing::instrument;
 
 use crate::materializers::deferred::clean_stale::CleanInvalidatedPathRequest;
+use crate::materializers::deferred::object_store::ObjectStore;
 use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
 use crate::materializers::deferred::ArtifactTree;
@@ -86,6 +88,7 @@ pub struct DefaultIoHandler {
     /// Executor for blocking IO operations
     io_executor: Arc<dyn BlockingExecutor>,
     http_client: HttpClient,
+    object_store: Option<Arc<ObjectStore>>,
 }
 
 struct MaterializationStat {
@@ -104,6 +107,16 @@ pub trait IoHandler: Sized + Sync + Send + 'static {
         cancellations: &'a CancellationContext,
     ) -> BoxFuture<'a, Result<(), SharedMaterializingError>>;
 
+    /// Deduplicate the files of an artifact produced locally against the object store.
+    fn ingest_objects<'a>(
+        self: &Arc<Self>,
+        path: ProjectRelativePathBuf,
+        entry: ActionDirectoryEntry<ActionSharedDirectory>,
+        version: Version,
+        command_sender: Arc<MaterializerSender<Self>>,
+        cancellations: &'a CancellationContext,
+    ) -> BoxFuture<'a, Result<(), SharedMaterializingError>>;
+
     async fn immediate_write<'a>(
         self: &Arc<Self>,
         gen: Box<dyn FnOnce() -> buck2_error::Result<Vec<WriteRequest>> + Send + 'a>,
@@ -123,6 +136,14 @@ pub trait IoHandler: Sized + Sync + Send + 'static {
         cancellations: &'a CancellationContext,
     ) -> buck2_error::Result<()>;
 
+    /// Delete objects of the object store found unreferenced by a clean, and return the sizes of
+    /// the ones deleted.
+    async fn clean_unreferenced_objects(
+        self: &Arc<Self>,
+        objects: Vec<(ProjectRelativePathBuf, u64)>,
+        liveliness_observer: Arc<dyn LivelinessObserverSync>,
+    ) -> buck2_error::Result<Vec<u64>>;
+
     async fn materialize_entry(
         self: &Arc<Self>,
         path: ProjectRelativePathBuf,
@@ -143,6 +164,7 @@ pub trait IoHandler: Sized + Sync + Send + 'static {
     fn re_client_manager(&self) -> &Arc<ReConnectionManager>;
     fn fs(&self) -> &ProjectRoot;
     fn digest_config(&self) -> DigestConfig;
+    fn object_store(&self) -> Option<&ObjectStore>;
 }
 
 impl DefaultIoHandler {
@@ -153,7 +175,9 @@ impl DefaultIoHandler {
         re_client_manager: Arc<ReConnectionManager>,
         io_executor: Arc<dyn BlockingExecutor>,
         http_client: HttpClient,
+        object_store: Option<ObjectStore>,
     ) -> Self {
+        let object_store = object_store.map(Arc::new);
         Self {
             fs,
             digest_config,
@@ -161,6 +185,7 @@ impl DefaultIoHandler {
             re_client_manager,
             io_executor,
             http_client,
+            object_store,
         }
     }
     /// Materializes an `entry` at `path`, using the materialization `method`
@@ -188,15 +213,43 @@ impl DefaultIoHandler {
         match method.as_ref() {
             ArtifactMaterializationMethod::CasDownload { info } => {
                 let mut files = Vec::new();
+                // With an object store, files are downloaded into the store (unless already
+                // there), then linked into place. The objects are pinned until then, so that a
+                // concurrent clean doesn't delete them.
+                let object_store = self
+                    .object_store
+                    .as_deref()
+                    .filter(|store| store.is_usable());
+                let mut pins = object_store.map(ObjectStore::pins);
+                let mut downloads = HashSet::new();
+                let mut stores = Vec::new();
+                let mut links = Vec::new();
 
                 {
                     let mut walk = unordered_entry_walk(entry.as_ref().map_dir(Directory::as_ref));
 
                     while let Some((entry_path, entry)) = walk.next() {
                         if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
-                            let name = path.join(entry_path.get());
+                            let mut name = path.join(entry_path.get());
                             let digest = maybe_tombstone_digest(f.digest.data())?.to_re();
 
+                            if let (Some(store), Some(pins)) = (object_store, pins.as_mut()) {
+                                let object = store.object_path(&ObjectStore::object_name(
+                                    f.digest.data(),
+                                    f.is_executable,
+                                ));
+                                pins.pin(&object);
+                                links.push((object.clone(), name));
+                                if !downloads.insert(object.clone())
+                                    || fs_util::try_exists(self.fs.resolve(&object))
+                                        .map_err(buck2_error::Error::from)?
+                                {
+                                    continue;
+                                }
+                                name = store.temp_path();
+                                stores.push((name.clone(), object));
+                            }
+
                             tracing::trace!(name = %name, digest = %digest, "push download");
                             let name = self
                                 .fs
@@ -216,12 +269,20 @@ impl DefaultIoHandler {
                         }
                     }
                 }
+                // With an object store, only count the files which had to be downloaded.
                 stat.file_count = files.len().try_into().unwrap_or_default();
                 stat.total_bytes = files
                     .iter()
                     .map(|x| u64::try_from(x.named_digest.digest.size_in_bytes).unwrap_or_default())
                     .sum();
 
+                if let Some(store) = object_store {
+                    if !files.is_empty() {
+                        fs_util::create_dir_all(self.fs.resolve(&store.temp_dir()))
+                            .map_err(buck2_error::Error::from)?;
+                    }
+                }
+
                 let connection = self.re_client_manager.get_re_connection();
                 let re_client = connection.get_client();
 
@@ -251,6 +312,21 @@ impl DefaultIoHandler {
                             ),
                         }
                     })?;
+
+                if let Some(store) = object_store {
+                    self.io_executor
+                        .execute_io_inline(|| {
+                            for (temp, object) in &stores {
+                                store.store(&self.fs, temp, object)?;
+                            }
+                            for (object, dest) in &links {
+                                store.link(&self.fs, object, dest)?;
+                            }
+                            Ok(())
+                        })
+                        .await?;
+                }
+                drop(pins);
             }
             ArtifactMaterializationMethod::HttpDownload { info } => {
                 async {
@@ -351,6 +427,36 @@ impl IoHandler for DefaultIoHandler {
             .boxed()
     }
 
+    fn ingest_objects<'a>(
+        self: &Arc<Self>,
+        path: ProjectRelativePathBuf,
+        entry: ActionDirectoryEntry<ActionSharedDirectory>,
+        version: Version,
+        command_sender: Arc<MaterializerSender<Self>>,
+        cancellations: &'a CancellationContext,
+    ) -> BoxFuture<'a, Result<(), SharedMaterializingError>> {
+        let Some(object_store) = self
+            .object_store
+            .dupe()
+            .filter(|store| store.ingests_local_outputs())
+        else {
+            return futures::future::ready(Ok(())).boxed();
+        };
+        self.io_executor
+            .execute_io(
+                Box::new(IngestIoRequest {
+                    path,
+                    entry,
+                    version,
+                    command_sender,
+                    object_store,
+                }),
+                cancellations,
+            )
+            .map_err(|e| SharedMaterializingError::Error(e.into()))
+            .boxed()
+    }
+
     async fn immediate_write<'a>(
         self: &Arc<Self>,
         gen: Box<dyn FnOnce() -> buck2_error::Result<Vec<WriteRequest>> + Send + 'a>,
@@ -395,6 +501,21 @@ impl IoHandler for DefaultIoHandler {
             .await
     }
 
+    async fn clean_unreferenced_objects(
+        self: &Arc<Self>,
+        objects: Vec<(ProjectRelativePathBuf, u64)>,
+        liveliness_observer: Arc<dyn LivelinessObserverSync>,
+    ) -> buck2_error::Result<Vec<u64>> {
+        let Some(store) = self.object_store.as_deref() else {
+            return Ok(Vec::new());
+        };
+        self.io_executor
+            .execute_io_inline(|| {
+                store.remove_unreferenced(&self.fs, objects, liveliness_observer.as_ref())
+            })
+            .await
+    }
+
     /// Materializes an `entry` at `path`, using the materialization `method`
     #[instrument(level = "debug", skip(self, cancellations), fields(path = %path, method = %method, entry = %entry))]
     async fn materialize_entry(
@@ -471,6 +592,10 @@ impl IoHandler for DefaultIoHandler {
     fn digest_config(&self) -> DigestConfig {
         self.digest_config
     }
+
+    fn object_store(&self) -> Option<&ObjectStore> {
+        self.object_store.as_deref()
+    }
 }
 
 /// This is used for testing to ingest digests (via BUCK2_TEST_TOMBSTONED_DIGESTS).
@@ -647,6 +772,45 @@ impl IoRequest for WriteIoRequest {
     }
 }
 
+struct IngestIoRequest {
+    path: ProjectRelativePathBuf,
+    entry: ActionDirectoryEntry<ActionSharedDirectory>,
+    version: Version,
+    command_sender: Arc<MaterializerSender<DefaultIoHandler>>,
+    object_store: Arc<ObjectStore>,
+}
+
+impl IoRequest for IngestIoRequest {
+    fn execute(self: Box<Self>, project_fs: &ProjectRoot) -> buck2_error::Result<()> {
+        let mut walk = unordered_entry_walk(self.entry.as_ref().map_dir(Directory::as_ref));
+        while let Some((entry_path, entry)) = walk.next() {
+            if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+                let object = self
+                    .object_store
+                    .object_path(&ObjectStore::object_name(f.digest.data(), f.is_executable));
+                let dest = self.path.join(entry_path.get());
+                // The artifact is usable either way, e.g. if the filesystem doesn't support
+                // reflinks.
+                if let Err(e) = self.object_store.ingest(project_fs, &object, &dest) {
+                    tracing::debug!(dest = %dest, "not deduplicating: {:#}", e);
+                }
+            }
+        }
+
+        // If the materializer has shut down, we ignore this.
+        let _ignored = self.command_sender.send_low_priority(
+            LowPriorityMaterializerCommand::MaterializationFinished {
+                path: self.path,
+                timestamp: Utc::now(),
+                version: self.version,
+                result: Ok(()),
+            },
+        );
+
+        Ok(())
+    }
+}
+
 struct CleanIoRequest {
     path: ProjectRelativePathBuf,
     version: Version,
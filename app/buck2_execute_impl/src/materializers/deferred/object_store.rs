@@ -0,0 +1,655 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A content-addressed store, inside buck-out, of the files the deferred materializer downloads
+//! from CAS or that local actions produce.
+//!
+//! Each file downloaded from CAS is downloaded once into the store, under a name derived from its
+//! digest, and then linked into every path where it is materialized. Links keep the permissions
+//! of their object, so whether the file is executable is part of the name too.
+//!
+//! With reflinks, which are copy-on-write, modifying a materialized file never modifies its
+//! object. Files produced locally are then also moved into the store, or replaced by a reflink of
+//! an identical object already there.
+//!
+//! Hardlinks share the object's inode, so objects are made read-only: a tool modifying a
+//! materialized file in place fails instead of modifying the object and every other file with
+//! the same contents. Files produced locally are left alone, since actions may rely on
+//! modifying their previous outputs.
+//!
+//! The daemon refuses to start if buck-out doesn't support the links. If linking an object fails
+//! later anyway, e.g. because part of buck-out is on another filesystem, it is copied, and the
+//! store stops being used, since downloading into it and copying uses twice the space.
+//!
+//! Which artifacts use which objects is recorded in the materializer's sqlite state. Objects that
+//! no artifact uses anymore are deleted when cleaning stale artifacts, unless a materialization
+//! has pinned them in the meantime.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use allocative::Allocative;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::liveliness_observer::LivelinessObserverSync;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_directory::directory::directory::Directory;
+use buck2_directory::directory::directory_iterator::DirectoryIterator;
+use buck2_directory::directory::entry::DirectoryEntry;
+use buck2_directory::directory::walk::unordered_entry_walk;
+use buck2_error::BuckErrorContext;
+use buck2_execute::directory::ActionDirectoryEntry;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::ActionSharedDirectory;
+use dupe::Dupe;
+use parking_lot::Mutex;
+
+use crate::materializers::deferred::clean_stale::get_size;
+
+const OBJECTS_DIR: &str = "objects";
+/// Where objects are downloaded before being renamed into place.
+const TEMP_DIR: &str = "tmp";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+pub enum ObjectStoreModeError {
+    #[error(
+        "Invalid value for buckconfig `[buck2] materializer_object_store`. Got `{0}`. Expected one of `reflink`, `hardlink` or `disabled`."
+    )]
+    InvalidValueForConfig(String),
+    #[error(
+        "buckconfig `[buck2] materializer_object_store = hardlink` is only supported on Unix, since read-only files can't be deleted elsewhere"
+    )]
+    HardlinkNotSupported,
+    #[error(
+        "buckconfig `[buck2] materializer_object_store` requires `[buck2] sqlite_materializer_state`, which records which artifacts use which objects"
+    )]
+    RequiresSqliteState,
+    #[error(
+        "buckconfig `[buck2] materializer_object_store = {0}` is set, but buck-out does not support {0}s ({1}). Set it to `disabled` instead"
+    )]
+    LinksNotSupported(&'static str, String),
+}
+
+/// How objects are linked into the paths where they are materialized.
+#[derive(Clone, Copy, Debug, Dupe, PartialEq, Allocative)]
+pub enum ObjectStoreMode {
+    /// Reflinks (copy-on-write clones), on filesystems which support them.
+    Reflink,
+    /// Hardlinks to read-only objects.
+    Hardlink,
+}
+
+impl ObjectStoreMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ObjectStoreMode::Reflink => "reflink",
+            ObjectStoreMode::Hardlink => "hardlink",
+        }
+    }
+
+    /// Without the sqlite materializer state, objects are never cleaned up, so the store
+    /// can't be enabled.
+    pub fn try_new_from_config_value(
+        config_value: Option<&str>,
+        sqlite_materializer_state: bool,
+    ) -> buck2_error::Result<Option<Self>> {
+        let mode = match config_value {
+            None | Some("") | Some("disabled") => return Ok(None),
+            Some("reflink") => ObjectStoreMode::Reflink,
+            Some("hardlink") if cfg!(unix) => ObjectStoreMode::Hardlink,
+            Some("hardlink") => return Err(ObjectStoreModeError::HardlinkNotSupported.into()),
+            Some(v) => {
+                return Err(ObjectStoreModeError::InvalidValueForConfig(v.to_owned()).into());
+            }
+        };
+        if !sqlite_materializer_state {
+            return Err(ObjectStoreModeError::RequiresSqliteState.into());
+        }
+        Ok(Some(mode))
+    }
+}
+
+#[derive(Allocative)]
+pub struct ObjectStore {
+    dir: ProjectRelativePathBuf,
+    mode: ObjectStoreMode,
+    #[allocative(skip)]
+    next_temp_id: AtomicU64,
+    /// Objects which materializations are about to link or store, with how many materializations
+    /// pinned them. A clean computes which objects are unreferenced before deleting them, so it
+    /// must not delete those.
+    #[allocative(skip)]
+    pinned: Mutex<HashMap<ProjectRelativePathBuf, usize>>,
+    /// Set once linking an object failed, after which new files don't go through the store.
+    #[allocative(skip)]
+    link_failed: AtomicBool,
+}
+
+/// Objects pinned by a materialization, until this is dropped.
+pub(crate) struct PinnedObjects<'a> {
+    store: &'a ObjectStore,
+    objects: Vec<ProjectRelativePathBuf>,
+}
+
+impl PinnedObjects<'_> {
+    /// Pin an object. This must happen before checking whether it exists, so that a concurrent
+    /// clean either deletes it before that check, or leaves it alone.
+    pub(crate) fn pin(&mut self, object: &ProjectRelativePath) {
+        *self
+            .store
+            .pinned
+            .lock()
+            .entry(object.to_owned())
+            .or_default() += 1;
+        self.objects.push(object.to_owned());
+    }
+}
+
+impl Drop for PinnedObjects<'_> {
+    fn drop(&mut self) {
+        let mut pinned = self.store.pinned.lock();
+        for object in &self.objects {
+            if let Some(count) = pinned.get_mut(object) {
+                *count -= 1;
+                if *count == 0 {
+                    pinned.remove(object);
+                }
+            }
+        }
+    }
+}
+
+impl ObjectStore {
+    pub fn new(buck_out_path: &ProjectRelativePath, mode: ObjectStoreMode) -> Self {
+        Self {
+            dir: buck_out_path.join(ForwardRelativePath::unchecked_new(OBJECTS_DIR)),
+            mode,
+            next_temp_id: AtomicU64::new(0),
+            pinned: Mutex::new(HashMap::new()),
+            link_failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Check that files in buck-out can be linked the way the store needs, by linking a file in
+    /// its temporary directory.
+    pub fn check_links(&self, fs: &ProjectRoot) -> buck2_error::Result<()> {
+        fs_util::create_dir_all(fs.resolve(&self.temp_dir()))?;
+        let src = fs.resolve(&self.temp_path());
+        let dest = fs.resolve(&self.temp_path());
+        fs_util::write(&src, "")?;
+        let linked = link_file(self.mode, src.as_path(), dest.as_path());
+        fs_util::remove_file(&src)?;
+        if let Err(e) = linked {
+            return Err(
+                ObjectStoreModeError::LinksNotSupported(self.mode.as_str(), e.to_string()).into(),
+            );
+        }
+        fs_util::remove_file(&dest)?;
+        Ok(())
+    }
+
+    /// Whether new files should go through the store.
+    pub(crate) fn is_usable(&self) -> bool {
+        !self.link_failed.load(Ordering::Relaxed)
+    }
+
+    /// Whether files produced by local actions should be added to the store.
+    pub(crate) fn ingests_local_outputs(&self) -> bool {
+        self.mode == ObjectStoreMode::Reflink && self.is_usable()
+    }
+
+    pub(crate) fn pins(&self) -> PinnedObjects<'_> {
+        PinnedObjects {
+            store: self,
+            objects: Vec::new(),
+        }
+    }
+
+    /// The name of the object for a file, relative to the store, which is how it is referred to
+    /// in the materializer state.
+    pub(crate) fn object_name(digest: &FileDigest, is_executable: bool) -> String {
+        let hash = digest.raw_digest().to_string();
+        format!(
+            "{}/{}_{}{}",
+            &hash[..2],
+            hash,
+            digest.size(),
+            if is_executable { "_x" } else { "" }
+        )
+    }
+
+    pub(crate) fn object_path(&self, name: &str) -> ProjectRelativePathBuf {
+        self.dir.join(ForwardRelativePath::unchecked_new(name))
+    }
+
+    pub(crate) fn temp_dir(&self) -> ProjectRelativePathBuf {
+        self.dir.join(ForwardRelativePath::unchecked_new(TEMP_DIR))
+    }
+
+    /// A path to download an object to. It's unique to this daemon, and other daemons use a
+    /// different buck-out.
+    pub(crate) fn temp_path(&self) -> ProjectRelativePathBuf {
+        let id = self.next_temp_id.fetch_add(1, Ordering::Relaxed);
+        self.temp_dir()
+            .join(ForwardRelativePath::unchecked_new(&format!(
+                "{}_{}",
+                std::process::id(),
+                id
+            )))
+    }
+
+    /// The names of the objects holding the files of a CAS-downloaded artifact.
+    pub(crate) fn entry_objects(
+        entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+    ) -> HashSet<String> {
+        let mut objects = HashSet::new();
+        let mut walk = unordered_entry_walk(entry.as_ref().map_dir(Directory::as_ref));
+        while let Some((_entry_path, entry)) = walk.next() {
+            if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+                objects.insert(Self::object_name(f.digest.data(), f.is_executable));
+            }
+        }
+        objects
+    }
+
+    /// Move a freshly downloaded object into place. This replaces the object if another
+    /// materialization stored it in the meantime, which is fine since they have the same contents.
+    pub(crate) fn store(
+        &self,
+        fs: &ProjectRoot,
+        temp: &ProjectRelativePath,
+        object: &ProjectRelativePath,
+    ) -> buck2_error::Result<()> {
+        let object = fs.resolve(object);
+        let temp = fs.resolve(temp);
+        if self.mode == ObjectStoreMode::Hardlink {
+            let mut permissions = fs_util::symlink_metadata(&temp)?.permissions();
+            permissions.set_readonly(true);
+            fs_util::set_permissions(&temp, permissions)?;
+        }
+        if let Some(parent) = object.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        fs_util::rename(&temp, &object)?;
+        Ok(())
+    }
+
+    /// Materialize `object` at `dest`, by linking it or, if that fails, by copying it.
+    pub(crate) fn link(
+        &self,
+        fs: &ProjectRoot,
+        object: &ProjectRelativePath,
+        dest: &ProjectRelativePath,
+    ) -> buck2_error::Result<()> {
+        let object = fs.resolve(object);
+        let dest = fs.resolve(dest);
+        if let Err(e) = link_file(self.mode, object.as_path(), dest.as_path()) {
+            // E.g. the store and the destination are on different filesystems.
+            if !self.link_failed.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "Linking `{}` from the materializer object store failed, no longer using the store: {}",
+                    dest,
+                    e
+                );
+            }
+            fs_util::copy(&object, &dest)
+                .with_buck_error_context(|| format!("Error materializing `{}`", dest))?;
+        }
+        Ok(())
+    }
+
+    /// Deduplicate a file a local action produced at `dest`: if an identical `object` is already
+    /// in the store, replace `dest` with a link to it, otherwise add a link to `dest` to the store.
+    /// Unlike `link`, this never copies, since that would use more space rather than less.
+    pub(crate) fn ingest(
+        &self,
+        fs: &ProjectRoot,
+        object: &ProjectRelativePath,
+        dest: &ProjectRelativePath,
+    ) -> buck2_error::Result<()> {
+        let mut pins = self.pins();
+        pins.pin(object);
+
+        fs_util::create_dir_all(fs.resolve(&self.temp_dir()))?;
+        let temp = fs.resolve(&self.temp_path());
+        let object = fs.resolve(object);
+        let dest = fs.resolve(dest);
+        let (src, target) = if fs_util::try_exists(&object)? {
+            (&object, &dest)
+        } else {
+            if let Some(parent) = object.parent() {
+                fs_util::create_dir_all(parent)?;
+            }
+            (&dest, &object)
+        };
+        link_file(self.mode, src.as_path(), temp.as_path())
+            .with_buck_error_context(|| format!("Error linking `{}`", src))?;
+        // Renaming the link into place replaces `target` atomically.
+        if let Err(e) = fs_util::rename(&temp, target) {
+            let _ignored = fs_util::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// The objects (and downloads left over by previous daemons) in the store that are not in
+    /// `referenced`, with their sizes.
+    pub(crate) fn find_unreferenced(
+        &self,
+        fs: &ProjectRoot,
+        referenced: &HashSet<String>,
+    ) -> buck2_error::Result<Vec<(ProjectRelativePathBuf, u64)>> {
+        let mut unreferenced = Vec::new();
+        let Some(shards) = fs_util::read_dir_if_exists(fs.resolve(&self.dir))? else {
+            return Ok(unreferenced);
+        };
+        // Temporary files of this daemon may still be in use.
+        let own_temp_prefix = format!("{}_", std::process::id());
+        for shard in shards {
+            let shard = shard?;
+            let Some(shard_name) = shard.file_name().to_str().map(|s| s.to_owned()) else {
+                continue;
+            };
+            for object in fs_util::read_dir(shard.path())? {
+                let object = object?;
+                let Some(object_name) = object.file_name().to_str().map(|s| s.to_owned()) else {
+                    continue;
+                };
+                let name = format!("{}/{}", shard_name, object_name);
+                let unreferenced_object = if shard_name == TEMP_DIR {
+                    !object_name.starts_with(&own_temp_prefix)
+                } else {
+                    !referenced.contains(&name)
+                };
+                if unreferenced_object {
+                    unreferenced.push((self.object_path(&name), get_size(&object.path())?));
+                }
+            }
+        }
+        Ok(unreferenced)
+    }
+
+    /// Delete objects found by `find_unreferenced`, except those pinned since, and return the
+    /// sizes of the ones deleted.
+    pub(crate) fn remove_unreferenced(
+        &self,
+        fs: &ProjectRoot,
+        objects: Vec<(ProjectRelativePathBuf, u64)>,
+        liveliness_observer: &dyn LivelinessObserverSync,
+    ) -> buck2_error::Result<Vec<u64>> {
+        let mut removed = Vec::new();
+        for (object, size) in objects {
+            if !liveliness_observer.is_alive_sync() {
+                break;
+            }
+            // Holding the lock while deleting means a materialization pinning this object
+            // afterwards sees that it no longer exists.
+            let pinned = self.pinned.lock();
+            if pinned.contains_key(&object) {
+                tracing::debug!(object = %object, "not deleting pinned object");
+                continue;
+            }
+            fs_util::remove_all(fs.resolve(&object))?;
+            drop(pinned);
+            removed.push(size);
+        }
+        Ok(removed)
+    }
+}
+
+fn link_file(mode: ObjectStoreMode, src: &Path, dest: &Path) -> io::Result<()> {
+    match mode {
+        ObjectStoreMode::Reflink => reflink(src, dest),
+        ObjectStoreMode::Hardlink => std::fs::hard_link(src, dest),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn reflink(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::fs::File;
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    // `_IOW(0x94, 9, int)`, from `linux/fs.h`.
+    const FICLONE: u64 = 0x40049409;
+
+    let src_file = File::open(src)?;
+    let mode = src_file.metadata()?.permissions().mode();
+    let dest_file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(dest)?;
+    // SAFETY: both file descriptors are valid for the duration of the call.
+    if unsafe { libc::ioctl(dest_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) } < 0 {
+        let e = io::Error::last_os_error();
+        drop(dest_file);
+        let _ignored = std::fs::remove_file(dest);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_src: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::liveliness_observer::LivelinessGuard;
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
+    #[test]
+    fn test_mode_from_config() {
+        assert_eq!(
+            None,
+            ObjectStoreMode::try_new_from_config_value(None, false).unwrap()
+        );
+        assert_eq!(
+            Some(ObjectStoreMode::Reflink),
+            ObjectStoreMode::try_new_from_config_value(Some("reflink"), true).unwrap()
+        );
+        assert_eq!(
+            cfg!(unix),
+            ObjectStoreMode::try_new_from_config_value(Some("hardlink"), true).is_ok()
+        );
+        assert!(ObjectStoreMode::try_new_from_config_value(Some("reflink"), false).is_err());
+        assert!(ObjectStoreMode::try_new_from_config_value(Some("symlink"), true).is_err());
+    }
+
+    #[test]
+    fn test_store_link_and_find_unreferenced() -> buck2_error::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = root.path();
+        let store = ObjectStore::new(
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            ObjectStoreMode::Reflink,
+        );
+
+        let digest = FileDigest::new_sha1([0xab; 20], 8);
+        let name = ObjectStore::object_name(&digest, false);
+        assert_eq!(
+            format!("ab/{}_8", "ab".repeat(20)),
+            name,
+            "objects are sharded by the first byte of their hash"
+        );
+        assert_ne!(name, ObjectStore::object_name(&digest, true));
+
+        let temp = store.temp_path();
+        fs_util::create_dir_all(fs.resolve(&store.temp_dir()))?;
+        fs_util::write(fs.resolve(&temp), "contents")?;
+        let object = store.object_path(&name);
+        store.store(fs, &temp, &object)?;
+
+        let dest = ProjectRelativePath::unchecked_new("buck-out/v2/gen/foo/bar");
+        fs_util::create_dir_all(fs.resolve(dest.parent().unwrap()))?;
+        store.link(fs, &object, dest)?;
+        assert_eq!("contents", fs_util::read_to_string(fs.resolve(dest))?);
+
+        // Temporary files of this daemon may be in use, but not those of previous daemons.
+        fs_util::write(fs.resolve(&store.temp_path()), "in use")?;
+        let leftover = store
+            .temp_dir()
+            .join(ForwardRelativePath::unchecked_new("0_0"));
+        fs_util::write(fs.resolve(&leftover), "partial")?;
+
+        assert_eq!(
+            vec![(leftover.clone(), 7)],
+            store.find_unreferenced(fs, &HashSet::from([name.clone()]))?
+        );
+        let mut unreferenced = store.find_unreferenced(fs, &HashSet::new())?;
+        unreferenced.sort();
+        let mut expected = vec![(object, 8), (leftover, 7)];
+        expected.sort();
+        assert_eq!(expected, unreferenced);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_links() -> buck2_error::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = root.path();
+        let store = ObjectStore::new(
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            ObjectStoreMode::Reflink,
+        );
+        // Whether this succeeds depends on the filesystem supporting reflinks.
+        if let Err(e) = store.check_links(fs) {
+            assert!(
+                format!("{:#}", e).contains("does not support reflinks"),
+                "{:#}",
+                e
+            );
+        }
+        // Nothing is left behind in the temporary directory either way.
+        assert_eq!(0, fs_util::read_dir(fs.resolve(&store.temp_dir()))?.count());
+        assert!(store.is_usable());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hardlinks_are_read_only() -> buck2_error::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = root.path();
+        let store = ObjectStore::new(
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            ObjectStoreMode::Hardlink,
+        );
+        store.check_links(fs)?;
+
+        let temp = store.temp_path();
+        fs_util::create_dir_all(fs.resolve(&store.temp_dir()))?;
+        fs_util::write(fs.resolve(&temp), "contents")?;
+        let object = store.object_path(&ObjectStore::object_name(
+            &FileDigest::new_sha1([0xab; 20], 8),
+            false,
+        ));
+        store.store(fs, &temp, &object)?;
+
+        let dest = ProjectRelativePath::unchecked_new("buck-out/v2/gen/foo/bar");
+        fs_util::create_dir_all(fs.resolve(dest.parent().unwrap()))?;
+        store.link(fs, &object, dest)?;
+        assert!(store.is_usable());
+        assert!(!store.ingests_local_outputs());
+        assert_eq!("contents", fs_util::read_to_string(fs.resolve(dest))?);
+        assert!(fs_util::symlink_metadata(fs.resolve(dest))?
+            .permissions()
+            .readonly());
+        // The materialized file can still be deleted, e.g. to clean it up before an action runs.
+        fs_util::remove_file(fs.resolve(dest))?;
+        assert_eq!("contents", fs_util::read_to_string(fs.resolve(&object))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ingest() -> buck2_error::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = root.path();
+        let store = ObjectStore::new(
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            ObjectStoreMode::Reflink,
+        );
+        let object = store.object_path(&ObjectStore::object_name(
+            &FileDigest::new_sha1([0xab; 20], 8),
+            false,
+        ));
+
+        let first = ProjectRelativePath::unchecked_new("buck-out/v2/gen/foo/first");
+        let second = ProjectRelativePath::unchecked_new("buck-out/v2/gen/bar/second");
+        for dest in [first, second] {
+            fs_util::create_dir_all(fs.resolve(dest.parent().unwrap()))?;
+            fs_util::write(fs.resolve(dest), "contents")?;
+            // Whether this works depends on the filesystem supporting reflinks, but the output
+            // must be intact either way.
+            let ingested = store.ingest(fs, &object, dest);
+            assert_eq!("contents", fs_util::read_to_string(fs.resolve(dest))?);
+            assert_eq!(ingested.is_ok(), fs_util::try_exists(fs.resolve(&object))?);
+        }
+        // Nothing is left behind in the temporary directory.
+        assert_eq!(0, fs_util::read_dir(fs.resolve(&store.temp_dir()))?.count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_unreferenced_skips_pinned() -> buck2_error::Result<()> {
+        let root = ProjectRootTemp::new()?;
+        let fs = root.path();
+        let store = ObjectStore::new(
+            ProjectRelativePath::unchecked_new("buck-out/v2"),
+            ObjectStoreMode::Reflink,
+        );
+        let (liveliness_observer, _guard) = LivelinessGuard::create_sync();
+
+        let object = store.object_path(&ObjectStore::object_name(
+            &FileDigest::new_sha1([0xab; 20], 8),
+            false,
+        ));
+        fs_util::create_dir_all(fs.resolve(object.parent().unwrap()))?;
+        fs_util::write(fs.resolve(&object), "contents")?;
+        let unreferenced = store.find_unreferenced(fs, &HashSet::new())?;
+        assert_eq!(vec![(object.clone(), 8)], unreferenced);
+
+        // A materialization pins the object after the clean found it unreferenced.
+        let mut pins = store.pins();
+        pins.pin(&object);
+        assert_eq!(
+            Vec::<u64>::new(),
+            store.remove_unreferenced(fs, unreferenced.clone(), liveliness_observer.as_ref())?
+        );
+        assert!(fs_util::try_exists(fs.resolve(&object))?);
+
+        drop(pins);
+        assert_eq!(
+            vec![8],
+            store.remove_unreferenced(fs, unreferenced, liveliness_observer.as_ref())?
+        );
+        assert!(!fs_util::try_exists(fs.resolve(&object))?);
+
+        Ok(())
+    }
+}
@@ -95,6 +95,8 @@ mod state_machine {
     use std::thread;
 
     use assert_matches::assert_matches;
+    use buck2_common::liveliness_observer::LivelinessObserverSync;
+    use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
     use buck2_core::fs::fs_util;
     use buck2_core::fs::fs_util::ReadDir;
     use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
@@ -106,6 +108,7 @@ mod state_machine {
     use buck2_events::source::ChannelEventSource;
     use buck2_execute::directory::Symlink;
     use buck2_execute::directory::INTERNER;
+    use buck2_execute::execute::action_digest::TrackedActionDigest;
     use buck2_execute::execute::blocking::IoRequest;
     use buck2_util::threads::ignore_stack_overflow_checks_for_future;
     use tokio::time::sleep;
@@ -113,6 +116,8 @@ mod state_machine {
 
     use super::*;
     use crate::materializers::deferred::clean_stale::CleanInvalidatedPathRequest;
+    use crate::materializers::deferred::clean_stale::EvictionConfig;
+    use crate::materializers::deferred::object_store::ObjectStore;
     use crate::materializers::deferred::subscriptions::SubscriptionHandle;
     use crate::materializers::sqlite::testing_materializer_state_sqlite_db;
 
@@ -234,6 +239,17 @@ mod state_machine {
             .boxed()
         }
 
+        fn ingest_objects<'a>(
+            self: &Arc<Self>,
+            _path: ProjectRelativePathBuf,
+            _entry: ActionDirectoryEntry<ActionSharedDirectory>,
+            _version: Version,
+            _command_sender: Arc<MaterializerSender<Self>>,
+            _cancellations: &'a CancellationContext,
+        ) -> BoxFuture<'a, Result<(), SharedMaterializingError>> {
+            unimplemented!("StubIoHandler has no object store")
+        }
+
         async fn immediate_write<'a>(
             self: &Arc<Self>,
             _gen: Box<dyn FnOnce() -> buck2_error::Result<Vec<WriteRequest>> + Send + 'a>,
@@ -276,6 +292,14 @@ mod state_machine {
             Box::new(request).execute(&self.fs)
         }
 
+        async fn clean_unreferenced_objects(
+            self: &Arc<Self>,
+            _objects: Vec<(ProjectRelativePathBuf, u64)>,
+            _liveliness_observer: Arc<dyn LivelinessObserverSync>,
+        ) -> buck2_error::Result<Vec<u64>> {
+            unimplemented!("StubIoHandler has no object store")
+        }
+
         async fn materialize_entry(
             self: &Arc<Self>,
             path: ProjectRelativePathBuf,
@@ -339,6 +363,10 @@ mod state_machine {
         fn digest_config(&self) -> DigestConfig {
             self.digest_config
         }
+
+        fn object_store(&self) -> Option<&ObjectStore> {
+            None
+        }
     }
 
     /// A stub command sender. We are calling materializer methods directly so that's all we need.
@@ -479,6 +507,18 @@ mod state_machine {
         DeferredMaterializerAccessor<StubIoHandler>,
         SubscriptionHandle<StubIoHandler>,
         ChannelEventSource,
+    ) {
+        make_materializer_with_eviction(io, clean_stale_config, None).await
+    }
+
+    async fn make_materializer_with_eviction(
+        io: Arc<StubIoHandler>,
+        clean_stale_config: Option<CleanStaleConfig>,
+        eviction_config: Option<EvictionConfig>,
+    ) -> (
+        DeferredMaterializerAccessor<StubIoHandler>,
+        SubscriptionHandle<StubIoHandler>,
+        ChannelEventSource,
     ) {
         let (mut processor, command_sender, command_receiver, daemon_dispatcher_events) =
             make_processor_for_io(io.dupe());
@@ -506,6 +546,7 @@ mod state_machine {
                     0,
                     AccessTimesUpdates::Disabled,
                     clean_stale_config,
+                    eviction_config,
                 ));
             }
         })
@@ -1300,6 +1341,200 @@ mod state_machine {
         .await
     }
 
+    #[tokio::test]
+    async fn test_eviction() -> buck2_error::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let old_path = make_path("buck-out/v2/gen/foo/old");
+            let new_path = make_path("buck-out/v2/gen/foo/new");
+            let project_root = temp_root();
+            let io = Arc::new(StubIoHandler::new(project_root.dupe()));
+            let (dm, mut handle, _) = make_materializer(io.dupe(), None).await;
+            materialize_write(&old_path, b"contents", &mut handle, &dm).await?;
+            // Access times are stored with a precision of one second.
+            sleep(TokioDuration::from_millis(1100)).await;
+            materialize_write(&new_path, b"contents", &mut handle, &dm).await?;
+            // Drop dm and flush sqlite connection.
+            dm.abort();
+
+            // Create new materializer from db state so that artifacts are not active
+            let eviction_config = EvictionConfig {
+                max_bytes: 10,
+                target_bytes: 8,
+                check_period: std::time::Duration::from_secs(1),
+                dry_run: false,
+            };
+            let (_dm, _, mut daemon_dispatcher_events) =
+                make_materializer_with_eviction(io.dupe(), None, Some(eviction_config)).await;
+
+            let res = loop {
+                let event = daemon_dispatcher_events.receive().unwrap();
+                if let buck2_data::buck_event::Data::Instant(instant) =
+                    event.unpack_buck().unwrap().data()
+                {
+                    if let Some(buck2_data::instant_event::Data::CleanStaleResult(res)) =
+                        instant.data.as_ref()
+                    {
+                        break res.clone();
+                    }
+                }
+            };
+            assert_eq!(res.evict_to_bytes, Some(8));
+            assert!(!res.evict_to_bytes_unmet);
+            let buck2_data::CleanStaleStats {
+                stale_artifact_count,
+                stale_bytes,
+                retained_artifact_count,
+                cleaned_artifact_count,
+                ..
+            } = res.stats.unwrap();
+            assert_eq!(
+                (
+                    stale_artifact_count,
+                    stale_bytes,
+                    retained_artifact_count,
+                    cleaned_artifact_count
+                ),
+                (1, 8, 1, 1)
+            );
+            // The least recently used artifact is the one evicted.
+            assert!(!fs_util::try_exists(project_root.resolve(&old_path))?);
+            assert!(fs_util::try_exists(project_root.resolve(&new_path))?);
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_eviction_active_artifacts() -> buck2_error::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let path = make_path("buck-out/v2/gen/foo/active");
+            let project_root = temp_root();
+            let io = Arc::new(StubIoHandler::new(project_root.dupe()));
+            let eviction_config = EvictionConfig {
+                max_bytes: 4,
+                target_bytes: 2,
+                check_period: std::time::Duration::from_secs(1),
+                dry_run: false,
+            };
+            let (dm, mut handle, mut daemon_dispatcher_events) =
+                make_materializer_with_eviction(io.dupe(), None, Some(eviction_config)).await;
+            // Declared by this materializer and not downloaded from the CAS, so it is in use and
+            // cannot be evicted.
+            materialize_write(&path, b"contents", &mut handle, &dm).await?;
+
+            let res = loop {
+                let event = daemon_dispatcher_events.receive().unwrap();
+                if let buck2_data::buck_event::Data::Instant(instant) =
+                    event.unpack_buck().unwrap().data()
+                {
+                    if let Some(buck2_data::instant_event::Data::CleanStaleResult(res)) =
+                        instant.data.as_ref()
+                    {
+                        break res.clone();
+                    }
+                }
+            };
+            assert_eq!(res.evict_to_bytes, Some(2));
+            assert!(res.evict_to_bytes_unmet);
+            let buck2_data::CleanStaleStats {
+                stale_artifact_count,
+                retained_artifact_count,
+                retained_bytes,
+                ..
+            } = res.stats.unwrap();
+            assert_eq!(
+                (
+                    stale_artifact_count,
+                    retained_artifact_count,
+                    retained_bytes
+                ),
+                (0, 1, 8)
+            );
+            assert!(fs_util::try_exists(project_root.resolve(&path))?);
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_eviction_active_cas_artifacts() -> buck2_error::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let path = make_path("buck-out/v2/gen/foo/cas");
+            let project_root = temp_root();
+            let io = Arc::new(StubIoHandler::new(project_root.dupe()));
+            let (mut dm, _, _, _) = make_processor_for_io(io);
+            let digest_config = dm.io.digest_config();
+
+            let content = b"contents";
+            let value = ArtifactValue::file(FileMetadata {
+                digest: TrackedFileDigest::from_content(content, digest_config.cas_digest_config()),
+                is_executable: false,
+            });
+            let info = Arc::new(CasDownloadInfo::new_execution(
+                TrackedActionDigest::empty(digest_config.cas_digest_config()),
+                RemoteExecutorUseCase::buck2_default(),
+                Utc::now(),
+                Duration::hours(1),
+            ));
+            dm.declare(
+                &path,
+                value,
+                Box::new(ArtifactMaterializationMethod::CasDownload { info }),
+            );
+            // StubIoHandler doesn't download anything, so write the artifact ourselves.
+            project_root.write_file(&path, content, false)?;
+            let res = dm
+                .materialize_artifact(&path, EventDispatcher::null())
+                .buck_error_context("Expected a future")?
+                .await;
+            dm.materialization_finished(
+                path.clone(),
+                Utc::now(),
+                dm.version_tracker.current(),
+                res,
+            );
+            dm.io.take_log();
+
+            // Declared by this materializer, but it can be downloaded again, so it is evicted.
+            let res: buck2_cli_proto::CleanStaleResponse = CleanStaleArtifactsCommand {
+                keep_since_time: DateTime::<Utc>::MAX_UTC,
+                dry_run: false,
+                tracked_only: false,
+                evict_to_bytes: Some(0),
+                dispatcher: EventDispatcher::null(),
+            }
+            .create_clean_fut(&mut dm, None)
+            .await?
+            .into();
+            let buck2_data::CleanStaleStats {
+                stale_artifact_count,
+                retained_artifact_count,
+                cleaned_artifact_count,
+                cleaned_bytes,
+                ..
+            } = res.stats.unwrap();
+            assert_eq!(
+                (
+                    stale_artifact_count,
+                    retained_artifact_count,
+                    cleaned_artifact_count,
+                    cleaned_bytes
+                ),
+                (1, 0, 1, 8)
+            );
+            assert_eq!(dm.io.take_log(), &[(Op::Clean, path.clone())]);
+
+            // It is declared again, so it is downloaded again when needed.
+            let _ignore = dm
+                .materialize_artifact(&path, EventDispatcher::null())
+                .buck_error_context("Expected a future")?
+                .await;
+            assert_eq!(dm.io.take_log(), &[(Op::Materialize, path.clone())]);
+            Ok(())
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn test_has_artifact_at() -> buck2_error::Result<()> {
         ignore_stack_overflow_checks_for_future(async {
@@ -8,6 +8,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -53,9 +54,10 @@ pub struct MaterializerStateIdentity(String);
 /// materializer state sqlite db schema! If you forget to bump this version,
 /// then you can fix forward by bumping the `buck2.sqlite_materializer_state_version`
 /// buckconfig in the project root's .buckconfig.
-pub const DB_SCHEMA_VERSION: u64 = 6;
+pub const DB_SCHEMA_VERSION: u64 = 7;
 
 const STATE_TABLE_NAME: &str = "materializer_state";
+const OBJECT_REFS_TABLE_NAME: &str = "object_refs";
 const IDENTITY_KEY: &str = "timestamp_on_initialization";
 
 pub type MaterializerState = Vec<(ProjectRelativePathBuf, (ArtifactMetadata, DateTime<Utc>))>;
@@ -474,6 +476,103 @@ impl MaterializerStateSqliteTable {
     }
 }
 
+/// Which objects of the materializer's object store each artifact uses. An object's reference
+/// count is the number of artifacts using it, and it can be deleted once that drops to zero.
+pub(crate) struct ObjectRefsSqliteTable {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl ObjectRefsSqliteTable {
+    pub fn new(connection: Arc<Mutex<Connection>>) -> Self {
+        Self { connection }
+    }
+
+    pub(crate) fn create_table(&self) -> buck2_error::Result<()> {
+        let sql = format!(
+            "CREATE TABLE {} (
+                path                    TEXT NOT NULL,
+                object                  TEXT NOT NULL,
+                PRIMARY KEY (path, object)
+            )",
+            OBJECT_REFS_TABLE_NAME,
+        );
+        tracing::trace!(sql = %*sql, "creating table");
+        self.connection
+            .lock()
+            .execute(&sql, [])
+            .with_buck_error_context(|| {
+                format!("creating sqlite table {}", OBJECT_REFS_TABLE_NAME)
+            })?;
+        Ok(())
+    }
+
+    pub(crate) fn insert(
+        &self,
+        path: &ProjectRelativePath,
+        objects: impl IntoIterator<Item = String>,
+    ) -> buck2_error::Result<()> {
+        static SQL: Lazy<String> = Lazy::new(|| {
+            format!(
+                "INSERT OR IGNORE INTO {} (path, object) VALUES (?1, ?2)",
+                OBJECT_REFS_TABLE_NAME
+            )
+        });
+        let mut conn = self.connection.lock();
+        let tx = conn.transaction()?;
+        for object in objects {
+            tracing::trace!(sql = %*SQL, path = %path, object = %object, "inserting into table");
+            tx.execute(&SQL, rusqlite::params![path.as_str(), object])
+                .with_buck_error_context(|| {
+                    format!(
+                        "inserting `{}` into sqlite table {}",
+                        path, OBJECT_REFS_TABLE_NAME
+                    )
+                })?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The objects used by at least one artifact.
+    pub(crate) fn referenced_objects(&self) -> buck2_error::Result<HashSet<String>> {
+        static SQL: Lazy<String> =
+            Lazy::new(|| format!("SELECT DISTINCT object FROM {}", OBJECT_REFS_TABLE_NAME));
+        tracing::trace!(sql = %*SQL, "reading all from table");
+        let connection = self.connection.lock();
+        let mut stmt = connection.prepare(&SQL)?;
+        let result = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()
+            .with_buck_error_context(|| {
+                format!("reading from sqlite table {}", OBJECT_REFS_TABLE_NAME)
+            })?;
+        Ok(result)
+    }
+
+    /// Drop the references of artifacts which are no longer materialized.
+    pub(crate) fn delete(&self, paths: &[ProjectRelativePathBuf]) -> buck2_error::Result<()> {
+        for chunk in paths.chunks(100) {
+            let sql = format!(
+                "DELETE FROM {} WHERE path IN ({})",
+                OBJECT_REFS_TABLE_NAME,
+                itertools::repeat_n("?", chunk.len()).join(","),
+            );
+
+            tracing::trace!(sql = %sql, chunk = ?chunk, "deleting from table");
+            self.connection
+                .lock()
+                .execute(
+                    &sql,
+                    rusqlite::params_from_iter(chunk.iter().map(|p| p.as_str())),
+                )
+                .with_buck_error_context(|| {
+                    format!("deleting from sqlite table {}", OBJECT_REFS_TABLE_NAME)
+                })?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(buck2_error::Error, Debug, PartialEq, Eq)]
 enum MaterializerStateSqliteDbError {
     #[error("Path {} does not exist", .0)]
@@ -635,6 +734,10 @@ impl MaterializerStateSqliteDb {
         &self.tables.materializer_state_table
     }
 
+    pub(crate) fn object_refs_table(&mut self) -> &ObjectRefsSqliteTable {
+        &self.tables.object_refs_table
+    }
+
     pub fn identity(&self) -> &MaterializerStateIdentity {
         &self.identity
     }
@@ -643,6 +746,8 @@ impl MaterializerStateSqliteDb {
 struct MaterializerStateTables {
     /// Table storing actual materializer state
     materializer_state_table: MaterializerStateSqliteTable,
+    /// Table storing which objects of the object store are used by materialized artifacts
+    object_refs_table: ObjectRefsSqliteTable,
     /// Table for holding any metadata used to check version match. When loading
     /// from an existing db, we check if the versions from this table match the
     /// versions this buck2 binary expects. If the versions don't match, we throw
@@ -686,12 +791,14 @@ impl MaterializerStateTables {
 
         let connection = Arc::new(Mutex::new(connection));
         let materializer_state_table = MaterializerStateSqliteTable::new(connection.dupe());
+        let object_refs_table = ObjectRefsSqliteTable::new(connection.dupe());
         let versions_table = KeyValueSqliteTable::new("versions".to_owned(), connection.dupe());
         let created_by_table = KeyValueSqliteTable::new("created_by".to_owned(), connection.dupe());
         let last_read_by_table = KeyValueSqliteTable::new("last_read_by".to_owned(), connection);
 
         Ok(Self {
             materializer_state_table,
+            object_refs_table,
             versions_table,
             created_by_table,
             last_read_by_table,
@@ -700,6 +807,7 @@ impl MaterializerStateTables {
 
     fn create_all_tables(&self) -> buck2_error::Result<()> {
         self.materializer_state_table.create_table()?;
+        self.object_refs_table.create_table()?;
         self.versions_table.create_table()?;
         self.created_by_table.create_table()?;
         self.last_read_by_table.create_table()?;
@@ -1066,4 +1174,34 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_object_refs_table() -> buck2_error::Result<()> {
+        let conn = Connection::open_in_memory()?;
+
+        let table = ObjectRefsSqliteTable::new(Arc::new(Mutex::new(conn)));
+        table.create_table()?;
+
+        let foo = ProjectRelativePathBuf::unchecked_new("foo".to_owned());
+        let bar = ProjectRelativePathBuf::unchecked_new("bar".to_owned());
+        table.insert(&foo, ["a".to_owned(), "b".to_owned()])?;
+        table.insert(&bar, ["b".to_owned(), "c".to_owned()])?;
+        // Re-materializing an artifact doesn't count its references twice.
+        table.insert(&bar, ["b".to_owned(), "c".to_owned()])?;
+        assert_eq!(
+            HashSet::from(["a".to_owned(), "b".to_owned(), "c".to_owned()]),
+            table.referenced_objects()?
+        );
+
+        table.delete(&[bar])?;
+        assert_eq!(
+            HashSet::from(["a".to_owned(), "b".to_owned()]),
+            table.referenced_objects()?
+        );
+
+        table.delete(&[foo])?;
+        assert!(table.referenced_objects()?.is_empty());
+
+        Ok(())
+    }
 }
@@ -7,8 +7,13 @@
  * of this source tree.
  */
 
+use std::path::Path;
 use std::sync::Arc;
 
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_error::BuckErrorContext;
+use buck2_util::process::async_background_command;
 use dice::UserComputationData;
 use dupe::Dupe;
 
@@ -29,6 +34,120 @@ impl SetMergebase for UserComputationData {
     }
 }
 
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum MergebaseError {
+    #[error("Cannot find the files changed since `{0}`: `{1}` is not in a git, Sapling or Mercurial checkout")]
+    NoVcs(String, String),
+    #[error("Command `{0}` failed with {1}; stderr:\n{2}")]
+    StatusFailed(String, std::process::ExitStatus, String),
+}
+
+/// The version control systems whose changed files can be listed.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+enum Vcs {
+    Git,
+    Sapling,
+    Mercurial,
+}
+
+impl Vcs {
+    /// The VCS of the checkout `path` is in, and the root of that checkout.
+    fn detect(path: &Path) -> Option<(Vcs, &Path)> {
+        path.ancestors().find_map(|dir| {
+            [
+                (".git", Vcs::Git),
+                (".sl", Vcs::Sapling),
+                (".hg", Vcs::Mercurial),
+            ]
+            .into_iter()
+            .find(|(marker, _)| dir.join(marker).exists())
+            .map(|(_, vcs)| (vcs, dir))
+        })
+    }
+
+    /// The command listing the tracked files which changed between `mergebase` and the working
+    /// copy, relative to the root of the checkout, separated by NUL bytes. Untracked files are not
+    /// listed.
+    fn changed_files_command(self, mergebase: &str) -> (&'static str, Vec<&str>) {
+        let status = vec![
+            "status",
+            "--modified",
+            "--added",
+            "--removed",
+            "--deleted",
+            "--no-status",
+            "--print0",
+            "--rev",
+            mergebase,
+        ];
+        match self {
+            Vcs::Git => (
+                "git",
+                vec![
+                    "diff",
+                    "--name-only",
+                    "--no-relative",
+                    "--no-renames",
+                    "-z",
+                    mergebase,
+                    "--",
+                ],
+            ),
+            Vcs::Sapling => ("sl", status),
+            Vcs::Mercurial => ("hg", status),
+        }
+    }
+}
+
+/// The files that changed between `mergebase` and the working copy, according to the VCS of the
+/// checkout the project is in. Files outside the project are omitted.
+pub async fn files_changed_since(
+    project_root: &ProjectRoot,
+    mergebase: &str,
+) -> buck2_error::Result<Vec<ProjectRelativePathBuf>> {
+    let root = project_root.root().as_path();
+    let (vcs, vcs_root) = Vcs::detect(root)
+        .ok_or_else(|| MergebaseError::NoVcs(mergebase.to_owned(), root.display().to_string()))?;
+    let (program, args) = vcs.changed_files_command(mergebase);
+    let command = format!("{} {}", program, args.join(" "));
+    let output = async_background_command(program)
+        .args(&args)
+        .current_dir(vcs_root)
+        .env("HGPLAIN", "1")
+        .output()
+        .await
+        .with_buck_error_context(|| format!("Failed to run `{}`", command))?;
+    if !output.status.success() {
+        return Err(MergebaseError::StatusFailed(
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+        .into());
+    }
+    let output = std::str::from_utf8(&output.stdout)
+        .with_buck_error_context(|| format!("`{}` output is not UTF-8", command))?;
+    in_project(project_root, vcs_root, output)
+}
+
+/// The NUL-separated paths, relative to `vcs_root`, which are in the project, relative to it.
+fn in_project(
+    project_root: &ProjectRoot,
+    vcs_root: &Path,
+    paths: &str,
+) -> buck2_error::Result<Vec<ProjectRelativePathBuf>> {
+    paths
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .filter_map(|path| {
+            let path = vcs_root.join(path);
+            let path = path.strip_prefix(project_root.root().as_path()).ok()?;
+            Some(ProjectRelativePathBuf::try_from(path.to_str()?.to_owned()))
+        })
+        .collect()
+}
+
 impl GetMergebase for UserComputationData {
     fn get_mergebase(&self) -> Mergebase {
         self.data
@@ -37,3 +156,54 @@ impl GetMergebase for UserComputationData {
             .dupe()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_detect() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let repo = tempdir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git"))?;
+        std::fs::create_dir_all(repo.join("project/sub"))?;
+        assert_eq!(
+            Some((Vcs::Git, repo.as_path())),
+            Vcs::detect(&repo.join("project/sub"))
+        );
+
+        std::fs::create_dir_all(repo.join("project/.sl"))?;
+        assert_eq!(
+            Some((Vcs::Sapling, repo.join("project").as_path())),
+            Vcs::detect(&repo.join("project/sub"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_project() -> buck2_error::Result<()> {
+        let project_root = ProjectRoot::new_unchecked(AbsNormPathBuf::from(
+            if cfg!(windows) {
+                "C:\\repo\\project"
+            } else {
+                "/repo/project"
+            }
+            .to_owned(),
+        )?);
+        let vcs_root = project_root.root().as_path().parent().unwrap();
+        assert_eq!(
+            vec![
+                ProjectRelativePathBuf::unchecked_new("a.txt".to_owned()),
+                ProjectRelativePathBuf::unchecked_new("sub/b.txt".to_owned()),
+            ],
+            in_project(
+                &project_root,
+                vcs_root,
+                "project/a.txt\0other/c.txt\0project/sub/b.txt\0"
+            )?
+        );
+        Ok(())
+    }
+}
@@ -49,6 +49,8 @@ pub enum GatherOutputStatus {
     Finished {
         exit_code: i32,
         execution_stats: Option<buck2_data::CommandExecutionStats>,
+        /// What the command was denied, if it ran in a sandbox.
+        sandbox_denials: Vec<String>,
     },
     TimedOut(Duration),
     Cancelled,
@@ -61,9 +63,11 @@ impl From<DecodedStatus> for GatherOutputStatus {
             DecodedStatus::Status {
                 exit_code,
                 execution_stats,
+                sandbox_denials,
             } => Self::Finished {
                 exit_code,
                 execution_stats,
+                sandbox_denials,
             },
             DecodedStatus::SpawnFailed(v) => Self::SpawnFailed(v),
         }
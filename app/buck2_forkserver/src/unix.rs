@@ -7,9 +7,12 @@
  * of this source tree.
  */
 
+mod cgroup;
 mod command;
 mod launch;
 pub(crate) mod process_group;
+#[cfg(target_os = "linux")]
+mod sandbox;
 mod service;
 
 pub use command::run_forkserver;
@@ -0,0 +1,323 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Running each command in its own cgroup (v2), under a cgroup delegated to buck2, to limit the
+//! memory and CPU it can use and to measure how much of them it used.
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use buck2_common::init::ResourceControlConfig;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_error::BuckErrorContext;
+use rand::distributions::Alphanumeric;
+use rand::distributions::DistString;
+
+use crate::run::status_decoder::DecodedStatus;
+use crate::run::status_decoder::StatusDecoder;
+
+/// The period used for `cpu.max`.
+const CPU_MAX_PERIOD_US: u64 = 100_000;
+
+/// Prefix of the cgroups created for actions, to recognize them when cleaning up.
+const ACTION_CGROUP_PREFIX: &str = "action-";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum CgroupError {
+    #[error("`{0}` is not a cgroup v2 directory")]
+    NotCgroupV2(String),
+    #[error("Invalid `cpu_max_per_action`, expected a positive number of CPUs: `{0}`")]
+    InvalidCpuMax(String),
+}
+
+/// The cgroup under which actions' cgroups are created, and the limits to give them.
+pub(crate) struct ActionCgroups {
+    root: AbsNormPathBuf,
+    memory_max: Option<String>,
+    cpu_max: Option<String>,
+}
+
+impl ActionCgroups {
+    pub(crate) fn new(config: &ResourceControlConfig) -> buck2_error::Result<Option<Self>> {
+        let Some(root) = &config.action_cgroup_root else {
+            return Ok(None);
+        };
+        let root = AbsNormPathBuf::from(root.clone())?;
+
+        if !fs_util::try_exists(
+            root.join(ForwardRelativePath::unchecked_new("cgroup.controllers")),
+        )? {
+            return Err(CgroupError::NotCgroupV2(root.to_string()).into());
+        }
+
+        let cpu_max = config
+            .cpu_max_per_action
+            .as_ref()
+            .map(|cpus| match cpus.parse::<f64>() {
+                Ok(v) if v > 0.0 => Ok(format!(
+                    "{} {}",
+                    (v * CPU_MAX_PERIOD_US as f64).round() as u64,
+                    CPU_MAX_PERIOD_US
+                )),
+                _ => Err(CgroupError::InvalidCpuMax(cpus.clone())),
+            })
+            .transpose()?;
+
+        // Remove the cgroups left over by a previous forkserver. Those still in use can't be
+        // removed, which is fine.
+        for entry in fs_util::read_dir(&root)? {
+            let entry = entry?;
+            if entry
+                .file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with(ACTION_CGROUP_PREFIX))
+            {
+                let _ignored = fs_util::remove_dir(entry.path());
+            }
+        }
+
+        let controllers = if cpu_max.is_some() {
+            "+memory +cpu"
+        } else {
+            "+memory"
+        };
+        fs_util::write(
+            root.join(ForwardRelativePath::unchecked_new("cgroup.subtree_control")),
+            controllers,
+        )
+        .with_buck_error_context(|| {
+            format!(
+                "Error enabling controllers in `{}`, which must be writable and contain no processes",
+                root
+            )
+        })?;
+
+        Ok(Some(Self {
+            root,
+            memory_max: config.memory_max_per_action.clone(),
+            cpu_max,
+        }))
+    }
+
+    /// Create the cgroup for a new action.
+    pub(crate) fn create(&self) -> buck2_error::Result<ActionCgroup> {
+        let name = format!(
+            "{}{}",
+            ACTION_CGROUP_PREFIX,
+            Alphanumeric.sample_string(&mut rand::thread_rng(), 16)
+        );
+        let path = self.root.join(ForwardRelativePath::new(&name)?);
+        fs_util::create_dir(&path)?;
+
+        let cgroup = ActionCgroup { path };
+        cgroup.write("memory.oom.group", "1")?;
+        if let Some(memory_max) = &self.memory_max {
+            cgroup.write("memory.max", memory_max)?;
+        }
+        if let Some(cpu_max) = &self.cpu_max {
+            cgroup.write("cpu.max", cpu_max)?;
+        }
+        Ok(cgroup)
+    }
+}
+
+/// What a command used, as measured by its cgroup.
+struct CgroupUsage {
+    memory_peak: Option<u64>,
+    cpu_time_user_us: Option<u64>,
+    cpu_time_system_us: Option<u64>,
+    oom_killed: bool,
+}
+
+/// Find the value for `key` in a flat-keyed cgroup file like `cpu.stat`.
+fn parse_flat_keyed(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.split_once(' ')?;
+        if k == key {
+            v.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// A cgroup created for one action. It is removed by `CgroupStatusDecoder` once the command
+/// finishes, or when dropped if the command never ran.
+pub(crate) struct ActionCgroup {
+    path: AbsNormPathBuf,
+}
+
+impl Drop for ActionCgroup {
+    fn drop(&mut self) {
+        // Nothing can be running in the cgroup if we get here before the command was spawned,
+        // and the directory is already gone if `remove` succeeded.
+        let _ignored = fs_util::remove_dir(&self.path);
+    }
+}
+
+impl ActionCgroup {
+    fn file(&self, name: &str) -> AbsNormPathBuf {
+        self.path.join(ForwardRelativePath::unchecked_new(name))
+    }
+
+    fn write(&self, name: &str, value: &str) -> buck2_error::Result<()> {
+        let path = self.file(name);
+        fs_util::write(&path, value)
+            .with_buck_error_context(|| format!("Error writing `{}` to `{}`", value, path))
+    }
+
+    /// Make the command join this cgroup before it executes.
+    pub(crate) fn apply(&self, cmd: &mut Command) -> buck2_error::Result<()> {
+        let procs = self.file("cgroup.procs");
+        let procs = CString::new(procs.as_path().as_os_str().as_bytes())
+            .with_buck_error_context(|| format!("Invalid path: `{}`", procs))?;
+        // SAFETY: this only makes system calls and does not allocate.
+        unsafe {
+            cmd.pre_exec(move || {
+                // Writing 0 moves the writing process.
+                let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let res = if libc::write(fd, b"0".as_ptr().cast(), 1) < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(())
+                };
+                libc::close(fd);
+                res
+            });
+        }
+        Ok(())
+    }
+
+    async fn usage(&self) -> buck2_error::Result<CgroupUsage> {
+        let read = |name: &'static str| async move {
+            tokio::fs::read_to_string(self.file(name))
+                .await
+                .with_buck_error_context(|| format!("Error reading `{}` in `{}`", name, self.path))
+        };
+
+        let cpu_stat = read("cpu.stat").await?;
+        let memory_events = read("memory.events").await?;
+        // Only available from Linux 5.19.
+        let memory_peak = read("memory.peak")
+            .await
+            .ok()
+            .and_then(|peak| peak.trim().parse().ok());
+
+        Ok(CgroupUsage {
+            memory_peak,
+            cpu_time_user_us: parse_flat_keyed(&cpu_stat, "user_usec"),
+            cpu_time_system_us: parse_flat_keyed(&cpu_stat, "system_usec"),
+            oom_killed: parse_flat_keyed(&memory_events, "oom_kill").is_some_and(|n| n > 0),
+        })
+    }
+
+    /// Remove the cgroup, killing anything the command left running in it.
+    async fn remove(&self) -> buck2_error::Result<()> {
+        let mut attempts = 0;
+        loop {
+            match tokio::fs::remove_dir(&self.path).await {
+                Ok(()) => return Ok(()),
+                Err(e) if e.raw_os_error() == Some(libc::EBUSY) && attempts < 10 => {
+                    attempts += 1;
+                    // Only available from Linux 5.14, so this may fail.
+                    let _ignored = tokio::fs::write(self.file("cgroup.kill"), "1").await;
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Err(e) => {
+                    return Err(buck2_error::Error::from(e)
+                        .context(format!("Error removing cgroup `{}`", self.path)));
+                }
+            }
+        }
+    }
+}
+
+/// Adds the resource usage measured by the command's cgroup, if any, to the status decoded by
+/// another decoder, and removes the cgroup.
+pub(crate) struct CgroupStatusDecoder<D> {
+    inner: D,
+    cgroup: Option<ActionCgroup>,
+}
+
+impl<D> CgroupStatusDecoder<D> {
+    pub(crate) fn new(inner: D, cgroup: Option<ActionCgroup>) -> Self {
+        Self { inner, cgroup }
+    }
+}
+
+#[async_trait]
+impl<D: StatusDecoder + Send> StatusDecoder for CgroupStatusDecoder<D> {
+    async fn decode_status(self, status: ExitStatus) -> buck2_error::Result<DecodedStatus> {
+        let decoded = self.inner.decode_status(status).await?;
+        let Some(cgroup) = self.cgroup else {
+            return Ok(decoded);
+        };
+
+        let usage = cgroup.usage().await;
+        cgroup.remove().await?;
+        let usage = usage?;
+
+        Ok(match decoded {
+            DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                sandbox_denials,
+            } => {
+                let mut stats = execution_stats.unwrap_or_default();
+                // The cgroup also accounts for the command's children, so prefer it.
+                stats.memory_peak = usage.memory_peak.or(stats.memory_peak);
+                stats.cpu_time_user_us = usage.cpu_time_user_us;
+                stats.cpu_time_system_us = usage.cpu_time_system_us;
+                stats.oom_killed = Some(usage.oom_killed);
+                DecodedStatus::Status {
+                    exit_code,
+                    execution_stats: Some(stats),
+                    sandbox_denials,
+                }
+            }
+            spawn_failed @ DecodedStatus::SpawnFailed(..) => spawn_failed,
+        })
+    }
+
+    async fn cancel(self) -> buck2_error::Result<()> {
+        self.inner.cancel().await?;
+        if let Some(cgroup) = self.cgroup {
+            cgroup.remove().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flat_keyed() {
+        let cpu_stat = "usage_usec 1500\nuser_usec 1000\nsystem_usec 500\nnr_periods 0\n";
+        assert_eq!(Some(1000), parse_flat_keyed(cpu_stat, "user_usec"));
+        assert_eq!(Some(500), parse_flat_keyed(cpu_stat, "system_usec"));
+        assert_eq!(None, parse_flat_keyed(cpu_stat, "usage"));
+
+        let memory_events = "low 0\nhigh 0\nmax 3\noom 1\noom_kill 1\noom_group_kill 1\n";
+        assert_eq!(Some(1), parse_flat_keyed(memory_events, "oom_kill"));
+    }
+}
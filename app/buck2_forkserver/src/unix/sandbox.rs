@@ -0,0 +1,443 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Running commands in a sandbox made of Linux user, mount and network namespaces.
+//!
+//! Inside the sandbox, the sandbox root (the project root) is replaced by a read-only tmpfs into
+//! which only the declared inputs (read-only) and outputs (writable) are bind-mounted. The
+//! command also gets its own network namespace, in which only a loopback interface exists, and
+//! which is down. Paths outside the sandbox root, such as system toolchains, are left untouched.
+//!
+//! What the command was denied (see [`denials`]) is returned along with its exit status.
+
+mod denials;
+
+use std::collections::BTreeSet;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::ffi::OsStr;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::ptr;
+
+use async_trait::async_trait;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_error::BuckErrorContext;
+use buck2_forkserver_proto::Sandbox;
+
+use self::denials::DenialLog;
+use self::denials::Visibility;
+use crate::run::status_decoder::DecodedStatus;
+use crate::run::status_decoder::StatusDecoder;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum SandboxError {
+    #[error("Sandboxed path `{0}` is not under the sandbox root `{1}`")]
+    NotUnderRoot(String, String),
+}
+
+struct BindMount {
+    source: CString,
+    target: CString,
+    read_only: bool,
+}
+
+/// Everything needed to enter the sandbox, computed ahead of time because entering it happens
+/// between `fork` and `exec`, where allocating is not allowed.
+pub(crate) struct SandboxPlan {
+    /// A directory on which the sandbox's view of the root is assembled.
+    staging: CString,
+    root: CString,
+    cwd: CString,
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+    /// Directories to create in the staging directory, parents first.
+    dirs: Vec<CString>,
+    /// Empty files to create in the staging directory, to bind-mount files onto.
+    files: Vec<CString>,
+    /// Bind mounts, shallowest first so that nested paths are mounted on top of their parents.
+    mounts: Vec<BindMount>,
+    /// What the command can see, to log what it is denied, if requested.
+    visibility: Option<Visibility>,
+}
+
+fn cstring(path: &Path) -> buck2_error::Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .with_buck_error_context(|| format!("Invalid path: `{}`", path.display()))
+}
+
+impl SandboxPlan {
+    pub(crate) fn new(
+        sandbox: &Sandbox,
+        staging: &AbsNormPath,
+        cwd: &Path,
+    ) -> buck2_error::Result<Self> {
+        let root = Path::new(OsStr::from_bytes(&sandbox.root));
+        let to_staging = |path: &Path| -> buck2_error::Result<PathBuf> {
+            let rel = path.strip_prefix(root).map_err(|_| {
+                SandboxError::NotUnderRoot(path.display().to_string(), root.display().to_string())
+            })?;
+            Ok(staging.as_path().join(rel))
+        };
+
+        let mut paths: Vec<(&Path, bool)> = sandbox
+            .read_only_paths
+            .iter()
+            .map(|p| (Path::new(OsStr::from_bytes(p)), true))
+            .chain(
+                sandbox
+                    .writable_paths
+                    .iter()
+                    .map(|p| (Path::new(OsStr::from_bytes(p)), false)),
+            )
+            .collect();
+        paths.sort_by_key(|(path, _)| path.components().count());
+
+        let mut dirs = BTreeSet::new();
+        let mut files = Vec::new();
+        let mut mounts = Vec::new();
+        let mut mounted: Vec<(&Path, bool)> = Vec::new();
+
+        let add_dir = |dirs: &mut BTreeSet<PathBuf>, dir: &Path| {
+            for ancestor in dir.ancestors() {
+                if !ancestor.starts_with(staging.as_path()) || !dirs.insert(ancestor.to_owned()) {
+                    break;
+                }
+            }
+        };
+
+        add_dir(&mut dirs, &to_staging(cwd)?);
+
+        for (path, read_only) in paths {
+            // Nothing to do if the closest mounted ancestor already grants the same access.
+            let parent_access = mounted
+                .iter()
+                .rev()
+                .find(|(mounted, _)| path.starts_with(mounted))
+                .map(|(_, read_only)| *read_only);
+            if parent_access == Some(read_only) {
+                continue;
+            }
+
+            // Declared paths which do not exist (e.g. inputs the action will fail to find)
+            // cannot be mounted.
+            let Ok(metadata) = std::fs::metadata(path) else {
+                continue;
+            };
+
+            let target = to_staging(path)?;
+            if metadata.is_dir() {
+                add_dir(&mut dirs, &target);
+            } else {
+                if let Some(parent) = target.parent() {
+                    add_dir(&mut dirs, parent);
+                }
+                files.push(cstring(&target)?);
+            }
+
+            mounts.push(BindMount {
+                source: cstring(path)?,
+                target: cstring(&target)?,
+                read_only,
+            });
+            mounted.push((path, read_only));
+        }
+
+        let visibility = sandbox.log_denials.then(|| Visibility {
+            root: root.to_owned(),
+            visible: mounted.iter().map(|(path, _)| path.to_path_buf()).collect(),
+            cwd: cwd.to_owned(),
+        });
+
+        // SAFETY: these never fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        Ok(Self {
+            staging: cstring(staging.as_path())?,
+            root: cstring(root)?,
+            cwd: cstring(cwd)?,
+            uid_map: format!("{uid} {uid} 1\n").into_bytes(),
+            gid_map: format!("{gid} {gid} 1\n").into_bytes(),
+            dirs: dirs
+                .iter()
+                .map(|dir| cstring(dir))
+                .collect::<buck2_error::Result<_>>()?,
+            files,
+            mounts,
+            visibility,
+        })
+    }
+
+    /// Make the command enter the sandbox before it executes, and start recording what it is
+    /// denied in there if requested.
+    pub(crate) fn apply(self, cmd: &mut Command) -> buck2_error::Result<DenialLog> {
+        let (listener, log) = match &self.visibility {
+            Some(visibility) => {
+                let (listener, log) = denials::supervise(visibility.clone())
+                    .buck_error_context("Error starting the sandbox supervisor")?;
+                (Some(listener), log)
+            }
+            None => (None, DenialLog::default()),
+        };
+        // SAFETY: `enter` and `install` only make system calls and do not allocate.
+        unsafe {
+            cmd.pre_exec(move || {
+                self.enter()?;
+                if let Some(listener) = &listener {
+                    listener.install();
+                }
+                Ok(())
+            });
+        }
+        Ok(log)
+    }
+
+    fn enter(&self) -> io::Result<()> {
+        unsafe {
+            check(libc::unshare(
+                libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET,
+            ))?;
+
+            // Map ourselves to the same user in the new user namespace, which gives us the
+            // capabilities required to mount things in it.
+            write_file(c"/proc/self/setgroups", b"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+            // Don't propagate any of the mounts below out of the sandbox.
+            check(libc::mount(
+                ptr::null(),
+                c"/".as_ptr(),
+                ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                ptr::null(),
+            ))?;
+
+            check(libc::mount(
+                c"tmpfs".as_ptr(),
+                self.staging.as_ptr(),
+                c"tmpfs".as_ptr(),
+                0,
+                ptr::null(),
+            ))?;
+
+            for dir in &self.dirs {
+                if libc::mkdir(dir.as_ptr(), 0o755) < 0 {
+                    let e = io::Error::last_os_error();
+                    if e.raw_os_error() != Some(libc::EEXIST) {
+                        return Err(e);
+                    }
+                }
+            }
+
+            for file in &self.files {
+                let fd = check(libc::open(
+                    file.as_ptr(),
+                    libc::O_WRONLY | libc::O_CREAT | libc::O_CLOEXEC,
+                    0o644,
+                ))?;
+                libc::close(fd);
+            }
+
+            for mount in &self.mounts {
+                bind_mount(&mount.source, &mount.target)?;
+                if mount.read_only {
+                    remount_read_only(&mount.target)?;
+                }
+            }
+
+            // Swap the root for the staging directory. This shadows the staging directory if it
+            // is under the root, and otherwise only exposes the same view there, so it can be
+            // left mounted.
+            bind_mount(&self.staging, &self.root)?;
+            remount_read_only(&self.root)?;
+
+            // The working directory is still the one from the original root.
+            check(libc::chdir(self.cwd.as_ptr()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds what the command was denied in the sandbox to the status decoded by another decoder.
+pub(crate) struct SandboxStatusDecoder<D> {
+    inner: D,
+    denial_log: Option<DenialLog>,
+}
+
+impl<D> SandboxStatusDecoder<D> {
+    pub(crate) fn new(inner: D, denial_log: Option<DenialLog>) -> Self {
+        Self { inner, denial_log }
+    }
+}
+
+#[async_trait]
+impl<D: StatusDecoder + Send> StatusDecoder for SandboxStatusDecoder<D> {
+    async fn decode_status(self, status: ExitStatus) -> buck2_error::Result<DecodedStatus> {
+        let decoded = self.inner.decode_status(status).await?;
+        let Some(denial_log) = self.denial_log else {
+            return Ok(decoded);
+        };
+
+        Ok(match decoded {
+            DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                sandbox_denials: _,
+            } => DecodedStatus::Status {
+                exit_code,
+                execution_stats,
+                // Every system call the command made was recorded before it could return, so
+                // this is complete now that the command exited.
+                sandbox_denials: denial_log.denials(),
+            },
+            spawn_failed @ DecodedStatus::SpawnFailed(..) => spawn_failed,
+        })
+    }
+
+    async fn cancel(self) -> buck2_error::Result<()> {
+        self.inner.cancel().await
+    }
+}
+
+fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+unsafe fn write_file(path: &CStr, contents: &[u8]) -> io::Result<()> {
+    let fd = check(libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC))?;
+    let res = if libc::write(fd, contents.as_ptr().cast(), contents.len()) < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    };
+    libc::close(fd);
+    res
+}
+
+unsafe fn bind_mount(source: &CStr, target: &CStr) -> io::Result<()> {
+    check(libc::mount(
+        source.as_ptr(),
+        target.as_ptr(),
+        ptr::null(),
+        libc::MS_BIND | libc::MS_REC,
+        ptr::null(),
+    ))?;
+    Ok(())
+}
+
+unsafe fn remount_read_only(target: &CStr) -> io::Result<()> {
+    let mut stat = MaybeUninit::<libc::statvfs>::zeroed();
+    check(libc::statvfs(target.as_ptr(), stat.as_mut_ptr()))?;
+    let stat = stat.assume_init();
+
+    // Flags of mounts inherited from the parent user namespace are locked, and the remount fails
+    // unless they are preserved.
+    let mut flags = libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY;
+    for (st, ms) in [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ] {
+        if stat.f_flag & st != 0 {
+            flags |= ms;
+        }
+    }
+
+    check(libc::mount(
+        ptr::null(),
+        target.as_ptr(),
+        ptr::null(),
+        flags,
+        ptr::null(),
+    ))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::ffi::OsStringExt;
+
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_plan() -> buck2_error::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = tempdir.path().join("root");
+        std::fs::create_dir_all(root.join("src/nested"))?;
+        std::fs::write(root.join("src/a.c"), "")?;
+        std::fs::create_dir_all(root.join("out"))?;
+        let staging = AbsNormPathBuf::new(tempdir.path().join("staging"))?;
+
+        let bytes = |path: PathBuf| path.into_os_string().into_vec();
+        let sandbox = Sandbox {
+            root: bytes(root.clone()),
+            read_only_paths: vec![
+                bytes(root.join("src/nested")),
+                bytes(root.join("src")),
+                bytes(root.join("src/a.c")),
+                bytes(root.join("missing")),
+            ],
+            writable_paths: vec![bytes(root.join("out"))],
+            log_denials: true,
+        };
+        let plan = SandboxPlan::new(&sandbox, &staging, &root)?;
+
+        let in_staging = |rel: &str| cstring(&staging.as_path().join(rel)).unwrap();
+        assert_eq!(
+            vec![in_staging(""), in_staging("out"), in_staging("src")],
+            plan.dirs
+        );
+        assert!(plan.files.is_empty());
+        assert_eq!(
+            vec![(in_staging("src"), true), (in_staging("out"), false)],
+            plan.mounts
+                .iter()
+                .map(|m| (m.target.clone(), m.read_only))
+                .collect::<Vec<_>>()
+        );
+        let visibility = plan.visibility.as_ref().unwrap();
+        assert_eq!(vec![root.join("src"), root.join("out")], visibility.visible);
+        assert_eq!(root, visibility.cwd);
+
+        let unlogged = Sandbox {
+            log_denials: false,
+            ..sandbox
+        };
+        let plan = SandboxPlan::new(&unlogged, &staging, &root)?;
+        assert!(plan.visibility.is_none());
+
+        let outside = Sandbox {
+            root: bytes(root.clone()),
+            read_only_paths: vec![bytes(tempdir.path().join("elsewhere"))],
+            writable_paths: vec![],
+            log_denials: false,
+        };
+        assert!(SandboxPlan::new(&outside, &staging, &root).is_err());
+
+        Ok(())
+    }
+}
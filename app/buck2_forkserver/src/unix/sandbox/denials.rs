@@ -0,0 +1,665 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Recording what a sandboxed command was denied.
+//!
+//! The sandbox hides undeclared paths rather than refusing access to them, so a command reading
+//! an undeclared input just finds it missing. To tell those failures apart, the command's path
+//! lookups and connections are reported to a supervisor thread by a seccomp filter (using user
+//! notifications). The supervisor records lookups of paths which exist under the sandbox root but
+//! are hidden, as well as network connections, and then lets the system calls continue unchanged:
+//! it only observes the command.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::FileExt;
+use std::os::unix::net::UnixStream;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+
+/// Denials past this many are not recorded.
+const MAX_DENIALS: usize = 1000;
+
+/// How many processes' memory the supervisor keeps open.
+const MAX_OPEN_MEMORIES: usize = 1000;
+
+// Definitions from `linux/seccomp.h`, `linux/audit.h` and `linux/filter.h`, which the libc crate
+// doesn't all provide.
+const SECCOMP_SET_MODE_FILTER: libc::c_uint = 1;
+const SECCOMP_FILTER_FLAG_NEW_LISTENER: libc::c_ulong = 1 << 3;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff0000;
+const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc00000;
+const SECCOMP_USER_NOTIF_FLAG_CONTINUE: u32 = 1;
+const SECCOMP_IOCTL_NOTIF_RECV: libc::c_ulong = 0xc0502100;
+const SECCOMP_IOCTL_NOTIF_SEND: libc::c_ulong = 0xc0182101;
+const SECCOMP_IOCTL_NOTIF_ID_VALID: libc::c_ulong = 0x40082102;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_RET_K: u16 = 0x06;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc00000b7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+
+#[repr(C)]
+struct SeccompData {
+    nr: libc::c_int,
+    arch: u32,
+    instruction_pointer: u64,
+    args: [u64; 6],
+}
+
+#[repr(C)]
+struct SeccompNotif {
+    id: u64,
+    pid: u32,
+    flags: u32,
+    data: SeccompData,
+}
+
+#[repr(C)]
+struct SeccompNotifResp {
+    id: u64,
+    val: i64,
+    error: i32,
+    flags: u32,
+}
+
+/// Where a system call finds what it accesses in its arguments.
+#[derive(Clone, Copy)]
+enum Access {
+    /// A path (second index) relative to a directory file descriptor (first index).
+    PathAt(usize, usize),
+    /// A path relative to the working directory.
+    Path(usize),
+    /// A socket address, followed by its length.
+    Connect,
+}
+
+const SYSCALLS: &[(libc::c_long, Access)] = &[
+    (libc::SYS_openat, Access::PathAt(0, 1)),
+    (libc::SYS_openat2, Access::PathAt(0, 1)),
+    (libc::SYS_newfstatat, Access::PathAt(0, 1)),
+    (libc::SYS_statx, Access::PathAt(0, 1)),
+    (libc::SYS_faccessat, Access::PathAt(0, 1)),
+    (libc::SYS_faccessat2, Access::PathAt(0, 1)),
+    (libc::SYS_readlinkat, Access::PathAt(0, 1)),
+    (libc::SYS_execveat, Access::PathAt(0, 1)),
+    (libc::SYS_execve, Access::Path(0)),
+    (libc::SYS_connect, Access::Connect),
+];
+
+/// System calls which newer architectures only have in their `*at` form.
+#[cfg(target_arch = "x86_64")]
+const LEGACY_SYSCALLS: &[(libc::c_long, Access)] = &[
+    (libc::SYS_open, Access::Path(0)),
+    (libc::SYS_stat, Access::Path(0)),
+    (libc::SYS_lstat, Access::Path(0)),
+    (libc::SYS_access, Access::Path(0)),
+    (libc::SYS_readlink, Access::Path(0)),
+];
+#[cfg(not(target_arch = "x86_64"))]
+const LEGACY_SYSCALLS: &[(libc::c_long, Access)] = &[];
+
+fn syscalls() -> impl Iterator<Item = &'static (libc::c_long, Access)> {
+    SYSCALLS.iter().chain(LEGACY_SYSCALLS)
+}
+
+/// A filter which notifies the supervisor of the system calls above, and allows all others.
+fn filter(arch: u32) -> Vec<libc::sock_filter> {
+    let stmt = |code, k| libc::sock_filter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    let count = syscalls().count();
+
+    let mut filter = vec![
+        stmt(BPF_LD_W_ABS, mem::offset_of!(SeccompData, arch) as u32),
+        // Skip to the final "allow" for other architectures.
+        libc::sock_filter {
+            code: BPF_JMP_JEQ_K,
+            jt: 0,
+            jf: count as u8 + 1,
+            k: arch,
+        },
+        stmt(BPF_LD_W_ABS, mem::offset_of!(SeccompData, nr) as u32),
+    ];
+    for (i, (nr, _)) in syscalls().enumerate() {
+        // Jump to the final "notify".
+        filter.push(libc::sock_filter {
+            code: BPF_JMP_JEQ_K,
+            jt: (count - i) as u8,
+            jf: 0,
+            k: *nr as u32,
+        });
+    }
+    filter.push(stmt(BPF_RET_K, SECCOMP_RET_ALLOW));
+    filter.push(stmt(BPF_RET_K, SECCOMP_RET_USER_NOTIF));
+    filter
+}
+
+/// Whether the kernel can let notified system calls continue (Linux 5.5 and later), and reports
+/// when all the supervised processes exited (Linux 5.8 and later). Without the former, they could
+/// only be emulated, and without the latter, the supervisor can't tell when to stop, so the filter
+/// is not installed.
+fn supports_supervision() -> bool {
+    // SAFETY: `utsname` is plain data.
+    let mut utsname: libc::utsname = unsafe { mem::zeroed() };
+    if unsafe { libc::uname(&mut utsname) } < 0 {
+        return false;
+    }
+    let release = unsafe { CStr::from_ptr(utsname.release.as_ptr()) };
+    kernel_version(&release.to_string_lossy()) >= Some((5, 8))
+}
+
+/// Parses the major and minor version out of a kernel release, e.g. `6.4.3-0_fbk1`.
+fn kernel_version(release: &str) -> Option<(u32, u32)> {
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// Which paths under the sandbox root the sandboxed command can see.
+#[derive(Clone)]
+pub(crate) struct Visibility {
+    pub(crate) root: PathBuf,
+    /// The paths mounted in the sandbox. Their ancestors are visible too.
+    pub(crate) visible: Vec<PathBuf>,
+    /// The command's working directory. Only it and its ancestors are visible: it usually is the
+    /// sandbox root, whose contents are hidden unless mounted.
+    pub(crate) cwd: PathBuf,
+}
+
+impl Visibility {
+    fn is_hidden(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+            && !self.cwd.starts_with(path)
+            && !self
+                .visible
+                .iter()
+                .any(|visible| path.starts_with(visible) || visible.starts_with(path))
+    }
+}
+
+/// The command's end, which installs the filter right before the command executes.
+pub(crate) struct DenialListener {
+    socket: OwnedFd,
+    filter: Option<Vec<libc::sock_filter>>,
+}
+
+impl DenialListener {
+    /// Installs the filter and sends its listener to the supervisor. This happens between `fork`
+    /// and `exec`, so it does not allocate. If the filter can't be installed (e.g. on kernels
+    /// older than 5.8 or other architectures), the command just runs without recording what it is
+    /// denied.
+    pub(crate) unsafe fn install(&self) {
+        let Some(filter) = &self.filter else {
+            return;
+        };
+        // The notified system calls fail with `ENOSYS` if nothing listens to them, so don't install
+        // the filter if the supervisor is already gone.
+        let mut pollfd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: 0,
+            revents: 0,
+        };
+        if libc::poll(&mut pollfd, 1, 0) != 0 {
+            return;
+        }
+        let prog = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_ptr().cast_mut(),
+        };
+        // This doesn't require `no_new_privs`, since the command has `CAP_SYS_ADMIN` in the
+        // sandbox's user namespace.
+        let listener = libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            SECCOMP_FILTER_FLAG_NEW_LISTENER,
+            &prog as *const libc::sock_fprog,
+        );
+        if listener < 0 {
+            return;
+        }
+        let listener = listener as libc::c_int;
+        // This only fails if the supervisor exited since the check above.
+        let _ignored = send_fd(self.socket.as_raw_fd(), listener);
+        libc::close(listener);
+    }
+}
+
+/// The supervisor's end, from which the denials are read once the command exited. It is empty if
+/// the command isn't supervised.
+#[derive(Default)]
+pub(crate) struct DenialLog {
+    denials: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl DenialLog {
+    /// What the command was denied so far, sorted.
+    pub(crate) fn denials(&self) -> Vec<String> {
+        self.denials
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Starts a supervisor for a command which is about to run in the sandbox. It keeps running until
+/// the command and all the processes it spawned exited, even if their denials are no longer
+/// wanted, since closing the listener would make their system calls fail.
+pub(crate) fn supervise(visibility: Visibility) -> io::Result<(DenialListener, DenialLog)> {
+    let (child, parent) = UnixStream::pair()?;
+    let denials = Arc::new(Mutex::new(BTreeSet::new()));
+
+    // The supervisor must run on its own thread: spawning the command blocks until it executes,
+    // which is one of the system calls it waits for.
+    std::thread::Builder::new()
+        .name("sandbox-supervisor".to_owned())
+        .spawn({
+            let denials = denials.dupe();
+            move || {
+                if let Err(e) = run_supervisor(parent, &visibility, &denials) {
+                    tracing::debug!("Sandbox supervisor failed: {}", e);
+                }
+            }
+        })?;
+
+    Ok((
+        DenialListener {
+            socket: child.into(),
+            filter: AUDIT_ARCH.filter(|_| supports_supervision()).map(filter),
+        },
+        DenialLog { denials },
+    ))
+}
+
+fn run_supervisor(
+    socket: UnixStream,
+    visibility: &Visibility,
+    denials: &Arc<Mutex<BTreeSet<String>>>,
+) -> io::Result<()> {
+    // The command closes the socket without sending anything if it didn't install the filter.
+    let Some(listener) = receive_fd(&socket)? else {
+        return Ok(());
+    };
+    drop(socket);
+
+    let mut memories = Memories::default();
+    loop {
+        let mut pollfd = libc::pollfd {
+            fd: listener.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Errors here are transient (`EINTR` or `ENOMEM`): giving up would close the listener.
+        if unsafe { libc::poll(&mut pollfd, 1, -1) } <= 0 {
+            continue;
+        }
+        if pollfd.revents & libc::POLLIN == 0 {
+            // All the processes using the filter exited.
+            return Ok(());
+        }
+
+        // SAFETY: the kernel requires the notification to be zeroed.
+        let mut notif: SeccompNotif = unsafe { mem::zeroed() };
+        if unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                SECCOMP_IOCTL_NOTIF_RECV as _,
+                &mut notif,
+            )
+        } < 0
+        {
+            let e = io::Error::last_os_error();
+            match e.raw_os_error() {
+                // The process was killed while waiting, or we were interrupted.
+                Some(libc::ENOENT | libc::EINTR) => continue,
+                // Nothing can be received, and therefore answered, anymore.
+                _ => return Err(e),
+            }
+        }
+
+        // Whatever happens while recording the denial, the system call must continue.
+        let denial = panic::catch_unwind(AssertUnwindSafe(|| {
+            denial(&notif, visibility, &mut memories)
+        }))
+        .unwrap_or_else(|_| {
+            memories = Memories::default();
+            None
+        });
+
+        // The process might have been killed and its pid reused while we read its memory, in
+        // which case what we read is meaningless.
+        let valid = unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                SECCOMP_IOCTL_NOTIF_ID_VALID as _,
+                &notif.id,
+            )
+        } == 0;
+        if let (true, Some(denial)) = (valid, denial) {
+            let mut denials = denials.lock().unwrap_or_else(|e| e.into_inner());
+            if denials.len() < MAX_DENIALS {
+                denials.insert(denial);
+            }
+        }
+
+        let mut resp = SeccompNotifResp {
+            id: notif.id,
+            val: 0,
+            error: 0,
+            flags: SECCOMP_USER_NOTIF_FLAG_CONTINUE,
+        };
+        // This fails if the process was killed in the meantime, which is fine.
+        unsafe {
+            libc::ioctl(
+                listener.as_raw_fd(),
+                SECCOMP_IOCTL_NOTIF_SEND as _,
+                &mut resp,
+            );
+        }
+    }
+}
+
+/// The memory of the supervised processes, kept open across their system calls.
+#[derive(Default)]
+struct Memories(HashMap<u32, File>);
+
+impl Memories {
+    /// Reads from the memory of a process, opening it again if it executed another program (or
+    /// exited and its pid was reused) since it was opened.
+    fn read_at(&mut self, pid: u32, buf: &mut [u8], addr: u64) -> Option<usize> {
+        if let Some(Ok(read @ 1..)) = self.0.get(&pid).map(|mem| mem.read_at(buf, addr)) {
+            return Some(read);
+        }
+        if self.0.len() >= MAX_OPEN_MEMORIES {
+            self.0.clear();
+        }
+        let mem = File::open(format!("/proc/{}/mem", pid)).ok()?;
+        let read = mem.read_at(buf, addr).ok()?;
+        self.0.insert(pid, mem);
+        Some(read)
+    }
+}
+
+/// What the notified system call is denied, if anything.
+fn denial(
+    notif: &SeccompNotif,
+    visibility: &Visibility,
+    memories: &mut Memories,
+) -> Option<String> {
+    let (_, access) = syscalls().find(|(nr, _)| *nr == notif.data.nr as libc::c_long)?;
+    let args = &notif.data.args;
+    let mut read_at = |buf: &mut [u8], addr| memories.read_at(notif.pid, buf, addr);
+
+    let (dirfd, path) = match *access {
+        Access::PathAt(dirfd, path) => (args[dirfd] as libc::c_int, args[path]),
+        Access::Path(path) => (libc::AT_FDCWD, args[path]),
+        Access::Connect => {
+            let addr = read_socket_addr(&mut read_at, args[1], args[2])?;
+            return Some(format!("network access to `{}`", addr));
+        }
+    };
+
+    let path = read_path(&mut read_at, path)?;
+    let path = if path.is_absolute() {
+        normalize(&path)
+    } else if path.as_os_str().is_empty() {
+        // Accessing the directory file descriptor itself.
+        return None;
+    } else {
+        let dir = if dirfd == libc::AT_FDCWD {
+            format!("/proc/{}/cwd", notif.pid)
+        } else {
+            format!("/proc/{}/fd/{}", notif.pid, dirfd)
+        };
+        normalize(&std::fs::read_link(dir).ok()?.join(path))
+    };
+
+    // Paths which don't exist outside of the sandbox either were not hidden by it.
+    if visibility.is_hidden(&path) && path.symlink_metadata().is_ok() {
+        Some(format!("undeclared input `{}`", path.display()))
+    } else {
+        None
+    }
+}
+
+/// Reads a NUL-terminated path from the memory of a process.
+fn read_path(
+    read_at: &mut impl FnMut(&mut [u8], u64) -> Option<usize>,
+    mut addr: u64,
+) -> Option<PathBuf> {
+    // Read at most up to the next 4 KiB boundary each time, since the next page might not be
+    // mapped.
+    const CHUNK: u64 = 4096;
+
+    let mut path = Vec::new();
+    let mut buf = [0; CHUNK as usize];
+    while path.len() < libc::PATH_MAX as usize {
+        let len = (CHUNK - addr % CHUNK) as usize;
+        let read = read_at(&mut buf[..len], addr)?;
+        if read == 0 {
+            return None;
+        }
+        if let Some(end) = buf[..read].iter().position(|b| *b == 0) {
+            path.extend_from_slice(&buf[..end]);
+            return Some(PathBuf::from(OsString::from_vec(path)));
+        }
+        path.extend_from_slice(&buf[..read]);
+        addr += read as u64;
+    }
+    None
+}
+
+/// Reads an IPv4 or IPv6 socket address from the memory of a process.
+fn read_socket_addr(
+    read_at: &mut impl FnMut(&mut [u8], u64) -> Option<usize>,
+    addr: u64,
+    len: u64,
+) -> Option<SocketAddr> {
+    // Large enough for `sockaddr_in6`, which starts like `sockaddr_in`.
+    let mut buf = [0; 24];
+    let len = (len as usize).min(buf.len());
+    if read_at(&mut buf[..len], addr)? < len {
+        return None;
+    }
+
+    let family = u16::from_ne_bytes([buf[0], buf[1]]);
+    let port = u16::from_be_bytes([buf[2], buf[3]]);
+    match libc::c_int::from(family) {
+        libc::AF_INET if len >= 8 => {
+            let ip: [u8; 4] = buf[4..8].try_into().ok()?;
+            Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port))
+        }
+        libc::AF_INET6 if len >= 24 => {
+            let ip: [u8; 16] = buf[8..24].try_into().ok()?;
+            Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port))
+        }
+        _ => None,
+    }
+}
+
+/// Removes `.` and `..` components without resolving symlinks, like the sandbox's bind mounts
+/// are matched.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn receive_fd(socket: &UnixStream) -> io::Result<Option<OwnedFd>> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: (&mut byte as *mut u8).cast(),
+        iov_len: 1,
+    };
+    // `u64` for alignment.
+    let mut control = [0u64; 8];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    loop {
+        let ret = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+        if ret > 0 {
+            break;
+        } else if ret == 0 {
+            return Ok(None);
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_level != libc::SOL_SOCKET
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Ok(None);
+        }
+        let fd = ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+        Ok(Some(OwnedFd::from_raw_fd(fd)))
+    }
+}
+
+unsafe fn send_fd(socket: libc::c_int, fd: libc::c_int) -> io::Result<()> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: (&mut byte as *mut u8).cast(),
+        iov_len: 1,
+    };
+    let mut control = [0u64; 8];
+    let mut msg: libc::msghdr = mem::zeroed();
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) as _;
+
+    let cmsg = libc::CMSG_FIRSTHDR(&msg);
+    (*cmsg).cmsg_level = libc::SOL_SOCKET;
+    (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::c_int>() as u32) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, fd);
+
+    if libc::sendmsg(socket, &msg, 0) < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_hidden() {
+        let visibility = Visibility {
+            root: PathBuf::from("/repo"),
+            visible: vec![PathBuf::from("/repo/src"), PathBuf::from("/repo/out/a")],
+            cwd: PathBuf::from("/repo"),
+        };
+        assert!(!visibility.is_hidden(Path::new("/usr/include/stdio.h")));
+        assert!(!visibility.is_hidden(Path::new("/repo")));
+        assert!(!visibility.is_hidden(Path::new("/repo/out")));
+        assert!(!visibility.is_hidden(Path::new("/repo/src/a.c")));
+        assert!(!visibility.is_hidden(Path::new("/repo/out/a")));
+        assert!(visibility.is_hidden(Path::new("/repo/out/b")));
+        assert!(visibility.is_hidden(Path::new("/repo/srcs")));
+        assert!(visibility.is_hidden(Path::new("/repo/include/a.h")));
+        assert!(visibility.is_hidden(Path::new("/repo/undeclared.txt")));
+    }
+
+    #[test]
+    fn test_is_hidden_under_cwd() {
+        let visibility = Visibility {
+            root: PathBuf::from("/repo"),
+            visible: vec![PathBuf::from("/repo/src")],
+            cwd: PathBuf::from("/repo/pkg/dir"),
+        };
+        assert!(!visibility.is_hidden(Path::new("/repo/pkg")));
+        assert!(!visibility.is_hidden(Path::new("/repo/pkg/dir")));
+        assert!(visibility.is_hidden(Path::new("/repo/pkg/dir/undeclared.txt")));
+        assert!(visibility.is_hidden(Path::new("/repo/pkg/other")));
+    }
+
+    #[test]
+    fn test_kernel_version() {
+        assert_eq!(Some((6, 4)), kernel_version("6.4.3-0_fbk1_rc2"));
+        assert_eq!(Some((5, 15)), kernel_version("5.15.0-91-generic"));
+        assert_eq!(Some((4, 19)), kernel_version("4.19.0"));
+        assert_eq!(None, kernel_version("unknown"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            PathBuf::from("/repo/include/a.h"),
+            normalize(Path::new("/repo/src/./../include/a.h"))
+        );
+        assert_eq!(PathBuf::from("/"), normalize(Path::new("/../..")));
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = filter(0xc000003e);
+        let count = syscalls().count();
+        assert_eq!(count + 5, filter.len());
+        // The architecture check skips to "allow", and every system call jumps to "notify".
+        assert_eq!(SECCOMP_RET_ALLOW, filter[1 + filter[1].jf as usize + 1].k);
+        for i in 0..count {
+            let jeq = &filter[3 + i];
+            assert_eq!(
+                SECCOMP_RET_USER_NOTIF,
+                filter[3 + i + jeq.jt as usize + 1].k
+            );
+        }
+    }
+}
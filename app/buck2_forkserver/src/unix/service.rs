@@ -55,6 +55,8 @@ use crate::run::stream_command_events;
 use crate::run::timeout_into_cancellation;
 use crate::run::DefaultKillProcess;
 use crate::run::GatherOutputStatus;
+use crate::unix::cgroup::ActionCgroups;
+use crate::unix::cgroup::CgroupStatusDecoder;
 
 // Not quite BoxStream: it has to be Sync (...)
 type RunStream =
@@ -68,6 +70,13 @@ pub struct UnixForkserverService {
 
     /// Systemd runner for resource control
     systemd_runner: Option<SystemdRunner>,
+
+    /// Cgroups for resource control without systemd.
+    action_cgroups: Option<ActionCgroups>,
+
+    /// The directory on which sandboxes are assembled (each in its own mount namespace).
+    #[cfg_attr(not(target_os = "linux"), allow(unused))]
+    sandbox_staging_dir: AbsNormPathBuf,
 }
 
 impl UnixForkserverService {
@@ -84,10 +93,21 @@ impl UnixForkserverService {
                 // for this we inherit slice
                 ParentSlice::Inherit("forkserver".to_owned()),
             ))?;
+        let action_cgroups = ActionCgroups::new(&resource_control)?;
+        if systemd_runner.is_some() && action_cgroups.is_some() {
+            return Err(buck2_error::buck2_error!(
+                [],
+                "`action_cgroup_root` cannot be used together with systemd resource control"
+            ));
+        }
+        let sandbox_staging_dir = state_dir.join(ForwardRelativePath::unchecked_new("sandbox"));
+        fs_util::create_dir_all(&sandbox_staging_dir)?;
         Ok(Self {
             log_reload_handle,
             miniperf,
             systemd_runner,
+            action_cgroups,
+            sandbox_staging_dir,
         })
     }
 }
@@ -131,6 +151,7 @@ impl Forkserver for UnixForkserverService {
                 std_redirects,
                 graceful_shutdown_timeout_s,
                 action_digest,
+                sandbox,
             } = msg;
 
             let exe = OsStr::from_bytes(&exe);
@@ -146,6 +167,18 @@ impl Forkserver for UnixForkserverService {
             let exe = maybe_absolutize_exe(exe, cwd)?;
             let systemd_context = self.systemd_runner.as_ref().zip(action_digest);
 
+            if sandbox.is_some() && systemd_context.is_some() {
+                return Err(buck2_error::buck2_error!(
+                    [],
+                    "Sandboxed execution is not supported together with resource control"
+                )
+                .into());
+            }
+
+            // The Miniperf binary lives in the forkserver state dir, which is not visible from the
+            // sandbox.
+            let enable_miniperf = enable_miniperf && sandbox.is_none();
+
             let (mut cmd, miniperf_output) =
                 match (enable_miniperf, &self.miniperf, &systemd_context) {
                     (true, Some(miniperf), None) => {
@@ -206,6 +239,42 @@ impl Forkserver for UnixForkserverService {
                 cmd.env("MINIPERF_READ_CGROUP", "1");
             }
 
+            #[cfg(target_os = "linux")]
+            let sandbox_plan = sandbox
+                .map(|sandbox| {
+                    crate::unix::sandbox::SandboxPlan::new(
+                        &sandbox,
+                        &self.sandbox_staging_dir,
+                        cwd.as_path(),
+                    )
+                })
+                .transpose()?;
+
+            #[cfg(not(target_os = "linux"))]
+            if sandbox.is_some() {
+                return Err(buck2_error::buck2_error!(
+                    [],
+                    "Sandboxed execution is only supported on Linux"
+                )
+                .into());
+            }
+
+            // Join the cgroup first, from outside of the sandbox's namespaces. It is created only
+            // once the sandbox is planned, and removed when dropped if the command doesn't start.
+            let cgroup = match &self.action_cgroups {
+                Some(action_cgroups) => {
+                    let cgroup = action_cgroups.create()?;
+                    cgroup.apply(&mut cmd)?;
+                    Some(cgroup)
+                }
+                None => None,
+            };
+
+            #[cfg(target_os = "linux")]
+            let denial_log = sandbox_plan
+                .map(|sandbox_plan| sandbox_plan.apply(&mut cmd))
+                .transpose()?;
+
             let stream_stdio = std_redirects.is_none();
             let mut cmd = ProcessCommand::new(cmd);
             if let Some(std_redirects) = std_redirects {
@@ -223,23 +292,34 @@ impl Forkserver for UnixForkserverService {
                 Some(out) => stream_command_events(
                     process_group,
                     cancellation,
-                    MiniperfStatusDecoder::new(out),
+                    CgroupStatusDecoder::new(MiniperfStatusDecoder::new(out), cgroup),
                     DefaultKillProcess {
                         graceful_shutdown_timeout_s,
                     },
                     stream_stdio,
                 )?
                 .left_stream(),
-                None => stream_command_events(
-                    process_group,
-                    cancellation,
-                    DefaultStatusDecoder,
-                    DefaultKillProcess {
-                        graceful_shutdown_timeout_s,
-                    },
-                    stream_stdio,
-                )?
-                .right_stream(),
+                None => {
+                    // Sandboxed commands never use Miniperf.
+                    #[cfg(target_os = "linux")]
+                    let decoder = crate::unix::sandbox::SandboxStatusDecoder::new(
+                        DefaultStatusDecoder,
+                        denial_log,
+                    );
+                    #[cfg(not(target_os = "linux"))]
+                    let decoder = DefaultStatusDecoder;
+
+                    stream_command_events(
+                        process_group,
+                        cancellation,
+                        CgroupStatusDecoder::new(decoder, cgroup),
+                        DefaultKillProcess {
+                            graceful_shutdown_timeout_s,
+                        },
+                        stream_stdio,
+                    )?
+                    .right_stream()
+                }
             };
             let stream = encode_event_stream(stream);
             Ok(Box::pin(stream) as _)
@@ -8,6 +8,7 @@
  */
 
 use std::ops::DerefMut;
+use std::time::Duration;
 
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::legacy_configs::key::BuckconfigKeyRef;
@@ -48,16 +49,43 @@ pub async fn with_starlark_eval_provider<'a, D: DerefMut<Target = DiceComputatio
                 property: "starlark_max_callstack_size",
             })?;
 
+    let budgets = StarlarkEvalBudgets {
+        max_instructions: root_buckconfig.view(&mut ctx).parse(BuckconfigKeyRef {
+            section: "buck2",
+            property: "starlark_max_instructions",
+        })?,
+        max_heap_bytes: root_buckconfig.view(&mut ctx).parse(BuckconfigKeyRef {
+            section: "buck2",
+            property: "starlark_max_heap_bytes",
+        })?,
+        timeout: root_buckconfig
+            .view(&mut ctx)
+            .parse(BuckconfigKeyRef {
+                section: "buck2",
+                property: "starlark_timeout_ms",
+            })?
+            .map(Duration::from_millis),
+    };
+
     let debugger_handle = ctx.get_starlark_debugger_handle();
     let debugger = match debugger_handle {
         Some(v) => Some(v.start_eval(&description).await?),
         None => None,
     };
 
+    /// Limits on each evaluation, so that a runaway BUCK or `.bzl` file fails instead of hanging
+    /// or exhausting the daemon's memory.
+    struct StarlarkEvalBudgets {
+        max_instructions: Option<u64>,
+        max_heap_bytes: Option<usize>,
+        timeout: Option<Duration>,
+    }
+
     struct EvalProvider<'a, 'b> {
         profiler: &'a mut StarlarkProfilerOpt<'b>,
         debugger: Option<Box<dyn StarlarkDebugController>>,
         starlark_max_callstack_size: Option<usize>,
+        budgets: StarlarkEvalBudgets,
     }
 
     impl StarlarkEvaluatorProvider for EvalProvider<'_, '_> {
@@ -69,6 +97,16 @@ pub async fn with_starlark_eval_provider<'a, D: DerefMut<Target = DiceComputatio
             if let Some(stack_size) = self.starlark_max_callstack_size {
                 eval.set_max_callstack_size(stack_size)?;
             }
+            if let Some(max) = self.budgets.max_instructions {
+                eval.set_max_instructions(max);
+            }
+            if let Some(max) = self.budgets.max_heap_bytes {
+                eval.set_max_heap_bytes(max);
+            }
+            // Time stopped at a breakpoint would count against the timeout.
+            if let (Some(timeout), None) = (self.budgets.timeout, &self.debugger) {
+                eval.set_timeout(timeout);
+            }
 
             let is_profiling_enabled = self.profiler.initialize(&mut eval)?;
             if let Some(v) = &mut self.debugger {
@@ -94,6 +132,7 @@ pub async fn with_starlark_eval_provider<'a, D: DerefMut<Target = DiceComputatio
             profiler: profiler_instrumentation,
             debugger,
             starlark_max_callstack_size,
+            budgets,
         };
 
         // If we're debugging, we need to move this to a tokio blocking task.
@@ -179,6 +179,21 @@ impl CqueryUniverse {
         configured_nodes
     }
 
+    /// All the configured targets in the universe defined in any of `packages`.
+    pub fn get_from_packages(
+        &self,
+        packages: impl IntoIterator<Item = PackageLabel>,
+    ) -> TargetSet<ConfiguredTargetNode> {
+        let mut targets = TargetSet::new();
+        for package in packages {
+            targets.extend(
+                self.get_from_package(package, &PackageSpec::<TargetPatternExtra>::All)
+                    .map(|(node, TargetPatternExtra)| node.to_owned()),
+            );
+        }
+        targets
+    }
+
     pub fn get_target_label(&self, label: &TargetLabel) -> Vec<ConfiguredTargetLabel> {
         self.get_from_package(
             label.pkg(),
@@ -67,6 +67,10 @@ impl QueryTarget for TargetNode {
         Some(self.tests().map(|t| t.target().dupe()))
     }
 
+    fn is_visible_to(&self, other: &Self) -> buck2_error::Result<bool> {
+        TargetNode::is_visible_to(self, other.label())
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr<'_>,
         filter: &dyn Fn(&str) -> buck2_error::Result<bool>,
@@ -109,6 +109,12 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
         None::<iter::Empty<Self::Key>>
     }
 
+    /// Whether `other` is allowed to depend on this target, according to this target's
+    /// `visibility`. `visible()` function uses this.
+    fn is_visible_to(&self, _other: &Self) -> buck2_error::Result<bool> {
+        Err(QueryError::FunctionUnimplemented("visible").into())
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr<'_>,
         filter: &dyn Fn(&str) -> buck2_error::Result<bool>,
@@ -365,6 +371,27 @@ pub trait QueryEnvironment: Send + Sync {
         deps(self, targets, depth, filter).await
     }
 
+    /// All targets defined in the same packages as `targets`, including `targets` themselves.
+    async fn siblings(
+        &self,
+        _targets: &TargetSet<Self::Target>,
+    ) -> buck2_error::Result<TargetSet<Self::Target>> {
+        Err(QueryError::FunctionUnimplemented(
+            "siblings() is implemented only for uquery and cquery.",
+        )
+        .into())
+    }
+
+    /// Targets in the same packages as `targets` that depend directly on any of `targets`.
+    async fn same_pkg_direct_rdeps(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> buck2_error::Result<TargetSet<Self::Target>> {
+        self.siblings(targets)
+            .await?
+            .filter(|target| Ok(target.deps().any(|dep| targets.contains(dep))))
+    }
+
     async fn owner(&self, _paths: &FileSet) -> buck2_error::Result<TargetSet<Self::Target>>;
 
     async fn targets_in_buildfile(
@@ -10,6 +10,7 @@
 #![cfg(test)]
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::sync::Arc;
 
@@ -27,6 +28,13 @@ struct TestTargetId(u64);
 
 impl NodeKey for TestTargetId {}
 
+impl TestTargetId {
+    /// Targets are grouped into packages by all but the last digit of their ID.
+    fn package(self) -> u64 {
+        self.0 / 10
+    }
+}
+
 #[derive(Debug, Copy, Clone, Dupe, Eq, PartialEq, Hash, Display)]
 struct TestTargetAttr;
 
@@ -90,6 +98,11 @@ impl QueryTarget for TestTarget {
         Box::new(std::iter::empty())
     }
 
+    /// Targets with an even ID are public, the rest are only visible within their package.
+    fn is_visible_to(&self, other: &Self) -> buck2_error::Result<bool> {
+        Ok(self.id.0 % 2 == 0 || self.id.package() == other.id.package())
+    }
+
     fn attr_any_matches(
         _attr: &Self::Attr<'_>,
         _filter: &dyn Fn(&str) -> buck2_error::Result<bool>,
@@ -198,6 +211,22 @@ impl QueryEnvironment for TestEnv {
         async_depth_limited_traversal(self, root.iter_names(), delegate, visit, depth).await
     }
 
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> buck2_error::Result<TargetSet<Self::Target>> {
+        let packages: HashSet<u64> = targets.iter().map(|t| t.id.package()).collect();
+        let mut ids: Vec<_> = self
+            .graph
+            .keys()
+            .filter(|id| packages.contains(&id.package()))
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids.into_iter()
+            .map(|id| <Self as NodeLookup<TestTarget>>::get(self, id))
+            .collect()
+    }
+
     async fn owner(&self, _paths: &FileSet) -> buck2_error::Result<TargetSet<Self::Target>> {
         unimplemented!()
     }
@@ -427,3 +456,55 @@ async fn test_rdeps() -> buck2_error::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_siblings() -> buck2_error::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(10, 11);
+    env.edge(12, 11);
+    env.edge(13, 20);
+    env.edge(20, 11);
+    env.edge(21, 22);
+    let env = env.build();
+
+    assert_eq!(
+        env.siblings(&env.set("11")?).await?,
+        env.set("10,11,12,13")?
+    );
+    assert_eq!(
+        env.siblings(&env.set("11,20")?).await?,
+        env.set("10,11,12,13,20,21,22")?
+    );
+
+    // Only direct dependents in the same package.
+    assert_eq!(
+        env.same_pkg_direct_rdeps(&env.set("11")?).await?,
+        env.set("10,12")?
+    );
+    assert_eq!(
+        env.same_pkg_direct_rdeps(&env.set("11,22")?).await?,
+        env.set("10,12,20,21")?
+    );
+    assert_eq!(
+        env.same_pkg_direct_rdeps(&env.set("10")?).await?,
+        TargetSet::new()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_visible_to() -> buck2_error::Result<()> {
+    let mut env = TestEnvBuilder::default();
+    env.edge(10, 11);
+    env.edge(12, 13);
+    env.edge(20, 21);
+    let env = env.build();
+
+    let targets = env.set("10,11,12,13,21")?;
+    assert_eq!(targets.visible_to(&env.set("20")?)?, env.set("10,12,21")?);
+    assert_eq!(targets.visible_to(&env.set("20,11")?)?, env.set("10,12")?);
+    assert_eq!(targets.visible_to(&TargetSet::new())?, targets);
+
+    Ok(())
+}
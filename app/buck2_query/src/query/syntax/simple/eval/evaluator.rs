@@ -25,6 +25,7 @@ use crate::query::syntax::simple::eval::set::TargetSet;
 use crate::query::syntax::simple::eval::values::QueryEvaluationValue;
 use crate::query::syntax::simple::eval::values::QueryResult;
 use crate::query::syntax::simple::eval::values::QueryValue;
+use crate::query::syntax::simple::functions::LetQueryFunctions;
 use crate::query::syntax::simple::functions::QueryFunctions;
 pub struct QueryEvaluator<'e, Env: QueryEnvironment> {
     env: &'e Env,
@@ -105,6 +106,18 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
 
                 Ok(files.into())
             }
+            Expr::Let { name, value, body } => {
+                // Evaluate the value once, however many times the body refers to it. Functions
+                // that evaluate captured expressions see the binding through `functions`.
+                let value = self.eval(value).await?.value;
+                let functions = LetQueryFunctions::bind(self.functions, name.fragment(), value);
+                let evaluator = QueryEvaluator::new(self.env, &functions);
+                Ok(evaluator.eval(body).await?.value)
+            }
+            Expr::Variable(name) => match self.functions.get_variable(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(QueryError::UnboundVariable((*name).to_owned())),
+            },
         }
     }
 
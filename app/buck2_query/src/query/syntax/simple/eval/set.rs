@@ -218,6 +218,18 @@ impl<T: QueryTarget> TargetSet<T> {
         self.filter(|node| Ok(re.is_match(&node.rule_type())?))
     }
 
+    /// Targets that every target in `scope` is allowed to depend on.
+    pub fn visible_to(&self, scope: &TargetSet<T>) -> buck2_error::Result<TargetSet<T>> {
+        self.filter(|node| {
+            for target in scope.iter() {
+                if !node.is_visible_to(target)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })
+    }
+
     pub fn intersect(&self, right: &TargetSet<T>) -> buck2_error::Result<TargetSet<T>> {
         self.filter(|node| Ok(right.contains(node.node_key())))
     }
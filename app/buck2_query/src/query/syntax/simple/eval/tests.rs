@@ -12,12 +12,16 @@
 #![cfg(test)]
 
 use std::borrow::Cow;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 
 use async_trait::async_trait;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_query_parser::parse_expr;
+use buck2_query_parser::BinaryOp;
+use buck2_query_parser::SpannedExpr;
 use derive_more::Display;
 use dupe::Dupe;
 
@@ -30,7 +34,12 @@ use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use crate::query::syntax::simple::eval::file_set::FileSet;
 use crate::query::syntax::simple::eval::set::TargetSet;
+use crate::query::syntax::simple::eval::values::QueryValue;
+use crate::query::syntax::simple::functions::helpers::QueryArgType;
+use crate::query::syntax::simple::functions::helpers::QueryBinaryOp;
+use crate::query::syntax::simple::functions::helpers::QueryFunction;
 use crate::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+use crate::query::syntax::simple::functions::QueryFunctions;
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Display)]
 struct TargetRef(String);
@@ -216,3 +225,99 @@ pub async fn test_missing_arg() -> buck2_error::Result<()> {
     }
     Ok(())
 }
+
+/// Provides `count(...)`, which evaluates its arguments and returns how many times it was called
+/// before.
+#[derive(Debug, Default)]
+struct CountingFunctions {
+    calls: AtomicU64,
+}
+
+#[async_trait]
+impl QueryFunction<Env> for CountingFunctions {
+    fn name(&self) -> &'static str {
+        "count"
+    }
+
+    async fn invoke(
+        &self,
+        evaluator: &QueryEvaluator<Env>,
+        args: &[SpannedExpr<'_>],
+    ) -> Result<QueryValue<Target>, QueryError> {
+        for arg in args {
+            evaluator.eval(arg).await?;
+        }
+        Ok(QueryValue::Integer(
+            self.calls.fetch_add(1, Ordering::SeqCst),
+        ))
+    }
+
+    fn arg_type(&self, _idx: usize) -> Result<QueryArgType, QueryError> {
+        Ok(QueryArgType::Value)
+    }
+}
+
+impl QueryFunctions for CountingFunctions {
+    type Env = Env;
+
+    fn get(&self, name: &str) -> Option<&dyn QueryFunction<Env>> {
+        if name == "count" {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    fn get_op(&self, _op: BinaryOp) -> Option<&dyn QueryBinaryOp<Env>> {
+        None
+    }
+}
+
+async fn eval_counting(input: &str) -> buck2_error::Result<(QueryValue<Target>, u64)> {
+    let functions = CountingFunctions::default();
+    let parsed = parse_expr(input)?;
+    let value = QueryEvaluator::new(&Env, &functions)
+        .eval(&parsed)
+        .await
+        .map_err(|e| QueryError::convert_error(e, input))?;
+    Ok((value.value, functions.calls.load(Ordering::SeqCst)))
+}
+
+#[tokio::test]
+pub async fn test_let_evaluates_once() -> buck2_error::Result<()> {
+    assert_eq!(
+        (QueryValue::Integer(3), 4),
+        eval_counting("count(count(), count(), count())").await?
+    );
+    assert_eq!(
+        (QueryValue::Integer(1), 2),
+        eval_counting("let x = count() in count($x, $x, $x)").await?
+    );
+    assert_eq!(
+        (QueryValue::Integer(0), 1),
+        eval_counting("let x = count() in let y = $x in $y").await?
+    );
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_let_scope() -> buck2_error::Result<()> {
+    assert_eq!(
+        (QueryValue::String("b".to_owned()), 0),
+        eval_counting("let x = a in let x = b in $x").await?
+    );
+    assert_eq!(
+        (QueryValue::String("a".to_owned()), 0),
+        eval_counting("let x = a in let y = b in $x").await?
+    );
+
+    let err = eval_counting("count(let x = a in $x, $x)")
+        .await
+        .unwrap_err();
+    let msg = format!("{:#}", err);
+    let expected = "variable `$x` is not bound by an enclosing `let`";
+    if !msg.contains(expected) {
+        return Err(err.context(format!("Expected error to contain `{}`", expected)));
+    }
+    Ok(())
+}
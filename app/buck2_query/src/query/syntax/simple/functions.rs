@@ -55,6 +55,14 @@ pub trait QueryFunctions: Debug + Send + Sync {
     fn get(&self, name: &str) -> Option<&dyn QueryFunction<Self::Env>>;
 
     fn get_op(&self, op: BinaryOp) -> Option<&dyn QueryBinaryOp<Self::Env>>;
+
+    /// The value of a variable bound by an enclosing `let`.
+    fn get_variable(
+        &self,
+        _name: &str,
+    ) -> Option<&QueryValue<<Self::Env as QueryEnvironment>::Target>> {
+        None
+    }
 }
 
 pub trait QueryFunctionsVisitLiterals: Debug + Send + Sync {
@@ -116,6 +124,13 @@ impl<F: QueryFunctions> QueryFunctionsVisitLiterals for F {
                     Ok(())
                 }
                 Expr::FileSet(_args) => Ok(()),
+                Expr::Let { value, body, .. } => {
+                    // The literals in the value are visited once, wherever the variable is used.
+                    visit_literals_item(this, visitor, value, true)?;
+                    visit_literals_item(this, visitor, body, true)?;
+                    Ok(())
+                }
+                Expr::Variable(..) => Ok(()),
                 Expr::String(..) | Expr::Integer(..) => {
                     panic!(
                         "This shouldn't be called with literals, they should be handled in the caller"
@@ -507,10 +522,62 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// Find the targets in the same packages as the given targets that depend on them directly.
+    ///
+    /// For example:
+    ///
+    /// ```text
+    /// $ buck2 uquery "same_pkg_direct_rdeps(//foo:lib)"
+    /// ```
+    /// returns the targets in the `//foo` package that have `//foo:lib` as a direct dependency.
+    async fn same_pkg_direct_rdeps(
+        &self,
+        env: &Env,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .same_pkg_direct_rdeps(env, &targets)
+            .await?
+            .into())
+    }
+
+    /// All targets in the same packages as the given targets.
+    ///
+    /// The returned values include the `targets` themselves. For example:
+    ///
+    /// ```text
+    /// $ buck2 uquery "siblings(//foo:lib)"
+    /// ```
+    /// returns every target defined in the `//foo` package.
+    async fn siblings(&self, env: &Env, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
+        Ok(self.implementation.siblings(env, &targets).await?.into())
+    }
+
     async fn testsof(&self, env: &Env, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.testsof(env, &targets).await?.into())
     }
 
+    /// Filter targets by visibility.
+    ///
+    /// Returns the subset of `targets` that every target in `scope` is allowed to depend on,
+    /// according to the `visibility` attribute of each target. Targets are always visible to
+    /// other targets in the same package. This applies the same checks as `buck2 audit visibility`.
+    ///
+    /// For example:
+    ///
+    /// ```text
+    /// $ buck2 uquery "visible(//bar:bin, //foo/...)"
+    /// ```
+    /// returns the targets under `//foo/...` that `//bar:bin` may depend on.
+    async fn visible(
+        &self,
+        scope: TargetSet<Env::Target>,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self.implementation.visible(&scope, &targets)?.into())
+    }
+
     // These three functions are intentionally implemented as errors. They are only available within the context
     // of a deps functions 3rd parameter expr. When used in that context, the QueryFunctions will be augmented to
     // have non-erroring implementations.
@@ -789,6 +856,22 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         .await
     }
 
+    pub async fn same_pkg_direct_rdeps(
+        &self,
+        env: &Env,
+        targets: &TargetSet<Env::Target>,
+    ) -> buck2_error::Result<TargetSet<Env::Target>> {
+        env.same_pkg_direct_rdeps(targets).await
+    }
+
+    pub async fn siblings(
+        &self,
+        env: &Env,
+        targets: &TargetSet<Env::Target>,
+    ) -> buck2_error::Result<TargetSet<Env::Target>> {
+        env.siblings(targets).await
+    }
+
     pub async fn testsof(
         &self,
         env: &Env,
@@ -797,6 +880,14 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         env.testsof(targets).await
     }
 
+    pub fn visible(
+        &self,
+        scope: &TargetSet<Env::Target>,
+        targets: &TargetSet<Env::Target>,
+    ) -> buck2_error::Result<TargetSet<Env::Target>> {
+        targets.visible_to(scope)
+    }
+
     pub async fn testsof_with_default_target_platform(
         &self,
         env: &Env,
@@ -907,4 +998,55 @@ impl<'a, Env: QueryEnvironment> QueryFunctions for AugmentedQueryFunctions<'a, E
             Some(v) => Some(v),
         }
     }
+
+    fn get_variable(&self, name: &str) -> Option<&QueryValue<Env::Target>> {
+        match self.extra.get_variable(name) {
+            None => self.inner.get_variable(name),
+            Some(v) => Some(v),
+        }
+    }
+}
+
+/// Query functions with an additional variable bound, as used for the body of a `let`.
+pub struct LetQueryFunctions<'a, Env: QueryEnvironment> {
+    inner: &'a dyn QueryFunctions<Env = Env>,
+    name: &'a str,
+    value: QueryValue<Env::Target>,
+}
+
+impl<'a, Env: QueryEnvironment> Debug for LetQueryFunctions<'a, Env> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LetQueryFunctions")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, Env: QueryEnvironment> LetQueryFunctions<'a, Env> {
+    pub fn bind(
+        inner: &'a dyn QueryFunctions<Env = Env>,
+        name: &'a str,
+        value: QueryValue<Env::Target>,
+    ) -> Self {
+        Self { inner, name, value }
+    }
+}
+
+impl<'a, Env: QueryEnvironment> QueryFunctions for LetQueryFunctions<'a, Env> {
+    type Env = Env;
+    fn get(&self, name: &str) -> Option<&dyn QueryFunction<Env>> {
+        self.inner.get(name)
+    }
+
+    fn get_op(&self, op: BinaryOp) -> Option<&dyn QueryBinaryOp<Env>> {
+        self.inner.get_op(op)
+    }
+
+    fn get_variable(&self, name: &str) -> Option<&QueryValue<Env::Target>> {
+        if name == self.name {
+            Some(&self.value)
+        } else {
+            self.inner.get_variable(name)
+        }
+    }
 }
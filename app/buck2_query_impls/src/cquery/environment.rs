@@ -217,6 +217,18 @@ impl<'c> QueryEnvironment for CqueryEnvironment<'c> {
         Err(QueryError::FunctionUnimplemented("targets_in_buildfile").into())
     }
 
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> buck2_error::Result<TargetSet<Self::Target>> {
+        // Only the configurations in the universe are known, so siblings are found there.
+        let universe = self
+            .universe
+            .as_ref()
+            .internal_error("Target universe not specified")?;
+        Ok(universe.get_from_packages(targets.iter().map(|t| t.label().pkg())))
+    }
+
     async fn deps(
         &self,
         targets: &TargetSet<Self::Target>,
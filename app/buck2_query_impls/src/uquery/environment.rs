@@ -378,6 +378,28 @@ impl<'c> QueryEnvironment for UqueryEnvironment<'c> {
 
         Ok(result)
     }
+
+    async fn siblings(
+        &self,
+        targets: &TargetSet<Self::Target>,
+    ) -> buck2_error::Result<TargetSet<Self::Target>> {
+        let packages: IndexSet<_> = targets.iter().map(|t| t.label().pkg()).collect();
+        let evaluations = try_join_all(packages.into_iter().map(|package| async move {
+            self.delegate.ctx().get_interpreter_results(package).await
+        }))
+        .await?;
+
+        let mut result: TargetSet<Self::Target> = TargetSet::new();
+        for evaluation_result in evaluations {
+            result.extend(
+                evaluation_result
+                    .targets()
+                    .values()
+                    .map(|target_ref| target_ref.to_owned()),
+            );
+        }
+        Ok(result)
+    }
 }
 
 pub(crate) async fn allbuildfiles<'c, T: QueryTarget>(
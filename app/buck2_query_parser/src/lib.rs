@@ -25,6 +25,8 @@
 //!        | '(' EXPR ')'
 //!        | 'set(' WORD * ')'
 //!        | FUNCTION_NAME '(' EXPR ( ',' EXPR ) * ')'
+//!        | 'let' NAME '=' EXPR 'in' EXPR
+//!        | '$' NAME
 //!        | EXPR 'intersect' EXPR
 //!        | EXPR ' ^ ' EXPR
 //!        | EXPR ' union ' EXPR
@@ -41,8 +43,14 @@
 //!
 //! INTEGER ::= "0" | ("1-9" "0-9"*)
 //!
-//! FUNCTION_NAME ::= "a-zA-Z_" "a-zA-Z0-9_" *
+//! FUNCTION_NAME ::= NAME
+//!
+//! NAME ::= "a-zA-Z_" "a-zA-Z0-9_" *
 //! ```
+//!
+//! The body of a `let` extends as far to the right as possible, so `let x = a in x + b` binds `x`
+//! in `x + b`. A `$` followed by a name is a variable reference, unless it is part of a longer word
+//! (for example the regex `foo$.*`).
 
 pub mod multi_query;
 pub mod placeholder;
@@ -67,6 +75,7 @@ use nom::character::complete::multispace0;
 use nom::character::complete::multispace1;
 use nom::combinator::all_consuming;
 use nom::combinator::cut;
+use nom::combinator::not;
 use nom::combinator::recognize;
 use nom::error::context;
 use nom::error::convert_error;
@@ -84,8 +93,6 @@ use nom::IResult;
 use crate::span::Span;
 use crate::spanned::Spanned;
 
-// TODO(cjhopman): Add `LET WORD = expr IN expr`
-
 // TODO(cjhopman): We should switch to our own error type here. VerboseError doesn't even allow us to construct
 // our own error messages (so, for example, we can't have a good error message for too large integers) and doesn't
 // support propagating anyhow or std errors (and since we can't do a custom message, we can't even capture them as a string).
@@ -124,6 +131,14 @@ pub enum Expr<'a> {
     BinaryOpSequence(Box<SpannedExpr<'a>>, Vec<(BinaryOp, SpannedExpr<'a>)>),
     Set(Vec<Span<'a>>),
     FileSet(Vec<Span<'a>>),
+    /// `let name = value in body`. `value` is evaluated once and `name` refers to it within `body`.
+    Let {
+        name: Span<'a>,
+        value: Box<SpannedExpr<'a>>,
+        body: Box<SpannedExpr<'a>>,
+    },
+    /// A reference to a variable bound by an enclosing `let`, without the leading `$`.
+    Variable(&'a str),
 }
 
 impl Display for Expr<'_> {
@@ -180,11 +195,19 @@ impl Display for Expr<'_> {
                 }
                 f.write_str(")")?;
             }
+            Expr::Let { name, value, body } => {
+                // Parenthesized since the body would otherwise extend over anything following it.
+                write!(f, "(let {} = {} in {})", name.fragment(), value, body)?;
+            }
+            Expr::Variable(name) => write!(f, "${}", name)?,
         }
         Ok(())
     }
 }
 
+/// Characters other than alphanumerics that may appear in an unquoted word.
+const WORD_SYMBOLS: &str = "*/@.-_:$#%";
+
 const INTERSECT: &str = "^";
 const EXCEPT: &str = "-";
 const UNION: &str = "+";
@@ -277,8 +300,10 @@ fn single_expr<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Spann
         preceded(char('('), cut(terminated(expr, char(')')))),
         expr_set,
         expr_fileset,
+        expr_let,
         expr_function,
         expr_int,
+        expr_variable,
         expr_word,
     ))(input)?;
 
@@ -354,7 +379,7 @@ fn expr_int<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedE
 
 fn word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
     fn non_quoted_word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
-        recognize(many1(alt((alphanumeric1, is_a("*/@.-_:$#%")))))(input)
+        recognize(many1(alt((alphanumeric1, is_a(WORD_SYMBOLS)))))(input)
     }
 
     alt((
@@ -370,6 +395,47 @@ fn word<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E>
     ))(input)
 }
 
+/// Parses a NAME, as used for functions and variables.
+fn name<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Span<'a>, E> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0(alt((alphanumeric1, tag("_")))),
+    ))(input)
+}
+
+/// Tries to parse an Expr::Variable. A `$` that doesn't start a whole name is left to be parsed as
+/// part of a word.
+fn expr_variable<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedExpr<'a>, E> {
+    spanned(|input| {
+        let (input, variable) = preceded(char('$'), name)(input)?;
+        let (input, _) = not(is_a(WORD_SYMBOLS))(input)?;
+        Ok((input, Expr::Variable(variable.fragment())))
+    })(input)
+}
+
+/// Tries to parse an Expr::Let. Will fail if it detects an unfinished "let NAME".
+fn expr_let<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, SpannedExpr<'a>, E> {
+    spanned(|input| {
+        let (input, _) = terminated(tag("let"), multispace1)(input)?;
+        let (input, name) = name(input)?;
+        cut(move |input| {
+            let (input, _) = delimited(multispace0, char('='), multispace0)(input)?;
+            let (input, value) = expr(input)?;
+            // `expr` consumes the whitespace before `in`.
+            let (input, _) = terminated(tag("in"), multispace1)(input)?;
+            let (input, body) = expr(input)?;
+            Ok((
+                input,
+                Expr::Let {
+                    name,
+                    value: Box::new(value),
+                    body: Box::new(body),
+                },
+            ))
+        })(input)
+    })(input)
+}
+
 fn binary_op(input: Span) -> NomResult<BinaryOp, ()> {
     fn keyword(long: &'static str) -> impl Fn(Span) -> NomResult<Span, ()> {
         // keywords require spaces separating from the exprs
@@ -438,10 +504,7 @@ fn expr_function<'a, E: NomParseError<'a>>(input: Span<'a>) -> NomResult<'a, Spa
     }
 
     spanned(|input| {
-        let (input, function_name) = recognize(pair(
-            alt((alpha1, tag("_"))),
-            many0(alt((alphanumeric1, tag("_")))),
-        ))(input)?;
+        let (input, function_name) = name(input)?;
         let (input, _) = char('(')(input)?;
         cut(move |input| {
             let (input, args) = terminated(function_args, char(')'))(input)?;
@@ -622,6 +685,61 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_let() -> buck2_error::Result<()> {
+        run_tests(
+            expr_let,
+            &[
+                "let x = a in $x",
+                "let x = deps(a) in $x + rdeps($x, b)",
+                "let x=a in let y = $x in $y",
+                "let x = (let y = a in $y) in $x",
+            ],
+            // As long as we don't match "let NAME", it should be recoverable
+            &["let", "letter", "let(a)", "x = a in $x", "let 1"],
+            // An error after "let NAME" is non-recoverable
+            &["let x", "let x = a", "let x = a in", "let x a in $x"],
+        );
+
+        match parse_expr("let x = deps(a) in $x ^ b") {
+            Ok(Spanned {
+                value: Expr::Let { name, value, body },
+                ..
+            }) => {
+                assert_eq!("x", name.fragment());
+                assert!(matches!(value.value, Expr::Function { .. }));
+                assert!(matches!(body.value, Expr::BinaryOpSequence(..)));
+            }
+            v => panic!("expected let expr, got `{:?}`", v),
+        }
+
+        assert_eq!(
+            "( (let x = 'a' in $x) + 'b')",
+            parse_expr("(let x = a in $x) + b")?.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_variable() -> buck2_error::Result<()> {
+        run_tests(
+            expr_variable,
+            &["$x", "$_x1"],
+            // Anything else is left to be parsed as a word.
+            &["x", "$", "$1", "$x.*", "$x:y", "'$x'"],
+            &[],
+        );
+
+        match parse_expr("'$x'") {
+            Ok(Spanned {
+                value: Expr::String("$x"),
+                ..
+            }) => {}
+            v => panic!("expected '$x', got `{:?}`", v),
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_integer() -> buck2_error::Result<()> {
         run_tests(expr_int, &["0", "1234"], &["w123", ".1", ""], &["0123"]);
@@ -669,6 +669,27 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
             .unwrap_or_else(RolloutPercentage::always)
             .roll();
 
+        let sandbox_local_actions = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "sandbox_local_actions",
+            })?
+            .unwrap_or(false);
+
+        let sandbox_log_denials = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "sandbox_log_denials",
+            })?
+            .unwrap_or(false);
+
+        let sandbox_rerun_failed_actions = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "sandbox_rerun_failed_actions",
+            })?
+            .unwrap_or(false);
+
         let log_configured_graph_size = root_config
             .parse::<bool>(BuckconfigKeyRef {
                 section: "buck2",
@@ -686,6 +707,9 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
         let executor_global_knobs = ExecutorGlobalKnobs {
             enable_miniperf,
             log_action_keys,
+            sandbox_local_actions,
+            sandbox_log_denials,
+            sandbox_rerun_failed_actions,
         };
 
         let host_sharing_broker =
@@ -770,6 +794,7 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
             override_use_case,
             self.cmd_ctx.base_context.daemon.memory_tracker.dupe(),
             resource_control_config.hybrid_execution_memory_limit_gibibytes,
+            self.cmd_ctx.base_context.daemon.local_action_cache.dupe(),
         )));
         data.set_blocking_executor(self.cmd_ctx.base_context.daemon.blocking_executor.dupe());
         data.set_http_client(self.cmd_ctx.base_context.daemon.http_client.dupe());
@@ -798,6 +823,7 @@ impl<'a, 's> DiceCommandUpdater<'a, 's> {
         let tags = vec![
             format!("lazy-cycle-detector:{}", has_cycle_detector),
             format!("miniperf:{}", enable_miniperf),
+            format!("sandbox-local-actions:{}", sandbox_local_actions),
             format!("log-configured-graph-size:{}", log_configured_graph_size),
         ];
         self.cmd_ctx
@@ -50,6 +50,8 @@ use buck2_execute_impl::executors::caching::CacheUploader;
 use buck2_execute_impl::executors::hybrid::FallbackTracker;
 use buck2_execute_impl::executors::hybrid::HybridExecutor;
 use buck2_execute_impl::executors::local::LocalExecutor;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCacheExecutor;
 use buck2_execute_impl::executors::local_actions_throttle::LocalActionsThrottle;
 use buck2_execute_impl::executors::re::ReExecutor;
 use buck2_execute_impl::executors::stacked::StackedExecutor;
@@ -100,6 +102,7 @@ pub struct CommandExecutorFactory {
     fallback_tracker: Arc<FallbackTracker>,
     re_use_case_override: Option<RemoteExecutorUseCase>,
     local_actions_throttle: Option<Arc<LocalActionsThrottle>>,
+    local_action_cache: Option<Arc<LocalActionCache>>,
 }
 
 impl CommandExecutorFactory {
@@ -122,6 +125,7 @@ impl CommandExecutorFactory {
         re_use_case_override: Option<RemoteExecutorUseCase>,
         memory_tracker: Option<Arc<MemoryTracker>>,
         hybrid_execution_memory_limit_gibibytes: Option<u64>,
+        local_action_cache: Option<Arc<LocalActionCache>>,
     ) -> Self {
         let cache_upload_permission_checker = Arc::new(ActionCacheUploadPermissionChecker::new(
             re_connection
@@ -150,6 +154,7 @@ impl CommandExecutorFactory {
             fallback_tracker: Arc::new(FallbackTracker::new()),
             re_use_case_override,
             local_actions_throttle,
+            local_action_cache,
         }
     }
 
@@ -194,6 +199,21 @@ impl HasCommandExecutor for CommandExecutorFactory {
             )
         };
 
+        // The hybrid executor races its local leg against RE, and only claims the action once
+        // the local executor starts running it, so the local action cache, which restores
+        // outputs before claiming, isn't used there.
+        let local_cached_executor_new =
+            |options: &LocalExecutorOptions| -> Arc<dyn PreparedCommandExecutor> {
+                let local_executor = local_executor_new(options);
+                match (&self.local_action_cache, self.skip_cache_read) {
+                    (Some(cache), false) => Arc::new(LocalActionCacheExecutor {
+                        inner: local_executor,
+                        cache: cache.dupe(),
+                    }),
+                    _ => Arc::new(local_executor),
+                }
+            };
+
         if !buck2_core::is_open_source() && !cfg!(fbcode_build) {
             static WARN: OnceLock<()> = OnceLock::new();
             WARN.get_or_init(|| {
@@ -246,7 +266,7 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     None
                 } else {
                     Some(CommandExecutorResponse {
-                        executor: Arc::new(local_executor_new(local)),
+                        executor: local_cached_executor_new(local),
                         platform: Default::default(),
                         cache_checker: Arc::new(NoOpCommandOptionalExecutor {}),
                         cache_uploader: Arc::new(NoOpCacheUploader {}),
@@ -315,7 +335,7 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 let executor: Option<Arc<dyn PreparedCommandExecutor>> =
                     match &remote_options.executor {
                         RemoteEnabledExecutor::Local(local) if !self.strategy.ban_local() => {
-                            Some(Arc::new(local_executor_new(local)))
+                            Some(local_cached_executor_new(local))
                         }
                         RemoteEnabledExecutor::Remote(remote) if !self.strategy.ban_remote() => {
                             Some(Arc::new(remote_executor_new(
@@ -35,6 +35,7 @@ use buck2_core::buck2_env;
 use buck2_core::cells::name::CellName;
 use buck2_core::facebook_only;
 use buck2_core::fs::cwd::WorkingDirectory;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::is_open_source;
@@ -53,7 +54,10 @@ use buck2_execute::execute::blocking::BuckBlockingExecutor;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
 use buck2_execute_impl::materializers::deferred::clean_stale::CleanStaleConfig;
+use buck2_execute_impl::materializers::deferred::clean_stale::EvictionConfig;
+use buck2_execute_impl::materializers::deferred::object_store::ObjectStoreMode;
 use buck2_execute_impl::materializers::deferred::AccessTimesUpdates;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializer;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializerConfigs;
@@ -177,6 +181,9 @@ pub struct DaemonStateData {
     /// If enabled, paranoid RE downloads.
     pub paranoid: Option<ParanoidDownloader>,
 
+    /// If enabled, an on-disk cache of the results of local actions.
+    pub local_action_cache: Option<Arc<LocalActionCache>>,
+
     /// Spawner
     pub spawner: Arc<BuckSpawner>,
 
@@ -420,6 +427,15 @@ impl DaemonState {
                     .unwrap_or(false);
 
                 let clean_stale_config = CleanStaleConfig::from_buck_config(root_config)?;
+                let eviction_config = EvictionConfig::from_buck_config(root_config)?;
+
+                let object_store = ObjectStoreMode::try_new_from_config_value(
+                    root_config.get(BuckconfigKeyRef {
+                        section: "buck2",
+                        property: "materializer_object_store",
+                    }),
+                    disk_state_options.sqlite_materializer_state,
+                )?;
 
                 let disable_eager_write_dispatch = root_config
                     .parse::<RolloutPercentage>(BuckconfigKeyRef {
@@ -443,6 +459,8 @@ impl DaemonState {
                     update_access_times,
                     verbose_materializer_log,
                     clean_stale_config,
+                    eviction_config,
+                    object_store,
                     disable_eager_write_dispatch,
                 }
             };
@@ -581,6 +599,34 @@ impl DaemonState {
                 None
             };
 
+            let local_action_cache = match root_config.get(BuckconfigKeyRef {
+                section: "buck2",
+                property: "local_action_cache_dir",
+            }) {
+                Some(dir) => {
+                    let max_bytes = root_config
+                        .parse(BuckconfigKeyRef {
+                            section: "buck2",
+                            property: "local_action_cache_max_bytes",
+                        })?
+                        .unwrap_or(DEFAULT_LOCAL_ACTION_CACHE_MAX_BYTES);
+                    let cache_local_only = root_config
+                        .parse(BuckconfigKeyRef {
+                            section: "buck2",
+                            property: "local_action_cache_local_only",
+                        })?
+                        .unwrap_or(false);
+                    // Relative paths are relative to the project root.
+                    let dir = AbsNormPathBuf::new(fs.root().as_path().join(dir))?;
+                    Some(Arc::new(LocalActionCache::new(
+                        dir,
+                        max_bytes,
+                        cache_local_only,
+                    )))
+                }
+                None => None,
+            };
+
             let remote_dep_files_enabled = root_config
                 .parse(BuckconfigKeyRef {
                     section: "build",
@@ -597,6 +643,7 @@ impl DaemonState {
                     disk_state_options.sqlite_materializer_state
                 ),
                 format!("paranoid:{}", paranoid.is_some()),
+                format!("local-action-cache:{}", local_action_cache.is_some()),
                 format!("remote-dep-files:{}", remote_dep_files_enabled),
                 #[cfg(fbcode_build)]
                 format!(
@@ -636,6 +683,7 @@ impl DaemonState {
                 enable_restarter,
                 http_client,
                 paranoid,
+                local_action_cache,
                 spawner: Arc::new(BuckSpawner::new(daemon_state_data_rt)),
                 tags,
                 system_warning_config,
@@ -909,6 +957,8 @@ fn convert_algorithm_kind(kind: DigestAlgorithmFamily) -> buck2_error::Result<Di
     })
 }
 
+const DEFAULT_LOCAL_ACTION_CACHE_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
 /// Sensible defaults for http client when building from a DaemonStartupConfig.
 const DEFAULT_MAX_REDIRECTS: usize = 10;
 const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
@@ -17,6 +17,7 @@ use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_cli_proto::*;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::file_ops::DiceFileComputations;
+use buck2_common::file_ops::FileType;
 use buck2_common::package_listing::dice::DicePackageListingResolver;
 use buck2_core::bxl::BxlFilePath;
 use buck2_core::bzl::ImportPath;
@@ -61,10 +62,12 @@ use starlark::analysis::find_call_name::AstModuleFindCallName;
 use starlark::codemap::Span;
 use starlark::docs::markdown::render_doc_item_no_link;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
 use starlark_lsp::error::eval_message_to_lsp_diagnostic;
 use starlark_lsp::server::server_with_connection;
+use starlark_lsp::server::LspChecks;
 use starlark_lsp::server::LspContext;
 use starlark_lsp::server::LspEvalResult;
 use starlark_lsp::server::LspUrl;
@@ -287,6 +290,8 @@ struct BuckLspContext<'a> {
     server_ctx: &'a dyn ServerCommandContextTrait,
     fs: ProjectRoot,
     docs_cache_manager: DocsCacheManager,
+    /// The builtin globals, used to typecheck files as they are edited.
+    globals: Globals,
     runtime: Handle,
 }
 
@@ -315,6 +320,7 @@ impl<'a> BuckLspContext<'a> {
             server_ctx,
             fs,
             docs_cache_manager,
+            globals: base_globals().build(),
             runtime: Handle::current(),
         })
     }
@@ -385,6 +391,41 @@ impl<'a> BuckLspContext<'a> {
         }
     }
 
+    /// All the build files and `.bzl`/`.bxl` files in the cell containing `root`, skipping
+    /// ignored directories.
+    async fn cell_starlark_files(&self, root: &Path) -> buck2_error::Result<Vec<LspUrl>> {
+        let root = AbsPath::new(root)?;
+        let relative_root = self.fs.relativize_any(root)?;
+        self.with_dice_ctx(|mut dice_ctx| async move {
+            let cell_resolver = dice_ctx.get_cell_resolver().await?;
+            let root = cell_resolver.get_cell_path(&relative_root)?;
+            let buildfiles = DiceFileComputations::buildfiles(&mut dice_ctx, root.cell()).await?;
+
+            let mut files = Vec::new();
+            let mut dirs = vec![root];
+            while let Some(dir) = dirs.pop() {
+                let listing = DiceFileComputations::read_dir(&mut dice_ctx, dir.as_ref()).await?;
+                for entry in listing.included.iter() {
+                    let path = dir.join(&entry.file_name);
+                    match entry.file_type {
+                        FileType::Directory => dirs.push(path),
+                        FileType::File
+                            if buildfiles.contains(&entry.file_name)
+                                || matches!(entry.file_name.extension(), Some("bzl" | "bxl")) =>
+                        {
+                            let abs_path =
+                                self.fs.resolve(&cell_resolver.resolve_path(path.as_ref())?);
+                            files.push(Url::from_file_path(abs_path).unwrap().try_into()?);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Ok(files)
+        })
+        .await
+    }
+
     async fn parse_file_with_contents(&self, uri: &LspUrl, content: String) -> LspEvalResult {
         match self
             .parse_file_from_contents_and_handle_diagnostic(uri, content)
@@ -666,6 +707,16 @@ impl<'a> LspContext for BuckLspContext<'a> {
             }))?)
     }
 
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        let dispatcher = self.server_ctx.events().dupe();
+        let root = workspace_root.unwrap_or(self.fs.root().as_path());
+        Ok(self
+            .runtime
+            .block_on(with_dispatcher_async(dispatcher, async {
+                self.cell_starlark_files(root).await
+            }))?)
+    }
+
     fn get_url_for_global_symbol(
         &self,
         _current_file: &LspUrl,
@@ -695,6 +746,16 @@ impl<'a> LspContext for BuckLspContext<'a> {
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
         DocModule::default()
     }
+
+    fn get_checks(&self, _uri: &LspUrl) -> LspChecks {
+        // Most names used in build files come from the prelude, so don't flag names the
+        // builtin globals don't know about.
+        LspChecks {
+            lint: true,
+            lint_globals: None,
+            typecheck_globals: Some(self.globals.dupe()),
+        }
+    }
 }
 
 pub(crate) async fn run_lsp_server_command(
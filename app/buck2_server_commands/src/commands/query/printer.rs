@@ -32,6 +32,8 @@ use buck2_util::indent::indent;
 use dupe::Clone_;
 use dupe::Copy_;
 use dupe::Dupe_;
+use futures::stream::FuturesOrdered;
+use futures::StreamExt;
 use gazebo::variants::UnpackVariants;
 use indent_write::fmt::IndentWriter;
 use indent_write::io::IndentWriter as IoIndentWriter;
@@ -46,6 +48,8 @@ use crate::commands::query::QueryCommandError;
 use crate::dot::targets::DotTargetGraph;
 use crate::dot::Dot;
 use crate::dot::DotCompact;
+use crate::graphml::GraphMl;
+use crate::protobuf;
 
 #[derive(Copy_, Dupe_, Clone_, UnpackVariants)]
 pub(crate) enum ShouldPrintProviders<'a, T> {
@@ -135,13 +139,8 @@ impl<'a, T: QueryCommandTarget> Display for PrintableQueryTarget<'a, T> {
     }
 }
 
-impl<'a, T: QueryCommandTarget> Serialize for PrintableQueryTarget<'a, T> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut map = serializer.serialize_map(None)?;
-
+impl<'a, T: QueryCommandTarget> PrintableQueryTarget<'a, T> {
+    fn serialize_entries<M: SerializeMap>(&self, map: &mut M) -> Result<(), M::Error> {
         QueryTargets::for_all_attrs(self.value, |attr_name, attr_value| {
             if let Some(attr_regex) = self.attributes {
                 if attr_regex.is_match(attr_name) {
@@ -179,6 +178,33 @@ impl<'a, T: QueryCommandTarget> Serialize for PrintableQueryTarget<'a, T> {
             map.serialize_entry("buck.providers", providers)?;
         }
 
+        Ok(())
+    }
+}
+
+impl<'a, T: QueryCommandTarget> Serialize for PrintableQueryTarget<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        self.serialize_entries(&mut map)?;
+        map.end()
+    }
+}
+
+/// A target on its own line of ndjson output, which carries its label alongside the rest of
+/// its entries.
+struct NdjsonTarget<'a, 'b, T: QueryTarget>(&'b PrintableQueryTarget<'a, T>);
+
+impl<'a, 'b, T: QueryCommandTarget> Serialize for NdjsonTarget<'a, 'b, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("buck.label", &self.0.label())?;
+        self.0.serialize_entries(&mut map)?;
         map.end()
     }
 }
@@ -392,6 +418,17 @@ impl<'a> QueryResultPrinter<'a> {
                     // need to add a newline to flush the output.
                     writeln!(&mut output)?
                 }
+                QueryOutputFormat::Ndjson => {
+                    // Write each target as soon as it is ready rather than once all of them are.
+                    let mut printable: FuturesOrdered<_> = targets
+                        .iter()
+                        .map(|t| printable_target(t, print_providers, &self.attributes, call_stack))
+                        .collect();
+                    while let Some(target) = printable.next().await {
+                        serde_json::to_writer(&mut output, &NdjsonTarget(&target?))?;
+                        writeln!(&mut output)?;
+                    }
+                }
                 QueryOutputFormat::Dot => {
                     Dot::render(
                         &DotTargetGraph {
@@ -410,6 +447,18 @@ impl<'a> QueryResultPrinter<'a> {
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::Graphml => {
+                    GraphMl::render(
+                        &DotTargetGraph {
+                            targets,
+                            attributes: self.attributes.clone(),
+                        },
+                        &mut output,
+                    )?;
+                }
+                QueryOutputFormat::Protobuf => {
+                    protobuf::write_target_graph(&targets, &self.attributes, &mut output)?;
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
@@ -436,12 +485,27 @@ impl<'a> QueryResultPrinter<'a> {
                         // need to add a newline to flush the output.
                         writeln!(&mut output)?;
                     }
+                    QueryOutputFormat::Ndjson => {
+                        for file in files.iter() {
+                            serde_json::to_writer(
+                                &mut output,
+                                &self.resolver.resolve_path(file.as_ref())?.to_string(),
+                            )?;
+                            writeln!(&mut output)?;
+                        }
+                    }
                     QueryOutputFormat::Dot => {
                         unimplemented!("dot output for files not implemented yet")
                     }
                     QueryOutputFormat::DotCompact => {
                         unimplemented!("dot_compact output for files not implemented yet")
                     }
+                    QueryOutputFormat::Graphml => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("graphml").into());
+                    }
+                    QueryOutputFormat::Protobuf => {
+                        return Err(QueryCommandError::FileSetHasNoGraph("protobuf").into());
+                    }
                 }
             }
         }
@@ -450,25 +514,36 @@ impl<'a> QueryResultPrinter<'a> {
     }
 }
 
+async fn printable_target<'a, T: QueryTarget>(
+    target: &'a T,
+    print_providers: ShouldPrintProviders<'a, T>,
+    attributes: &'a Option<RegexSet>,
+    target_call_stacks: bool,
+) -> buck2_error::Result<PrintableQueryTarget<'a, T>> {
+    Ok(PrintableQueryTarget {
+        value: target,
+        attributes,
+        target_call_stacks,
+        providers: match print_providers {
+            ShouldPrintProviders::No => None,
+            ShouldPrintProviders::Yes(lookup) => {
+                Some(lookup.lookup(target).await?.require_compatible()?)
+            }
+        },
+    })
+}
+
 async fn printable_targets<'a, T: QueryTarget>(
     targets: &'a TargetSet<T>,
     print_providers: ShouldPrintProviders<'a, T>,
     attributes: &'a Option<RegexSet>,
     target_call_stacks: bool,
 ) -> buck2_error::Result<Vec<PrintableQueryTarget<'a, T>>> {
-    futures::future::join_all(targets.iter().map(|t| async move {
-        Ok(PrintableQueryTarget {
-            value: t,
-            attributes,
-            target_call_stacks,
-            providers: match print_providers {
-                ShouldPrintProviders::No => None,
-                ShouldPrintProviders::Yes(lookup) => {
-                    Some(lookup.lookup(t).await?.require_compatible()?)
-                }
-            },
-        })
-    }))
+    futures::future::join_all(
+        targets
+            .iter()
+            .map(|t| printable_target(t, print_providers, attributes, target_call_stacks)),
+    )
     .await
     .into_iter()
     .collect::<buck2_error::Result<_>>()
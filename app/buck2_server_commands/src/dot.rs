@@ -67,8 +67,8 @@ pub(crate) trait DotNode {
 
 /// Represents a directed edge between two nodes, identified by their id.
 pub struct DotEdge<'a> {
-    from: &'a str,
-    to: &'a str,
+    pub(crate) from: &'a str,
+    pub(crate) to: &'a str,
 }
 
 pub(crate) trait DotDigraph<'a> {
@@ -0,0 +1,114 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Writing graphs in GraphML format (see <http://graphml.graphdrawing.org/specification.html>).
+//!
+//! Reuses the `DotDigraph` abstraction, so anything that can be rendered as dot can be rendered
+//! as GraphML. Only the `extra` node attributes are written, the others being dot styling.
+
+use std::io::Write;
+
+use starlark_map::small_set::SmallSet;
+
+use crate::dot::DotDigraph;
+use crate::dot::DotNode;
+
+/// Escape a string for use in XML text or a double-quoted attribute value.
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub struct GraphMl {}
+
+impl GraphMl {
+    pub(crate) fn render<'a, T: DotDigraph<'a>, W: Write>(
+        graph: &'a T,
+        mut w: W,
+    ) -> buck2_error::Result<()> {
+        // GraphML requires every attribute to be declared before the graph itself.
+        let mut keys = SmallSet::new();
+        graph.for_each_node(|node| {
+            keys.extend(node.attrs()?.extra.into_keys());
+            Ok(())
+        })?;
+
+        writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            w,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )?;
+        for key in &keys {
+            let key = escape_xml(key);
+            writeln!(
+                w,
+                "  <key id=\"{key}\" for=\"node\" attr.name=\"{key}\" attr.type=\"string\"/>"
+            )?;
+        }
+        writeln!(
+            w,
+            "  <graph id=\"{}\" edgedefault=\"directed\">",
+            escape_xml(graph.name())
+        )?;
+        graph.for_each_node(|node| {
+            let attrs = node.attrs()?;
+            if attrs.extra.is_empty() {
+                writeln!(w, "    <node id=\"{}\"/>", escape_xml(&node.id()))?;
+            } else {
+                writeln!(w, "    <node id=\"{}\">", escape_xml(&node.id()))?;
+                for (key, value) in &attrs.extra {
+                    writeln!(
+                        w,
+                        "      <data key=\"{}\">{}</data>",
+                        escape_xml(key),
+                        escape_xml(value)
+                    )?;
+                }
+                writeln!(w, "    </node>")?;
+            }
+            graph.for_each_edge(node, |edge| {
+                writeln!(
+                    w,
+                    "    <edge source=\"{}\" target=\"{}\"/>",
+                    escape_xml(edge.from),
+                    escape_xml(edge.to)
+                )?;
+                Ok(())
+            })?;
+            Ok(())
+        })?;
+        writeln!(w, "  </graph>")?;
+        writeln!(w, "</graphml>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!("//foo:bar", escape_xml("//foo:bar"));
+        assert_eq!(
+            "a &lt;b&gt; &amp; &quot;c&quot; &apos;d&apos;",
+            escape_xml("a <b> & \"c\" 'd'")
+        );
+    }
+}
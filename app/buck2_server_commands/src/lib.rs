@@ -17,7 +17,9 @@
 
 pub mod commands;
 pub mod dot;
+pub(crate) mod graphml;
 pub(crate) mod json;
+pub(crate) mod protobuf;
 pub mod target_hash;
 
 pub fn init_late_bindings() {
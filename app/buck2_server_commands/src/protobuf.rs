@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Writing a query result as a sequence of length-delimited `QueryGraphNode` messages.
+//!
+//! The query result is fully evaluated before any node is written, but readers can decode
+//! the output one node at a time.
+
+use std::io::Write;
+
+use buck2_cli_proto::query_graph_node::Attribute;
+use buck2_cli_proto::QueryGraphNode;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::environment::QueryTargets;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use prost::Message;
+use regex::RegexSet;
+
+use crate::commands::query::query_target_ext::QueryCommandTarget;
+
+fn graph_node<T: QueryCommandTarget>(
+    target: &T,
+    targets: &TargetSet<T>,
+    attributes: &Option<RegexSet>,
+) -> buck2_error::Result<QueryGraphNode> {
+    let mut node_attributes = Vec::new();
+    if let Some(attr_regex) = attributes {
+        QueryTargets::for_all_attrs::<buck2_error::Error, _, _>(
+            target,
+            |attr_name, attr_value| {
+                if attr_regex.is_match(attr_name) {
+                    let mut value = Vec::new();
+                    target
+                        .attr_serialize(attr_value, &mut serde_json::Serializer::new(&mut value))?;
+                    node_attributes.push(Attribute {
+                        name: attr_name.to_owned(),
+                        value: String::from_utf8(value)?,
+                    });
+                }
+                Ok(())
+            },
+        )?;
+    }
+
+    Ok(QueryGraphNode {
+        label: target.node_key().to_string(),
+        // Only include edges to other nodes within the subgraph.
+        deps: target
+            .deps()
+            .filter(|dep| targets.contains(dep))
+            .map(|dep| dep.to_string())
+            .collect(),
+        attributes: node_attributes,
+    })
+}
+
+pub(crate) fn write_target_graph<T: QueryCommandTarget, W: Write>(
+    targets: &TargetSet<T>,
+    attributes: &Option<RegexSet>,
+    mut w: W,
+) -> buck2_error::Result<()> {
+    for target in targets.iter() {
+        w.write_all(&graph_node(target, targets, attributes)?.encode_length_delimited_to_vec())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Selection of the tests affected by a set of changed files, for `buck2 test --affected-by`.
+
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::interpreter::rule_defs::provider::test_provider::TestProvider;
+use buck2_build_api::query::oneshot::QUERY_FRONTEND;
+use buck2_cli_proto::TestRequest;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::paths::abs_path::AbsPath;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::global_cfg_options::GlobalCfgOptions;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::label::label::TargetLabel;
+use buck2_file_watcher::mergebase::files_changed_since;
+use buck2_file_watcher::mergebase::GetMergebase;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use futures::FutureExt;
+use itertools::Itertools;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum AffectedTestsError {
+    #[error("Selecting affected tests requires target patterns to select them from")]
+    NoTargetPatterns,
+    #[error(
+        "The file watcher did not report a mergebase to find changed files from. Is `[buck2] file_watcher = watchman` set, with a `[project] watchman_merge_base`?"
+    )]
+    NoMergebase,
+    #[error("Cannot select tests affected by `{0}`: it contains both `'` and `\"`")]
+    UnquotableWord(String),
+}
+
+/// The changed files the request selects affected tests by, relative to the project root, or
+/// `None` if it doesn't.
+pub(crate) async fn affected_files(
+    server_ctx: &dyn ServerCommandContextTrait,
+    ctx: &DiceTransaction,
+    request: &TestRequest,
+) -> buck2_error::Result<Option<Vec<ProjectRelativePathBuf>>> {
+    if request.affected_by.is_empty() && !request.affected_by_file && !request.affected_by_mergebase
+    {
+        return Ok(None);
+    }
+
+    let mut files = request
+        .affected_by
+        .iter()
+        .map(|file| {
+            server_ctx
+                .project_root()
+                .relativize_any(AbsPath::new(file)?)
+        })
+        .collect::<buck2_error::Result<Vec<_>>>()?;
+    if request.affected_by_mergebase {
+        let mergebase = ctx.per_transaction_data().get_mergebase();
+        let mergebase = mergebase
+            .0
+            .as_deref()
+            .ok_or(AffectedTestsError::NoMergebase)?;
+        files.extend(files_changed_since(server_ctx.project_root(), mergebase).await?);
+    }
+    Ok(Some(files))
+}
+
+/// `word` as a quoted query literal. The query grammar has no escapes, so a word is quoted with
+/// whichever quote it doesn't contain.
+fn quote_word(word: &str) -> buck2_error::Result<String> {
+    let quote = match (word.contains('\''), word.contains('"')) {
+        (false, _) => '\'',
+        (true, false) => '"',
+        (true, true) => return Err(AffectedTestsError::UnquotableWord(word.to_owned()).into()),
+    };
+    Ok(format!("{0}{1}{0}", quote, word))
+}
+
+/// `set('a' 'b' ...)`, as the query frontend expands `%Ss`.
+fn query_set<'a>(items: impl IntoIterator<Item = &'a str>) -> buck2_error::Result<String> {
+    Ok(format!(
+        "set({})",
+        items
+            .into_iter()
+            .map(quote_word)
+            .collect::<buck2_error::Result<Vec<_>>>()?
+            .join(" ")
+    ))
+}
+
+/// The test targets, out of those matched by `target_patterns` (relative to `cwd`), which depend
+/// on any of `files` (transitively), or are in the `tests` attribute of a target which does.
+pub(crate) async fn affected_tests(
+    ctx: &mut DiceTransaction,
+    cwd: &ProjectRelativePath,
+    global_cfg_options: &GlobalCfgOptions,
+    target_patterns: &[String],
+    files: &[ProjectRelativePathBuf],
+) -> buck2_error::Result<Vec<TargetLabel>> {
+    if target_patterns.is_empty() {
+        return Err(AffectedTestsError::NoTargetPatterns.into());
+    }
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let universe = query_set(target_patterns.iter().map(|p| p.as_str()))?;
+    let owners = query_set(files.iter().map(|f| f.as_str()))?;
+    let affected = format!("rdeps({}, owner({}))", universe, owners);
+    let query = format!("{} + testsof({})", affected, affected);
+
+    let (result, _universes) = QUERY_FRONTEND
+        .get()?
+        .eval_cquery(
+            ctx,
+            cwd,
+            &query,
+            &[],
+            global_cfg_options.dupe(),
+            Some(target_patterns),
+            false,
+        )
+        .await?;
+    let labels = result
+        .targets()
+        .map(|node| node.map(|node| node.label().dupe()))
+        .collect::<buck2_error::Result<Vec<_>>>()?;
+
+    // Only targets that provide `ExternalRunnerTestInfo` are tests.
+    let tests = ctx
+        .try_compute_join(labels, |ctx, label| {
+            async move {
+                let providers = ctx
+                    .get_providers(&ConfiguredProvidersLabel::default_for(label.dupe()))
+                    .await?;
+                let is_test = match providers {
+                    MaybeCompatible::Compatible(providers) => {
+                        <dyn TestProvider>::from_collection(providers.provider_collection())
+                            .is_some()
+                    }
+                    MaybeCompatible::Incompatible(_) => false,
+                };
+                buck2_error::Ok(is_test.then(|| label.unconfigured().dupe()))
+            }
+            .boxed()
+        })
+        .await?;
+
+    Ok(tests.into_iter().flatten().unique().sorted().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_set() {
+        assert_eq!(
+            "set('//...' 'a b.txt' \"it's.txt\" 'say \"hi\".txt')",
+            query_set(["//...", "a b.txt", "it's.txt", "say \"hi\".txt"]).unwrap()
+        );
+        assert!(query_set(["it's \"both\".txt"]).is_err());
+    }
+}
@@ -90,6 +90,8 @@ use futures::stream::TryStreamExt;
 use indexmap::IndexSet;
 use itertools::Itertools;
 
+use crate::affected::affected_files;
+use crate::affected::affected_tests;
 use crate::downward_api::BuckTestDownwardApi;
 use crate::executor_launcher::ExecutorLaunch;
 use crate::executor_launcher::ExecutorLauncher;
@@ -98,8 +100,13 @@ use crate::executor_launcher::TestExecutorClientWrapper;
 use crate::local_resource_registry::HasLocalResourceRegistry;
 use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::ExecutorMessage;
+use crate::report::generate_report;
+use crate::report::ReportedTestResult;
+use crate::session::TestCaseShard;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
+use crate::sharding::TestSharding;
+use crate::sharding::SHARD_TEST_CASES_LABEL;
 use crate::translations::build_configured_target_handle;
 
 struct TestOutcome {
@@ -108,6 +115,7 @@ struct TestOutcome {
     executor_stdout: String,
     executor_stderr: String,
     build_target_result: BuildTargetResult,
+    shard: Option<buck2_cli_proto::test_response::TestShard>,
 }
 
 impl TestOutcome {
@@ -124,13 +132,18 @@ struct ExecutorReport {
     exit_code: Option<i32>,
     statuses: TestStatuses,
     info_messages: Vec<String>,
+    /// The individual test results, kept when the request asks for reports of them.
+    results: Option<Vec<ReportedTestResult>>,
 }
 
 impl ExecutorReport {
-    fn ingest(&mut self, status: &ExecutorMessage) {
+    fn ingest(&mut self, status: &ExecutorMessage, session: &TestSession) -> anyhow::Result<()> {
         match status {
             ExecutorMessage::TestResult(res) => {
                 self.statuses.ingest(res);
+                if let Some(results) = &mut self.results {
+                    results.push(ReportedTestResult::new(res, session)?);
+                }
             }
             ExecutorMessage::ExitCode(exit_code) => {
                 self.exit_code = Some(*exit_code);
@@ -139,6 +152,7 @@ impl ExecutorReport {
                 self.info_messages.push(message.clone());
             }
         }
+        Ok(())
     }
 }
 
@@ -183,6 +197,7 @@ struct TestStatuses {
     fatals: CounterWithExamples,
     listing_success: CounterWithExamples,
     listing_failed: CounterWithExamples,
+    flaky: CounterWithExamples,
 }
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
@@ -197,6 +212,7 @@ impl TestStatuses {
             TestStatus::RERUN => {}
             TestStatus::LISTING_SUCCESS => self.listing_success.add(&result.name),
             TestStatus::LISTING_FAILED => self.listing_failed.add(&result.name),
+            TestStatus::FLAKY => self.flaky.add(&result.name),
         }
     }
 }
@@ -314,8 +330,29 @@ async fn test(
         }
     };
 
-    let parsed_patterns =
-        parse_patterns_from_cli_args(&mut ctx, &request.target_patterns, cwd).await?;
+    let target_patterns = match affected_files(server_ctx, &ctx, request).await? {
+        Some(files) => {
+            let tests = affected_tests(
+                &mut ctx,
+                cwd,
+                &global_cfg_options,
+                &request.target_patterns,
+                &files,
+            )
+            .await?;
+            if request.list_affected {
+                return Ok(TestResponse {
+                    exit_code: Some(0),
+                    affected_tests: tests.iter().map(|t| t.to_string()).collect(),
+                    ..Default::default()
+                });
+            }
+            tests.iter().map(|t| t.to_string()).collect()
+        }
+        None => request.target_patterns.clone(),
+    };
+
+    let parsed_patterns = parse_patterns_from_cli_args(&mut ctx, &target_patterns, cwd).await?;
     server_ctx.log_target_pattern(&parsed_patterns);
 
     let resolved_pattern = ResolveTargetPatterns::resolve(&mut ctx, &parsed_patterns).await?;
@@ -335,6 +372,10 @@ async fn test(
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
+        test_case_shard: request.sharding.as_ref().map(|sharding| TestCaseShard {
+            index: sharding.index,
+            count: sharding.count,
+        }),
     });
 
     let build_opts = request
@@ -349,6 +390,19 @@ async fn test(
         .transpose()
         .context("Invalid `duration`")?;
 
+    let sharding = request
+        .sharding
+        .as_ref()
+        .map(|sharding| {
+            let durations = sharding
+                .target_durations_ms
+                .iter()
+                .map(|(target, ms)| (target.clone(), Duration::from_millis(*ms)))
+                .collect();
+            TestSharding::new(sharding.index, sharding.count, &durations)
+        })
+        .transpose()?;
+
     let test_outcome = test_targets(
         ctx.dupe(),
         resolved_pattern,
@@ -368,6 +422,8 @@ async fn test(
         MissingTargetBehavior::from_skip(build_opts.skip_missing_targets),
         timeout,
         request.ignore_tests_attribute,
+        !request.report_formats.is_empty(),
+        sharding,
     )
     .await?;
 
@@ -423,6 +479,13 @@ async fn test(
                 .listing_failed
                 .to_cli_proto_counter(),
         ),
+        flaky: Some(
+            test_outcome
+                .executor_report
+                .statuses
+                .flaky
+                .to_cli_proto_counter(),
+        ),
     };
 
     let serialized_build_report = if build_opts.unstable_print_build_report {
@@ -443,6 +506,21 @@ async fn test(
         None
     };
 
+    let reports = request
+        .report_formats()
+        .map(|format| buck2_cli_proto::test_response::TestReport {
+            format: format as i32,
+            contents: generate_report(
+                format,
+                test_outcome
+                    .executor_report
+                    .results
+                    .as_deref()
+                    .unwrap_or_default(),
+            ),
+        })
+        .collect();
+
     Ok(TestResponse {
         exit_code,
         errors: test_outcome.errors,
@@ -451,6 +529,9 @@ async fn test(
         executor_stderr: test_outcome.executor_stderr,
         executor_info_messages: test_outcome.executor_report.info_messages,
         serialized_build_report,
+        reports,
+        shard: test_outcome.shard,
+        affected_tests: Vec::new(),
     })
 }
 
@@ -468,6 +549,8 @@ async fn test_targets(
     missing_target_behavior: MissingTargetBehavior,
     timeout: Option<Duration>,
     ignore_tests_attribute: bool,
+    collect_results: bool,
+    sharding: Option<TestSharding>,
 ) -> anyhow::Result<TestOutcome> {
     let session = Arc::new(session);
 
@@ -548,6 +631,7 @@ async fn test_targets(
                     working_dir_cell,
                     missing_target_behavior,
                     ignore_tests_attribute,
+                    sharding: sharding.as_ref(),
                 });
 
                 driver.push_pattern(
@@ -576,10 +660,13 @@ async fn test_targets(
                     .context("Failed to notify test executor of end-of-tests")?;
 
                 // Wait for the tests to finish running.
+                let executor_report = ExecutorReport {
+                    results: collect_results.then(Vec::new),
+                    ..Default::default()
+                };
                 let test_statuses = test_status_receiver
-                    .try_fold(ExecutorReport::default(), |mut acc, result| {
-                        acc.ingest(&result);
-                        future::ready(Ok(acc))
+                    .try_fold(executor_report, |mut acc, result| {
+                        future::ready(acc.ingest(&result, &session).map(|()| acc))
                     })
                     .await
                     .context("Did not receive all results from executor")?;
@@ -606,8 +693,18 @@ async fn test_targets(
 
                 driver.build_target_result.extend(error_target_result);
 
+                let shard =
+                    sharding
+                        .as_ref()
+                        .map(|sharding| buck2_cli_proto::test_response::TestShard {
+                            index: sharding.index(),
+                            count: sharding.count(),
+                            targets: driver.targets_in_shard,
+                            targets_in_other_shards: driver.targets_in_other_shards,
+                        });
+
                 // And finally return our results;
-                anyhow::Ok((driver.build_target_result, test_statuses))
+                anyhow::Ok((driver.build_target_result, test_statuses, shard))
             },
         )
     });
@@ -631,7 +728,7 @@ async fn test_targets(
     )));
 
     // TODO(bobyf, torozco) we can use cancellation handle here instead of liveliness observer
-    let (build_target_result, executor_report) = test_server
+    let (build_target_result, executor_report, shard) = test_server
         .await
         .context("Failed to collect executor report")??;
 
@@ -653,6 +750,7 @@ async fn test_targets(
         executor_stderr: executor_output.stderr,
         executor_report,
         build_target_result,
+        shard,
     })
 }
 
@@ -674,6 +772,8 @@ enum TestDriverTask {
         providers: FrozenProviderCollectionValue,
         build_target_result: BuildTargetResult,
     },
+    /// The target is left to another shard.
+    SkipTargetInOtherShard,
 }
 
 #[derive(Copy, Clone, Dupe)]
@@ -687,6 +787,7 @@ struct TestDriverState<'a, 'e> {
     working_dir_cell: CellName,
     missing_target_behavior: MissingTargetBehavior,
     ignore_tests_attribute: bool,
+    sharding: Option<&'a TestSharding>,
 }
 
 /// Maintains the state of an ongoing test execution.
@@ -697,6 +798,8 @@ struct TestDriver<'a, 'e> {
     labels_tested: HashSet<ConfiguredProvidersLabel>,
     error_events: Vec<BuildEvent>,
     build_target_result: BuildTargetResult,
+    targets_in_shard: u64,
+    targets_in_other_shards: u64,
 }
 
 impl<'a, 'e> TestDriver<'a, 'e> {
@@ -708,6 +811,8 @@ impl<'a, 'e> TestDriver<'a, 'e> {
             labels_tested: HashSet::new(),
             error_events: Vec::new(),
             build_target_result: BuildTargetResult::new(),
+            targets_in_shard: 0,
+            targets_in_other_shards: 0,
         }
     }
 
@@ -758,6 +863,9 @@ impl<'a, 'e> TestDriver<'a, 'e> {
                             } => {
                                 self.test_target(label, providers, build_target_result);
                             }
+                            TestDriverTask::SkipTargetInOtherShard => {
+                                self.targets_in_other_shards += 1;
+                            }
                         }
                     }
                 }
@@ -949,11 +1057,16 @@ impl<'a, 'e> TestDriver<'a, 'e> {
 
             let result = match ctx
                 .with_linear_recompute(|ctx| async move {
-                    build_target_result(&ctx, &state.label_filtering, build_label).await
+                    build_target_result(&ctx, &state.label_filtering, state.sharding, build_label)
+                        .await
                 })
                 .await
             {
-                Ok(result) => result,
+                // Targets of other shards are neither built nor tested here.
+                Ok(None) => {
+                    return ControlFlow::Continue(vec![TestDriverTask::SkipTargetInOtherShard])
+                }
+                Ok(Some(result)) => result,
                 Err(e) => {
                     return ControlFlow::Break(vec![BuildEvent::new_configured(
                         label,
@@ -979,6 +1092,10 @@ impl<'a, 'e> TestDriver<'a, 'e> {
         providers: FrozenProviderCollectionValue,
         build_target_result: BuildTargetResult,
     ) {
+        if self.state.sharding.is_some() {
+            self.targets_in_shard += 1;
+        }
+
         let should_test = !build_target_result.build_failed && !build_target_result.is_empty();
         self.build_target_result.extend(build_target_result);
 
@@ -1014,11 +1131,13 @@ impl<'a, 'e> TestDriver<'a, 'e> {
     }
 }
 
+/// Builds the test target, or returns `None` if the target is left to another shard.
 async fn build_target_result(
     ctx: &LinearRecomputeDiceComputations<'_>,
     label_filtering: &TestLabelFiltering,
+    sharding: Option<&TestSharding>,
     label: ConfiguredProvidersLabel,
-) -> anyhow::Result<(BuildTargetResult, FrozenProviderCollectionValue)> {
+) -> anyhow::Result<Option<(BuildTargetResult, FrozenProviderCollectionValue)>> {
     // NOTE: We fail if we hit an incompatible target here. This can happen if we reach an
     // incompatible target via `tests = [...]`. This should perhaps change, but that's how it works
     // in v1: https://fb.workplace.com/groups/buckeng/posts/8520953297953210
@@ -1028,11 +1147,20 @@ async fn build_target_result(
         .await?
         .require_compatible()?;
     let collections = providers.provider_collection();
+    let test_info = <dyn TestProvider>::from_collection(collections);
+
+    if let Some(sharding) = sharding {
+        let shards_test_cases =
+            test_info.map_or(false, |t| t.labels().contains(&SHARD_TEST_CASES_LABEL));
+        if !sharding.includes(label.target().unconfigured(), shards_test_cases) {
+            return Ok(None);
+        }
+    }
 
-    let build_target_result = match <dyn TestProvider>::from_collection(collections) {
+    let build_target_result = match test_info {
         Some(test_info) => {
             if skip_build_based_on_labels(test_info, label_filtering) {
-                return Ok((BuildTargetResult::new(), providers));
+                return Ok(Some((BuildTargetResult::new(), providers)));
             }
             let stream = build_configured_label(
                 &ctx,
@@ -1059,7 +1187,7 @@ async fn build_target_result(
             BuildTargetResult::new()
         }
     };
-    Ok((build_target_result, providers))
+    Ok(Some((build_target_result, providers)))
 }
 
 async fn test_target(
@@ -12,6 +12,7 @@
 
 //! Implementation of test running.
 
+pub(crate) mod affected;
 pub mod command;
 pub mod downward_api;
 pub mod executor_launcher;
@@ -20,7 +21,9 @@ pub mod local_resource_registry;
 pub(crate) mod local_resource_setup;
 pub mod orchestrator;
 pub(crate) mod remote_storage;
+pub(crate) mod report;
 pub mod session;
+pub(crate) mod sharding;
 pub(crate) mod tcp;
 pub mod translations;
 #[cfg(unix)]
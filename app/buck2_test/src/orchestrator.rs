@@ -159,6 +159,7 @@ use crate::local_resource_setup::TestStageSimple;
 use crate::remote_storage;
 use crate::session::TestSession;
 use crate::session::TestSessionOptions;
+use crate::sharding::SHARD_TEST_CASES_LABEL;
 use crate::translations;
 
 const MAX_SUFFIX_LEN: usize = 1024;
@@ -345,6 +346,14 @@ impl<'a> BuckTestOrchestrator<'a> {
             start_time: timing.start_time,
             execution_time: timing.execution_time,
             execution_details: ExecutionDetails {
+                cached: matches!(
+                    execution_kind,
+                    Some(
+                        CommandExecutionKind::ActionCache { .. }
+                            | CommandExecutionKind::RemoteDepFileCache { .. }
+                            | CommandExecutionKind::LocalActionCache { .. }
+                    )
+                ),
                 execution_kind: execution_kind.map(|k| k.to_proto(false)),
             },
         })
@@ -448,6 +457,7 @@ impl<'a> BuckTestOrchestrator<'a> {
             required_resources,
             worker,
             test_executor.re_dynamic_image(),
+            test_executor.cacheable(),
         )
         .boxed()
         .await?;
@@ -460,6 +470,7 @@ impl<'a> BuckTestOrchestrator<'a> {
             execution_request,
             liveliness_observer.dupe(),
             test_executor.re_cache_enabled(),
+            test_executor.cacheable(),
         )
         .boxed()
         .await?;
@@ -818,6 +829,7 @@ impl<'a> TestOrchestrator for BuckTestOrchestrator<'a> {
             vec![],
             worker,
             test_executor.re_dynamic_image(),
+            test_executor.cacheable(),
         )
         .await?;
 
@@ -909,6 +921,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         request: CommandExecutionRequest,
         liveliness_observer: Arc<dyn LivelinessObserver>,
         re_cache_enabled: bool,
+        cacheable: bool,
     ) -> Result<ExecuteData, ExecuteError> {
         let events = dice.per_transaction_data().get_dispatcher().dupe();
         let manager = CommandExecutionManager::new(
@@ -933,8 +946,6 @@ impl<'b> BuckTestOrchestrator<'b> {
             action_key_suffix,
         };
 
-        // For test execution, we currently do not do any cache queries
-
         let prepared_action = match executor.prepare_action(&request, digest_config) {
             Ok(prepared_action) => prepared_action,
             Err(e) => return Err(ExecuteError::Error(e.into())),
@@ -1002,7 +1013,6 @@ impl<'b> BuckTestOrchestrator<'b> {
                 result
             }
             TestStage::Testing { suite, testcases } => {
-                let command = executor.exec_cmd(manager, &prepared_command, cancellation);
                 let test_suite = Some(TestSuite {
                     suite_name: suite.clone(),
                     test_names: testcases.clone(),
@@ -1011,9 +1021,28 @@ impl<'b> BuckTestOrchestrator<'b> {
                 let start = TestRunStart {
                     suite: test_suite.clone(),
                 };
-                events
+                let (result, cached) = events
                     .span_async(start, async move {
-                        let result = command.await;
+                        // Only the results of tests which declare themselves hermetic are cached.
+                        let (result, cached) = if cacheable {
+                            match executor
+                                .action_cache(manager, &prepared_command, cancellation)
+                                .await
+                            {
+                                ControlFlow::Continue(manager) => {
+                                    let result = executor
+                                        .exec_cmd(manager, &prepared_command, cancellation)
+                                        .await;
+                                    (result, false)
+                                }
+                                ControlFlow::Break(result) => (result, true),
+                            }
+                        } else {
+                            let result = executor
+                                .exec_cmd(manager, &prepared_command, cancellation)
+                                .await;
+                            (result, false)
+                        };
                         let end = TestRunEnd {
                             suite: test_suite,
                             command_report: Some(
@@ -1023,9 +1052,29 @@ impl<'b> BuckTestOrchestrator<'b> {
                                     .await,
                             ),
                         };
-                        (result, end)
+                        ((result, cached), end)
                     })
-                    .await
+                    .await;
+                // Only passing results are cached, so that failing tests are always rerun.
+                if cacheable && !cached && result.was_success() {
+                    let info = CacheUploadInfo {
+                        target: &test_target as _,
+                        digest_config,
+                    };
+                    if let Err(e) = executor
+                        .cache_upload(
+                            &info,
+                            &result,
+                            None,
+                            None,
+                            &prepared_action.action_and_blobs,
+                        )
+                        .await
+                    {
+                        return Err(ExecuteError::Error(e.into()));
+                    }
+                }
+                result
             }
         };
 
@@ -1110,7 +1159,7 @@ impl<'b> BuckTestOrchestrator<'b> {
     fn executor_config_with_remote_cache_override<'a>(
         test_target_node: &'a ConfiguredTargetNode,
         executor_override: Option<&'a CommandExecutorConfig>,
-        stage: &TestStage,
+        cacheable: bool,
     ) -> anyhow::Result<Cow<'a, CommandExecutorConfig>> {
         let executor_config = match executor_override {
             Some(o) => o,
@@ -1120,7 +1169,7 @@ impl<'b> BuckTestOrchestrator<'b> {
                 .buck_error_context_anyhow("Error accessing executor config")?,
         };
 
-        if let TestStage::Listing(_) = &stage {
+        if cacheable {
             return Ok(Cow::Borrowed(executor_config));
         }
 
@@ -1142,7 +1191,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         dice: &mut DiceComputations<'_>,
         fs: &ArtifactFs,
         executor_config: &CommandExecutorConfig,
-        stage: &TestStage,
+        cacheable: bool,
     ) -> anyhow::Result<CommandExecutor> {
         let CommandExecutorResponse {
             executor,
@@ -1151,13 +1200,13 @@ impl<'b> BuckTestOrchestrator<'b> {
             cache_uploader,
         } = dice.get_command_executor_from_dice(executor_config).await?;
 
-        // Caching is enabled only for listings
-        let (cache_uploader, cache_checker) = match stage {
-            TestStage::Listing(_) => (cache_uploader, cache_checker),
-            TestStage::Testing { .. } => (
+        let (cache_uploader, cache_checker) = if cacheable {
+            (cache_uploader, cache_checker)
+        } else {
+            (
                 Arc::new(NoOpCacheUploader {}) as _,
                 Arc::new(NoOpCommandOptionalExecutor {}) as _,
-            ),
+            )
         };
 
         let executor = CommandExecutor::new(
@@ -1247,19 +1296,26 @@ impl<'b> BuckTestOrchestrator<'b> {
             None => test_info.default_executor().map(|o| &o.0),
         };
 
+        // Listings are cached, and so are the results of tests which declare themselves hermetic.
+        let cacheable = match stage {
+            TestStage::Listing(_) => true,
+            TestStage::Testing { .. } => test_info.hermetic(),
+        };
+
         let executor_config = Self::executor_config_with_remote_cache_override(
             &node,
             resolved_executor_override.as_ref().map(|a| &***a),
-            &stage,
+            cacheable,
         )?;
 
-        let executor = Self::get_command_executor(dice, fs, &executor_config, stage)
+        let executor = Self::get_command_executor(dice, fs, &executor_config, cacheable)
             .await
             .context("Error constructing CommandExecutor")?;
 
         Ok(TestExecutor {
             test_executor: executor,
             executor_config: executor_config.into_owned(),
+            cacheable,
         })
     }
 
@@ -1276,6 +1332,21 @@ impl<'b> BuckTestOrchestrator<'b> {
     ) -> anyhow::Result<ExpandedTestExecutable> {
         let output_root = resolve_output_root(dice, test_target, prefix).await?;
 
+        let mut env = env;
+        if let Some(shard) = opts.test_case_shard {
+            if test_info.labels().any(|l| l == SHARD_TEST_CASES_LABEL) {
+                let verbatim = |value: u32| ArgValue {
+                    content: ArgValueContent::ExternalRunnerSpecValue(
+                        ExternalRunnerSpecValue::Verbatim(value.to_string()),
+                    ),
+                    format: None,
+                };
+                let env = env.to_mut();
+                env.insert("TEST_TOTAL_SHARDS".to_owned(), verbatim(shard.count));
+                env.insert("TEST_SHARD_INDEX".to_owned(), verbatim(shard.index));
+            }
+        }
+
         let mut declared_outputs = IndexMap::<BuckOutTestPath, OutputCreationBehavior>::new();
 
         let mut supports_re = true;
@@ -1345,6 +1416,7 @@ impl<'b> BuckTestOrchestrator<'b> {
         required_local_resources: Vec<LocalResourceState>,
         worker: Option<WorkerSpec>,
         re_dynamic_image: Option<RemoteExecutorCustomImage>,
+        cacheable: bool,
     ) -> anyhow::Result<CommandExecutionRequest> {
         let mut inputs = Vec::with_capacity(cmd_inputs.len());
         for input in &cmd_inputs {
@@ -1373,6 +1445,7 @@ impl<'b> BuckTestOrchestrator<'b> {
             .with_working_directory(cwd)
             .with_local_environment_inheritance(EnvironmentInheritance::test_allowlist())
             .with_disable_miniperf(true)
+            .with_cacheable(cacheable)
             .with_worker(worker)
             .with_remote_execution_custom_image(re_dynamic_image)
             .with_required_local_resources(required_local_resources)?;
@@ -1482,7 +1555,9 @@ impl<'b> BuckTestOrchestrator<'b> {
             .collect();
         let paths = CommandExecutionPaths::new(inputs, indexset![], fs, digest_config)?;
         let mut execution_request =
-            CommandExecutionRequest::new(vec![], context.cmd, paths, Default::default());
+            CommandExecutionRequest::new(vec![], context.cmd, paths, Default::default())
+                // Setting up a resource has side effects, so it always has to run.
+                .with_cacheable(false);
         execution_request =
             execution_request.with_timeout(context.timeout.unwrap_or(default_timeout));
         Ok(PreparedLocalResourceSetupContext {
@@ -2020,9 +2095,15 @@ impl CommandExecutionTarget for LocalResourceTarget<'_> {
 struct TestExecutor {
     test_executor: CommandExecutor,
     executor_config: CommandExecutorConfig,
+    cacheable: bool,
 }
 
 impl TestExecutor {
+    /// Whether results may be served from and stored in the action caches.
+    pub fn cacheable(&self) -> bool {
+        self.cacheable
+    }
+
     pub fn re_cache_enabled(&self) -> bool {
         self.executor_config.re_cache_enabled()
     }
@@ -2113,6 +2194,9 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    stdout: None,
+                    stderr: None,
+                    cached: false,
                 })
                 .await?;
 
@@ -2124,6 +2208,9 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    stdout: None,
+                    stderr: None,
+                    cached: false,
                 })
                 .await?;
 
@@ -2145,6 +2232,9 @@ mod tests {
                     name: "First - test".to_owned(),
                     duration: Some(Duration::from_micros(1)),
                     details: "1".to_owned(),
+                    stdout: None,
+                    stderr: None,
+                    cached: false,
                 }),
                 ExecutorMessage::TestResult(TestResult {
                     target,
@@ -2154,6 +2244,9 @@ mod tests {
                     name: "Second - test".to_owned(),
                     duration: Some(Duration::from_micros(2)),
                     details: "2".to_owned(),
+                    stdout: None,
+                    stderr: None,
+                    cached: false,
                 }),
                 ExecutorMessage::ExitCode(0),
             ]
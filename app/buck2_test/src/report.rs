@@ -0,0 +1,410 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Reports of the individual test results of a `buck2 test` run, in formats that CI systems
+//! consume: JUnit XML and TAP.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use buck2_cli_proto::TestReportFormat;
+use buck2_test_api::data::TestResult;
+use buck2_test_api::data::TestStatus;
+use dupe::Dupe;
+use indexmap::IndexMap;
+
+use crate::session::TestSession;
+
+/// A test result, with the target it came from resolved to a label.
+#[derive(Debug, Clone)]
+pub(crate) struct ReportedTestResult {
+    pub(crate) target_label: String,
+    pub(crate) name: String,
+    pub(crate) status: TestStatus,
+    pub(crate) msg: Option<String>,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) details: String,
+    pub(crate) stdout: Option<String>,
+    pub(crate) stderr: Option<String>,
+    pub(crate) cached: bool,
+}
+
+impl ReportedTestResult {
+    pub(crate) fn new(result: &TestResult, session: &TestSession) -> anyhow::Result<Self> {
+        Ok(Self {
+            target_label: session.get(result.target)?.target().to_string(),
+            name: result.name.clone(),
+            status: result.status.dupe(),
+            msg: result.msg.clone(),
+            duration: result.duration,
+            details: result.details.clone(),
+            stdout: result.stdout.clone(),
+            stderr: result.stderr.clone(),
+            cached: result.cached,
+        })
+    }
+
+    /// Whether the result belongs in a report. Successful listings aren't tests, and reruns are
+    /// superseded by the result of the run that follows them.
+    fn is_reported(&self) -> bool {
+        !matches!(self.status, TestStatus::LISTING_SUCCESS | TestStatus::RERUN)
+    }
+
+    /// The message explaining a result that isn't a pass.
+    fn message(&self) -> String {
+        if let Some(msg) = &self.msg {
+            return msg.clone();
+        }
+        match self.status {
+            TestStatus::TIMEOUT => match self.duration {
+                Some(d) => format!("Timed out after {:.3}s", d.as_secs_f64()),
+                None => "Timed out".to_owned(),
+            },
+            TestStatus::OMITTED => "Omitted".to_owned(),
+            TestStatus::LISTING_FAILED => "Listing failed".to_owned(),
+            TestStatus::UNKNOWN => "Unknown status".to_owned(),
+            _ => String::new(),
+        }
+    }
+
+    /// The test's stdout, or its combined output if the executor didn't capture it separately.
+    fn stdout(&self) -> &str {
+        match (&self.stdout, &self.stderr) {
+            (Some(stdout), _) => stdout,
+            (None, None) => &self.details,
+            (None, Some(_)) => "",
+        }
+    }
+
+    fn stderr(&self) -> &str {
+        self.stderr.as_deref().unwrap_or_default()
+    }
+
+    fn seconds(&self) -> f64 {
+        self.duration.map_or(0.0, |d| d.as_secs_f64())
+    }
+}
+
+pub(crate) fn generate_report(format: TestReportFormat, results: &[ReportedTestResult]) -> String {
+    let results = results.iter().filter(|r| r.is_reported());
+    match format {
+        TestReportFormat::Junit => junit_report(results),
+        TestReportFormat::Tap => tap_report(results),
+    }
+}
+
+/// How a result counts in the JUnit totals.
+enum JunitOutcome {
+    Pass,
+    Failure,
+    Error,
+    Skipped,
+}
+
+fn junit_outcome(status: &TestStatus) -> JunitOutcome {
+    match status {
+        TestStatus::PASS | TestStatus::FLAKY | TestStatus::LISTING_SUCCESS | TestStatus::RERUN => {
+            JunitOutcome::Pass
+        }
+        TestStatus::FAIL | TestStatus::TIMEOUT => JunitOutcome::Failure,
+        TestStatus::FATAL | TestStatus::LISTING_FAILED | TestStatus::UNKNOWN => JunitOutcome::Error,
+        TestStatus::SKIP | TestStatus::OMITTED => JunitOutcome::Skipped,
+    }
+}
+
+#[derive(Default)]
+struct JunitTotals {
+    tests: u64,
+    failures: u64,
+    errors: u64,
+    skipped: u64,
+    seconds: f64,
+}
+
+impl JunitTotals {
+    fn add(&mut self, result: &ReportedTestResult) {
+        self.tests += 1;
+        match junit_outcome(&result.status) {
+            JunitOutcome::Pass => {}
+            JunitOutcome::Failure => self.failures += 1,
+            JunitOutcome::Error => self.errors += 1,
+            JunitOutcome::Skipped => self.skipped += 1,
+        }
+        self.seconds += result.seconds();
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            self.tests, self.failures, self.errors, self.skipped, self.seconds
+        )
+    }
+}
+
+/// A JUnit XML report, with a test suite per target.
+fn junit_report<'a>(results: impl Iterator<Item = &'a ReportedTestResult>) -> String {
+    let mut suites: IndexMap<&str, Vec<&ReportedTestResult>> = IndexMap::new();
+    let mut totals = JunitTotals::default();
+    for result in results {
+        totals.add(result);
+        suites
+            .entry(result.target_label.as_str())
+            .or_default()
+            .push(result);
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    writeln!(out, "<testsuites {}>", totals.attributes()).unwrap();
+    for (label, results) in suites {
+        let mut suite_totals = JunitTotals::default();
+        results.iter().for_each(|r| suite_totals.add(r));
+        writeln!(
+            out,
+            "  <testsuite name=\"{}\" {}>",
+            xml_escape(label),
+            suite_totals.attributes()
+        )
+        .unwrap();
+        for result in results {
+            writeln!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+                xml_escape(&result.name),
+                xml_escape(label),
+                result.seconds()
+            )
+            .unwrap();
+            if result.cached {
+                out.push_str(
+                    "      <properties><property name=\"cached\" value=\"true\"/></properties>\n",
+                );
+            }
+            let element = match junit_outcome(&result.status) {
+                JunitOutcome::Pass => None,
+                JunitOutcome::Failure => Some("failure"),
+                JunitOutcome::Error => Some("error"),
+                JunitOutcome::Skipped => Some("skipped"),
+            };
+            if let Some(element) = element {
+                writeln!(
+                    out,
+                    "      <{} message=\"{}\" type=\"{:?}\"/>",
+                    element,
+                    xml_escape(&result.message()),
+                    result.status
+                )
+                .unwrap();
+            }
+            for (element, output) in [
+                ("system-out", result.stdout()),
+                ("system-err", result.stderr()),
+            ] {
+                if !output.is_empty() {
+                    writeln!(
+                        out,
+                        "      <{}>{}</{}>",
+                        element,
+                        xml_escape(output),
+                        element
+                    )
+                    .unwrap();
+                }
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+/// Escapes text for use in XML attributes and elements. Characters which XML 1.0 doesn't allow
+/// at all are replaced.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {
+                out.push(char::REPLACEMENT_CHARACTER)
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A TAP version 13 report. Results other than passes carry a YAML block with their status,
+/// message and output.
+fn tap_report<'a>(results: impl Iterator<Item = &'a ReportedTestResult>) -> String {
+    let results = results.collect::<Vec<_>>();
+    let mut out = String::new();
+    out.push_str("TAP version 13\n");
+    writeln!(out, "1..{}", results.len()).unwrap();
+    for (i, result) in results.iter().enumerate() {
+        let ok = matches!(
+            junit_outcome(&result.status),
+            JunitOutcome::Pass | JunitOutcome::Skipped
+        );
+        write!(
+            out,
+            "{} {} - {} {}",
+            if ok { "ok" } else { "not ok" },
+            i + 1,
+            tap_escape(&result.target_label),
+            tap_escape(&result.name)
+        )
+        .unwrap();
+        if matches!(result.status, TestStatus::SKIP | TestStatus::OMITTED) {
+            write!(out, " # SKIP {}", tap_escape(&result.message())).unwrap();
+        }
+        out.push('\n');
+
+        if ok {
+            continue;
+        }
+        out.push_str("  ---\n");
+        writeln!(out, "  status: {:?}", result.status).unwrap();
+        writeln!(
+            out,
+            "  duration_ms: {}",
+            (result.seconds() * 1000.0).round()
+        )
+        .unwrap();
+        tap_yaml_block(&mut out, "message", &result.message());
+        tap_yaml_block(&mut out, "stdout", result.stdout());
+        tap_yaml_block(&mut out, "stderr", result.stderr());
+        out.push_str("  ...\n");
+    }
+    out
+}
+
+/// `#` starts a directive in a TAP test line, so it's escaped in descriptions.
+fn tap_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Writes a YAML literal block scalar, which needs no escaping other than indentation.
+fn tap_yaml_block(out: &mut String, key: &str, value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    writeln!(out, "  {}: |", key).unwrap();
+    for line in value.lines() {
+        writeln!(out, "    {}", line).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(
+        target_label: &str,
+        name: &str,
+        status: TestStatus,
+        stdout: Option<&str>,
+    ) -> ReportedTestResult {
+        ReportedTestResult {
+            target_label: target_label.to_owned(),
+            name: name.to_owned(),
+            status,
+            msg: None,
+            duration: Some(Duration::from_millis(1500)),
+            details: "combined output".to_owned(),
+            stdout: stdout.map(|s| s.to_owned()),
+            stderr: None,
+            cached: false,
+        }
+    }
+
+    fn results() -> Vec<ReportedTestResult> {
+        vec![
+            ReportedTestResult {
+                cached: true,
+                ..result("cell//pkg:a", "passes", TestStatus::PASS, Some(""))
+            },
+            result("cell//pkg:a", "fails <&>", TestStatus::FAIL, Some("out\n")),
+            result("cell//pkg:b", "times out", TestStatus::TIMEOUT, None),
+            result("cell//pkg:b", "omitted", TestStatus::OMITTED, Some("")),
+            result("cell//pkg:b", "listed", TestStatus::LISTING_SUCCESS, None),
+        ]
+    }
+
+    #[test]
+    fn test_junit_report() {
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="4" failures="2" errors="0" skipped="1" time="6.000">
+  <testsuite name="cell//pkg:a" tests="2" failures="1" errors="0" skipped="0" time="3.000">
+    <testcase name="passes" classname="cell//pkg:a" time="1.500">
+      <properties><property name="cached" value="true"/></properties>
+    </testcase>
+    <testcase name="fails &lt;&amp;&gt;" classname="cell//pkg:a" time="1.500">
+      <failure message="" type="FAIL"/>
+      <system-out>out
+</system-out>
+    </testcase>
+  </testsuite>
+  <testsuite name="cell//pkg:b" tests="2" failures="1" errors="0" skipped="1" time="3.000">
+    <testcase name="times out" classname="cell//pkg:b" time="1.500">
+      <failure message="Timed out after 1.500s" type="TIMEOUT"/>
+      <system-out>combined output</system-out>
+    </testcase>
+    <testcase name="omitted" classname="cell//pkg:b" time="1.500">
+      <skipped message="Omitted" type="OMITTED"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+            generate_report(TestReportFormat::Junit, &results())
+        );
+    }
+
+    #[test]
+    fn test_tap_report() {
+        assert_eq!(
+            r#"TAP version 13
+1..4
+ok 1 - cell//pkg:a passes
+not ok 2 - cell//pkg:a fails <&>
+  ---
+  status: FAIL
+  duration_ms: 1500
+  stdout: |
+    out
+  ...
+not ok 3 - cell//pkg:b times out
+  ---
+  status: TIMEOUT
+  duration_ms: 1500
+  message: |
+    Timed out after 1.500s
+  stdout: |
+    combined output
+  ...
+ok 4 - cell//pkg:b omitted # SKIP Omitted
+"#,
+            generate_report(TestReportFormat::Tap, &results())
+        );
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!("a&quot;b&apos;\u{FFFD}\n", xml_escape("a\"b'\u{1b}\n"));
+    }
+}
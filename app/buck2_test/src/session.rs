@@ -27,6 +27,8 @@ pub struct TestSessionOptions {
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// The shard whose test cases to run, of the tests that shard their test cases.
+    pub test_case_shard: Option<TestCaseShard>,
 }
 
 impl fmt::Display for TestSessionOptions {
@@ -35,10 +37,20 @@ impl fmt::Display for TestSessionOptions {
             f,
             "allow_re = {}, force_use_project_relative_paths = {}, force_run_from_project_root = {}",
             self.allow_re, self.force_use_project_relative_paths, self.force_run_from_project_root
-        )
+        )?;
+        if let Some(shard) = self.test_case_shard {
+            write!(f, ", test_case_shard = {}/{}", shard.index, shard.count)?;
+        }
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy, Dupe, Allocative, PartialEq, Hash, Eq)]
+pub struct TestCaseShard {
+    pub index: u32,
+    pub count: u32,
+}
+
 /// The state of a buck2 test command.
 pub struct TestSession {
     /// The next ConfiguredTargetHandle that will be assigned.
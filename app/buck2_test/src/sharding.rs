@@ -0,0 +1,171 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Partitioning of test targets across the shards of a `buck2 test --shard-count N` run.
+//!
+//! Every shard makes the same decision independently: targets with a known duration (from a
+//! prior run) are spread greedily so that shards take about as long, and the rest are assigned
+//! by a stable hash of their label.
+//!
+//! Test targets labelled `shard_test_cases` are run by every shard instead, which each tell the
+//! test which of its test cases to run, through the `TEST_TOTAL_SHARDS` and `TEST_SHARD_INDEX`
+//! environment variables.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use buck2_core::target::label::label::TargetLabel;
+
+/// The label of test targets whose test cases are sharded, rather than the whole target.
+pub(crate) const SHARD_TEST_CASES_LABEL: &str = "shard_test_cases";
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+pub(crate) enum TestShardingError {
+    #[error("Invalid test sharding: shard index {index} is not less than shard count {count}")]
+    IndexOutOfRange { index: u32, count: u32 },
+}
+
+pub(crate) struct TestSharding {
+    index: u32,
+    count: u32,
+    /// The shard of each target that has a known duration.
+    assigned: HashMap<String, u32>,
+}
+
+impl TestSharding {
+    pub(crate) fn new(
+        index: u32,
+        count: u32,
+        durations: &HashMap<String, Duration>,
+    ) -> buck2_error::Result<Self> {
+        if index >= count {
+            return Err(TestShardingError::IndexOutOfRange { index, count }.into());
+        }
+
+        // Longest first, assigning each target to the least loaded shard (the lowest index on
+        // ties), so every shard computes the same assignment.
+        let mut by_duration = durations.iter().collect::<Vec<_>>();
+        by_duration.sort_by(|(a_label, a), (b_label, b)| b.cmp(a).then(a_label.cmp(b_label)));
+        let mut loads = vec![Duration::ZERO; count as usize];
+        let mut assigned = HashMap::with_capacity(by_duration.len());
+        for (label, duration) in by_duration {
+            let (shard, load) = loads
+                .iter_mut()
+                .enumerate()
+                .min_by_key(|(_, load)| **load)
+                .unwrap();
+            *load += *duration;
+            assigned.insert(label.clone(), shard as u32);
+        }
+
+        Ok(Self {
+            index,
+            count,
+            assigned,
+        })
+    }
+
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether this shard runs the tests of `target`. Every shard runs a target that shards its
+    /// test cases.
+    pub(crate) fn includes(&self, target: &TargetLabel, shards_test_cases: bool) -> bool {
+        shards_test_cases || self.shard_of(&target.to_string()) == self.index
+    }
+
+    fn shard_of(&self, label: &str) -> u32 {
+        match self.assigned.get(label) {
+            Some(shard) => *shard,
+            None => (stable_hash(label) % self.count as u64) as u32,
+        }
+    }
+}
+
+/// FNV-1a, which unlike the std hashers is the same across processes, releases and platforms.
+fn stable_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> Vec<String> {
+        (0..100).map(|i| format!("root//foo:test_{}", i)).collect()
+    }
+
+    #[test]
+    fn test_shards_partition_targets() -> buck2_error::Result<()> {
+        let shards = (0..4)
+            .map(|i| TestSharding::new(i, 4, &HashMap::new()))
+            .collect::<buck2_error::Result<Vec<_>>>()?;
+        for label in labels() {
+            assert_eq!(
+                1,
+                shards
+                    .iter()
+                    .filter(|s| s.shard_of(&label) == s.index())
+                    .count(),
+                "{} must be in exactly one shard",
+                label
+            );
+        }
+        assert!(
+            shards
+                .iter()
+                .all(|s| labels().iter().any(|l| s.shard_of(l) == s.index())),
+            "no shard is empty"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_shards_balance_known_durations() -> buck2_error::Result<()> {
+        let durations = HashMap::from([
+            ("root//foo:a".to_owned(), Duration::from_secs(60)),
+            ("root//foo:b".to_owned(), Duration::from_secs(30)),
+            ("root//foo:c".to_owned(), Duration::from_secs(20)),
+            ("root//foo:d".to_owned(), Duration::from_secs(10)),
+        ]);
+        let shard = TestSharding::new(1, 2, &durations)?;
+        assert_eq!(0, shard.shard_of("root//foo:a"));
+        assert_eq!(1, shard.shard_of("root//foo:b"));
+        assert_eq!(1, shard.shard_of("root//foo:c"));
+        assert_eq!(1, shard.shard_of("root//foo:d"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shards_all_include_test_case_sharded_targets() -> buck2_error::Result<()> {
+        let target = TargetLabel::testing_parse("root//foo:test");
+        let shards = (0..4)
+            .map(|i| TestSharding::new(i, 4, &HashMap::new()))
+            .collect::<buck2_error::Result<Vec<_>>>()?;
+        assert_eq!(
+            1,
+            shards.iter().filter(|s| s.includes(&target, false)).count()
+        );
+        assert!(shards.iter().all(|s| s.includes(&target, true)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shard_index_out_of_range() {
+        assert!(TestSharding::new(2, 2, &HashMap::new()).is_err());
+    }
+}
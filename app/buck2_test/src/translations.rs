@@ -59,6 +59,9 @@ pub(crate) fn convert_test_result(
         duration,
         details,
         target: test_target,
+        stdout: _,
+        stderr: _,
+        cached,
     } = test_result;
 
     let test_target = session.get(test_target)?;
@@ -70,6 +73,7 @@ pub(crate) fn convert_test_result(
         duration: duration.and_then(|d| d.try_into().ok()),
         details,
         target_label: Some(test_target.target().as_proto()),
+        cached,
     })
 }
 
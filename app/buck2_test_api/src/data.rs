@@ -109,6 +109,11 @@ pub struct TestResult {
     pub duration: Option<Duration>,
     // the output of the test execution (combining stdout and stderr)
     pub details: String,
+    // the stdout and stderr of the test execution, if captured separately
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    // whether the result was replayed from a cache rather than executed
+    pub cached: bool,
 }
 
 /// different possible test results
@@ -128,6 +133,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    // Failed, then passed when retried
+    FLAKY,
 }
 
 /// The set of information about a test rule that is passed to the test executor
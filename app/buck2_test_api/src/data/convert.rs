@@ -223,6 +223,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::Flaky => TestStatus::FLAKY,
         })
     }
 }
@@ -242,6 +243,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::FLAKY => buck2_test_proto::TestStatus::Flaky,
         } as i32)
     }
 }
@@ -257,6 +259,9 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg,
             duration,
             details,
+            stdout,
+            stderr,
+            cached,
         } = s;
 
         let duration = duration
@@ -274,6 +279,9 @@ impl TryFrom<buck2_test_proto::TestResult> for TestResult {
             msg: msg.map(|m| m.msg),
             duration,
             details,
+            stdout,
+            stderr,
+            cached,
         })
     }
 }
@@ -291,6 +299,9 @@ impl TryInto<buck2_test_proto::TestResult> for TestResult {
             details: self.details,
             msg: self.msg.map(|msg| OptionalMsg { msg }),
             duration: self.duration.try_map(|d| d.try_into())?,
+            stdout: self.stdout,
+            stderr: self.stderr,
+            cached: self.cached,
         })
     }
 }
@@ -23,6 +23,11 @@ pub struct Config {
     #[clap(long, default_value = "600", value_parser = try_parse_timeout_from_str)]
     pub timeout: Duration,
 
+    /// Number of times to rerun a test that fails or times out. A test that passes when rerun is
+    /// reported as flaky. A `retries=N` label on a test target overrides this for that target.
+    #[clap(long, default_value = "0")]
+    pub retries: u32,
+
     /// Ignored arg included for backwards compatibility.
     #[clap(long, hide = true)]
     buck_test_info: String,
@@ -14,6 +14,7 @@ use buck2_test_api::data::ConfiguredTargetHandle;
 use buck2_test_api::data::ExecuteResponse;
 use buck2_test_api::data::ExecutionResult2;
 use buck2_test_api::data::ExecutionStatus;
+use buck2_test_api::data::ExecutionStream;
 use buck2_test_api::data::ExternalRunnerSpec;
 use buck2_test_api::data::ExternalRunnerSpecValue;
 use buck2_test_api::data::RequiredLocalResources;
@@ -32,6 +33,9 @@ use crate::config::EnvValue;
 
 pub type SpecReceiver = UnboundedReceiver<ExternalRunnerSpec>;
 
+/// Test targets with this label are reported as skipped instead of being run.
+const SKIP_LABEL: &str = "skip";
+
 /// Internal test runner implementation for Buck2.
 ///
 /// This is a basic test runner intended to be used by the open-source Buck2 build
@@ -76,33 +80,83 @@ impl Buck2TestRunner {
                 );
                 let target_handle = spec.target.handle.to_owned();
 
-                let execution_response = self
-                    .execute_test_from_spec(spec)
-                    .await
-                    .expect("Test execution request failed");
-
-                let execution_result = match execution_response {
-                    ExecuteResponse::Result(r) => r,
-                    ExecuteResponse::Cancelled => return TestStatus::OMITTED,
+                let retries = match retries_from_labels(&spec.labels) {
+                    Ok(retries) => retries.unwrap_or(self.config.retries),
+                    Err(e) => {
+                        self.report_test_result(TestResult {
+                            target: target_handle,
+                            name,
+                            status: TestStatus::FATAL,
+                            msg: Some(format!("{:#}", e)),
+                            duration: None,
+                            details: String::new(),
+                            stdout: None,
+                            stderr: None,
+                            cached: false,
+                        })
+                        .await
+                        .expect("Test result reporting failed");
+                        return TestStatus::FATAL;
+                    }
                 };
 
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
-
-                self.report_test_result(test_result)
+                if spec.labels.iter().any(|label| label == SKIP_LABEL) {
+                    self.report_test_result(TestResult {
+                        target: target_handle,
+                        name,
+                        status: TestStatus::SKIP,
+                        msg: Some(format!("Skipped by the `{}` label", SKIP_LABEL)),
+                        duration: None,
+                        details: String::new(),
+                        stdout: None,
+                        stderr: None,
+                        cached: false,
+                    })
                     .await
                     .expect("Test result reporting failed");
+                    return TestStatus::SKIP;
+                }
+
+                let mut attempt = 0;
+                loop {
+                    let execution_response = self
+                        .execute_test_from_spec(spec.clone())
+                        .await
+                        .expect("Test execution request failed");
+
+                    let execution_result = match execution_response {
+                        ExecuteResponse::Result(r) => r,
+                        ExecuteResponse::Cancelled => return TestStatus::OMITTED,
+                    };
+
+                    let mut test_result =
+                        get_test_result(name.clone(), target_handle, execution_result);
+                    let (status, retry) = classify_attempt(test_result.status, attempt, retries);
+                    test_result.status = status;
+                    let test_status = test_result.status.clone();
+
+                    self.report_test_result(test_result)
+                        .await
+                        .expect("Test result reporting failed");
 
-                test_status
+                    if !retry {
+                        return test_status;
+                    }
+                    attempt += 1;
+                }
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
             .buffer_unordered(10000)
-            // If any individual test failed, consider the entire run to have failed.
+            // If any individual test failed, consider the entire run to have failed. Flaky tests
+            // eventually passed, and skipped tests didn't run, so neither fails the run.
             .fold(
                 RunVerdict::Pass,
                 |mut run_verdict, test_status| async move {
-                    if test_status != TestStatus::PASS {
+                    if !matches!(
+                        test_status,
+                        TestStatus::PASS | TestStatus::FLAKY | TestStatus::SKIP
+                    ) {
                         run_verdict = RunVerdict::Fail;
                     }
                     run_verdict
@@ -217,6 +271,42 @@ fn get_test_result(
             "---- STDOUT ----\n{:?}\n---- STDERR ----\n{:?}\n",
             execution_result.stdout, execution_result.stderr
         ),
+        stdout: Some(stream_to_string(&execution_result.stdout)),
+        stderr: Some(stream_to_string(&execution_result.stderr)),
+        cached: execution_result.execution_details.cached,
+    }
+}
+
+/// The status to report for an attempt at running a test, and whether to run it again.
+///
+/// Failed and timed out attempts that will be retried are reported as reruns, and a pass after
+/// such an attempt is reported as flaky.
+fn classify_attempt(status: TestStatus, attempt: u32, retries: u32) -> (TestStatus, bool) {
+    match status {
+        TestStatus::FAIL | TestStatus::TIMEOUT if attempt < retries => (TestStatus::RERUN, true),
+        TestStatus::PASS if attempt > 0 => (TestStatus::FLAKY, false),
+        status => (status, false),
+    }
+}
+
+/// The number of retries set by a `retries=N` label, if any.
+fn retries_from_labels(labels: &[String]) -> anyhow::Result<Option<u32>> {
+    let mut retries = None;
+    for label in labels {
+        if let Some(value) = label.strip_prefix("retries=") {
+            retries = Some(
+                value
+                    .parse()
+                    .with_context(|| format!("Invalid test label `{}`", label))?,
+            );
+        }
+    }
+    Ok(retries)
+}
+
+fn stream_to_string(stream: &ExecutionStream) -> String {
+    match stream {
+        ExecutionStream::Inline(d) => String::from_utf8_lossy(d).into_owned(),
     }
 }
 
@@ -234,3 +324,78 @@ impl RunVerdict {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|l| (*l).to_owned()).collect()
+    }
+
+    #[test]
+    fn test_retries_from_labels() -> anyhow::Result<()> {
+        assert_eq!(None, retries_from_labels(&[])?);
+        assert_eq!(None, retries_from_labels(&labels(&["slow", "retries"]))?);
+        assert_eq!(
+            Some(3),
+            retries_from_labels(&labels(&["slow", "retries=3"]))?
+        );
+        // The last label wins.
+        assert_eq!(
+            Some(0),
+            retries_from_labels(&labels(&["retries=3", "retries=0"]))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_retries_from_labels_invalid() {
+        for label in ["retries=", "retries=-1", "retries=many"] {
+            let err = retries_from_labels(&labels(&[label])).unwrap_err();
+            assert_eq!(format!("Invalid test label `{}`", label), err.to_string());
+        }
+    }
+
+    #[test]
+    fn test_classify_attempt() {
+        // No retries.
+        assert_eq!(
+            (TestStatus::PASS, false),
+            classify_attempt(TestStatus::PASS, 0, 0)
+        );
+        assert_eq!(
+            (TestStatus::FAIL, false),
+            classify_attempt(TestStatus::FAIL, 0, 0)
+        );
+
+        // Failures and timeouts are retried until the retries run out.
+        assert_eq!(
+            (TestStatus::RERUN, true),
+            classify_attempt(TestStatus::FAIL, 0, 2)
+        );
+        assert_eq!(
+            (TestStatus::RERUN, true),
+            classify_attempt(TestStatus::TIMEOUT, 1, 2)
+        );
+        assert_eq!(
+            (TestStatus::FAIL, false),
+            classify_attempt(TestStatus::FAIL, 2, 2)
+        );
+
+        // A pass after a retry is flaky.
+        assert_eq!(
+            (TestStatus::PASS, false),
+            classify_attempt(TestStatus::PASS, 0, 2)
+        );
+        assert_eq!(
+            (TestStatus::FLAKY, false),
+            classify_attempt(TestStatus::PASS, 1, 2)
+        );
+
+        // Other statuses aren't failures of the test, so are never retried.
+        for status in [TestStatus::SKIP, TestStatus::OMITTED, TestStatus::FATAL] {
+            assert_eq!((status.clone(), false), classify_attempt(status, 0, 2));
+        }
+    }
+}
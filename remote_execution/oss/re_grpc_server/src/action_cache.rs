@@ -0,0 +1,87 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use re_grpc_proto::build::bazel::remote::execution::v2::action_cache_server;
+use re_grpc_proto::build::bazel::remote::execution::v2::ActionResult;
+use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetActionResultRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::UpdateActionResultRequest;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+use crate::digest::display_digest;
+
+/// An in-memory action cache.
+#[derive(Default)]
+pub(crate) struct ActionCache {
+    /// Action results, keyed by action digest hash.
+    results: Mutex<HashMap<String, ActionResult>>,
+}
+
+impl ActionCache {
+    pub(crate) fn get(&self, action_digest: &Digest) -> Option<ActionResult> {
+        self.results
+            .lock()
+            .unwrap()
+            .get(&action_digest.hash)
+            .cloned()
+    }
+
+    pub(crate) fn insert(&self, action_digest: &Digest, result: ActionResult) {
+        self.results
+            .lock()
+            .unwrap()
+            .insert(action_digest.hash.clone(), result);
+    }
+}
+
+/// Serves the `ActionCache` service.
+pub(crate) struct ActionCacheService {
+    pub(crate) action_cache: Arc<ActionCache>,
+}
+
+#[tonic::async_trait]
+impl action_cache_server::ActionCache for ActionCacheService {
+    async fn get_action_result(
+        &self,
+        request: Request<GetActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let action_digest = request
+            .into_inner()
+            .action_digest
+            .ok_or_else(|| Status::invalid_argument("Missing `action_digest`"))?;
+        match self.action_cache.get(&action_digest) {
+            Some(result) => Ok(Response::new(result)),
+            None => Err(Status::not_found(format!(
+                "No cached result for action `{}`",
+                display_digest(&action_digest)
+            ))),
+        }
+    }
+
+    async fn update_action_result(
+        &self,
+        request: Request<UpdateActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let request = request.into_inner();
+        let action_digest = request
+            .action_digest
+            .ok_or_else(|| Status::invalid_argument("Missing `action_digest`"))?;
+        let result = request
+            .action_result
+            .ok_or_else(|| Status::invalid_argument("Missing `action_result`"))?;
+        self.action_cache.insert(&action_digest, result.clone());
+        Ok(Response::new(result))
+    }
+}
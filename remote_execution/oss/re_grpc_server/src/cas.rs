@@ -0,0 +1,425 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use dupe::Dupe;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use prost::Message;
+use re_grpc_proto::build::bazel::remote::execution::v2::batch_read_blobs_response;
+use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_response;
+use re_grpc_proto::build::bazel::remote::execution::v2::content_addressable_storage_server::ContentAddressableStorage;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchReadBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchReadBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchUpdateBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchUpdateBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
+use re_grpc_proto::build::bazel::remote::execution::v2::Directory;
+use re_grpc_proto::build::bazel::remote::execution::v2::FindMissingBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::FindMissingBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetTreeRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetTreeResponse;
+use re_grpc_proto::google::bytestream::byte_stream_server::ByteStream;
+use re_grpc_proto::google::bytestream::QueryWriteStatusRequest;
+use re_grpc_proto::google::bytestream::QueryWriteStatusResponse;
+use re_grpc_proto::google::bytestream::ReadRequest;
+use re_grpc_proto::google::bytestream::ReadResponse;
+use re_grpc_proto::google::bytestream::WriteRequest;
+use re_grpc_proto::google::bytestream::WriteResponse;
+use re_grpc_proto::google::rpc::Status as RpcStatus;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+use tonic::Streaming;
+
+use crate::digest::display_digest;
+use crate::digest::DigestAlgorithm;
+
+/// Size of the chunks blobs are sent in by `ByteStream.Read`.
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Convert a `tonic::Status` to a status to embed in a response.
+pub(crate) fn rpc_status(status: &Status) -> RpcStatus {
+    RpcStatus {
+        code: status.code() as i32,
+        message: status.message().to_owned(),
+        details: Vec::new(),
+    }
+}
+
+/// An in-memory content-addressable storage.
+pub(crate) struct Cas {
+    digest_algorithm: DigestAlgorithm,
+    /// Blobs, keyed by hash.
+    blobs: Mutex<HashMap<String, Arc<Vec<u8>>>>,
+}
+
+impl Cas {
+    pub(crate) fn new(digest_algorithm: DigestAlgorithm) -> Self {
+        Self {
+            digest_algorithm,
+            blobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store a blob, and return its digest.
+    pub(crate) fn put(&self, data: Vec<u8>) -> Digest {
+        let digest = self.digest_algorithm.digest(&data);
+        self.blobs
+            .lock()
+            .unwrap()
+            .insert(digest.hash.clone(), Arc::new(data));
+        digest
+    }
+
+    /// Store a blob uploaded by a client, checking that it matches the digest the client claims
+    /// it has.
+    pub(crate) fn put_with_digest(&self, digest: &Digest, data: Vec<u8>) -> Result<(), Status> {
+        let actual = self.digest_algorithm.digest(&data);
+        if &actual != digest {
+            return Err(Status::invalid_argument(format!(
+                "Digest mismatch: the blob uploaded as `{}` is `{}`",
+                display_digest(digest),
+                display_digest(&actual)
+            )));
+        }
+        self.put(data);
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, digest: &Digest) -> Option<Arc<Vec<u8>>> {
+        // Clients may assume the empty blob is always present.
+        if digest.size_bytes == 0 {
+            return Some(Arc::new(Vec::new()));
+        }
+        self.blobs
+            .lock()
+            .unwrap()
+            .get(&digest.hash)
+            .filter(|blob| blob.len() as i64 == digest.size_bytes)
+            .map(|blob| blob.dupe())
+    }
+
+    pub(crate) fn get_or_not_found(&self, digest: &Digest) -> Result<Arc<Vec<u8>>, Status> {
+        self.get(digest).ok_or_else(|| {
+            Status::not_found(format!("Blob not found: `{}`", display_digest(digest)))
+        })
+    }
+
+    /// Get a blob and decode it as a message.
+    pub(crate) fn get_message<M: Message + Default>(&self, digest: &Digest) -> Result<M, Status> {
+        let blob = self.get_or_not_found(digest)?;
+        M::decode(blob.as_slice()).map_err(|e| {
+            Status::invalid_argument(format!(
+                "Error decoding blob `{}`: {}",
+                display_digest(digest),
+                e
+            ))
+        })
+    }
+
+    /// Get all the directories in the tree rooted at `root`, parents first.
+    fn get_tree(&self, root: &Digest) -> Result<Vec<Directory>, Status> {
+        let mut directories = Vec::new();
+        let mut queue = vec![root.clone()];
+        while let Some(digest) = queue.pop() {
+            let directory: Directory = self.get_message(&digest)?;
+            queue.extend(
+                directory
+                    .directories
+                    .iter()
+                    .filter_map(|d| d.digest.clone()),
+            );
+            directories.push(directory);
+        }
+        Ok(directories)
+    }
+}
+
+/// Parse the digest out of a `ByteStream` resource name, which is
+/// `{instance_name}/blobs/{hash}/{size}` for reads and
+/// `{instance_name}/uploads/{uuid}/blobs/{hash}/{size}` for writes.
+fn parse_resource_name(resource_name: &str) -> Result<Digest, Status> {
+    let segments: Vec<&str> = resource_name.split('/').collect();
+    segments
+        .iter()
+        .position(|s| *s == "blobs")
+        .and_then(|i| match &segments[i + 1..] {
+            [hash, size, ..] => Some(Digest {
+                hash: (*hash).to_owned(),
+                size_bytes: size.parse().ok()?,
+            }),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Status::invalid_argument(format!("Invalid resource name: `{}`", resource_name))
+        })
+}
+
+/// Serves the `ContentAddressableStorage` and `ByteStream` services.
+#[derive(Clone, Dupe)]
+pub(crate) struct CasService {
+    pub(crate) cas: Arc<Cas>,
+}
+
+#[tonic::async_trait]
+impl ContentAddressableStorage for CasService {
+    type GetTreeStream = BoxStream<'static, Result<GetTreeResponse, Status>>;
+
+    async fn find_missing_blobs(
+        &self,
+        request: Request<FindMissingBlobsRequest>,
+    ) -> Result<Response<FindMissingBlobsResponse>, Status> {
+        let missing_blob_digests = request
+            .into_inner()
+            .blob_digests
+            .into_iter()
+            .filter(|digest| self.cas.get(digest).is_none())
+            .collect();
+        Ok(Response::new(FindMissingBlobsResponse {
+            missing_blob_digests,
+        }))
+    }
+
+    async fn batch_update_blobs(
+        &self,
+        request: Request<BatchUpdateBlobsRequest>,
+    ) -> Result<Response<BatchUpdateBlobsResponse>, Status> {
+        let responses = request
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(|request| {
+                let digest = request.digest.unwrap_or_default();
+                let status = match self.cas.put_with_digest(&digest, request.data) {
+                    Ok(()) => RpcStatus::default(),
+                    Err(e) => rpc_status(&e),
+                };
+                batch_update_blobs_response::Response {
+                    digest: Some(digest),
+                    status: Some(status),
+                }
+            })
+            .collect();
+        Ok(Response::new(BatchUpdateBlobsResponse { responses }))
+    }
+
+    async fn batch_read_blobs(
+        &self,
+        request: Request<BatchReadBlobsRequest>,
+    ) -> Result<Response<BatchReadBlobsResponse>, Status> {
+        let responses = request
+            .into_inner()
+            .digests
+            .into_iter()
+            .map(|digest| {
+                let (data, status) = match self.cas.get_or_not_found(&digest) {
+                    Ok(blob) => (blob.to_vec(), RpcStatus::default()),
+                    Err(e) => (Vec::new(), rpc_status(&e)),
+                };
+                batch_read_blobs_response::Response {
+                    digest: Some(digest),
+                    data,
+                    status: Some(status),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        Ok(Response::new(BatchReadBlobsResponse { responses }))
+    }
+
+    async fn get_tree(
+        &self,
+        request: Request<GetTreeRequest>,
+    ) -> Result<Response<Self::GetTreeStream>, Status> {
+        let root = request
+            .into_inner()
+            .root_digest
+            .ok_or_else(|| Status::invalid_argument("Missing `root_digest`"))?;
+        let directories = self.cas.get_tree(&root)?;
+        Ok(Response::new(
+            futures::stream::once(futures::future::ready(Ok(GetTreeResponse {
+                directories,
+                next_page_token: String::new(),
+            })))
+            .boxed(),
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl ByteStream for CasService {
+    type ReadStream = BoxStream<'static, Result<ReadResponse, Status>>;
+
+    async fn read(
+        &self,
+        request: Request<ReadRequest>,
+    ) -> Result<Response<Self::ReadStream>, Status> {
+        let request = request.into_inner();
+        let digest = parse_resource_name(&request.resource_name)?;
+        let blob = self.cas.get_or_not_found(&digest)?;
+
+        let start = usize::try_from(request.read_offset)
+            .ok()
+            .filter(|start| *start <= blob.len())
+            .ok_or_else(|| Status::out_of_range("Invalid `read_offset`"))?;
+        let end = match usize::try_from(request.read_limit) {
+            Ok(0) => blob.len(),
+            Ok(limit) => blob.len().min(start + limit),
+            Err(_) => return Err(Status::out_of_range("Invalid `read_limit`")),
+        };
+
+        let chunks: Vec<_> = blob[start..end]
+            .chunks(READ_CHUNK_SIZE)
+            .map(|chunk| {
+                Ok(ReadResponse {
+                    data: chunk.to_vec(),
+                })
+            })
+            .collect();
+        Ok(Response::new(futures::stream::iter(chunks).boxed()))
+    }
+
+    async fn write(
+        &self,
+        request: Request<Streaming<WriteRequest>>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let mut requests = request.into_inner();
+        let mut resource_name = None;
+        let mut data = Vec::new();
+
+        while let Some(request) = requests.message().await? {
+            // Only the first request needs to have the resource name.
+            if resource_name.is_none() {
+                resource_name = Some(request.resource_name);
+            }
+            if request.write_offset != data.len() as i64 {
+                return Err(Status::invalid_argument(format!(
+                    "Expected `write_offset` {}, got {}",
+                    data.len(),
+                    request.write_offset
+                )));
+            }
+            data.extend_from_slice(&request.data);
+            if request.finish_write {
+                break;
+            }
+        }
+
+        let resource_name = resource_name.ok_or_else(|| Status::invalid_argument("Empty write"))?;
+        let digest = parse_resource_name(&resource_name)?;
+        let committed_size = data.len() as i64;
+        self.cas.put_with_digest(&digest, data)?;
+        Ok(Response::new(WriteResponse { committed_size }))
+    }
+
+    async fn query_write_status(
+        &self,
+        _request: Request<QueryWriteStatusRequest>,
+    ) -> Result<Response<QueryWriteStatusResponse>, Status> {
+        Err(Status::unimplemented(
+            "Writes cannot be resumed, they are only committed once complete",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_request;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_resource_name() {
+        let digest = |hash: &str, size_bytes| Digest {
+            hash: hash.to_owned(),
+            size_bytes,
+        };
+        assert_eq!(
+            digest("abc", 3),
+            parse_resource_name("blobs/abc/3").unwrap()
+        );
+        assert_eq!(
+            digest("abc", 3),
+            parse_resource_name("instance/uploads/1234/blobs/abc/3").unwrap()
+        );
+        assert_eq!(
+            digest("abc", 3),
+            parse_resource_name("instance/uploads/1234/blobs/abc/3/metadata").unwrap()
+        );
+        assert!(parse_resource_name("instance/blobs/abc").is_err());
+        assert!(parse_resource_name("instance/blobs/abc/size").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cas() {
+        let service = CasService {
+            cas: Arc::new(Cas::new(DigestAlgorithm::Sha256)),
+        };
+        let stored = DigestAlgorithm::Sha256.digest(b"stored");
+        let wrong = DigestAlgorithm::Sha256.digest(b"wrong");
+
+        let responses = service
+            .batch_update_blobs(Request::new(BatchUpdateBlobsRequest {
+                requests: vec![
+                    batch_update_blobs_request::Request {
+                        digest: Some(stored.clone()),
+                        data: b"stored".to_vec(),
+                        ..Default::default()
+                    },
+                    batch_update_blobs_request::Request {
+                        digest: Some(wrong.clone()),
+                        data: b"not wrong".to_vec(),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .responses;
+        assert_eq!(
+            vec![tonic::Code::Ok as i32, tonic::Code::InvalidArgument as i32],
+            responses
+                .iter()
+                .map(|r| r.status.as_ref().unwrap().code)
+                .collect::<Vec<_>>()
+        );
+
+        let missing = service
+            .find_missing_blobs(Request::new(FindMissingBlobsRequest {
+                blob_digests: vec![stored.clone(), wrong.clone()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .missing_blob_digests;
+        assert_eq!(vec![wrong.clone()], missing);
+
+        let responses = service
+            .batch_read_blobs(Request::new(BatchReadBlobsRequest {
+                digests: vec![stored, wrong],
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .responses;
+        assert_eq!(b"stored".to_vec(), responses[0].data);
+        assert_eq!(
+            tonic::Code::NotFound as i32,
+            responses[1].status.as_ref().unwrap().code
+        );
+    }
+}
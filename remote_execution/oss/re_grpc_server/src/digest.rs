@@ -0,0 +1,99 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use re_grpc_proto::build::bazel::remote::execution::v2::digest_function;
+use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
+use sha1::Digest as _;
+use sha1::Sha1;
+use sha2::Sha256;
+
+/// The hash function used to address blobs. It must match buck2's `digest_algorithms`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    pub(crate) fn digest(self, data: &[u8]) -> Digest {
+        let hash = match self {
+            DigestAlgorithm::Sha1 => hex::encode(Sha1::digest(data)),
+            DigestAlgorithm::Sha256 => hex::encode(Sha256::digest(data)),
+        };
+        Digest {
+            hash,
+            size_bytes: data.len() as i64,
+        }
+    }
+
+    pub(crate) fn digest_function(self) -> digest_function::Value {
+        match self {
+            DigestAlgorithm::Sha1 => digest_function::Value::Sha1,
+            DigestAlgorithm::Sha256 => digest_function::Value::Sha256,
+        }
+    }
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "SHA1" => Ok(DigestAlgorithm::Sha1),
+            "SHA256" => Ok(DigestAlgorithm::Sha256),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported digest algorithm `{}`, expected `SHA1` or `SHA256`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestAlgorithm::Sha1 => write!(f, "SHA1"),
+            DigestAlgorithm::Sha256 => write!(f, "SHA256"),
+        }
+    }
+}
+
+/// Display a digest the same way buck2 does.
+pub(crate) fn display_digest(digest: &Digest) -> String {
+    format!("{}:{}", digest.hash, digest.size_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709:0",
+            display_digest(&DigestAlgorithm::Sha1.digest(b""))
+        );
+        assert_eq!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824:5",
+            display_digest(&DigestAlgorithm::Sha256.digest(b"hello"))
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            DigestAlgorithm::Sha256,
+            "SHA256".parse::<DigestAlgorithm>().unwrap()
+        );
+        assert!("BLAKE3".parse::<DigestAlgorithm>().is_err());
+    }
+}
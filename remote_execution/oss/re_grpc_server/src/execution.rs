@@ -0,0 +1,628 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs;
+use std::io;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use dupe::Dupe;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use prost::Message;
+use re_grpc_proto::build::bazel::remote::execution::v2::execution_server::Execution;
+use re_grpc_proto::build::bazel::remote::execution::v2::execution_stage;
+use re_grpc_proto::build::bazel::remote::execution::v2::Action;
+use re_grpc_proto::build::bazel::remote::execution::v2::ActionResult;
+use re_grpc_proto::build::bazel::remote::execution::v2::Command;
+use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
+use re_grpc_proto::build::bazel::remote::execution::v2::Directory;
+use re_grpc_proto::build::bazel::remote::execution::v2::DirectoryNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecuteOperationMetadata;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecuteRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecuteResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecutedActionMetadata;
+use re_grpc_proto::build::bazel::remote::execution::v2::FileNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputDirectory;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputFile;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputSymlink;
+use re_grpc_proto::build::bazel::remote::execution::v2::SymlinkNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::Tree;
+use re_grpc_proto::build::bazel::remote::execution::v2::WaitExecutionRequest;
+use re_grpc_proto::google::longrunning::operation;
+use re_grpc_proto::google::longrunning::Operation;
+use tokio::sync::Semaphore;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+use crate::action_cache::ActionCache;
+use crate::cas::rpc_status;
+use crate::cas::Cas;
+use crate::digest::display_digest;
+
+/// Reported as the worker in the metadata of executed actions.
+const WORKER_NAME: &str = "local-re-server";
+
+fn to_any<M: Message>(message: &M, type_name: &str) -> prost_types::Any {
+    prost_types::Any {
+        type_url: format!("type.googleapis.com/{}", type_name),
+        value: message.encode_to_vec(),
+    }
+}
+
+fn io_error(e: io::Error, path: &Path) -> Status {
+    Status::internal(format!("Error accessing `{}`: {}", path.display(), e))
+}
+
+fn required<'a, T>(field: &'a Option<T>, name: &str) -> Result<&'a T, Status> {
+    field
+        .as_ref()
+        .ok_or_else(|| Status::invalid_argument(format!("Missing `{}`", name)))
+}
+
+/// Inputs missing from the CAS are reported as a failed precondition, as the spec requires.
+fn missing_input(status: Status) -> Status {
+    if status.code() == tonic::Code::NotFound {
+        Status::failed_precondition(status.message())
+    } else {
+        status
+    }
+}
+
+/// Check that a name in a `Directory` is a single path component.
+fn check_name(name: &str) -> Result<&str, Status> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        Err(Status::invalid_argument(format!(
+            "Invalid name in input root: `{}`",
+            name
+        )))
+    } else {
+        Ok(name)
+    }
+}
+
+/// Check that a path from a `Command` stays within the input root.
+fn relative_path(path: &str) -> Result<&Path, Status> {
+    let path = Path::new(path);
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(Status::invalid_argument(format!(
+            "Invalid path in command: `{}`",
+            path.display()
+        )))
+    }
+}
+
+/// The outputs of a command. They are declared in `output_paths`, or in `output_files` and
+/// `output_directories` by clients which predate REv2.1.
+fn output_paths(command: &Command) -> Vec<&String> {
+    if command.output_paths.is_empty() {
+        command
+            .output_files
+            .iter()
+            .chain(&command.output_directories)
+            .collect()
+    } else {
+        command.output_paths.iter().collect()
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)
+}
+
+fn read_link(path: &Path) -> Result<String, Status> {
+    fs::read_link(path)
+        .map_err(|e| io_error(e, path))?
+        .into_os_string()
+        .into_string()
+        .map_err(|target| {
+            Status::internal(format!(
+                "Non UTF-8 symlink target: `{}`",
+                Path::new(&target).display()
+            ))
+        })
+}
+
+/// Write the tree rooted at `digest` to `dir`.
+fn materialize(cas: &Cas, digest: &Digest, dir: &Path) -> Result<(), Status> {
+    let directory: Directory = cas.get_message(digest).map_err(missing_input)?;
+
+    for file in &directory.files {
+        let path = dir.join(check_name(&file.name)?);
+        let blob = cas
+            .get_or_not_found(required(&file.digest, "digest")?)
+            .map_err(missing_input)?;
+        fs::write(&path, blob.as_slice()).map_err(|e| io_error(e, &path))?;
+        if file.is_executable {
+            set_executable(&path).map_err(|e| io_error(e, &path))?;
+        }
+    }
+
+    for symlink in &directory.symlinks {
+        let path = dir.join(check_name(&symlink.name)?);
+        create_symlink(&symlink.target, &path).map_err(|e| io_error(e, &path))?;
+    }
+
+    for child in &directory.directories {
+        let path = dir.join(check_name(&child.name)?);
+        fs::create_dir(&path).map_err(|e| io_error(e, &path))?;
+        materialize(cas, required(&child.digest, "digest")?, &path)?;
+    }
+
+    Ok(())
+}
+
+/// Create the working directory, and the parent directories of the outputs, which the command
+/// can expect to exist.
+fn create_output_parents(working_dir: &Path, command: &Command) -> Result<(), Status> {
+    fs::create_dir_all(working_dir).map_err(|e| io_error(e, working_dir))?;
+    for output in output_paths(command) {
+        if let Some(parent) = working_dir.join(relative_path(output)?).parent() {
+            fs::create_dir_all(parent).map_err(|e| io_error(e, parent))?;
+        }
+    }
+    Ok(())
+}
+
+fn upload_file(cas: &Cas, path: &Path) -> Result<Digest, Status> {
+    Ok(cas.put(fs::read(path).map_err(|e| io_error(e, path))?))
+}
+
+/// Store the contents of `dir` in the CAS and return it as a `Directory`, adding the directories
+/// it contains to `children`.
+fn upload_directory(
+    cas: &Cas,
+    dir: &Path,
+    children: &mut Vec<Directory>,
+) -> Result<Directory, Status> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(|e| io_error(e, dir))?;
+    // The entries of a `Directory` must be sorted by name.
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut directory = Directory::default();
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().into_string().map_err(|name| {
+            Status::internal(format!(
+                "Non UTF-8 output path: `{}`",
+                Path::new(&name).display()
+            ))
+        })?;
+        let metadata = fs::symlink_metadata(&path).map_err(|e| io_error(e, &path))?;
+        if metadata.is_symlink() {
+            directory.symlinks.push(SymlinkNode {
+                name,
+                target: read_link(&path)?,
+                ..Default::default()
+            });
+        } else if metadata.is_dir() {
+            let child = upload_directory(cas, &path, children)?;
+            let digest = cas.put(child.encode_to_vec());
+            children.push(child);
+            directory.directories.push(DirectoryNode {
+                name,
+                digest: Some(digest),
+            });
+        } else {
+            directory.files.push(FileNode {
+                name,
+                digest: Some(upload_file(cas, &path)?),
+                is_executable: is_executable(&metadata),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(directory)
+}
+
+/// Store the outputs the command produced in the CAS, and record them in `result`. Outputs which
+/// were not produced are skipped, it is up to the client to decide whether that is an error.
+fn upload_outputs(
+    cas: &Cas,
+    working_dir: &Path,
+    command: &Command,
+    result: &mut ActionResult,
+) -> Result<(), Status> {
+    for output in output_paths(command) {
+        let path = working_dir.join(relative_path(output)?);
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(io_error(e, &path)),
+        };
+        if metadata.is_symlink() {
+            result.output_symlinks.push(OutputSymlink {
+                path: output.clone(),
+                target: read_link(&path)?,
+                ..Default::default()
+            });
+        } else if metadata.is_dir() {
+            let mut children = Vec::new();
+            let root = upload_directory(cas, &path, &mut children)?;
+            let tree = Tree {
+                root: Some(root),
+                children,
+            };
+            result.output_directories.push(OutputDirectory {
+                path: output.clone(),
+                tree_digest: Some(cas.put(tree.encode_to_vec())),
+                ..Default::default()
+            });
+        } else {
+            result.output_files.push(OutputFile {
+                path: output.clone(),
+                digest: Some(upload_file(cas, &path)?),
+                is_executable: is_executable(&metadata),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Run blocking filesystem operations off the async runtime.
+async fn blocking<R: Send + 'static>(
+    f: impl FnOnce() -> Result<R, Status> + Send + 'static,
+) -> Result<R, Status> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+}
+
+/// Serves the `Execution` service, running actions on the local machine.
+#[derive(Clone)]
+pub(crate) struct ExecutionService {
+    pub(crate) cas: Arc<Cas>,
+    pub(crate) action_cache: Arc<ActionCache>,
+    /// Bounds the number of actions running at once.
+    pub(crate) workers: Arc<Semaphore>,
+    /// Where the input roots of actions are materialized.
+    pub(crate) work_dir: PathBuf,
+}
+
+impl ExecutionService {
+    /// Return the cached result of an action, or run it. Errors are reported in the response's
+    /// status, as the spec requires.
+    async fn execute_action(
+        &self,
+        action_digest: &Digest,
+        skip_cache_lookup: bool,
+    ) -> ExecuteResponse {
+        if !skip_cache_lookup {
+            if let Some(result) = self.action_cache.get(action_digest) {
+                return ExecuteResponse {
+                    result: Some(result),
+                    cached_result: true,
+                    ..Default::default()
+                };
+            }
+        }
+
+        match self.run_action(action_digest).await {
+            Ok(result) => ExecuteResponse {
+                result: Some(result),
+                ..Default::default()
+            },
+            Err(status) => {
+                tracing::debug!(
+                    "Error executing action `{}`: {}",
+                    display_digest(action_digest),
+                    status
+                );
+                ExecuteResponse {
+                    status: Some(rpc_status(&status)),
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    async fn run_action(&self, action_digest: &Digest) -> Result<ActionResult, Status> {
+        let action: Action = self.cas.get_message(action_digest).map_err(missing_input)?;
+        let command: Command = self
+            .cas
+            .get_message(required(&action.command_digest, "command_digest")?)
+            .map_err(missing_input)?;
+        let input_root_digest = required(&action.input_root_digest, "input_root_digest")?.clone();
+        let (program, args) = command
+            .arguments
+            .split_first()
+            .ok_or_else(|| Status::invalid_argument("Empty command"))?;
+        let timeout = action
+            .timeout
+            .clone()
+            .and_then(|timeout| Duration::try_from(timeout).ok())
+            .filter(|timeout| !timeout.is_zero());
+
+        let _permit = self
+            .workers
+            .acquire()
+            .await
+            .map_err(|_| Status::unavailable("The server is shutting down"))?;
+
+        let worker_start = SystemTime::now();
+        let exec_dir = tempfile::Builder::new()
+            .prefix("action-")
+            .tempdir_in(&self.work_dir)
+            .map_err(|e| io_error(e, &self.work_dir))?;
+        let working_dir = exec_dir
+            .path()
+            .join(relative_path(&command.working_directory)?);
+
+        {
+            let cas = self.cas.dupe();
+            let input_root = exec_dir.path().to_owned();
+            let working_dir = working_dir.clone();
+            let command = command.clone();
+            blocking(move || {
+                materialize(&cas, &input_root_digest, &input_root)?;
+                create_output_parents(&working_dir, &command)
+            })
+            .await?;
+        }
+
+        let execution_start = SystemTime::now();
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.args(args)
+            .env_clear()
+            .envs(
+                command
+                    .environment_variables
+                    .iter()
+                    .map(|var| (&var.name, &var.value)),
+            )
+            .current_dir(&working_dir)
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        let output = cmd.output();
+        let output = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, output).await.map_err(|_| {
+                Status::deadline_exceeded(format!(
+                    "Command timed out after {:.1}s",
+                    timeout.as_secs_f64()
+                ))
+            })?,
+            None => output.await,
+        }
+        .map_err(|e| Status::invalid_argument(format!("Error spawning `{}`: {}", program, e)))?;
+        let execution_completed = SystemTime::now();
+
+        let mut result = ActionResult {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout_digest: Some(self.cas.put(output.stdout)),
+            stderr_digest: Some(self.cas.put(output.stderr)),
+            ..Default::default()
+        };
+        let cas = self.cas.dupe();
+        let mut result = blocking(move || {
+            upload_outputs(&cas, &working_dir, &command, &mut result)?;
+            // Deleting the directory is blocking too.
+            drop(exec_dir);
+            Ok(result)
+        })
+        .await?;
+        let completed = SystemTime::now();
+
+        result.execution_metadata = Some(ExecutedActionMetadata {
+            worker: WORKER_NAME.to_owned(),
+            worker_start_timestamp: Some(worker_start.into()),
+            input_fetch_start_timestamp: Some(worker_start.into()),
+            input_fetch_completed_timestamp: Some(execution_start.into()),
+            execution_start_timestamp: Some(execution_start.into()),
+            execution_completed_timestamp: Some(execution_completed.into()),
+            output_upload_start_timestamp: Some(execution_completed.into()),
+            output_upload_completed_timestamp: Some(completed.into()),
+            worker_completed_timestamp: Some(completed.into()),
+            ..Default::default()
+        });
+
+        if result.exit_code == 0 && !action.do_not_cache {
+            self.action_cache.insert(action_digest, result.clone());
+        }
+        Ok(result)
+    }
+}
+
+#[tonic::async_trait]
+impl Execution for ExecutionService {
+    type ExecuteStream = BoxStream<'static, Result<Operation, Status>>;
+    type WaitExecutionStream = BoxStream<'static, Result<Operation, Status>>;
+
+    async fn execute(
+        &self,
+        request: Request<ExecuteRequest>,
+    ) -> Result<Response<Self::ExecuteStream>, Status> {
+        let request = request.into_inner();
+        let action_digest = request
+            .action_digest
+            .ok_or_else(|| Status::invalid_argument("Missing `action_digest`"))?;
+        let name = format!("operations/{}", uuid::Uuid::new_v4());
+
+        let executing = Operation {
+            name: name.clone(),
+            metadata: Some(to_any(
+                &ExecuteOperationMetadata {
+                    stage: execution_stage::Value::Executing as i32,
+                    action_digest: Some(action_digest.clone()),
+                    ..Default::default()
+                },
+                "build.bazel.remote.execution.v2.ExecuteOperationMetadata",
+            )),
+            done: false,
+            result: None,
+        };
+
+        // The action runs when the client polls for the second message, and is killed if the
+        // client goes away.
+        let this = self.clone();
+        let done = async move {
+            let response = this
+                .execute_action(&action_digest, request.skip_cache_lookup)
+                .await;
+            Ok(Operation {
+                name,
+                metadata: None,
+                done: true,
+                result: Some(operation::Result::Response(to_any(
+                    &response,
+                    "build.bazel.remote.execution.v2.ExecuteResponse",
+                ))),
+            })
+        };
+
+        Ok(Response::new(
+            futures::stream::once(futures::future::ready(Ok(executing)))
+                .chain(futures::stream::once(done))
+                .boxed(),
+        ))
+    }
+
+    async fn wait_execution(
+        &self,
+        _request: Request<WaitExecutionRequest>,
+    ) -> Result<Response<Self::WaitExecutionStream>, Status> {
+        Err(Status::unimplemented(
+            "Operations are only streamed back by `Execute`",
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use re_grpc_proto::build::bazel::remote::execution::v2::command;
+
+    use super::*;
+    use crate::digest::DigestAlgorithm;
+
+    #[tokio::test]
+    async fn test_execute_action() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let cas = Arc::new(Cas::new(DigestAlgorithm::Sha256));
+        let service = ExecutionService {
+            cas: cas.dupe(),
+            action_cache: Arc::new(ActionCache::default()),
+            workers: Arc::new(Semaphore::new(1)),
+            work_dir: work_dir.path().to_owned(),
+        };
+
+        let input_root = Directory {
+            files: vec![FileNode {
+                name: "in.txt".to_owned(),
+                digest: Some(cas.put(b"hello".to_vec())),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let command = Command {
+            arguments: vec![
+                "sh".to_owned(),
+                "-c".to_owned(),
+                "mkdir -p out/d && cp in.txt out/d/f && echo done".to_owned(),
+            ],
+            environment_variables: vec![command::EnvironmentVariable {
+                name: "PATH".to_owned(),
+                value: "/bin:/usr/bin".to_owned(),
+            }],
+            output_paths: vec!["out".to_owned(), "missing/file".to_owned()],
+            ..Default::default()
+        };
+        let action = Action {
+            command_digest: Some(cas.put(command.encode_to_vec())),
+            input_root_digest: Some(cas.put(input_root.encode_to_vec())),
+            ..Default::default()
+        };
+        let action_digest = cas.put(action.encode_to_vec());
+
+        let response = service.execute_action(&action_digest, false).await;
+        assert_eq!(None, response.status);
+        assert!(!response.cached_result);
+        let result = response.result.unwrap();
+        assert_eq!(0, result.exit_code);
+        assert_eq!(
+            b"done\n".as_slice(),
+            cas.get(result.stdout_digest.as_ref().unwrap())
+                .unwrap()
+                .as_slice()
+        );
+
+        assert_eq!(1, result.output_directories.len());
+        assert_eq!("out", result.output_directories[0].path);
+        let tree: Tree = cas
+            .get_message(result.output_directories[0].tree_digest.as_ref().unwrap())
+            .unwrap();
+        assert_eq!("d", tree.root.unwrap().directories[0].name);
+        let file = &tree.children[0].files[0];
+        assert_eq!("f", file.name);
+        assert_eq!(
+            b"hello".as_slice(),
+            cas.get(file.digest.as_ref().unwrap()).unwrap().as_slice()
+        );
+
+        let response = service.execute_action(&action_digest, false).await;
+        assert!(response.cached_result);
+    }
+
+    #[tokio::test]
+    async fn test_missing_input() {
+        let work_dir = tempfile::tempdir().unwrap();
+        let cas = Arc::new(Cas::new(DigestAlgorithm::Sha256));
+        let service = ExecutionService {
+            cas: cas.dupe(),
+            action_cache: Arc::new(ActionCache::default()),
+            workers: Arc::new(Semaphore::new(1)),
+            work_dir: work_dir.path().to_owned(),
+        };
+
+        let response = service
+            .execute_action(&DigestAlgorithm::Sha256.digest(b"missing"), false)
+            .await;
+        assert_eq!(
+            tonic::Code::FailedPrecondition as i32,
+            response.status.unwrap().code
+        );
+    }
+}
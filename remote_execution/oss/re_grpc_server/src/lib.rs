@@ -0,0 +1,176 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A remote execution server which runs on the local machine, to test buck2's remote execution
+//! code paths without a remote execution service.
+//!
+//! It implements the parts of the Remote Execution API which buck2 uses, with an in-memory CAS and
+//! action cache, and runs actions on a pool of local workers, in temporary directories.
+
+// Errors are `tonic::Status` throughout, since that is what the services return.
+#![allow(clippy::result_large_err)]
+
+mod action_cache;
+mod cas;
+mod digest;
+mod execution;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use dupe::Dupe;
+use re_grpc_proto::build::bazel::remote::execution::v2::action_cache_server::ActionCacheServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::capabilities_server::Capabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::capabilities_server::CapabilitiesServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::content_addressable_storage_server::ContentAddressableStorageServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::execution_server::ExecutionServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::symlink_absolute_path_strategy;
+use re_grpc_proto::build::bazel::remote::execution::v2::ActionCacheUpdateCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::CacheCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecutionCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetCapabilitiesRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::ServerCapabilities;
+use re_grpc_proto::build::bazel::semver::SemVer;
+use re_grpc_proto::google::bytestream::byte_stream_server::ByteStreamServer;
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+use crate::action_cache::ActionCache;
+use crate::action_cache::ActionCacheService;
+use crate::cas::Cas;
+use crate::cas::CasService;
+pub use crate::digest::DigestAlgorithm;
+use crate::execution::ExecutionService;
+
+/// The maximum total size of the blobs in a batch request, which clients use to decide whether to
+/// use the `ByteStream` service instead.
+const MAX_BATCH_TOTAL_SIZE_BYTES: i64 = 4 * 1024 * 1024;
+
+/// The maximum size of a message the server accepts. This is more than
+/// `MAX_BATCH_TOTAL_SIZE_BYTES` to leave room for the rest of the message, and because clients
+/// also write chunks of up to that size to the `ByteStream` service.
+const MAX_DECODING_MESSAGE_SIZE: usize = 2 * MAX_BATCH_TOTAL_SIZE_BYTES as usize;
+
+pub struct LocalReServerOptions {
+    /// The address to listen on. Use port 0 to pick a free port.
+    pub addr: SocketAddr,
+    /// The number of actions which may run at once.
+    pub workers: usize,
+    /// The directory in which actions run.
+    pub work_dir: PathBuf,
+    pub digest_algorithm: DigestAlgorithm,
+}
+
+struct CapabilitiesService {
+    digest_algorithm: DigestAlgorithm,
+}
+
+#[tonic::async_trait]
+impl Capabilities for CapabilitiesService {
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<ServerCapabilities>, Status> {
+        let digest_function = self.digest_algorithm.digest_function() as i32;
+        Ok(Response::new(ServerCapabilities {
+            cache_capabilities: Some(CacheCapabilities {
+                digest_functions: vec![digest_function],
+                action_cache_update_capabilities: Some(ActionCacheUpdateCapabilities {
+                    update_enabled: true,
+                }),
+                max_batch_total_size_bytes: MAX_BATCH_TOTAL_SIZE_BYTES,
+                symlink_absolute_path_strategy: symlink_absolute_path_strategy::Value::Allowed
+                    as i32,
+                ..Default::default()
+            }),
+            execution_capabilities: Some(ExecutionCapabilities {
+                digest_function,
+                exec_enabled: true,
+                ..Default::default()
+            }),
+            low_api_version: Some(SemVer {
+                major: 2,
+                ..Default::default()
+            }),
+            high_api_version: Some(SemVer {
+                major: 2,
+                minor: 3,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+}
+
+pub struct LocalReServer {
+    listener: TcpListener,
+    options: LocalReServerOptions,
+}
+
+impl LocalReServer {
+    /// Bind the server to its address, without serving requests yet.
+    pub async fn bind(options: LocalReServerOptions) -> anyhow::Result<Self> {
+        if options.workers == 0 {
+            return Err(anyhow::anyhow!("At least one worker is required"));
+        }
+        std::fs::create_dir_all(&options.work_dir).with_context(|| {
+            format!(
+                "Error creating work directory `{}`",
+                options.work_dir.display()
+            )
+        })?;
+        let listener = TcpListener::bind(options.addr)
+            .await
+            .with_context(|| format!("Error binding to `{}`", options.addr))?;
+        Ok(Self { listener, options })
+    }
+
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until the process exits. Everything the server stores is lost then.
+    pub async fn serve(self) -> anyhow::Result<()> {
+        let cas = Arc::new(Cas::new(self.options.digest_algorithm));
+        let action_cache = Arc::new(ActionCache::default());
+        let cas_service = CasService { cas: cas.dupe() };
+
+        Server::builder()
+            .add_service(CapabilitiesServer::new(CapabilitiesService {
+                digest_algorithm: self.options.digest_algorithm,
+            }))
+            .add_service(
+                ContentAddressableStorageServer::new(cas_service.dupe())
+                    .max_decoding_message_size(MAX_DECODING_MESSAGE_SIZE),
+            )
+            .add_service(
+                ByteStreamServer::new(cas_service)
+                    .max_decoding_message_size(MAX_DECODING_MESSAGE_SIZE),
+            )
+            .add_service(ActionCacheServer::new(ActionCacheService {
+                action_cache: action_cache.dupe(),
+            }))
+            .add_service(ExecutionServer::new(ExecutionService {
+                cas,
+                action_cache,
+                workers: Arc::new(Semaphore::new(self.options.workers)),
+                work_dir: self.options.work_dir,
+            }))
+            .serve_with_incoming(TcpListenerStream::new(self.listener))
+            .await
+            .context("Error serving remote execution requests")
+    }
+}
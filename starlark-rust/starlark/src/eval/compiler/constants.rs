@@ -50,6 +50,7 @@ pub(crate) struct Constants {
     pub(crate) fn_set: BuiltinFn,
     // Technically, this is not a function.
     pub(crate) typing_callable: BuiltinFn,
+    pub(crate) typing_type_var: BuiltinFn,
 }
 
 impl Constants {
@@ -72,6 +73,14 @@ impl Constants {
                         .unwrap();
                     BuiltinFn(typing.as_ref().get("Callable").unwrap())
                 },
+                typing_type_var: {
+                    let typing = g
+                        .get_frozen("typing")
+                        .unwrap()
+                        .downcast_frozen_ref::<FrozenNamespace>()
+                        .unwrap();
+                    BuiltinFn(typing.as_ref().get("TypeVar").unwrap())
+                },
             }
         });
         Lazy::force(&RES)
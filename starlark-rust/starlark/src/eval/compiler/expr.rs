@@ -62,6 +62,7 @@ use crate::eval::runtime::slots::LocalSlotId;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
 use crate::values::bool::StarlarkBool;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::function::BoundMethodGen;
 use crate::values::function::FrozenBoundMethod;
 use crate::values::list::ListRef;
@@ -1052,6 +1053,7 @@ impl AstLiteralCompile for AstLiteral {
             AstLiteral::Int(i) => heap.alloc(StarlarkInt::from(i.node.clone())),
             AstLiteral::Float(f) => heap.alloc(f.node),
             AstLiteral::String(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::Bytes(x) => heap.alloc(StarlarkBytes::new(x.node.as_slice())),
             AstLiteral::Ellipsis => heap.alloc(Ellipsis),
         }
     }
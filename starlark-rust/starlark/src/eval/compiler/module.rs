@@ -201,14 +201,18 @@ impl<'v> Compiler<'v, '_, '_, '_> {
     }
 
     fn mk_module_var_types(&self) -> ModuleVarTypes {
-        let types = self
-            .eval
-            .module_env
-            .values_by_slot_id()
-            .into_iter()
-            .map(|(module_slot_id, value)| (module_slot_id, Ty::of_value(value)))
+        let values = self.eval.module_env.values_by_slot_id();
+        let types = values
+            .iter()
+            .map(|(module_slot_id, value)| (*module_slot_id, Ty::of_value(*value)))
             .collect();
-        ModuleVarTypes { types }
+        let eval_types = values
+            .iter()
+            .filter_map(|(module_slot_id, value)| {
+                Some((*module_slot_id, value.get_ref().eval_type()?))
+            })
+            .collect();
+        ModuleVarTypes { types, eval_types }
     }
 
     pub(crate) fn eval_module(
@@ -80,7 +80,9 @@ impl<'v> Compiler<'v, '_, '_, '_> {
             );
         };
         let type_value = TypeCompiled::from_ty(ty, self.eval.heap());
-        if type_value.is_runtime_wildcard() {
+        // Type variables are kept even when they match anything at runtime,
+        // so the function type has them for the typechecker.
+        if type_value.is_runtime_wildcard() && !ty.has_type_vars() {
             return None;
         }
         let type_value = type_value.to_frozen(self.eval.frozen_heap());
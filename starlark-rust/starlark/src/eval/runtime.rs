@@ -17,6 +17,7 @@
 
 pub(crate) mod arguments;
 pub(crate) mod before_stmt;
+pub(crate) mod budget;
 pub(crate) mod cheap_call_stack;
 pub(crate) mod evaluator;
 pub(crate) mod file_loader;
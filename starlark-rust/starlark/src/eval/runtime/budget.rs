@@ -0,0 +1,127 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Limits on the resources an evaluation can use.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use starlark_syntax::ErrorKind;
+use thiserror::Error;
+
+/// Budgets are checked every this many instructions, so the heap size and the deadline are
+/// checked at that granularity. The instruction count is exact.
+const CHECK_INTERVAL: u64 = 1000;
+
+#[derive(Debug, Error)]
+enum EvalBudgetError {
+    #[error("Starlark evaluation exceeded the budget of {0} bytecode instructions")]
+    Instructions(u64),
+    #[error("Starlark evaluation exceeded the budget of {max} heap bytes (allocated {allocated})")]
+    HeapBytes { max: usize, allocated: usize },
+    #[error("Starlark evaluation exceeded its time budget of {0:.3?}")]
+    Deadline(Duration),
+}
+
+/// The budgets set on an evaluator, and how much of them was used.
+///
+/// Once a budget is set, the evaluator runs bytecode with `EvalCallbacksEnabled`, which
+/// decrements `countdown` before each instruction and calls `check` when it reaches zero.
+pub(crate) struct EvalBudget {
+    max_instructions: Option<u64>,
+    max_heap_bytes: Option<usize>,
+    /// The deadline, and the time budget it was set from.
+    deadline: Option<(Instant, Duration)>,
+    /// Instructions executed before the current window.
+    instructions: u64,
+    /// Length of the current window of instructions between checks.
+    window: u64,
+    pub(crate) countdown: u64,
+}
+
+impl EvalBudget {
+    pub(crate) fn new() -> EvalBudget {
+        EvalBudget {
+            max_instructions: None,
+            max_heap_bytes: None,
+            deadline: None,
+            instructions: 0,
+            window: u64::MAX,
+            countdown: u64::MAX,
+        }
+    }
+
+    pub(crate) fn set_max_instructions(&mut self, max: u64) {
+        self.max_instructions = Some(max);
+        self.restart_window();
+    }
+
+    pub(crate) fn set_max_heap_bytes(&mut self, max: usize) {
+        self.max_heap_bytes = Some(max);
+        self.restart_window();
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some((Instant::now() + timeout, timeout));
+        self.restart_window();
+    }
+
+    pub(crate) fn instructions_executed(&self) -> u64 {
+        self.instructions + (self.window - self.countdown)
+    }
+
+    /// Start a new window at the current instruction.
+    fn restart_window(&mut self) {
+        self.instructions = self.instructions_executed();
+        self.window = match self.max_instructions {
+            Some(max) => CHECK_INTERVAL.min(max.saturating_sub(self.instructions)),
+            None => CHECK_INTERVAL,
+        };
+        self.countdown = self.window;
+    }
+
+    /// Called when `countdown` reaches zero, before executing the next instruction.
+    #[cold]
+    #[inline(never)]
+    pub(crate) fn check(&mut self, allocated_bytes: usize) -> crate::Result<()> {
+        self.instructions += self.window;
+        self.window = 0;
+        self.countdown = 0;
+        let error = if self
+            .max_instructions
+            .is_some_and(|max| self.instructions >= max)
+        {
+            EvalBudgetError::Instructions(self.instructions)
+        } else if let Some(max) = self.max_heap_bytes.filter(|max| allocated_bytes > *max) {
+            EvalBudgetError::HeapBytes {
+                max,
+                allocated: allocated_bytes,
+            }
+        } else if let Some((_, timeout)) = self
+            .deadline
+            .filter(|(deadline, _)| Instant::now() >= *deadline)
+        {
+            EvalBudgetError::Deadline(timeout)
+        } else {
+            self.restart_window();
+            return Ok(());
+        };
+        Err(crate::Error::new_kind(ErrorKind::BudgetExceeded(
+            error.into(),
+        )))
+    }
+}
@@ -19,6 +19,7 @@ use std::collections::HashSet;
 use std::mem;
 use std::mem::MaybeUninit;
 use std::path::Path;
+use std::time::Duration;
 
 use dupe::Dupe;
 use starlark_syntax::eval_exception::EvalException;
@@ -48,6 +49,7 @@ use crate::eval::compiler::def::DefInfo;
 use crate::eval::compiler::def::FrozenDef;
 use crate::eval::runtime::before_stmt::BeforeStmt;
 use crate::eval::runtime::before_stmt::BeforeStmtFunc;
+use crate::eval::runtime::budget::EvalBudget;
 use crate::eval::runtime::cheap_call_stack::CheapCallStack;
 use crate::eval::runtime::frame_span::FrameSpan;
 use crate::eval::runtime::inlined_frame::InlinedFrames;
@@ -162,6 +164,8 @@ pub struct Evaluator<'v, 'a, 'e> {
     pub(crate) soft_error_handler: &'a (dyn SoftErrorHandler + 'a),
     /// Max size of starlark stack
     pub(crate) max_callstack_size: Option<usize>,
+    /// Limits on instructions, heap size and time, checked by the bytecode interpreter.
+    pub(crate) budget: EvalBudget,
     // The Starlark-level call-stack of functions.
     // Must go last because it's quite a big structure
     pub(crate) call_stack: CheapCallStack<'v>,
@@ -174,6 +178,8 @@ struct EvaluationInstrumentation<'a, 'e: 'a> {
     // Extra functions to run on each statement, usually empty
     before_stmt: BeforeStmt<'a, 'e>,
     heap_or_flame_profile: bool,
+    // Whether a budget is set, which is checked before each instruction.
+    budget: bool,
     // Whether we need to instrument evaluation or not, should be set if before_stmt or bc_profile are enabled.
     enabled: bool,
 }
@@ -184,6 +190,7 @@ impl<'a, 'e: 'a> EvaluationInstrumentation<'a, 'e> {
             bc_profile: BcProfile::new(),
             before_stmt: BeforeStmt::default(),
             heap_or_flame_profile: false,
+            budget: false,
             enabled: false,
         }
     }
@@ -192,10 +199,16 @@ impl<'a, 'e: 'a> EvaluationInstrumentation<'a, 'e> {
         self.heap_or_flame_profile = true;
     }
 
+    fn enable_budget(&mut self) {
+        self.budget = true;
+    }
+
     fn change<F: FnOnce(&mut EvaluationInstrumentation<'a, 'e>) -> R, R>(&mut self, f: F) -> R {
         let r = f(self);
-        self.enabled =
-            self.bc_profile.enabled() || self.before_stmt.enabled() || self.heap_or_flame_profile;
+        self.enabled = self.bc_profile.enabled()
+            || self.before_stmt.enabled()
+            || self.heap_or_flame_profile
+            || self.budget;
         r
     }
 }
@@ -235,6 +248,7 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
             verbose_gc: false,
             static_typechecking: false,
             max_callstack_size: None,
+            budget: EvalBudget::new(),
         }
     }
 
@@ -794,10 +808,23 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         bc: &Bc,
     ) -> Result<Value<'v>, EvalException> {
         debug_assert!(self.eval_instrumentation.enabled);
+        let budget = self.eval_instrumentation.budget;
         if self.eval_instrumentation.heap_or_flame_profile {
             self.heap_profile.record_call_enter(def, self.heap());
             self.time_flame_profile.record_call_enter(def);
-            let res = bc.run(self, &mut EvalCallbacksDisabled);
+            let res = if budget {
+                bc.run(
+                    self,
+                    &mut EvalCallbacksEnabled {
+                        mode: None,
+                        budget,
+                        stmt_locs: &bc.instrs.stmt_locs,
+                        bc_start_ptr: bc.instrs.start_ptr(),
+                    },
+                )
+            } else {
+                bc.run(self, &mut EvalCallbacksDisabled)
+            };
             self.heap_profile.record_call_exit(self.heap());
             self.time_flame_profile.record_call_exit();
             res
@@ -809,19 +836,21 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
                         self.eval_instrumentation.before_stmt.enabled(),
                         self.eval_instrumentation.bc_profile.enabled(),
                     ) {
-                        (true, false) => EvalCallbacksMode::BeforeStmt,
-                        (false, true) => EvalCallbacksMode::BcProfile,
+                        (true, false) => Some(EvalCallbacksMode::BeforeStmt),
+                        (false, true) => Some(EvalCallbacksMode::BcProfile),
                         (true, true) => {
                             return Err(EvalException::new_unknown_span(internal_error!(
                                 "both before_stmt and bc_profile are enabled"
                             )));
                         }
+                        (false, false) if budget => None,
                         (false, false) => {
                             return Err(EvalException::new_unknown_span(internal_error!(
-                                "neither before_stmt nor bc_profile are enabled"
+                                "neither before_stmt, bc_profile nor a budget are enabled"
                             )));
                         }
                     },
+                    budget,
                     stmt_locs: &bc.instrs.stmt_locs,
                     bc_start_ptr: bc.instrs.start_ptr(),
                 },
@@ -850,6 +879,40 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.max_callstack_size = Some(stack_size);
         Ok(())
     }
+
+    /// Fail the evaluation, with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded),
+    /// once it has executed this many bytecode instructions.
+    pub fn set_max_instructions(&mut self, max: u64) {
+        self.budget.set_max_instructions(max);
+        self.eval_instrumentation.change(|v| v.enable_budget());
+    }
+
+    /// Fail the evaluation, with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded),
+    /// once the heap grows over this many bytes.
+    ///
+    /// The heap size is checked periodically between instructions, so a single operation can
+    /// allocate past the limit.
+    pub fn set_max_heap_bytes(&mut self, max: usize) {
+        self.budget.set_max_heap_bytes(max);
+        self.eval_instrumentation.change(|v| v.enable_budget());
+    }
+
+    /// Fail the evaluation, with [`ErrorKind::BudgetExceeded`](crate::ErrorKind::BudgetExceeded),
+    /// once it has run for longer than `timeout` from now.
+    ///
+    /// The time is checked periodically between instructions, so time spent in a single native
+    /// function call is not interrupted.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.budget.set_timeout(timeout);
+        self.eval_instrumentation.change(|v| v.enable_budget());
+    }
+
+    /// Number of bytecode instructions this evaluator has executed since a budget was set.
+    ///
+    /// Instructions are not counted when no budget is set, to keep evaluation fast.
+    pub fn instructions_executed(&self) -> u64 {
+        self.budget.instructions_executed()
+    }
 }
 
 pub(crate) trait EvaluationCallbacks {
@@ -881,7 +944,9 @@ pub(crate) enum EvalCallbacksMode {
 }
 
 pub(crate) struct EvalCallbacksEnabled<'a> {
-    pub(crate) mode: EvalCallbacksMode,
+    pub(crate) mode: Option<EvalCallbacksMode>,
+    /// Check the evaluator's budget before each instruction.
+    pub(crate) budget: bool,
     pub(crate) stmt_locs: &'a BcStatementLocations,
     pub(crate) bc_start_ptr: BcPtrAddr<'a>,
 }
@@ -904,12 +969,20 @@ impl<'a> EvaluationCallbacks for EvalCallbacksEnabled<'a> {
         ip: BcPtrAddr,
         opcode: BcOpcode,
     ) -> crate::Result<()> {
+        if self.budget {
+            if eval.budget.countdown == 0 {
+                let allocated_bytes = eval.heap().allocated_bytes();
+                eval.budget.check(allocated_bytes)?;
+            }
+            eval.budget.countdown -= 1;
+        }
         match self.mode {
-            EvalCallbacksMode::BcProfile => {
+            Some(EvalCallbacksMode::BcProfile) => {
                 eval.eval_instrumentation.bc_profile.before_instr(opcode);
                 Ok(())
             }
-            EvalCallbacksMode::BeforeStmt => self.before_stmt(eval, ip),
+            Some(EvalCallbacksMode::BeforeStmt) => self.before_stmt(eval, ip),
+            None => Ok(()),
         }
     }
 }
@@ -34,7 +34,9 @@ pub(crate) mod partial;
 pub use extra::PrintHandler;
 
 use crate::stdlib::funcs::globals::register_globals;
+use crate::stdlib::funcs::other::register_bytes_hash;
 use crate::stdlib::internal::register_internal;
+use crate::values::bytes::globals::register_bytes;
 use crate::values::enumeration::globals::register_enum;
 use crate::values::record::globals::register_record;
 use crate::values::structs::structs::register_struct;
@@ -98,6 +100,10 @@ pub enum LibraryExtension {
     CallStack,
     /// Definitions to support the `set` type, the `set()` constructor.
     SetType,
+    /// Definitions to support the `bytes` type, the `bytes()` constructor.
+    /// Usually used in conjunction with
+    /// [`Dialect::enable_bytes`](crate::syntax::Dialect::enable_bytes).
+    BytesType,
     // Make sure if you add anything new, you add it to `all` below.
 }
 
@@ -124,6 +130,7 @@ impl LibraryExtension {
             Internal,
             CallStack,
             SetType,
+            BytesType,
         ]
     }
 
@@ -136,6 +143,10 @@ impl LibraryExtension {
             RecordType => register_record(builder),
             EnumType => register_enum(builder),
             SetType => register_set(builder),
+            BytesType => {
+                register_bytes(builder);
+                register_bytes_hash(builder);
+            }
             Map => extra::map(builder),
             Filter => extra::filter(builder),
             Partial => partial::partial(builder),
@@ -20,11 +20,13 @@
 
 use std::cmp::Ordering;
 
+use either::Either;
 use starlark_derive::starlark_module;
 
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::list::AllocList;
 use crate::values::tuple::UnpackTuple;
 use crate::values::typing::never::StarlarkNever;
@@ -236,29 +238,7 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     /// ```
     #[starlark(speculative_exec_safe)]
     fn hash(#[starlark(require = pos)] a: &str) -> anyhow::Result<i32> {
-        // From the starlark spec:
-        // > the hash function for strings is the same as that implemented by java.lang.String.hashCode,
-        // > a simple polynomial accumulator over the UTF-16 transcoding of the string:
-        // > `s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1]`
-        // As per spec the function should only support string and bytes types.
-        // We don't have support for bytes, so parameter is forced to be a string.
-
-        // Most strings are ASCII strings, try them first.
-        #[allow(clippy::never_loop)]
-        'ascii: loop {
-            let mut hash = 0i32;
-            for &b in a.as_bytes() {
-                if b > 0x7f {
-                    break 'ascii;
-                }
-                hash = hash.wrapping_mul(31i32).wrapping_add(b as i32);
-            }
-            return Ok(hash);
-        }
-
-        Ok(a.encode_utf16().fold(0i32, |hash: i32, c: u16| {
-            31i32.wrapping_mul(hash).wrapping_add(c as i32)
-        }))
+        Ok(str_hash(a))
     }
 
     /// [len](
@@ -395,6 +375,59 @@ pub(crate) fn register_other(builder: &mut GlobalsBuilder) {
     }
 }
 
+/// Replaces `hash` with one which also accepts bytes, for `LibraryExtension::BytesType`.
+#[starlark_module]
+pub(crate) fn register_bytes_hash(builder: &mut GlobalsBuilder) {
+    /// [hash](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#hash
+    /// ): returns the hash number of a string or bytes.
+    ///
+    /// ASCII bytes hash like the corresponding string.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// hash(b"hello") == hash("hello")
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn hash(#[starlark(require = pos)] a: Either<&str, &StarlarkBytes>) -> anyhow::Result<i32> {
+        Ok(match a {
+            Either::Left(a) => str_hash(a),
+            // The accumulator is over the bytes themselves.
+            Either::Right(b) => b.as_bytes().iter().fold(0i32, |hash: i32, &b| {
+                31i32.wrapping_mul(hash).wrapping_add(b as i32)
+            }),
+        })
+    }
+}
+
+/// The hash of a string, as defined by the spec (and `java.lang.String.hashCode`).
+fn str_hash(a: &str) -> i32 {
+    // From the starlark spec:
+    // > the hash function for strings is the same as that implemented by java.lang.String.hashCode,
+    // > a simple polynomial accumulator over the UTF-16 transcoding of the string:
+    // > `s[0]*31^(n-1) + s[1]*31^(n-2) + ... + s[n-1]`
+    // As per spec the function should only support string and bytes types.
+    // Bytes are only accepted by the `hash` of `register_bytes_hash`.
+
+    // Most strings are ASCII strings, try them first.
+    #[allow(clippy::never_loop)]
+    'ascii: loop {
+        let mut hash = 0i32;
+        for &b in a.as_bytes() {
+            if b > 0x7f {
+                break 'ascii;
+            }
+            hash = hash.wrapping_mul(31i32).wrapping_add(b as i32);
+        }
+        return hash;
+    }
+
+    a.encode_utf16().fold(0i32, |hash: i32, c: u16| {
+        31i32.wrapping_mul(hash).wrapping_add(c as i32)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use crate::assert;
@@ -0,0 +1,110 @@
+/*
+ * Copyright 2018 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Duration;
+
+use crate::environment::Globals;
+use crate::environment::Module;
+use crate::eval::Evaluator;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+use crate::ErrorKind;
+
+fn eval_with_budget(program: &str, set_budget: impl FnOnce(&mut Evaluator)) -> crate::Result<()> {
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut evaluator = Evaluator::new(&module);
+    set_budget(&mut evaluator);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::AllOptionsInternal).unwrap();
+    evaluator.eval_module(ast, &globals).map(|_| ())
+}
+
+fn assert_budget_exceeded(r: crate::Result<()>, message: &str) {
+    let e = r.unwrap_err();
+    assert!(
+        matches!(e.kind(), ErrorKind::BudgetExceeded(_)),
+        "expected a budget error, got: {}",
+        e
+    );
+    assert!(e.to_string().contains(message), "{}", e);
+}
+
+const RUNAWAY: &str = "\
+def f():
+    x = []
+    for i in range(100000000):
+        x.append(str(i))
+f()
+";
+
+#[test]
+fn test_max_instructions() {
+    let program = "\
+def f(n):
+    return [i for i in range(n)]
+f(10)
+";
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut evaluator = Evaluator::new(&module);
+    // Instructions are only counted when there is a budget.
+    evaluator.set_max_instructions(u64::MAX);
+    let ast = AstModule::parse("a.star", program.to_owned(), &Dialect::AllOptionsInternal).unwrap();
+    evaluator.eval_module(ast, &globals).unwrap();
+    let executed = evaluator.instructions_executed();
+    assert!(executed > 10, "{}", executed);
+
+    // Exactly enough instructions is fine, one fewer is not.
+    eval_with_budget(program, |eval| eval.set_max_instructions(executed)).unwrap();
+    assert_budget_exceeded(
+        eval_with_budget(program, |eval| eval.set_max_instructions(executed - 1)),
+        "bytecode instructions",
+    );
+}
+
+#[test]
+fn test_max_instructions_has_call_stack() {
+    let e = eval_with_budget(RUNAWAY, |eval| eval.set_max_instructions(10000)).unwrap_err();
+    // The error points into `f`, and the call stack at its caller.
+    assert!(e.call_stack().to_string().contains("a.star:5"), "{}", e);
+    assert!(e.to_string().contains("a.star:4"), "{}", e);
+}
+
+#[test]
+fn test_max_heap_bytes() {
+    assert_budget_exceeded(
+        eval_with_budget(RUNAWAY, |eval| eval.set_max_heap_bytes(1 << 20)),
+        "heap bytes",
+    );
+}
+
+#[test]
+fn test_no_budget_counts_nothing() {
+    let module = Module::new();
+    let globals = Globals::standard();
+    let mut evaluator = Evaluator::new(&module);
+    let ast = AstModule::parse("a.star", "x = [1, 2, 3]".to_owned(), &Dialect::Standard).unwrap();
+    evaluator.eval_module(ast, &globals).unwrap();
+    assert_eq!(0, evaluator.instructions_executed());
+}
+
+#[test]
+fn test_timeout() {
+    assert_budget_exceeded(
+        eval_with_budget(RUNAWAY, |eval| eval.set_timeout(Duration::from_millis(10))),
+        "time budget",
+    );
+}
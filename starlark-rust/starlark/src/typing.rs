@@ -43,6 +43,7 @@ pub(crate) mod starlark_value;
 pub(crate) mod structs;
 pub(crate) mod tuple;
 pub(crate) mod ty;
+pub(crate) mod type_var;
 pub(crate) mod typecheck;
 pub(crate) mod user;
 
@@ -65,6 +66,7 @@ pub use structs::TyStruct;
 pub use ty::Approximation;
 pub use ty::Ty;
 pub use ty::TypeRenderConfig;
+pub use type_var::TyTypeVar;
 pub use typecheck::AstModuleTypecheck;
 pub use typecheck::TypeMap;
 pub use user::TyUser;
@@ -28,6 +28,7 @@ use crate::typing::custom::TyCustomImpl;
 use crate::typing::starlark_value::TyStarlarkValue;
 use crate::typing::tuple::TyTuple;
 use crate::typing::ty::TypeRenderConfig;
+use crate::typing::type_var::TyTypeVar;
 use crate::typing::Ty;
 use crate::typing::TyFunction;
 use crate::values::none::NoneType;
@@ -59,6 +60,8 @@ pub enum TyBasic {
     Custom(TyCustom),
     /// A set.
     Set(ArcTy),
+    /// A type variable, bound at call sites.
+    TypeVar(TyTypeVar),
 }
 
 impl TyBasic {
@@ -131,7 +134,7 @@ impl TyBasic {
             TyBasic::Dict(..) => Some("dict"),
             TyBasic::Type => Some("type"),
             TyBasic::Custom(c) => c.as_name(),
-            TyBasic::Any | TyBasic::Iter(_) | TyBasic::Callable(_) => None,
+            TyBasic::Any | TyBasic::Iter(_) | TyBasic::Callable(_) | TyBasic::TypeVar(_) => None,
             TyBasic::Set(_) => Some("set"),
         }
     }
@@ -193,6 +196,7 @@ impl TyBasic {
             TyBasic::Type => write!(f, "type"),
             TyBasic::Custom(c) => Display::fmt(c, f),
             TyBasic::Set(x) => write!(f, "set[{}]", x.display_with(config)),
+            TyBasic::TypeVar(v) => Display::fmt(v, f),
         }
     }
 }
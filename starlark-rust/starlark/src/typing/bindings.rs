@@ -19,9 +19,12 @@ use std::collections::HashMap;
 
 use dupe::Dupe;
 use starlark_map::small_map::SmallMap;
+use starlark_syntax::syntax::ast::ArgumentP;
 use starlark_syntax::syntax::ast::AssignOp;
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTargetP;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::BinOp;
 use starlark_syntax::syntax::ast::ClauseP;
 use starlark_syntax::syntax::ast::DefP;
 use starlark_syntax::syntax::ast::ExprP;
@@ -37,6 +40,7 @@ use starlark_syntax::syntax::uniplate::Visit;
 use crate::codemap::CodeMap;
 use crate::codemap::Span;
 use crate::codemap::Spanned;
+use crate::eval::compiler::scope::payload::CstAssignIdent;
 use crate::eval::compiler::scope::payload::CstAssignIdentExt;
 use crate::eval::compiler::scope::payload::CstAssignTarget;
 use crate::eval::compiler::scope::payload::CstExpr;
@@ -83,6 +87,43 @@ impl<'a> BindExpr<'a> {
     }
 }
 
+/// A type a variable is compared with in a condition.
+#[derive(Clone, Copy)]
+pub(crate) enum NarrowTy<'a> {
+    /// `x == None`.
+    None,
+    /// `type(x) == "string"`.
+    Name(&'a str),
+    /// `isinstance(x, t)`.
+    IsInstance(&'a CstExpr),
+}
+
+/// A condition on a variable which is known to hold or to not hold where the variable is used,
+/// so the type of the variable there can be narrowed.
+#[derive(Clone, Copy)]
+pub(crate) struct Narrow<'a> {
+    pub(crate) ty: NarrowTy<'a>,
+    pub(crate) holds: bool,
+}
+
+/// A narrowing which applies to the code being visited.
+struct ActiveNarrow<'a> {
+    binding: BindingId,
+    narrow: Narrow<'a>,
+    /// Cleared when the variable is assigned.
+    live: bool,
+}
+
+/// Whether `x` is the builtin `name`, like `None` or `isinstance`.
+fn is_global(x: &CstExpr, name: &str) -> bool {
+    match &**x {
+        ExprP::Identifier(x) => {
+            x.ident == name && matches!(x.payload, Some(ResolvedIdent::Global(_)))
+        }
+        _ => false,
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Bindings<'a> {
     pub(crate) expressions: SmallMap<BindingId, Vec<BindExpr<'a>>>,
@@ -98,11 +139,21 @@ pub(crate) struct Bindings<'a> {
     /// ```
     pub(crate) check: Vec<&'a CstExpr>,
     pub(crate) check_type: Vec<(Span, Option<&'a CstExpr>, Ty)>,
+    /// Narrowings of the types of variables at their uses, by the span of the identifier.
+    /// For example `x` in `return x` is not `None` in:
+    ///
+    /// ```python
+    /// if x == None:
+    ///     fail()
+    /// return x
+    /// ```
+    pub(crate) narrows: HashMap<Span, Vec<Narrow<'a>>>,
 }
 
 pub(crate) struct BindingsCollect<'a, 'b> {
     pub(crate) bindings: Bindings<'a>,
     pub(crate) approximations: &'b mut Vec<Approximation>,
+    narrows: Vec<ActiveNarrow<'a>>,
 }
 
 impl<'a, 'b> BindingsCollect<'a, 'b> {
@@ -118,6 +169,7 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
         let mut res = BindingsCollect {
             bindings: Bindings::default(),
             approximations,
+            narrows: Vec::new(),
         };
 
         res.visit(Visit::Stmt(x), &Ty::any(), typecheck_mode, codemap)?;
@@ -132,9 +184,11 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
     ) -> Result<(), InternalError> {
         match &**lhs {
             AssignTargetP::Identifier(x) => {
+                let binding_id = x.resolved_binding_id(codemap)?;
+                self.forget_narrows(binding_id);
                 self.bindings
                     .expressions
-                    .entry(x.resolved_binding_id(codemap)?)
+                    .entry(binding_id)
                     .or_default()
                     .push(rhs);
             }
@@ -175,6 +229,160 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
         Ok(())
     }
 
+    fn forget_narrows(&mut self, binding_id: BindingId) {
+        for n in &mut self.narrows {
+            if n.binding == binding_id {
+                n.live = false;
+            }
+        }
+    }
+
+    /// Forget the narrowings of the variables assigned in a loop,
+    /// because the loop body can run again after the assignment.
+    fn forget_narrows_assigned_in(
+        &mut self,
+        stmt: &CstStmt,
+        codemap: &CodeMap,
+    ) -> Result<(), InternalError> {
+        let mut assigned = Vec::new();
+        fn collect<'x>(stmt: &'x CstStmt, assigned: &mut Vec<&'x CstAssignIdent>) {
+            match &**stmt {
+                StmtP::Assign(AssignP { lhs, .. }) | StmtP::AssignModify(lhs, _, _) => {
+                    lhs.visit_lvalue(|x| assigned.push(x))
+                }
+                StmtP::For(ForP { var, .. }) => var.visit_lvalue(|x| assigned.push(x)),
+                _ => {}
+            }
+            stmt.visit_stmt(|x| collect(x, assigned));
+        }
+        collect(stmt, &mut assigned);
+        for x in assigned {
+            self.forget_narrows(x.resolved_binding_id(codemap)?);
+        }
+        Ok(())
+    }
+
+    fn push_narrow(&mut self, x: &CstExpr, ty: NarrowTy<'a>, holds: bool) {
+        if let ExprP::Identifier(Spanned {
+            node:
+                IdentP {
+                    payload: Some(ResolvedIdent::Slot(_, binding)),
+                    ..
+                },
+            ..
+        }) = &**x
+        {
+            self.narrows.push(ActiveNarrow {
+                binding: *binding,
+                narrow: Narrow { ty, holds },
+                live: true,
+            });
+        }
+    }
+
+    /// Narrow the variables compared in `cond`, knowing whether `cond` is true.
+    fn push_narrows(&mut self, cond: &'a CstExpr, holds: bool) {
+        /// `x` in `type(x)`.
+        fn type_arg(x: &CstExpr) -> Option<&CstExpr> {
+            match &**x {
+                ExprP::Call(f, args) if is_global(f, "type") => match &*args.args {
+                    [arg] if matches!(arg.node, ArgumentP::Positional(_)) => Some(arg.expr()),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+
+        match &**cond {
+            ExprP::Not(x) => self.push_narrows(x, !holds),
+            ExprP::Op(lhs, BinOp::And, rhs) if holds => {
+                self.push_narrows(lhs, true);
+                self.push_narrows(rhs, true);
+            }
+            ExprP::Op(lhs, BinOp::Or, rhs) if !holds => {
+                self.push_narrows(lhs, false);
+                self.push_narrows(rhs, false);
+            }
+            ExprP::Op(lhs, op @ (BinOp::Equal | BinOp::NotEqual), rhs) => {
+                let holds = holds == (*op == BinOp::Equal);
+                for (x, y) in [(lhs, rhs), (rhs, lhs)] {
+                    if is_global(y, "None") {
+                        self.push_narrow(x, NarrowTy::None, holds);
+                    } else if let (Some(x), ExprP::Literal(AstLiteral::String(name))) =
+                        (type_arg(x), &y.node)
+                    {
+                        self.push_narrow(x, NarrowTy::Name(name.node.as_str()), holds);
+                    }
+                }
+            }
+            ExprP::Call(f, args) if is_global(f, "isinstance") => {
+                if let [Spanned {
+                    node: ArgumentP::Positional(x),
+                    ..
+                }, Spanned {
+                    node: ArgumentP::Positional(ty),
+                    ..
+                }] = &*args.args
+                {
+                    self.push_narrow(x, NarrowTy::IsInstance(ty), holds);
+                }
+            }
+            // A true value is not `None`.
+            ExprP::Identifier(_) if holds => self.push_narrow(cond, NarrowTy::None, false),
+            _ => {}
+        }
+    }
+
+    /// Visit `x` with the variables in `cond` narrowed.
+    fn visit_narrowed(
+        &mut self,
+        cond: &'a CstExpr,
+        holds: bool,
+        x: Visit<'a, CstPayload>,
+        return_type: &Ty,
+        typecheck_mode: TypecheckMode,
+        codemap: &CodeMap,
+    ) -> Result<(), InternalError> {
+        let len = self.narrows.len();
+        self.push_narrows(cond, holds);
+        self.visit(x, return_type, typecheck_mode, codemap)?;
+        self.narrows.truncate(len);
+        Ok(())
+    }
+
+    fn visit_for_clause(
+        &mut self,
+        x: &'a ForClauseP<CstPayload>,
+        return_type: &Ty,
+        typecheck_mode: TypecheckMode,
+        codemap: &CodeMap,
+    ) -> Result<(), InternalError> {
+        let mut exprs = Vec::new();
+        x.visit_expr(|x| exprs.push(x));
+        for x in exprs {
+            self.visit(Visit::Expr(x), return_type, typecheck_mode, codemap)?;
+        }
+        self.assign(
+            &x.var,
+            BindExpr::Iter(Box::new(BindExpr::Expr(&x.over))),
+            codemap,
+        )
+    }
+
+    /// Whether the statement always returns, fails or jumps out of the loop,
+    /// so the statements after it are only executed if it is not.
+    fn terminates(x: &CstStmt) -> bool {
+        match &**x {
+            StmtP::Return(_) | StmtP::Break | StmtP::Continue => true,
+            StmtP::Expression(x) => matches!(&**x, ExprP::Call(f, _) if is_global(f, "fail")),
+            StmtP::Statements(xs) => xs.iter().any(Self::terminates),
+            StmtP::IfElse(_, then_else) => {
+                Self::terminates(&then_else.0) && Self::terminates(&then_else.1)
+            }
+            _ => false,
+        }
+    }
+
     /// Type must be populated earlier.
     fn resolved_ty(
         expr: &CstTypeExpr,
@@ -283,7 +491,10 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
             name.resolved_binding_id(codemap)?,
             Ty::function(params2, ret_ty.clone()),
         );
+        // Variables narrowed outside the function may be reassigned before it is called.
+        let narrows = std::mem::take(&mut self.narrows);
         def.visit_children_err(|x| self.visit(x, &ret_ty, typecheck_mode, codemap))?;
+        self.narrows = narrows;
         Ok(())
     }
 
@@ -316,6 +527,7 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
                     self.assign(lhs, BindExpr::AssignModify(lhs, *op, rhs), codemap)?
                 }
                 StmtP::For(ForP { var, over, body: _ }) => {
+                    self.forget_narrows_assigned_in(x, codemap)?;
                     self.assign(var, BindExpr::Iter(Box::new(BindExpr::Expr(over))), codemap)?
                 }
                 StmtP::Def(def) => {
@@ -360,28 +572,135 @@ impl<'a, 'b> BindingsCollect<'a, 'b> {
 
                     self.bindings.check.push(x)
                 }
-                StmtP::If(x, _) => self.bindings.check.push(x),
-                StmtP::IfElse(x, _) => self.bindings.check.push(x),
+                StmtP::Statements(xs) => {
+                    let len = self.narrows.len();
+                    for x in xs {
+                        self.visit(Visit::Stmt(x), return_type, typecheck_mode, codemap)?;
+                        // The statements after `if x == None: return` are only executed
+                        // if the condition is false.
+                        match &**x {
+                            StmtP::If(cond, then) if Self::terminates(then) => {
+                                self.push_narrows(cond, false)
+                            }
+                            StmtP::IfElse(cond, then_else) => {
+                                if Self::terminates(&then_else.0) {
+                                    self.push_narrows(cond, false);
+                                } else if Self::terminates(&then_else.1) {
+                                    self.push_narrows(cond, true);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    self.narrows.truncate(len);
+                    return Ok(());
+                }
+                StmtP::If(cond, then) => {
+                    self.bindings.check.push(cond);
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        cond,
+                        true,
+                        Visit::Stmt(then),
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                StmtP::IfElse(cond, then_else) => {
+                    self.bindings.check.push(cond);
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    for (holds, x) in [(true, &then_else.0), (false, &then_else.1)] {
+                        self.visit_narrowed(
+                            cond,
+                            holds,
+                            Visit::Stmt(x),
+                            return_type,
+                            typecheck_mode,
+                            codemap,
+                        )?;
+                    }
+                    return Ok(());
+                }
                 _ => {}
             },
             Visit::Expr(x) => match &**x {
+                ExprP::Identifier(ident) => {
+                    if let Some(ResolvedIdent::Slot(_, binding_id)) = &ident.payload {
+                        let narrows: Vec<Narrow> = self
+                            .narrows
+                            .iter()
+                            .filter(|n| n.live && n.binding == *binding_id)
+                            .map(|n| n.narrow)
+                            .collect();
+                        if !narrows.is_empty() {
+                            self.bindings.narrows.insert(ident.span, narrows);
+                        }
+                    }
+                }
+                ExprP::Op(lhs, op @ (BinOp::And | BinOp::Or), rhs) => {
+                    // `rhs` is only evaluated if `lhs` is true for `and`, and false for `or`.
+                    self.visit(Visit::Expr(lhs), return_type, typecheck_mode, codemap)?;
+                    self.visit_narrowed(
+                        lhs,
+                        *op == BinOp::And,
+                        Visit::Expr(rhs),
+                        return_type,
+                        typecheck_mode,
+                        codemap,
+                    )?;
+                    return Ok(());
+                }
+                ExprP::If(c_t_f) => {
+                    let (cond, then, els) = &**c_t_f;
+                    self.visit(Visit::Expr(cond), return_type, typecheck_mode, codemap)?;
+                    for (holds, x) in [(true, then), (false, els)] {
+                        self.visit_narrowed(
+                            cond,
+                            holds,
+                            Visit::Expr(x),
+                            return_type,
+                            typecheck_mode,
+                            codemap,
+                        )?;
+                    }
+                    return Ok(());
+                }
                 ExprP::ListComprehension(_, for1, clauses)
                 | ExprP::DictComprehension(_, for1, clauses) => {
-                    fn get_for_clause(x: &ClauseP<CstPayload>) -> Option<&ForClauseP<CstPayload>> {
-                        match x {
-                            ClauseP::For(x) => Some(x),
-                            _ => None,
+                    // Visit the clauses in the order they are evaluated,
+                    // so `if` clauses narrow the following clauses and the result.
+                    let len = self.narrows.len();
+                    self.visit_for_clause(for1, return_type, typecheck_mode, codemap)?;
+                    for clause in clauses {
+                        match clause {
+                            ClauseP::For(x) => {
+                                self.visit_for_clause(x, return_type, typecheck_mode, codemap)?
+                            }
+                            ClauseP::If(cond) => {
+                                self.visit(
+                                    Visit::Expr(cond),
+                                    return_type,
+                                    typecheck_mode,
+                                    codemap,
+                                )?;
+                                self.push_narrows(cond, true);
+                            }
                         }
                     }
-                    for x in
-                        std::iter::once(&**for1).chain(clauses.iter().filter_map(get_for_clause))
-                    {
-                        self.assign(
-                            &x.var,
-                            BindExpr::Iter(Box::new(BindExpr::Expr(&x.over))),
-                            codemap,
-                        )?
+                    match &**x {
+                        ExprP::ListComprehension(x, ..) => {
+                            self.visit(Visit::Expr(x), return_type, typecheck_mode, codemap)?
+                        }
+                        ExprP::DictComprehension(k_v, ..) => {
+                            self.visit(Visit::Expr(&k_v.0), return_type, typecheck_mode, codemap)?;
+                            self.visit(Visit::Expr(&k_v.1), return_type, typecheck_mode, codemap)?;
+                        }
+                        _ => {}
                     }
+                    self.narrows.truncate(len);
+                    return Ok(());
                 }
                 _ => {}
             },
@@ -336,6 +336,22 @@ impl ParamSpec {
         }
     }
 
+    /// The same parameters with types mapped by `f`.
+    pub(crate) fn map_tys(&self, f: impl Fn(&Ty) -> Ty) -> ParamSpec {
+        let params = self
+            .params
+            .iter()
+            .map(|p| Param {
+                mode: p.mode.dupe(),
+                ty: f(&p.ty),
+            })
+            .collect::<Vec<_>>();
+        ParamSpec {
+            params: SmallArcVec1OrStatic::clone_from_slice(&params),
+            indices: self.indices,
+        }
+    }
+
     /// Is `*args, **kwargs`.
     pub(crate) fn is_any(&self) -> bool {
         self == &Self::any()
@@ -16,8 +16,10 @@
  */
 
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::Debug;
 
+use dupe::Dupe;
 use starlark_map::unordered_map::UnorderedMap;
 use starlark_syntax::slice_vec_ext::SliceExt;
 use starlark_syntax::syntax::ast::AssignOp;
@@ -41,6 +43,8 @@ use crate::eval::compiler::scope::ResolvedIdent;
 use crate::eval::compiler::scope::Slot;
 use crate::typing::basic::TyBasic;
 use crate::typing::bindings::BindExpr;
+use crate::typing::bindings::Narrow;
+use crate::typing::bindings::NarrowTy;
 use crate::typing::call_args::TyCallArgs;
 use crate::typing::error::InternalError;
 use crate::typing::error::TypingError;
@@ -51,6 +55,7 @@ use crate::typing::oracle::traits::TypingBinOp;
 use crate::typing::oracle::traits::TypingUnOp;
 use crate::typing::ty::Approximation;
 use crate::typing::ty::Ty;
+use crate::values::bytes::StarlarkBytes;
 
 pub(crate) struct TypingContext<'a> {
     pub(crate) oracle: TypingOracleCtx<'a>,
@@ -60,6 +65,7 @@ pub(crate) struct TypingContext<'a> {
     pub(crate) approximoations: RefCell<Vec<Approximation>>,
     pub(crate) types: UnorderedMap<BindingId, Ty>,
     pub(crate) module_var_types: &'a ModuleVarTypes,
+    pub(crate) narrows: &'a HashMap<Span, Vec<Narrow<'a>>>,
 }
 
 impl TypingContext<'_> {
@@ -380,7 +386,75 @@ impl TypingContext<'_> {
         Ok(self.result_to_ty(self.oracle.expr_slice(span, self.expression_type(x)?)))
     }
 
-    fn expr_ident(&self, x: &CstIdent) -> Ty {
+    /// Type of the value of `isinstance` type argument, if it is a known type.
+    fn isinstance_ty(&self, x: &CstExpr) -> Option<Ty> {
+        match &**x {
+            ExprP::Identifier(x) => match &x.node.payload {
+                Some(ResolvedIdent::Global(g)) => g.to_value().get_ref().eval_type(),
+                Some(ResolvedIdent::Slot(Slot::Module(module_slot_id), _)) => self
+                    .module_var_types
+                    .eval_types
+                    .get(module_slot_id)
+                    .cloned(),
+                _ => None,
+            },
+            ExprP::Op(lhs, BinOp::BitOr, rhs) => Some(Ty::union2(
+                self.isinstance_ty(lhs)?,
+                self.isinstance_ty(rhs)?,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Keep the alternatives of `ty` which intersect `to` if `holds`, and the others otherwise.
+    fn narrow_to(&self, ty: &Ty, to: &Ty, holds: bool) -> Result<Ty, InternalError> {
+        let mut res = Vec::new();
+        for t in ty.iter_union() {
+            if let TyBasic::Any = t {
+                res.push(if holds { to.dupe() } else { Ty::any() });
+            } else if self.oracle.intersects(&Ty::basic(t.dupe()), to)? == holds {
+                res.push(Ty::basic(t.dupe()));
+            }
+        }
+        Ok(Ty::unions(res))
+    }
+
+    fn narrow(&self, ty: Ty, narrow: &Narrow) -> Result<Ty, InternalError> {
+        let narrowed = match narrow.ty {
+            NarrowTy::None => self.narrow_to(&ty, &Ty::none(), narrow.holds)?,
+            NarrowTy::Name(name) => Ty::unions(
+                ty.iter_union()
+                    .iter()
+                    .filter(|t| match t.as_name() {
+                        Some(t) => (t == name) == narrow.holds,
+                        None => true,
+                    })
+                    .map(|t| Ty::basic(t.dupe()))
+                    .collect(),
+            ),
+            NarrowTy::IsInstance(t) => match self.isinstance_ty(t) {
+                Some(t) => self.narrow_to(&ty, &t, narrow.holds)?,
+                None => ty.dupe(),
+            },
+        };
+        // A condition which cannot hold more likely means the type is imprecise
+        // than that the code is unreachable.
+        if narrowed.is_never() {
+            Ok(ty)
+        } else {
+            Ok(narrowed)
+        }
+    }
+
+    fn expr_ident(&self, x: &CstIdent) -> Result<Ty, InternalError> {
+        let mut ty = self.expr_ident_unnarrowed(x);
+        for narrow in self.narrows.get(&x.span).into_iter().flatten() {
+            ty = self.narrow(ty, narrow)?;
+        }
+        Ok(ty)
+    }
+
+    fn expr_ident_unnarrowed(&self, x: &CstIdent) -> Ty {
         match &x.node.payload {
             Some(ResolvedIdent::Slot(Slot::Module(module_slot_id), _)) => self
                 .module_var_types
@@ -428,7 +502,7 @@ impl TypingContext<'_> {
                 stop.as_deref(),
                 stride.as_deref(),
             ),
-            ExprP::Identifier(x) => Ok(self.expr_ident(x)),
+            ExprP::Identifier(x) => self.expr_ident(x),
             ExprP::Lambda(_) => {
                 self.approximation("We don't type check lambdas", ());
                 Ok(Ty::any_callable())
@@ -437,6 +511,7 @@ impl TypingContext<'_> {
                 AstLiteral::Int(_) => Ok(Ty::int()),
                 AstLiteral::Float(_) => Ok(Ty::float()),
                 AstLiteral::String(_) => Ok(Ty::string()),
+                AstLiteral::Bytes(_) => Ok(Ty::starlark_value::<StarlarkBytes>()),
                 AstLiteral::Ellipsis => Ok(Ty::any()),
             },
             ExprP::Not(x) => {
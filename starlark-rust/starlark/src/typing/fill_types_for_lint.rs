@@ -21,6 +21,7 @@ use std::iter;
 use starlark_map::unordered_map;
 use starlark_map::unordered_map::UnorderedMap;
 use starlark_syntax::slice_vec_ext::SliceExt;
+use starlark_syntax::syntax::ast::ArgumentP;
 use starlark_syntax::syntax::ast::AssignP;
 use starlark_syntax::syntax::ast::AssignTargetP;
 use starlark_syntax::syntax::ast::AstLiteral;
@@ -64,6 +65,7 @@ use crate::util::arc_str::ArcStr;
 use crate::values::tuple::AllocTuple;
 use crate::values::types::ellipsis::Ellipsis;
 use crate::values::typing::type_compiled::compiled::TypeCompiled;
+use crate::values::typing::type_var::TypingTypeVar;
 use crate::values::Heap;
 use crate::values::Value;
 
@@ -136,13 +138,62 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
 
     fn call(
         &mut self,
-        _f: &CstExpr,
-        _args: &CallArgsP<CstPayload>,
+        span: Span,
+        f: &CstExpr,
+        args: &CallArgsP<CstPayload>,
     ) -> Result<GlobalValue<'v>, InternalError> {
+        // Only `typing.TypeVar(...)` is evaluated. Other callees may be unresolved identifiers,
+        // which are reported by the scope resolver.
+        if let ExprP::Dot(object, field) = &f.node {
+            if let ExprP::Identifier(ident) = &object.node {
+                if ident.payload.is_some() && field.node == "TypeVar" {
+                    if let Some(f) = self.expr(f)?.value {
+                        if f.ptr_eq(Constants::get().typing_type_var.0.to_value()) {
+                            return self.type_var(span, args);
+                        }
+                    }
+                }
+            }
+        }
         // TODO(nga): could be a call like `record(...)`, and we need to evaluate it.
         Ok(GlobalValue::any())
     }
 
+    /// `typing.TypeVar("T", bound = ...)`, so it can be used in the following type expressions.
+    fn type_var(
+        &mut self,
+        span: Span,
+        args: &CallArgsP<CstPayload>,
+    ) -> Result<GlobalValue<'v>, InternalError> {
+        let mut name = None;
+        let mut bound = None;
+        for arg in &args.args {
+            match &arg.node {
+                ArgumentP::Positional(x) if name.is_none() => name = Some(self.expr(x)?),
+                ArgumentP::Named(n, x) if n.node == "bound" => bound = Some(self.expr(x)?),
+                _ => return Ok(GlobalValue::any()),
+            }
+        }
+        let Some(name) = name.and_then(|n| n.value).and_then(|n| n.unpack_str()) else {
+            return Ok(GlobalValue::any());
+        };
+        let bound = match bound {
+            None => None,
+            Some(GlobalValue {
+                value: Some(bound), ..
+            }) => Some(bound),
+            Some(GlobalValue { value: None, .. }) => return Ok(GlobalValue::any()),
+        };
+        match TypingTypeVar::new(name, bound, self.heap) {
+            Ok(type_var) => Ok(GlobalValue::value(self.heap.alloc_simple(type_var))),
+            Err(e) => {
+                self.errors
+                    .push(TypingError::new_anyhow(e, span, self.ctx.codemap));
+                Ok(GlobalValue::any())
+            }
+        }
+    }
+
     fn expr_ident(&self, ident: &CstIdent) -> Result<GlobalValue<'v>, InternalError> {
         let Some(resolved_ident) = &ident.payload else {
             return Err(self.internal_error(ident.span, "unresolved ident"));
@@ -258,7 +309,7 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
         match &expr.node {
             ExprP::Tuple(xs) => self.tuple(xs),
             ExprP::Dot(object, field) => self.dot(span, object, field),
-            ExprP::Call(f, args) => self.call(f, args),
+            ExprP::Call(f, args) => self.call(span, f, args),
             ExprP::Index(a_i) => {
                 let (a, i) = &**a_i;
                 self.index(span, a, i)
@@ -757,6 +808,9 @@ impl<'a, 'v> GlobalTypesBuilder<'a, 'v> {
 #[derive(Default)]
 pub(crate) struct ModuleVarTypes {
     pub(crate) types: UnorderedMap<ModuleSlotId, Ty>,
+    /// Types of the values of module-level variables which are types, like record types.
+    /// Used for `isinstance(x, t)` narrowing.
+    pub(crate) eval_types: UnorderedMap<ModuleSlotId, Ty>,
 }
 
 /// Populate `TypeExprP` type payload when running lint typechecker.
@@ -780,6 +834,10 @@ pub(crate) fn fill_types_for_lint_typechecker(
         builder.top_level_stmt(stmt)?;
     }
     let GlobalTypesBuilder { errors, values, .. } = builder;
+    let eval_types = values
+        .entries_unordered()
+        .filter_map(|(k, v)| Some((*k, v.value?.get_ref().eval_type()?)))
+        .collect();
     let types = values.map_values(|v| v.ty);
-    Ok((errors, ModuleVarTypes { types }))
+    Ok((errors, ModuleVarTypes { types, eval_types }))
 }
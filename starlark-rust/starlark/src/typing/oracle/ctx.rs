@@ -37,6 +37,7 @@ use crate::typing::error::TypingNoContextOrInternalError;
 use crate::typing::error::TypingOrInternalError;
 use crate::typing::starlark_value::TyStarlarkValue;
 use crate::typing::tuple::TyTuple;
+use crate::typing::type_var::TypeVarBindings;
 use crate::typing::ParamSpec;
 use crate::typing::Ty;
 use crate::typing::TypingBinOp;
@@ -142,24 +143,21 @@ impl<'a> TypingOracleCtx<'a> {
         }
     }
 
-    #[allow(clippy::redundant_pattern_matching)]
-    fn validate_args(
+    /// Figure out which arguments go to which parameters.
+    fn match_args<'t>(
         &self,
         params: &ParamSpec,
-        args: &TyCallArgs,
-        span: Span,
-    ) -> Result<(), TypingOrInternalError> {
-        // Want to figure out which arguments go in which positions
+        args: &'t TyCallArgs,
+    ) -> Result<Vec<Vec<Spanned<&'t Ty>>>, TypingOrInternalError> {
         let mut param_args: Vec<Vec<Spanned<&Ty>>> = vec![vec![]; params.params().len()];
         // The next index a positional parameter might fill
         let mut param_pos = 0;
-        let mut seen_vargs = false;
 
         let TyCallArgs {
             pos: args_pos,
             named: args_named,
-            args: args_args,
-            kwargs: args_kwargs,
+            args: _,
+            kwargs: _,
         } = args;
         for ty in args_pos {
             loop {
@@ -205,10 +203,22 @@ impl<'a> TypingOracleCtx<'a> {
                 ));
             }
         }
-        if let Some(_) = args_args {
+        Ok(param_args)
+    }
+
+    #[allow(clippy::redundant_pattern_matching)]
+    fn validate_args(
+        &self,
+        params: &ParamSpec,
+        args: &TyCallArgs,
+        span: Span,
+    ) -> Result<(), TypingOrInternalError> {
+        let param_args = self.match_args(params, args)?;
+        let mut seen_vargs = false;
+        if let Some(_) = &args.args {
             seen_vargs = true;
         }
-        if let Some(_) = args_kwargs {
+        if let Some(_) = &args.kwargs {
             seen_vargs = true;
         }
 
@@ -259,8 +269,23 @@ impl<'a> TypingOracleCtx<'a> {
         fun: &TyCallable,
         args: &TyCallArgs,
     ) -> Result<Ty, TypingOrInternalError> {
-        self.validate_args(fun.params(), args, span)?;
-        Ok(fun.result().dupe())
+        if !fun.has_type_vars() {
+            self.validate_args(fun.params(), args, span)?;
+            return Ok(fun.result().dupe());
+        }
+
+        // Bind the type variables to the argument types, and check the call
+        // as if the function was declared with the types they are bound to.
+        let mut bindings = TypeVarBindings::default();
+        for (param, args) in iter::zip(fun.params().params(), self.match_args(fun.params(), args)?)
+        {
+            for arg in args {
+                bindings.bind(&param.ty, arg.node, self)?;
+            }
+        }
+        let params = fun.params().map_tys(|ty| ty.subst_type_vars(&bindings));
+        self.validate_args(&params, args, span)?;
+        Ok(fun.result().subst_type_vars(&bindings))
     }
 
     #[allow(clippy::collapsible_else_if)]
@@ -286,6 +311,7 @@ impl<'a> TypingOracleCtx<'a> {
             }
             TyBasic::Callable(c) => c.validate_call(span, args, *self),
             TyBasic::Custom(t) => t.0.validate_call_dyn(span, args, *self),
+            TyBasic::TypeVar(v) => self.validate_call(span, v.bound(), args),
         }
     }
 
@@ -325,7 +351,7 @@ impl<'a> TypingOracleCtx<'a> {
         }
     }
 
-    fn iter_item_basic(&self, ty: &TyBasic) -> Result<Ty, TypingNoContextError> {
+    pub(crate) fn iter_item_basic(&self, ty: &TyBasic) -> Result<Ty, TypingNoContextError> {
         match ty {
             TyBasic::Any => Ok(Ty::any()),
             TyBasic::StarlarkValue(ty) => ty.iter_item(),
@@ -337,6 +363,9 @@ impl<'a> TypingOracleCtx<'a> {
             TyBasic::Iter(ty) => Ok(ty.to_ty()),
             TyBasic::Custom(ty) => ty.0.iter_item_dyn(),
             TyBasic::Set(item) => Ok((**item).dupe()),
+            TyBasic::TypeVar(v) => v
+                .bound()
+                .typecheck_union_simple(|basic| self.iter_item_basic(basic)),
         }
     }
 
@@ -386,6 +415,23 @@ impl<'a> TypingOracleCtx<'a> {
             }
             TyBasic::StarlarkValue(array) => Ok(array.index(index.node)?),
             TyBasic::Custom(c) => Ok(c.0.index_dyn(index.node, self)?),
+            TyBasic::TypeVar(v) => {
+                let mut good = Vec::new();
+                for bound in v.bound().iter_union() {
+                    match self.expr_index_ty(bound, index) {
+                        Ok(ty) => good.push(ty),
+                        Err(TypingNoContextOrInternalError::Internal(e)) => {
+                            return Err(TypingNoContextOrInternalError::Internal(e));
+                        }
+                        Err(TypingNoContextOrInternalError::Typing) => {}
+                    }
+                }
+                if good.is_empty() {
+                    Err(TypingNoContextOrInternalError::Typing)
+                } else {
+                    Ok(Ty::unions(good))
+                }
+            }
         }
     }
 
@@ -511,6 +557,9 @@ impl<'a> TypingOracleCtx<'a> {
             TyBasic::Custom(custom) => custom.0.attribute_dyn(attr),
             //TODO(romanp) add match on attr similar to Dict
             TyBasic::Set(_) => TyStarlarkValue::new::<MutableSet>().attr(attr),
+            TyBasic::TypeVar(v) => v
+                .bound()
+                .typecheck_union_simple(|basic| self.expr_dot_basic(basic, attr)),
         }
     }
 
@@ -537,6 +586,9 @@ impl<'a> TypingOracleCtx<'a> {
                 Ok(x) => Ok(Ty::basic(TyBasic::StarlarkValue(x))),
                 Err(TypingNoContextError) => Err(TypingNoContextError),
             },
+            TyBasic::TypeVar(v) => v
+                .bound()
+                .typecheck_union_simple(|basic| self.expr_un_op_basic(basic, un_op)),
             _ => Err(TypingNoContextError),
         }
     }
@@ -564,6 +616,8 @@ impl<'a> TypingOracleCtx<'a> {
     ) -> Result<Ty, TypingNoContextOrInternalError> {
         match lhs {
             TyBasic::Any | TyBasic::Iter(_) | TyBasic::Callable(_) | TyBasic::Type => Ok(Ty::any()),
+            // Replaced with the bound in `expr_bin_op_ty_basic`.
+            TyBasic::TypeVar(_) => Ok(Ty::any()),
             TyBasic::StarlarkValue(lhs) => Ok(lhs.bin_op(bin_op, rhs.node)?),
             lhs @ TyBasic::List(elem) => match bin_op {
                 TypingBinOp::Less => {
@@ -692,6 +746,14 @@ impl<'a> TypingOracleCtx<'a> {
         if let TyBasic::Any = lhs.node {
             return Ok(Ty::any());
         }
+        if let TyBasic::TypeVar(v) = lhs.node {
+            let rhs = rhs.map(|rhs| Ty::basic(rhs.dupe()));
+            return self.expr_bin_op_ty(span, lhs.map(|_| v.bound().dupe()), bin_op, rhs);
+        }
+        if let TyBasic::TypeVar(v) = rhs.node {
+            let lhs = lhs.map(|lhs| Ty::basic(lhs.dupe()));
+            return self.expr_bin_op_ty(span, lhs, bin_op, rhs.map(|_| v.bound().dupe()));
+        }
 
         if let Ok(r) = self.expr_bin_op_ty_basic_lhs(lhs.node, bin_op, rhs) {
             return Ok(r);
@@ -938,6 +1000,7 @@ impl<'a> TypingOracleCtx<'a> {
     fn intersects_one_side(&self, x: &TyBasic, y: &TyBasic) -> Result<bool, InternalError> {
         match (x, y) {
             (TyBasic::Any, _) => Ok(true),
+            (TyBasic::TypeVar(x), y) => self.intersects(x.bound(), &Ty::basic(y.dupe())),
             (TyBasic::List(x), TyBasic::List(y)) => self.intersects(x, y),
             (TyBasic::List(_), TyBasic::StarlarkValue(y)) => Ok(y.is_list()),
             (TyBasic::List(_), _) => Ok(false),
@@ -49,8 +49,10 @@ use crate::values::ValueOfUnchecked;
 mod call;
 mod callable;
 mod list;
+mod narrowing;
 mod special_function;
 mod tuple;
+mod type_var;
 mod types;
 
 #[derive(Default)]
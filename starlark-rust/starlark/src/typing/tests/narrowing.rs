@@ -0,0 +1,102 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::typing::tests::TypeCheck;
+
+#[test]
+fn test_narrowing_none() {
+    TypeCheck::new().ty("a").ty("b").ty("c").check(
+        "narrowing_none",
+        r#"
+def early_return(x: str | None) -> str:
+    if x == None:
+        return ""
+    return x.upper()
+
+def branches(x: str | None):
+    if x != None:
+        a = x
+    else:
+        b = x
+    if x:
+        c = x
+
+def wrong(x: str | None):
+    if x == None:
+        x.upper()
+"#,
+    );
+}
+
+#[test]
+fn test_narrowing_isinstance() {
+    TypeCheck::new().ty("s").ty("n").ty("l").check(
+        "narrowing_isinstance",
+        r#"
+def test(x: int | str | list[str]):
+    if isinstance(x, str):
+        s = x
+    elif isinstance(x, int):
+        n = x
+    else:
+        l = x
+"#,
+    );
+}
+
+#[test]
+fn test_narrowing_type_name() {
+    TypeCheck::new().ty("s").ty("other").check(
+        "narrowing_type_name",
+        r#"
+def test(x: int | str):
+    if type(x) != "string":
+        other = x
+        return
+    s = x
+"#,
+    );
+}
+
+#[test]
+fn test_narrowing_expressions() {
+    TypeCheck::new().ty("a").ty("b").ty("c").check(
+        "narrowing_expressions",
+        r#"
+def test(x: str | None, xs: list[str | None]):
+    a = x != None and x.upper()
+    b = x.upper() if x != None else ""
+    c = [y.upper() for y in xs if y != None]
+"#,
+    );
+}
+
+#[test]
+fn test_narrowing_reassigned() {
+    TypeCheck::new().ty("before").ty("inside").check(
+        "narrowing_reassigned",
+        r#"
+def test(x: str | None, xs: list[str]):
+    if x == None:
+        fail("x is required")
+    before = x
+    for s in xs:
+        inside = x
+        x = None
+"#,
+    );
+}
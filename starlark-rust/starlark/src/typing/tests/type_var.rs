@@ -0,0 +1,93 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::typing::tests::TypeCheck;
+
+#[test]
+fn test_type_var_return() {
+    TypeCheck::new().ty("x").ty("y").check(
+        "type_var_return",
+        r#"
+T = typing.TypeVar("T")
+
+def first(xs: list[T]) -> T:
+    return xs[0]
+
+def test():
+    x = first([1, 2])
+    y = first(["a"]).upper()
+    first([1]).upper()
+"#,
+    );
+}
+
+#[test]
+fn test_type_var_unify_params() {
+    TypeCheck::new().ty("x").ty("y").check(
+        "type_var_unify_params",
+        r#"
+T = typing.TypeVar("T")
+
+def pair(a: T, b: T) -> list[T]:
+    return [a, b]
+
+def get(d: dict[str, T], key: str, default: T | None) -> T | None:
+    return d.get(key, default)
+
+def test():
+    x = pair(1, "a")
+    y = get({"a": True}, "a", None)
+"#,
+    );
+}
+
+#[test]
+fn test_type_var_bound() {
+    TypeCheck::new().ty("r").check(
+        "type_var_bound",
+        r#"
+N = typing.TypeVar("N", bound = int | float)
+
+def double(x: N) -> N:
+    return x * 2
+
+def test():
+    r = double(1)
+    double("a")
+"#,
+    );
+}
+
+#[test]
+fn test_type_var_callable() {
+    TypeCheck::new().ty("r").check(
+        "type_var_callable",
+        r#"
+T = typing.TypeVar("T")
+U = typing.TypeVar("U")
+
+def map_list(f: typing.Callable[[T], U], xs: list[T]) -> list[U]:
+    return [f(x) for x in xs]
+
+def to_str(x: int) -> str:
+    return str(x)
+
+def test():
+    r = map_list(to_str, [1, 2])
+"#,
+    );
+}
@@ -0,0 +1,265 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Type variables, like `T` in `def first(xs: list[T]) -> T`.
+//!
+//! Type variables are bound at call sites: the typechecker unifies the parameter types
+//! with the argument types, and substitutes the result into the return type.
+//! Everywhere else (in the function body, and at runtime), a type variable
+//! behaves like its bound, which is `typing.Any` unless specified.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use starlark_map::small_map::SmallMap;
+
+use crate::typing::arc_ty::ArcTy;
+use crate::typing::callable::TyCallable;
+use crate::typing::error::InternalError;
+use crate::typing::tuple::TyTuple;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TypingOracleCtx;
+use crate::util::arc_str::ArcStr;
+
+/// `typing.TypeVar`.
+#[derive(Eq, PartialEq, Hash, Clone, Dupe, Debug, Ord, PartialOrd, Allocative)]
+pub struct TyTypeVar {
+    name: ArcStr,
+    bound: ArcTy,
+}
+
+impl TyTypeVar {
+    /// Create a type variable which can be bound to subtypes of `bound`.
+    pub fn new(name: ArcStr, bound: Ty) -> TyTypeVar {
+        TyTypeVar {
+            name,
+            bound: ArcTy::new(bound),
+        }
+    }
+
+    /// Name of the type variable.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The type this variable behaves like when it is not bound.
+    pub fn bound(&self) -> &Ty {
+        &self.bound
+    }
+}
+
+impl Display for TyTypeVar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Ty {
+    /// Whether this type mentions any type variables.
+    pub(crate) fn has_type_vars(&self) -> bool {
+        self.iter_union().iter().any(|t| match t {
+            TyBasic::TypeVar(_) => true,
+            TyBasic::List(x) | TyBasic::Iter(x) | TyBasic::Set(x) => x.has_type_vars(),
+            TyBasic::Dict(k, v) => k.has_type_vars() || v.has_type_vars(),
+            TyBasic::Tuple(TyTuple::Elems(xs)) => xs.iter().any(|x| x.has_type_vars()),
+            TyBasic::Tuple(TyTuple::Of(x)) => x.has_type_vars(),
+            TyBasic::Callable(c) => c.has_type_vars(),
+            TyBasic::Any | TyBasic::StarlarkValue(_) | TyBasic::Type | TyBasic::Custom(_) => false,
+        })
+    }
+
+    /// Replace type variables with their bindings, or their bounds if they are not bound.
+    pub(crate) fn subst_type_vars(&self, bindings: &TypeVarBindings) -> Ty {
+        if !self.has_type_vars() {
+            return self.dupe();
+        }
+        Ty::unions(
+            self.iter_union()
+                .iter()
+                .map(|t| match t {
+                    TyBasic::TypeVar(v) => bindings.get(v),
+                    TyBasic::List(x) => Ty::list(x.subst_type_vars(bindings)),
+                    TyBasic::Iter(x) => Ty::iter(x.subst_type_vars(bindings)),
+                    TyBasic::Set(x) => Ty::set(x.subst_type_vars(bindings)),
+                    TyBasic::Dict(k, v) => {
+                        Ty::dict(k.subst_type_vars(bindings), v.subst_type_vars(bindings))
+                    }
+                    TyBasic::Tuple(TyTuple::Elems(xs)) => {
+                        Ty::tuple(xs.iter().map(|x| x.subst_type_vars(bindings)).collect())
+                    }
+                    TyBasic::Tuple(TyTuple::Of(x)) => Ty::tuple_of(x.subst_type_vars(bindings)),
+                    TyBasic::Callable(c) => Ty::basic(TyBasic::Callable(TyCallable::new(
+                        c.params().map_tys(|t| t.subst_type_vars(bindings)),
+                        c.result().subst_type_vars(bindings),
+                    ))),
+                    t => Ty::basic(t.dupe()),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl TyCallable {
+    /// Whether the signature mentions any type variables.
+    pub(crate) fn has_type_vars(&self) -> bool {
+        self.result().has_type_vars() || self.params().params().iter().any(|p| p.ty.has_type_vars())
+    }
+}
+
+/// Types type variables are bound to at a call site.
+#[derive(Default, Debug)]
+pub(crate) struct TypeVarBindings {
+    bindings: SmallMap<TyTypeVar, Ty>,
+}
+
+impl TypeVarBindings {
+    /// The type `var` is bound to, or its bound if it is not bound.
+    pub(crate) fn get(&self, var: &TyTypeVar) -> Ty {
+        match self.bindings.get(var) {
+            Some(ty) if !ty.is_never() => ty.dupe(),
+            _ => var.bound().dupe(),
+        }
+    }
+
+    fn bind_var(
+        &mut self,
+        var: &TyTypeVar,
+        arg: Ty,
+        oracle: &TypingOracleCtx,
+    ) -> Result<(), InternalError> {
+        // An argument outside of the bound does not bind the variable,
+        // so it is reported as incompatible with the bound.
+        if !oracle.intersects(&arg, var.bound())? {
+            return Ok(());
+        }
+        let ty = match self.bindings.get(var) {
+            Some(ty) => Ty::union2(ty.dupe(), arg),
+            None => arg,
+        };
+        self.bindings.insert(var.dupe(), ty);
+        Ok(())
+    }
+
+    /// Bind the type variables in a parameter type `param` to match argument type `arg`.
+    pub(crate) fn bind(
+        &mut self,
+        param: &Ty,
+        arg: &Ty,
+        oracle: &TypingOracleCtx,
+    ) -> Result<(), InternalError> {
+        if !param.has_type_vars() {
+            return Ok(());
+        }
+        if let [TyBasic::TypeVar(var)] = param.iter_union() {
+            return self.bind_var(var, arg.dupe(), oracle);
+        }
+        for p in param.iter_union() {
+            match p {
+                TyBasic::TypeVar(var) => {
+                    // For `T | None`, `T` is bound to the part of the argument
+                    // not matched by the other alternatives.
+                    let rest = arg
+                        .iter_union()
+                        .iter()
+                        .filter(|a| !param.iter_union().contains(a))
+                        .map(|a| Ty::basic(a.dupe()))
+                        .collect();
+                    self.bind_var(var, Ty::unions(rest), oracle)?;
+                }
+                p => {
+                    for a in arg.iter_union() {
+                        self.bind_basic(p, a, oracle)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn bind_basic(
+        &mut self,
+        param: &TyBasic,
+        arg: &TyBasic,
+        oracle: &TypingOracleCtx,
+    ) -> Result<(), InternalError> {
+        match (param, arg) {
+            (TyBasic::List(p), TyBasic::List(a)) | (TyBasic::Set(p), TyBasic::Set(a)) => {
+                self.bind(p, a, oracle)
+            }
+            (TyBasic::Dict(p_k, p_v), TyBasic::Dict(a_k, a_v)) => {
+                self.bind(p_k, a_k, oracle)?;
+                self.bind(p_v, a_v, oracle)
+            }
+            (TyBasic::Iter(p), a) => match oracle.iter_item_basic(a) {
+                Ok(item) => self.bind(p, &item, oracle),
+                Err(_) => Ok(()),
+            },
+            (TyBasic::Tuple(TyTuple::Elems(ps)), TyBasic::Tuple(TyTuple::Elems(xs))) => {
+                if ps.len() == xs.len() {
+                    for (p, a) in ps.iter().zip(xs.iter()) {
+                        self.bind(p, a, oracle)?;
+                    }
+                }
+                Ok(())
+            }
+            (TyBasic::Tuple(TyTuple::Of(p)), TyBasic::Tuple(a)) => {
+                self.bind(p, &a.item_ty(), oracle)
+            }
+            (TyBasic::Tuple(TyTuple::Elems(ps)), TyBasic::Tuple(TyTuple::Of(a))) => {
+                for p in ps.iter() {
+                    self.bind(p, a, oracle)?;
+                }
+                Ok(())
+            }
+            (TyBasic::Callable(p), a) => {
+                let a = match a {
+                    TyBasic::Callable(a) => a.dupe(),
+                    TyBasic::Custom(a) => match a.0.as_callable_dyn() {
+                        Some(a) => a,
+                        None => return Ok(()),
+                    },
+                    _ => return Ok(()),
+                };
+                self.bind_callable(p, &a, oracle)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn bind_callable(
+        &mut self,
+        param: &TyCallable,
+        arg: &TyCallable,
+        oracle: &TypingOracleCtx,
+    ) -> Result<(), InternalError> {
+        if let (Some(p_pos), Some(a_pos)) = (
+            param.params().all_required_pos_only(),
+            arg.params().all_required_pos_only(),
+        ) {
+            if p_pos.len() == a_pos.len() {
+                for (p, a) in p_pos.into_iter().zip(a_pos) {
+                    self.bind(p, a, oracle)?;
+                }
+            }
+        }
+        self.bind(param.result(), arg.result(), oracle)
+    }
+}
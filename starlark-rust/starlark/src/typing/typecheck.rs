@@ -77,6 +77,7 @@ pub(crate) fn solve_bindings(
         approximoations: RefCell::new(Vec::new()),
         types,
         module_var_types,
+        narrows: &bindings.narrows,
     };
     const ITERATIONS: usize = 100;
     for _iteration in 0..ITERATIONS {
@@ -0,0 +1,67 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use starlark_derive::starlark_module;
+use starlark_syntax::value_error;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::values::bytes::StarlarkBytes;
+use crate::values::Heap;
+use crate::values::UnpackValue;
+use crate::values::Value;
+use crate::values::ValueLike;
+
+#[starlark_module]
+pub(crate) fn register_bytes(globals: &mut GlobalsBuilder) {
+    /// [bytes](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes
+    /// ): construct a bytes value.
+    ///
+    /// `bytes(x)` returns the UTF-8 encoding of a string `x`,
+    /// `x` itself if it is bytes,
+    /// or bytes made from an iterable of ints in the range 0-255.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// bytes("héllo") == b"h\xc3\xa9llo"
+    /// bytes(b"abc") == b"abc"
+    /// bytes([65, 66]) == b"AB"
+    /// # "#);
+    /// ```
+    #[starlark(as_type = StarlarkBytes, speculative_exec_safe)]
+    fn bytes<'v>(
+        #[starlark(require = pos)] x: Value<'v>,
+        heap: &'v Heap,
+    ) -> starlark::Result<StarlarkBytes> {
+        if let Some(s) = x.unpack_str() {
+            return Ok(StarlarkBytes::new(s.as_bytes()));
+        }
+        if let Some(b) = x.downcast_ref::<StarlarkBytes>() {
+            return Ok(b.clone());
+        }
+        let mut res = Vec::new();
+        for elem in x.iterate(heap)? {
+            let elem = i32::unpack_value_err(elem)?;
+            match u8::try_from(elem) {
+                Ok(b) => res.push(b),
+                Err(_) => return Err(value_error!("bytes: {elem} out of range")),
+            }
+        }
+        Ok(StarlarkBytes::new(res))
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `bytes` type.
+
+use starlark_derive::starlark_module;
+use starlark_syntax::value_error;
+
+use crate as starlark;
+use crate::environment::MethodsBuilder;
+use crate::values::bytes::StarlarkBytes;
+
+/// `bytes.decode` and `str.encode` only support UTF-8.
+pub(crate) fn check_utf8_encoding(encoding: &str) -> crate::Result<()> {
+    match encoding.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => Ok(()),
+        _ => Err(value_error!(
+            "Unsupported encoding `{encoding}`, only `utf-8` is supported"
+        )),
+    }
+}
+
+#[starlark_module]
+pub(crate) fn bytes_methods(builder: &mut MethodsBuilder) {
+    /// [bytes.elems](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes·elems
+    /// ): returns the byte values of bytes.
+    ///
+    /// `B.elems()` returns the sequence of integer byte values in B,
+    /// each in the range 0-255.
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// list(b"AB\xff".elems()) == [65, 66, 255]
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn elems(this: &StarlarkBytes) -> anyhow::Result<Vec<i32>> {
+        Ok(this.as_bytes().iter().map(|b| *b as i32).collect())
+    }
+
+    /// Decode bytes to a string.
+    ///
+    /// `B.decode()` decodes B as UTF-8, replacing each invalid sequence
+    /// with the Unicode replacement character, U+FFFD.
+    /// The only supported `encoding` is `"utf-8"`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// b"h\xc3\xa9llo".decode() == "héllo"
+    /// b"a\xffb".decode("utf-8") == "a�b"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn decode(
+        this: &StarlarkBytes,
+        #[starlark(require = pos, default = "utf-8")] encoding: &str,
+    ) -> starlark::Result<String> {
+        check_utf8_encoding(encoding)?;
+        Ok(String::from_utf8_lossy(this.as_bytes()).into_owned())
+    }
+}
@@ -0,0 +1,229 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+use std::hash::Hasher;
+
+use allocative::Allocative;
+use serde::Serialize;
+use starlark_derive::starlark_value;
+use starlark_syntax::value_error;
+
+use crate as starlark;
+use crate::any::ProvidesStaticType;
+use crate::collections::StarlarkHasher;
+use crate::environment::Methods;
+use crate::environment::MethodsStatic;
+use crate::starlark_simple_value;
+use crate::typing::Ty;
+use crate::values::bytes::methods::bytes_methods;
+use crate::values::index::apply_slice;
+use crate::values::index::convert_index;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueError;
+use crate::values::ValueLike;
+
+/// Immutable sequence of bytes, the `bytes` type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, ProvidesStaticType, Allocative)]
+pub struct StarlarkBytes(Box<[u8]>);
+
+impl Display for StarlarkBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b\"{}\"", self.0.escape_ascii())
+    }
+}
+
+starlark_simple_value!(StarlarkBytes);
+
+impl StarlarkBytes {
+    /// The result of calling `type()` on bytes.
+    pub const TYPE: &'static str = "bytes";
+
+    /// Create a new [`StarlarkBytes`].
+    pub fn new(bytes: impl Into<Box<[u8]>>) -> StarlarkBytes {
+        StarlarkBytes(bytes.into())
+    }
+
+    /// The bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Bytes are encoded as JSON strings, with invalid UTF-8 replaced by U+FFFD.
+impl Serialize for StarlarkBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[starlark_value(type = StarlarkBytes::TYPE)]
+impl<'v> StarlarkValue<'v> for StarlarkBytes {
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn write_hash(&self, hasher: &mut StarlarkHasher) -> crate::Result<()> {
+        hasher.write(&self.0);
+        Ok(())
+    }
+
+    fn equals(&self, other: Value<'v>) -> crate::Result<bool> {
+        match other.downcast_ref::<StarlarkBytes>() {
+            Some(other) => Ok(self.0 == other.0),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, other: Value<'v>) -> crate::Result<std::cmp::Ordering> {
+        match other.downcast_ref::<StarlarkBytes>() {
+            Some(other) => Ok(self.0.cmp(&other.0)),
+            None => ValueError::unsupported_with(self, "compare", other),
+        }
+    }
+
+    fn length(&self) -> crate::Result<i32> {
+        i32::try_from(self.0.len()).map_err(|_| ValueError::IntegerOverflow.into())
+    }
+
+    fn at(&self, index: Value, heap: &'v Heap) -> crate::Result<Value<'v>> {
+        let index = convert_index(index, self.length()?)?;
+        Ok(heap.alloc(self.0[index as usize] as i32))
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> crate::Result<Value<'v>> {
+        let bytes = apply_slice(&self.0, start, stop, stride)?;
+        Ok(heap.alloc(StarlarkBytes::new(bytes)))
+    }
+
+    fn is_in(&self, other: Value<'v>) -> crate::Result<bool> {
+        if let Some(needle) = other.downcast_ref::<StarlarkBytes>() {
+            Ok(needle.0.is_empty() || self.0.windows(needle.0.len()).any(|w| *w == *needle.0))
+        } else if let Some(byte) = other.unpack_i32() {
+            match u8::try_from(byte) {
+                Ok(byte) => Ok(self.0.contains(&byte)),
+                Err(_) => Err(value_error!("int in bytes: {byte} out of range")),
+            }
+        } else {
+            ValueError::unsupported_owned(other.get_type(), "in", Some(Self::TYPE))
+        }
+    }
+
+    fn add(&self, rhs: Value<'v>, heap: &'v Heap) -> Option<crate::Result<Value<'v>>> {
+        let rhs = rhs.downcast_ref::<StarlarkBytes>()?;
+        Some(Ok(
+            heap.alloc(StarlarkBytes::new([&*self.0, &*rhs.0].concat()))
+        ))
+    }
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(bytes_methods)
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        Ty::starlark_value::<StarlarkBytes>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+    use crate::assert::Assert;
+    use crate::environment::Globals;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_bytes_literal() {
+        assert::all_true(
+            r#"
+type(b"") == "bytes"
+len(b"\x00\xff") == 2
+b"abc"[1] == 98
+b"abc"[-1] == 99
+b"abcd"[1:3] == b"bc"
+b"abcd"[::-1] == b"dcba"
+b"ab" + b"cd" == b"abcd"
+b"b" in b"abc"
+98 in b"abc"
+not (b"d" in b"abc")
+b"a" < b"b"
+repr(b"a\"\x00\xff") == 'b"a\\"\\x00\\xff"'
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bytes_hash() {
+        assert::all_true(
+            r#"
+{b"a": 1}[b"a"] == 1
+hash(b"abc") == hash("abc")
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bytes_conversions() {
+        assert::all_true(
+            r#"
+bytes("hé") == b"h\xc3\xa9"
+"hé".encode() == bytes("hé")
+b"h\xc3\xa9".decode() == "hé"
+str(b"a\xffb") == "a\ufffdb"
+list(b"ab".elems()) == [97, 98]
+bytes([97, 98]) == b"ab"
+json.encode(b"ab") == '"ab"'
+"#,
+        );
+        assert::fail("bytes([256])", "bytes: 256 out of range");
+        assert::fail("b'ab'.decode('latin-1')", "Unsupported encoding");
+    }
+
+    #[test]
+    fn test_bytes_index_out_of_range() {
+        assert::fail("b'ab'[2]", "out of bound");
+        assert::fail("256 in b'ab'", "out of range");
+    }
+
+    #[test]
+    fn test_bytes_literal_dialect() {
+        let mut a = Assert::new();
+        a.dialect(&Dialect::Standard);
+        a.fail("b'ab'", "bytes literals are not allowed in this dialect");
+    }
+
+    #[test]
+    fn test_bytes_global_extension() {
+        let mut a = Assert::new();
+        a.globals(Globals::standard());
+        a.fail("bytes('ab')", "Variable `bytes` not found");
+        a.fail("hash(b'ab')", "Expected type `str` but got `bytes`");
+    }
+}
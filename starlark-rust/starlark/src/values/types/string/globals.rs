@@ -20,6 +20,7 @@ use starlark_derive::starlark_module;
 use crate as starlark;
 use crate::environment::GlobalsBuilder;
 use crate::eval::Evaluator;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::string::StarlarkStr;
 use crate::values::StringValue;
 use crate::values::Value;
@@ -123,12 +124,15 @@ pub(crate) fn register_str(globals: &mut GlobalsBuilder) {
     /// If x is a string, the result is x (without quotation).
     /// All other strings, such as elements of a list of strings, are
     /// double-quoted.
+    /// If x is bytes, the result is its UTF-8 decoding, with invalid
+    /// sequences replaced by U+FFFD.
     ///
     /// ```
     /// # starlark::assert::all_true(r#"
     /// str(1)                          == '1'
     /// str("x")                        == 'x'
     /// str([1, "x"])                   == "[1, \"x\"]"
+    /// str(b"x")                       == 'x'
     /// # "#);
     /// ```
     #[starlark(as_type = StarlarkStr, speculative_exec_safe)]
@@ -139,6 +143,10 @@ pub(crate) fn register_str(globals: &mut GlobalsBuilder) {
         if let Some(a) = StringValue::new(a) {
             // Special case that can avoid reallocating, but is equivalent.
             Ok(a)
+        } else if let Some(b) = a.downcast_ref::<StarlarkBytes>() {
+            Ok(eval
+                .heap()
+                .alloc_str(&String::from_utf8_lossy(b.as_bytes())))
         } else {
             let mut s = eval.string_pool.alloc();
             a.collect_repr(&mut s);
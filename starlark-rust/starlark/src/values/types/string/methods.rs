@@ -28,6 +28,8 @@ use crate as starlark;
 use crate::environment::MethodsBuilder;
 use crate::eval::Arguments;
 use crate::eval::Evaluator;
+use crate::values::bytes::methods::check_utf8_encoding;
+use crate::values::bytes::StarlarkBytes;
 use crate::values::list::AllocList;
 use crate::values::list::UnpackList;
 use crate::values::none::NoneOr;
@@ -217,6 +219,26 @@ pub(crate) fn string_methods(builder: &mut MethodsBuilder) {
         }
     }
 
+    /// Encode a string as bytes.
+    ///
+    /// `S.encode()` returns the UTF-8 encoding of S.
+    /// The only supported `encoding` is `"utf-8"`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "héllo".encode() == b"h\xc3\xa9llo"
+    /// "abc".encode("utf-8").decode() == "abc"
+    /// # "#);
+    /// ```
+    #[starlark(speculative_exec_safe)]
+    fn encode(
+        this: &str,
+        #[starlark(require = pos, default = "utf-8")] encoding: &str,
+    ) -> starlark::Result<StarlarkBytes> {
+        check_utf8_encoding(encoding)?;
+        Ok(StarlarkBytes::new(this.as_bytes()))
+    }
+
     /// [string.endswith](
     /// https://github.com/bazelbuild/starlark/blob/master/spec.md#string·endswith
     /// ): determine if a string ends with a given suffix.
@@ -146,6 +146,7 @@ pub trait TypeMatcherAlloc: Sized {
             TyBasic::Type => self.alloc(IsType),
             TyBasic::Custom(custom) => self.custom(custom),
             TyBasic::Set(item) => self.set_of(item),
+            TyBasic::TypeVar(v) => self.ty(v.bound()),
         }
     }
 
@@ -0,0 +1,133 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use allocative::Allocative;
+use dupe::Dupe;
+use starlark_derive::starlark_module;
+use starlark_derive::starlark_value;
+use starlark_derive::NoSerialize;
+use starlark_derive::ProvidesStaticType;
+
+use crate as starlark;
+use crate::environment::GlobalsBuilder;
+use crate::typing::Ty;
+use crate::typing::TyBasic;
+use crate::typing::TyTypeVar;
+use crate::util::ArcStr;
+use crate::values::typing::ty::AbstractType;
+use crate::values::typing::TypeCompiled;
+use crate::values::Heap;
+use crate::values::StarlarkValue;
+use crate::values::Value;
+use crate::values::ValueOfUnchecked;
+
+/// Value returned by `typing.TypeVar("T")`.
+#[derive(
+    Allocative,
+    Debug,
+    ProvidesStaticType,
+    NoSerialize,
+    derive_more::Display
+)]
+#[display("typing.TypeVar(\"{}\")", var.name())]
+pub(crate) struct TypingTypeVar {
+    var: TyTypeVar,
+}
+
+impl TypingTypeVar {
+    pub(crate) fn new<'v>(
+        name: &str,
+        bound: Option<Value<'v>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<TypingTypeVar> {
+        let bound = match bound {
+            Some(bound) => TypeCompiled::new(bound, heap)?.as_ty().dupe(),
+            None => Ty::any(),
+        };
+        Ok(TypingTypeVar {
+            var: TyTypeVar::new(ArcStr::from(name), bound),
+        })
+    }
+}
+
+#[starlark_value(type = "typing.TypeVar")]
+impl<'v> StarlarkValue<'v> for TypingTypeVar {
+    fn eval_type(&self) -> Option<Ty> {
+        Some(Ty::basic(TyBasic::TypeVar(self.var.dupe())))
+    }
+}
+
+#[starlark_module]
+pub(crate) fn register_type_var(globals: &mut GlobalsBuilder) {
+    /// Declare a type variable, which can be used in function signatures
+    /// to relate the types of parameters and the return type.
+    ///
+    /// ```python
+    /// T = typing.TypeVar("T")
+    ///
+    /// def first(xs: list[T]) -> T:
+    ///     return xs[0]
+    /// ```
+    ///
+    /// The typechecker binds the type variables to the argument types at each call,
+    /// so `first([1, 2])` is typechecked as `int`.
+    /// `bound` restricts the types a variable can be bound to;
+    /// it is also the type checked at runtime.
+    #[starlark(speculative_exec_safe)]
+    fn TypeVar<'v>(
+        #[starlark(require = pos)] name: &str,
+        #[starlark(require = named)] bound: Option<ValueOfUnchecked<'v, AbstractType>>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc_simple(TypingTypeVar::new(name, bound.map(|b| b.get()), heap)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_type_var_runtime() {
+        assert::pass(
+            r#"
+T = typing.TypeVar("T")
+
+def first(xs: list[T]) -> T:
+    return xs[0]
+
+assert_eq(1, first([1, 2]))
+assert_eq("a", first(["a"]))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_type_var_runtime_bound() {
+        assert::fail(
+            r#"
+N = typing.TypeVar("N", bound = int)
+
+def double(x: N) -> N:
+    return x * 2
+
+double("a")
+"#,
+            "Value `a` of type `string` does not match the type annotation `N`",
+        );
+    }
+}
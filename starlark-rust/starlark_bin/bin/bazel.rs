@@ -58,6 +58,7 @@ use starlark_lsp::server::LspUrl;
 use starlark_lsp::server::StringLiteralResult;
 
 use self::label::Label;
+use crate::eval::workspace_files;
 use crate::eval::ContextMode;
 use crate::eval::EvalResult;
 
@@ -767,6 +768,18 @@ impl LspContext for BazelContext {
         DocModule::default()
     }
 
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        match workspace_root {
+            Some(root) => workspace_files(root, |path| {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let extension = path.extension().unwrap_or_default().to_string_lossy();
+                Self::BUILD_FILE_NAMES.contains(&file_name.as_ref())
+                    || Self::LOADABLE_EXTENSIONS.contains(&extension.as_ref())
+            }),
+            None => Ok(Vec::new()),
+        }
+    }
+
     fn get_url_for_global_symbol(
         &self,
         _current_file: &LspUrl,
@@ -41,6 +41,7 @@ use starlark_lsp::server::LspContext;
 use starlark_lsp::server::LspEvalResult;
 use starlark_lsp::server::LspUrl;
 use starlark_lsp::server::StringLiteralResult;
+use walkdir::WalkDir;
 
 use crate::suppression::GlobLintSuppression;
 
@@ -88,6 +89,20 @@ pub(crate) struct EvalResult<T: Iterator<Item = EvalMessage>> {
     pub ast: Option<AstModule>,
 }
 
+/// The files under `root` for which `is_starlark` holds, skipping hidden directories.
+pub(crate) fn workspace_files(
+    root: &Path,
+    is_starlark: impl Fn(&Path) -> bool,
+) -> anyhow::Result<Vec<LspUrl>> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && is_starlark(e.path()))
+        .map(|e| Ok(Url::from_file_path(e.path()).unwrap().try_into()?))
+        .collect()
+}
+
 /// Errors when [`LspContext::resolve_load()`] cannot resolve a given path.
 #[derive(thiserror::Error, Debug)]
 enum ResolveLoadError {
@@ -362,6 +377,16 @@ impl LspContext for Context {
         }
     }
 
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        match workspace_root {
+            Some(root) => workspace_files(
+                root,
+                |path| matches!(path.extension(), Some(e) if e == "star" || e == "bzl"),
+            ),
+            None => Ok(Vec::new()),
+        }
+    }
+
     fn get_url_for_global_symbol(
         &self,
         _current_file: &LspUrl,
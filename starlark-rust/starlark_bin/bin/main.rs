@@ -22,6 +22,8 @@
 use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -39,6 +41,7 @@ use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
 use starlark::read_line::ReadLine;
+use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
 use suppression::GlobLintSuppression;
 use walkdir::WalkDir;
@@ -59,6 +62,7 @@ struct Args {
         conflicts_with_all = &[
             "dap",
             "check",
+            "format",
             "json",
             "docs",
             "evaluate",
@@ -74,6 +78,7 @@ struct Args {
         conflicts_with_all = &[
             "lsp",
             "check",
+            "format",
             "json",
             "docs",
             "extension",
@@ -91,6 +96,13 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "format",
+        help = "Format files in place. With `--check`, report files that are not formatted instead.",
+        conflicts_with_all = &["lsp", "dap", "json", "docs", "evaluate"],
+    )]
+    format: bool,
+
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
@@ -249,6 +261,38 @@ fn drain(
     Ok(())
 }
 
+/// Format `files`, or with `check`, fail if any of them are not already formatted.
+fn format_files(
+    files: impl Iterator<Item = PathBuf>,
+    dialect: &Dialect,
+    check: bool,
+) -> anyhow::Result<()> {
+    let mut unformatted = 0;
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        let formatted = format_file(&file, content.clone(), dialect)?;
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            unformatted += 1;
+        } else {
+            fs::write(&file, formatted)?;
+        }
+    }
+    if unformatted > 0 {
+        return Err(anyhow::anyhow!("{} files are not formatted", unformatted));
+    }
+    Ok(())
+}
+
+fn format_file(file: &Path, content: String, dialect: &Dialect) -> anyhow::Result<String> {
+    let ast =
+        AstModule::parse(&file.to_string_lossy(), content, dialect).map_err(|e| e.into_anyhow())?;
+    Ok(ast.format())
+}
+
 fn interactive(ctx: &Context) -> anyhow::Result<()> {
     let mut rl = ReadLine::new("STARLARK_RUST_HISTFILE")?;
     loop {
@@ -310,6 +354,10 @@ fn main() -> anyhow::Result<()> {
             return Ok(());
         }
 
+        if args.format {
+            return format_files(expand_dirs(ext, args.files), &dialect, args.check);
+        }
+
         let mut ctx = Context::new(
             if args.check {
                 ContextMode::Check
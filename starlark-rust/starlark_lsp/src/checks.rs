@@ -0,0 +1,144 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Running the linter and typechecker over a parsed module to produce diagnostics.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use lsp_types::Diagnostic;
+use starlark::analysis::AstModuleLint;
+use starlark::analysis::EvalMessage;
+use starlark::syntax::AstModule;
+use starlark::typing::AstModuleTypecheck;
+use starlark::ErrorKind;
+use starlark_syntax::syntax::module::AstModuleFields;
+
+use crate::error::eval_message_to_lsp_diagnostic;
+use crate::server::LspChecks;
+
+/// The code given to diagnostics produced by the typechecker.
+pub(crate) const TYPECHECK_CODE: &str = "typecheck";
+
+/// Run the checks requested by `checks` over `ast`, returning any problems found.
+pub(crate) fn check_module(ast: &AstModule, checks: &LspChecks) -> Vec<Diagnostic> {
+    let mut messages = Vec::new();
+    if checks.lint {
+        messages.extend(
+            ast.lint(checks.lint_globals.as_ref())
+                .into_iter()
+                .map(EvalMessage::from),
+        );
+    }
+    if let Some(globals) = &checks.typecheck_globals {
+        let filename = ast.codemap().filename().to_owned();
+        let (errors, ..) = ast.clone().typecheck(globals, &HashMap::new());
+        messages.extend(
+            errors
+                .into_iter()
+                // Names the globals don't know about are reported by the linter when it
+                // is given the full set of globals, and are noise otherwise.
+                .filter(|error| !matches!(error.kind(), ErrorKind::Scope(_)))
+                .map(|error| EvalMessage {
+                    name: TYPECHECK_CODE.to_owned(),
+                    ..EvalMessage::from_error(Path::new(&filename), &error)
+                }),
+        );
+    }
+    messages
+        .into_iter()
+        .map(eval_message_to_lsp_diagnostic)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::NumberOrString;
+    use starlark::environment::Globals;
+    use starlark::syntax::Dialect;
+
+    use super::*;
+
+    fn module(x: &str) -> AstModule {
+        AstModule::parse("X", x.to_owned(), &Dialect::AllOptionsInternal).unwrap()
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| match &diagnostic.code {
+                Some(NumberOrString::String(code)) => code.clone(),
+                code => panic!("Unexpected code {:?}", code),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runs_nothing_by_default() {
+        let ast = module("load('a', 'b')\ndef f(x: int) -> str:\n    return x\n");
+        assert!(check_module(&ast, &LspChecks::default()).is_empty());
+    }
+
+    #[test]
+    fn reports_lints() {
+        let ast = module(
+            r#"
+load("a", "b")
+def f():
+    return
+x = undefined
+"#,
+        );
+        let checks = LspChecks {
+            lint: true,
+            ..LspChecks::default()
+        };
+        // Without globals, nothing is considered undefined.
+        assert_eq!(
+            vec!["redundant-return", "unused-load"],
+            codes(&check_module(&ast, &checks))
+        );
+
+        let checks = LspChecks {
+            lint: true,
+            lint_globals: Some(Default::default()),
+            ..LspChecks::default()
+        };
+        assert_eq!(
+            vec!["redundant-return", "unused-load", "using-undefined"],
+            codes(&check_module(&ast, &checks))
+        );
+    }
+
+    #[test]
+    fn reports_type_errors() {
+        let ast = module(
+            r#"
+def f(x: int) -> str:
+    return x
+y = unknown(1)
+"#,
+        );
+        let checks = LspChecks {
+            typecheck_globals: Some(Globals::standard()),
+            ..LspChecks::default()
+        };
+        let diagnostics = check_module(&ast, &checks);
+        assert_eq!(vec![TYPECHECK_CODE], codes(&diagnostics));
+        assert_eq!(2, diagnostics[0].range.start.line);
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Fixes for diagnostics that have an obvious resolution, offered as code actions.
+
+use lsp_types::Diagnostic;
+use lsp_types::NumberOrString;
+use lsp_types::Range;
+use lsp_types::TextEdit;
+use starlark::codemap::Span;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+
+use crate::definition::LspModule;
+
+/// A single edit that resolves a diagnostic.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct QuickFix {
+    /// A description of the fix, shown to the user.
+    pub(crate) title: String,
+    pub(crate) edit: TextEdit,
+}
+
+impl LspModule {
+    /// Find a fix for a diagnostic that was reported for this module, if there is one.
+    pub(crate) fn quick_fix(&self, diagnostic: &Diagnostic) -> Option<QuickFix> {
+        match &diagnostic.code {
+            Some(NumberOrString::String(code)) if code == "unused-load" => {
+                self.remove_unused_load(diagnostic.range)
+            }
+            _ => None,
+        }
+    }
+
+    /// Remove the symbol at `range` from its `load()` statement, or the whole statement if
+    /// it is the only symbol loaded.
+    fn remove_unused_load(&self, range: Range) -> Option<QuickFix> {
+        let codemap = self.ast.codemap();
+        for stmt in top_level_stmts(self.ast.statement()) {
+            let StmtP::Load(load) = &stmt.node else {
+                continue;
+            };
+            let Some(i) = load
+                .args
+                .iter()
+                .position(|arg| Range::from(codemap.resolve_span(arg.local.span)) == range)
+            else {
+                continue;
+            };
+            let arg = &load.args[i];
+
+            let (title, span) = if load.args.len() == 1 {
+                (
+                    format!("Remove unused load of `{}`", load.module.node),
+                    stmt.span,
+                )
+            } else if let Some(next) = load.args.get(i + 1) {
+                // Take the separator along with the argument.
+                (
+                    format!("Remove unused load of `{}`", arg.local.ident),
+                    Span::new(arg.span().begin(), next.span().begin()),
+                )
+            } else {
+                // The last argument, so take the separator before it.
+                (
+                    format!("Remove unused load of `{}`", arg.local.ident),
+                    Span::new(load.args[i - 1].span().end(), arg.span().end()),
+                )
+            };
+
+            let mut range: Range = codemap.resolve_span(span).into();
+            if load.args.len() == 1 && range.start.character == 0 {
+                // Remove the line the statement was on, rather than leaving it blank.
+                range.end.line += 1;
+                range.end.character = 0;
+            }
+            return Some(QuickFix {
+                title,
+                edit: TextEdit::new(range, String::new()),
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::analysis::AstModuleLint;
+    use starlark::analysis::EvalMessage;
+    use starlark::syntax::AstModule;
+    use starlark::syntax::Dialect;
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::error::eval_message_to_lsp_diagnostic;
+
+    /// Apply the fix for every diagnostic the linter reports, one at a time, returning
+    /// the resulting program after each fix.
+    fn fix_all(program: &str) -> Vec<String> {
+        let program = dedent(program).trim_start().to_owned();
+        let ast = AstModule::parse("foo.star", program.clone(), &Dialect::Standard).unwrap();
+        let module = LspModule::new(ast);
+        module
+            .ast
+            .lint(None)
+            .into_iter()
+            .filter_map(|lint| {
+                let diagnostic = eval_message_to_lsp_diagnostic(EvalMessage::from(lint));
+                module.quick_fix(&diagnostic)
+            })
+            .map(|fix| apply(&program, &fix.edit))
+            .collect()
+    }
+
+    fn apply(program: &str, edit: &TextEdit) -> String {
+        let offset = |line: u32, character: u32| {
+            program
+                .split_inclusive('\n')
+                .take(line as usize)
+                .map(|l| l.len())
+                .sum::<usize>()
+                + character as usize
+        };
+        let begin = offset(edit.range.start.line, edit.range.start.character);
+        let end = offset(edit.range.end.line, edit.range.end.character);
+        format!("{}{}{}", &program[..begin], edit.new_text, &program[end..])
+    }
+
+    #[test]
+    fn removes_unused_load_symbols() {
+        let fixed = fix_all(
+            r#"
+            load("a.star", "x", "y", z = "w")
+            x + y
+            "#,
+        );
+        assert_eq!(vec!["load(\"a.star\", \"x\", \"y\")\nx + y\n"], fixed);
+
+        let fixed = fix_all(
+            r#"
+            load("a.star", "x", "y")
+            y
+            "#,
+        );
+        assert_eq!(vec!["load(\"a.star\", \"y\")\ny\n"], fixed);
+    }
+
+    #[test]
+    fn removes_whole_load() {
+        let fixed = fix_all(
+            r#"
+            load("a.star", "x")
+            load("b.star", "y")
+            y
+            "#,
+        );
+        assert_eq!(vec!["load(\"b.star\", \"y\")\ny\n"], fixed);
+    }
+
+    #[test]
+    fn ignores_other_diagnostics() {
+        let fixed = fix_all(
+            r#"
+            def f():
+                x = 1
+            "#,
+        );
+        assert!(fixed.is_empty());
+    }
+}
@@ -244,14 +244,13 @@ impl LspModule {
         //            LSPModule doesn't need to reparse anything.
 
         let scope = scope(&self.ast);
-        let line_span = match self.ast.codemap().line_span_opt(line as usize) {
+        let current_pos = match self.pos_at_location(line, col) {
             None => {
                 // The document got edited to add new lines, just bail out
                 return Definition::Identifier(IdentifierDefinition::NotFound);
             }
-            Some(line_span) => line_span,
+            Some(pos) => pos,
         };
-        let current_pos = std::cmp::min(line_span.begin() + col, line_span.end());
 
         // Finalize the results after recursing down from and back up to the the top level scope.
         match Self::find_definition_in_scope(&scope, current_pos) {
@@ -0,0 +1,278 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The outline of a module, for "document symbols" support.
+
+use lsp_types::DocumentSymbol;
+use lsp_types::SymbolKind;
+use starlark::codemap::CodeMap;
+use starlark::codemap::Span;
+use starlark_syntax::syntax::ast::ArgumentP;
+use starlark_syntax::syntax::ast::AssignP;
+use starlark_syntax::syntax::ast::AstExprP;
+use starlark_syntax::syntax::ast::AstLiteral;
+use starlark_syntax::syntax::ast::AstPayload;
+use starlark_syntax::syntax::ast::AstStmtP;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::LoadArgP;
+use starlark_syntax::syntax::ast::ParameterP;
+use starlark_syntax::syntax::ast::StmtP;
+
+/// Walk the AST and produce a hierarchical outline of the symbols it defines.
+///
+/// This includes functions (with their parameters and nested definitions), assignments
+/// (with the fields of `struct()` values), `load()` statements and calls with a `name`
+/// argument, such as the targets in a `BUCK` file.
+pub(crate) fn find_document_symbols<P: AstPayload>(
+    codemap: &CodeMap,
+    ast: &AstStmtP<P>,
+) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    walk(codemap, ast, &mut symbols);
+    symbols
+}
+
+fn walk<P: AstPayload>(codemap: &CodeMap, ast: &AstStmtP<P>, symbols: &mut Vec<DocumentSymbol>) {
+    match &ast.node {
+        StmtP::Def(def) => {
+            let mut children: Vec<_> = def
+                .params
+                .iter()
+                .filter_map(|param| match &param.node {
+                    ParameterP::Normal(name, ..)
+                    | ParameterP::Args(name, _)
+                    | ParameterP::KwArgs(name, _) => Some(symbol(
+                        codemap,
+                        name.ident.clone(),
+                        None,
+                        SymbolKind::VARIABLE,
+                        param.span,
+                        name.span,
+                        None,
+                    )),
+                    ParameterP::NoArgs | ParameterP::Slash => None,
+                })
+                .collect();
+            walk(codemap, &def.body, &mut children);
+            symbols.push(symbol(
+                codemap,
+                def.name.ident.clone(),
+                None,
+                SymbolKind::FUNCTION,
+                ast.span,
+                def.name.span,
+                Some(children),
+            ));
+        }
+        StmtP::Assign(AssignP { lhs, ty: _, rhs }) => {
+            lhs.visit_lvalue(|x| {
+                let (kind, children) = match &rhs.node {
+                    ExprP::Lambda(_) => (SymbolKind::FUNCTION, None),
+                    ExprP::Call(function, args) if is_struct(function) => (
+                        SymbolKind::STRUCT,
+                        Some(
+                            args.args
+                                .iter()
+                                .filter_map(|arg| match &arg.node {
+                                    ArgumentP::Named(name, _) => Some(symbol(
+                                        codemap,
+                                        name.node.clone(),
+                                        None,
+                                        SymbolKind::FIELD,
+                                        arg.span,
+                                        name.span,
+                                        None,
+                                    )),
+                                    _ => None,
+                                })
+                                .collect(),
+                        ),
+                    ),
+                    _ => (SymbolKind::VARIABLE, None),
+                };
+                symbols.push(symbol(
+                    codemap,
+                    x.ident.clone(),
+                    None,
+                    kind,
+                    ast.span,
+                    x.span,
+                    children,
+                ));
+            });
+        }
+        StmtP::Load(load) => {
+            let children = load
+                .args
+                .iter()
+                .map(|LoadArgP { local, .. }| {
+                    symbol(
+                        codemap,
+                        local.ident.clone(),
+                        None,
+                        SymbolKind::METHOD,
+                        local.span,
+                        local.span,
+                        None,
+                    )
+                })
+                .collect();
+            symbols.push(symbol(
+                codemap,
+                load.module.node.clone(),
+                None,
+                SymbolKind::MODULE,
+                ast.span,
+                load.module.span,
+                Some(children),
+            ));
+        }
+        StmtP::Expression(expr) => {
+            if let ExprP::Call(function, args) = &expr.node {
+                let name = args.args.iter().find_map(|arg| match &arg.node {
+                    ArgumentP::Named(name, value) if name.node == "name" => match &value.node {
+                        ExprP::Literal(AstLiteral::String(s)) => Some((&s.node, value.span)),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+                if let Some((name, name_span)) = name {
+                    symbols.push(symbol(
+                        codemap,
+                        name.clone(),
+                        Some(codemap.source_span(function.span).to_owned()),
+                        SymbolKind::CONSTRUCTOR,
+                        ast.span,
+                        name_span,
+                        None,
+                    ));
+                }
+            }
+        }
+        stmt => stmt.visit_stmt(|x| walk(codemap, x, symbols)),
+    }
+}
+
+fn is_struct<P: AstPayload>(function: &AstExprP<P>) -> bool {
+    matches!(&function.node, ExprP::Identifier(name) if name.node.ident == "struct")
+}
+
+fn symbol(
+    codemap: &CodeMap,
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: Span,
+    selection_range: Span,
+    children: Option<Vec<DocumentSymbol>>,
+) -> DocumentSymbol {
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: codemap.resolve_span(range).into(),
+        selection_range: codemap.resolve_span(selection_range).into(),
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::syntax::AstModule;
+    use starlark::syntax::Dialect;
+    use starlark_syntax::syntax::module::AstModuleFields;
+
+    use super::*;
+
+    fn outline(symbols: &[DocumentSymbol], depth: usize, res: &mut Vec<String>) {
+        for symbol in symbols {
+            res.push(format!(
+                "{}{} {:?} {}:{}",
+                "  ".repeat(depth),
+                symbol.name,
+                symbol.kind,
+                symbol.selection_range.start.line,
+                symbol.selection_range.start.character,
+            ));
+            if let Some(children) = &symbol.children {
+                outline(children, depth + 1, res);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_document_symbols() {
+        let ast_module = AstModule::parse(
+            "t.star",
+            r#"load("foo.star", "a", b = "c")
+
+def method(param, *args, **kwargs):
+    def inner():
+        pass
+    local = 1
+
+my_var = True
+my_struct = struct(x = 1, y = lambda: 2)
+my_lambda = lambda x: x
+
+if my_var:
+    conditional = 1
+
+cc_library(
+    name = "lib",
+    srcs = ["lib.c"],
+)
+print("no name")
+"#
+            .to_owned(),
+            &Dialect::AllOptionsInternal,
+        )
+        .unwrap();
+
+        let symbols = find_document_symbols(ast_module.codemap(), ast_module.statement());
+        let mut res = Vec::new();
+        outline(&symbols, 0, &mut res);
+        assert_eq!(
+            vec![
+                "foo.star Module 0:5",
+                "  a Method 0:17",
+                "  b Method 0:22",
+                "method Function 2:4",
+                "  param Variable 2:11",
+                "  args Variable 2:19",
+                "  kwargs Variable 2:27",
+                "  inner Function 3:8",
+                "  local Variable 5:4",
+                "my_var Variable 7:0",
+                "my_struct Struct 8:0",
+                "  x Field 8:19",
+                "  y Field 8:26",
+                "my_lambda Function 9:0",
+                "conditional Variable 12:4",
+                "lib Constructor 15:11",
+            ],
+            res
+        );
+        assert_eq!(
+            Some("cc_library".to_owned()),
+            symbols.last().unwrap().detail
+        );
+    }
+}
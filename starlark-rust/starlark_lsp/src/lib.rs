@@ -22,14 +22,19 @@
 #[allow(clippy::needless_lifetimes)]
 #[allow(clippy::type_complexity)]
 mod bind;
+mod checks;
+mod code_actions;
 pub mod completion;
 mod definition;
 pub(crate) mod docs;
+mod document_symbols;
 pub mod error;
 mod exported;
 pub(crate) mod inspect;
 pub(crate) mod loaded;
+mod references;
 pub mod server;
+mod signature;
 mod symbols;
 #[cfg(test)]
 mod test;
@@ -0,0 +1,387 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Finding all of the places a symbol is used within a module, for "find references"
+//! and "rename" support.
+
+use std::collections::HashSet;
+
+use starlark::codemap::Pos;
+use starlark::codemap::ResolvedSpan;
+use starlark::codemap::Span;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::top_level_stmts::top_level_stmts;
+
+use crate::bind::scope;
+use crate::bind::Bind;
+use crate::bind::Scope;
+use crate::definition::LspModule;
+
+/// How a symbol appears at a given location, which determines how it is rewritten
+/// when the symbol is renamed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ReferenceKind {
+    /// A bare identifier, e.g. a variable access, an assignment or a `def`.
+    Identifier,
+    /// The quoted name of the symbol in a `load()` statement.
+    LoadString,
+}
+
+/// A single place in a module that refers to a symbol.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Reference {
+    pub(crate) span: ResolvedSpan,
+    pub(crate) kind: ReferenceKind,
+    /// Whether this is the place where the symbol is first bound.
+    pub(crate) declaration: bool,
+}
+
+/// The symbol found at a location in a module. See [`LspModule::find_reference_target`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum ReferenceTarget {
+    /// A symbol that can only be referenced from this module. It is identified by the
+    /// span of the place where it was first bound.
+    Local { binding: Span },
+    /// A top level symbol of this module that other modules can `load()`.
+    Exported { name: String },
+    /// A symbol that this module loads from `path` under its original `name`.
+    Loaded { path: String, name: String },
+}
+
+/// An identifier in the module, along with the binding it resolves to.
+struct Occurrence<'a> {
+    name: &'a str,
+    span: Span,
+    /// The span of the binding that this identifier refers to, and whether that
+    /// binding is in the top level scope. `None` for globals.
+    binding: Option<(Span, bool)>,
+}
+
+/// Find the binding `name` refers to from within the innermost of `parents`, and whether
+/// it is in the top level scope.
+fn resolve(name: &str, parents: &[&Scope]) -> Option<(Span, bool)> {
+    parents.iter().enumerate().rev().find_map(|(depth, scope)| {
+        let (_, span) = scope.bound.get(name)?;
+        Some((*span, depth == 0))
+    })
+}
+
+/// Collect every identifier in `scope` and its children, resolving each one against
+/// the scopes it is nested in (innermost last in `parents`).
+fn occurrences<'a>(scope: &'a Scope, parents: &mut Vec<&'a Scope>, res: &mut Vec<Occurrence<'a>>) {
+    parents.push(scope);
+    for bind in &scope.inner {
+        let (name, span) = match bind {
+            Bind::Set(_, x) => (x.ident.as_str(), x.span),
+            Bind::Get(x) => (x.ident.as_str(), x.span),
+            Bind::GetDotted(x) => (x.variable.ident.as_str(), x.variable.span),
+            Bind::Scope(inner) => {
+                occurrences(inner, parents, res);
+                continue;
+            }
+            Bind::Flow => continue,
+        };
+        res.push(Occurrence {
+            name,
+            span,
+            binding: resolve(name, parents),
+        });
+    }
+    parents.pop();
+}
+
+impl LspModule {
+    fn occurrences<'a>(&self, scope: &'a Scope) -> Vec<Occurrence<'a>> {
+        let mut res = Vec::new();
+        occurrences(scope, &mut Vec::new(), &mut res);
+        res
+    }
+
+    fn reference(&self, span: Span, kind: ReferenceKind, declaration: bool) -> Reference {
+        Reference {
+            span: self.ast.codemap().resolve_span(span),
+            kind,
+            declaration,
+        }
+    }
+
+    /// Find the symbol at the given location, if there is one that references can
+    /// be found for.
+    ///
+    /// `line` and `col` are zero based. Globals that are not defined in this module
+    /// return `None`, as do members accessed with `.`.
+    pub(crate) fn find_reference_target(&self, line: u32, col: u32) -> Option<ReferenceTarget> {
+        let pos = self.pos_at_location(line, col)?;
+
+        // Names in `load()` statements are string literals, so check those first.
+        for stmt in top_level_stmts(self.ast.statement()) {
+            if let StmtP::Load(load) = &stmt.node {
+                // Un-aliased loads share the span of the local name and the string.
+                for arg in &load.args {
+                    if arg.their.span.contains(pos) {
+                        return Some(ReferenceTarget::Loaded {
+                            path: load.module.node.clone(),
+                            name: arg.their.node.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let scope = scope(&self.ast);
+        let occurrence = self
+            .occurrences(&scope)
+            .into_iter()
+            .find(|occurrence| occurrence.span.contains(pos))?;
+        let (binding, top_level) = occurrence.binding?;
+        if !top_level {
+            return Some(ReferenceTarget::Local { binding });
+        }
+        match self.find_load_binding(binding) {
+            Some(target) => Some(target),
+            None if !occurrence.name.starts_with('_') => Some(ReferenceTarget::Exported {
+                name: occurrence.name.to_owned(),
+            }),
+            None => Some(ReferenceTarget::Local { binding }),
+        }
+    }
+
+    /// If `binding` is the local name of a symbol in a `load()` statement, get the
+    /// target for it. Aliases are local to this module, otherwise the target is the
+    /// symbol in the module that it was loaded from.
+    fn find_load_binding(&self, binding: Span) -> Option<ReferenceTarget> {
+        top_level_stmts(self.ast.statement())
+            .into_iter()
+            .filter_map(|stmt| match &stmt.node {
+                StmtP::Load(load) => Some(load),
+                _ => None,
+            })
+            .find_map(|load| {
+                let arg = load.args.iter().find(|arg| arg.local.span == binding)?;
+                if arg.local.ident == arg.their.node {
+                    Some(ReferenceTarget::Loaded {
+                        path: load.module.node.clone(),
+                        name: arg.their.node.clone(),
+                    })
+                } else {
+                    Some(ReferenceTarget::Local { binding })
+                }
+            })
+    }
+
+    /// Find all of the identifiers that resolve to the binding at `binding`.
+    pub(crate) fn find_local_references(&self, binding: Span) -> Vec<Reference> {
+        let scope = scope(&self.ast);
+        let mut seen = HashSet::new();
+        self.occurrences(&scope)
+            .into_iter()
+            .filter(|occurrence| matches!(occurrence.binding, Some((span, _)) if span == binding))
+            // `x += 1` both reads and writes `x` at the same location.
+            .filter(|occurrence| seen.insert(occurrence.span))
+            .map(|occurrence| {
+                self.reference(
+                    occurrence.span,
+                    ReferenceKind::Identifier,
+                    occurrence.span == binding,
+                )
+            })
+            .collect()
+    }
+
+    /// Find all of the references to the top level symbol `name` defined in this module.
+    pub(crate) fn find_exported_references(&self, name: &str) -> Vec<Reference> {
+        let scope = scope(&self.ast);
+        match scope.bound.get(name) {
+            Some((_, binding)) => self.find_local_references(*binding),
+            None => Vec::new(),
+        }
+    }
+
+    /// Find all of the references to the symbol `name` loaded from another module.
+    ///
+    /// `is_module` is called with the path of each `load()` statement, and should return
+    /// whether that path refers to the module that defines `name`.
+    pub(crate) fn find_loaded_references(
+        &self,
+        name: &str,
+        is_module: impl Fn(&str) -> bool,
+    ) -> Vec<Reference> {
+        let mut res = Vec::new();
+        for stmt in top_level_stmts(self.ast.statement()) {
+            let StmtP::Load(load) = &stmt.node else {
+                continue;
+            };
+            if !load.args.iter().any(|arg| arg.their.node == name) || !is_module(&load.module) {
+                continue;
+            }
+            for arg in load.args.iter().filter(|arg| arg.their.node == name) {
+                res.push(self.reference(arg.their.span, ReferenceKind::LoadString, false));
+                // An aliased symbol is only ever referred to by its alias in this module.
+                if arg.local.ident == arg.their.node {
+                    res.extend(
+                        self.find_local_references(arg.local.span)
+                            .into_iter()
+                            .filter(|reference| !reference.declaration),
+                    );
+                }
+            }
+        }
+        res
+    }
+
+    /// Convert a zero based line and column into a position in this module.
+    pub(crate) fn pos_at_location(&self, line: u32, col: u32) -> Option<Pos> {
+        let line_span = self.ast.codemap().line_span_opt(line as usize)?;
+        Some(std::cmp::min(line_span.begin() + col, line_span.end()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn module(fixture: &str) -> starlark::Result<(FixtureWithRanges, LspModule)> {
+        let parsed = FixtureWithRanges::from_fixture("foo.star", dedent(fixture).trim())?;
+        let module = parsed.module()?;
+        Ok((parsed, module))
+    }
+
+    fn spans(references: &[Reference]) -> Vec<ResolvedSpan> {
+        let mut spans: Vec<_> = references.iter().map(|r| r.span).collect();
+        spans.sort_by_key(|span| (span.begin.line, span.begin.column));
+        spans
+    }
+
+    #[test]
+    fn finds_local_references_respecting_scopes() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            <x1>x</x1> = 1
+            def _f(x):
+                return x + 1
+            def _g():
+                return <x2>x</x2> + 1
+            <x3>x</x3> += 2
+            "#,
+        )?;
+
+        let target =
+            module.find_reference_target(parsed.begin_line("x2"), parsed.begin_column("x2"));
+        assert_eq!(
+            Some(ReferenceTarget::Exported {
+                name: "x".to_owned()
+            }),
+            target
+        );
+
+        let references = module.find_exported_references("x");
+        assert_eq!(
+            vec![
+                parsed.resolved_span("x1"),
+                parsed.resolved_span("x2"),
+                parsed.resolved_span("x3"),
+            ],
+            spans(&references)
+        );
+        assert_eq!(1, references.iter().filter(|r| r.declaration).count());
+        Ok(())
+    }
+
+    #[test]
+    fn finds_parameter_references() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            x = 1
+            def _f(<x1>x</x1>):
+                return [<x2>x</x2> for _ in range(<x3>x</x3>)]
+            "#,
+        )?;
+
+        let target = module
+            .find_reference_target(parsed.begin_line("x3"), parsed.begin_column("x3"))
+            .unwrap();
+        let ReferenceTarget::Local { binding } = target else {
+            panic!("Expected a local target, got {:?}", target);
+        };
+        assert_eq!(
+            vec![
+                parsed.resolved_span("x1"),
+                parsed.resolved_span("x2"),
+                parsed.resolved_span("x3"),
+            ],
+            spans(&module.find_local_references(binding))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finds_loaded_references() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            load("bar.star", <foo1>"foo"</foo1>, baz = <foo2>"foo"</foo2>)
+            load("other.star", other = "foo")
+            <foo3>foo</foo3>(baz)
+            "#,
+        )?;
+
+        let target =
+            module.find_reference_target(parsed.begin_line("foo3"), parsed.begin_column("foo3"));
+        assert_eq!(
+            Some(ReferenceTarget::Loaded {
+                path: "bar.star".to_owned(),
+                name: "foo".to_owned(),
+            }),
+            target
+        );
+
+        let references = module.find_loaded_references("foo", |path| path == "bar.star");
+        assert_eq!(
+            vec![
+                parsed.resolved_span("foo1"),
+                parsed.resolved_span("foo2"),
+                parsed.resolved_span("foo3"),
+            ],
+            spans(&references)
+        );
+        assert_eq!(
+            2,
+            references
+                .iter()
+                .filter(|r| r.kind == ReferenceKind::LoadString)
+                .count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn globals_have_no_target() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            x = <print>print</print>(1)
+            "#,
+        )?;
+        assert_eq!(
+            None,
+            module.find_reference_target(parsed.begin_line("print"), parsed.begin_column("print"))
+        );
+        Ok(())
+    }
+}
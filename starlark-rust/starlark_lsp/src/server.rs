@@ -24,6 +24,7 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::time::SystemTime;
 
 use derivative::Derivative;
 use derive_more::Display;
@@ -40,11 +41,26 @@ use lsp_server::ResponseError;
 use lsp_types::notification::DidChangeTextDocument;
 use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::CodeActionRequest;
 use lsp_types::request::Completion;
+use lsp_types::request::DocumentSymbolRequest;
+use lsp_types::request::Formatting;
 use lsp_types::request::GotoDefinition;
 use lsp_types::request::HoverRequest;
+use lsp_types::request::References;
+use lsp_types::request::Rename;
+use lsp_types::request::SignatureHelpRequest;
+use lsp_types::request::WorkspaceSymbolRequest;
+use lsp_types::CodeAction;
+use lsp_types::CodeActionKind;
+use lsp_types::CodeActionOptions;
+use lsp_types::CodeActionOrCommand;
+use lsp_types::CodeActionParams;
+use lsp_types::CodeActionProviderCapability;
+use lsp_types::CodeActionResponse;
 use lsp_types::CompletionItem;
 use lsp_types::CompletionItemKind;
 use lsp_types::CompletionOptions;
@@ -55,6 +71,10 @@ use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DidSaveTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
+use lsp_types::DocumentSymbolParams;
+use lsp_types::DocumentSymbolResponse;
 use lsp_types::Documentation;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
@@ -64,6 +84,7 @@ use lsp_types::HoverParams;
 use lsp_types::HoverProviderCapability;
 use lsp_types::InitializeParams;
 use lsp_types::LanguageString;
+use lsp_types::Location;
 use lsp_types::LocationLink;
 use lsp_types::LogMessageParams;
 use lsp_types::MarkedString;
@@ -74,13 +95,24 @@ use lsp_types::OneOf;
 use lsp_types::Position;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
+use lsp_types::ReferenceParams;
+use lsp_types::RenameParams;
 use lsp_types::ServerCapabilities;
+use lsp_types::SignatureHelp;
+use lsp_types::SignatureHelpOptions;
+use lsp_types::SignatureHelpParams;
+use lsp_types::SymbolInformation;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextDocumentSyncOptions;
+use lsp_types::TextDocumentSyncSaveOptions;
 use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkDoneProgressOptions;
+use lsp_types::WorkspaceEdit;
 use lsp_types::WorkspaceFolder;
+use lsp_types::WorkspaceSymbolParams;
+use lsp_types::WorkspaceSymbolResponse;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -90,23 +122,34 @@ use starlark::codemap::ResolvedSpan;
 use starlark::codemap::Span;
 use starlark::docs::markdown::render_doc_item_no_link;
 use starlark::docs::markdown::render_doc_param;
+use starlark::docs::DocFunction;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::syntax::AstModule;
 use starlark_syntax::codemap::ResolvedPos;
+use starlark_syntax::lexer::lex_exactly_one_identifier;
 use starlark_syntax::syntax::ast::AstPayload;
 use starlark_syntax::syntax::ast::LoadArgP;
 use starlark_syntax::syntax::module::AstModuleFields;
 
+use crate::checks::check_module;
+use crate::code_actions::QuickFix;
 use crate::completion::StringCompletionResult;
 use crate::completion::StringCompletionType;
 use crate::definition::Definition;
 use crate::definition::DottedDefinition;
 use crate::definition::IdentifierDefinition;
 use crate::definition::LspModule;
+use crate::document_symbols::find_document_symbols;
+use crate::exported::AstModuleExportedSymbols;
 use crate::inspect::AstModuleInspect;
 use crate::inspect::AutocompleteType;
+use crate::references::Reference;
+use crate::references::ReferenceKind;
+use crate::references::ReferenceTarget;
+use crate::signature::signature_information;
 use crate::symbols::find_symbols_at_location;
 
 /// The request to get the file contents for a starlark: URI
@@ -276,6 +319,20 @@ pub struct LspEvalResult {
     pub ast: Option<AstModule>,
 }
 
+/// Static checks that the server runs over a file once it has been parsed, in addition to
+/// the diagnostics from [`LspContext::parse_file_with_contents`].
+#[derive(Default, Clone)]
+pub struct LspChecks {
+    /// Whether to run the linter.
+    pub lint: bool,
+    /// The names of all the globals available to the file. If provided, the linter also
+    /// reports uses of undefined names.
+    pub lint_globals: Option<HashSet<String>>,
+    /// If provided, typecheck the file against these globals. Uses of names that are not
+    /// in the globals are not reported.
+    pub typecheck_globals: Option<Globals>,
+}
+
 /// Settings that the LspContext can provide to change what capabilities the server enables
 /// or disables.
 #[derive(Dupe, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -349,6 +406,24 @@ pub trait LspContext {
     /// Get the preloaded environment for a particular file.
     fn get_environment(&self, uri: &LspUrl) -> DocModule;
 
+    /// Get the static checks to run over a file, whose results are published as diagnostics.
+    ///
+    /// By default no checks are run.
+    fn get_checks(&self, uri: &LspUrl) -> LspChecks {
+        let _unused = uri;
+        LspChecks::default()
+    }
+
+    /// Get the starlark files in the workspace that should be searched when looking for
+    /// references to a symbol across `load()` statements.
+    ///
+    /// Files that are open in the editor are always searched, so by default no
+    /// additional files are returned.
+    fn get_workspace_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        let _unused = workspace_root;
+        Ok(Vec::new())
+    }
+
     /// Get the LSPUrl for a global symbol if possible.
     ///
     /// The current file is provided in case different files have different global symbols
@@ -382,6 +457,17 @@ enum ResolveLoadError {
     WrongScheme(String, LspUrl),
 }
 
+/// Errors when renaming a symbol.
+#[derive(thiserror::Error, Debug)]
+enum RenameError {
+    #[error("`{}` is not a valid identifier", .0)]
+    InvalidName(String),
+    #[error("No symbol that can be renamed was found at the given location")]
+    NoSymbol,
+    #[error("`{}` has changes that don't parse, so it can't be renamed in", .0)]
+    OutdatedFile(LspUrl),
+}
+
 /// Errors when loading contents of a starlark program.
 #[derive(thiserror::Error, Debug)]
 pub(crate) enum LoadContentsError {
@@ -390,12 +476,54 @@ pub(crate) enum LoadContentsError {
     WrongScheme(String, LspUrl),
 }
 
+/// The references to a symbol, as found by `Backend::collect_references`.
+#[derive(Default)]
+struct CollectedReferences {
+    /// The references, grouped by the file they were found in.
+    references: Vec<(LspUrl, Vec<Reference>)>,
+    /// Files which referenced the symbol when they last parsed, but whose current contents
+    /// don't parse, so their references can't be located.
+    outdated: Vec<LspUrl>,
+}
+
+impl CollectedReferences {
+    fn add(&mut self, file: LspUrl, references: Vec<Reference>, outdated: bool) {
+        if references.is_empty() {
+            return;
+        }
+        if outdated {
+            self.outdated.push(file);
+        } else {
+            self.references.push((file, references));
+        }
+    }
+}
+
+/// A file which is not open, as parsed from disk.
+struct DiskParse {
+    /// When the file was last modified when it was parsed, to notice changes made outside of the
+    /// editor. `None` for files which are not on disk.
+    modified: Option<SystemTime>,
+    /// The parsed file, or `None` if it could not be found or parsed.
+    module: Option<Arc<LspModule>>,
+}
+
 pub(crate) struct Backend<T: LspContext> {
     connection: Connection,
     pub(crate) context: T,
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     pub(crate) last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// Files whose current contents failed to parse, so their entry in `last_valid_parse`
+    /// does not match what the client has.
+    outdated_parse: RwLock<HashSet<LspUrl>>,
+    /// Files which are not open, parsed from disk when they were loaded or searched, so that
+    /// requests searching the workspace don't parse every file again. Entries are evicted when
+    /// the client reports changing or saving the file, or when it was modified since.
+    disk_parses: RwLock<HashMap<LspUrl, DiskParse>>,
+    /// The files `LspContext::get_workspace_files` reported for each workspace root. Evicted
+    /// whenever a file is opened or saved, which is how most files are created.
+    workspace_files: RwLock<HashMap<Option<PathBuf>, Vec<LspUrl>>>,
 }
 
 /// The logic implementations of stuff
@@ -409,10 +537,31 @@ impl<T: LspContext> Backend<T> {
             })
         });
         ServerCapabilities {
-            text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+            text_document_sync: Some(TextDocumentSyncCapability::Options(
+                TextDocumentSyncOptions {
+                    open_close: Some(true),
+                    change: Some(TextDocumentSyncKind::FULL),
+                    save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                    ..TextDocumentSyncOptions::default()
+                },
+            )),
             definition_provider,
             completion_provider: Some(CompletionOptions::default()),
             hover_provider: Some(HoverProviderCapability::Simple(true)),
+            references_provider: Some(OneOf::Left(true)),
+            rename_provider: Some(OneOf::Left(true)),
+            document_symbol_provider: Some(OneOf::Left(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                code_action_kinds: Some(vec![CodeActionKind::QUICKFIX]),
+                ..CodeActionOptions::default()
+            })),
+            workspace_symbol_provider: Some(OneOf::Left(true)),
+            signature_help_provider: Some(SignatureHelpOptions {
+                trigger_characters: Some(vec!["(".to_owned(), ",".to_owned()]),
+                retrigger_characters: None,
+                work_done_progress_options: WorkDoneProgressOptions::default(),
+            }),
+            document_formatting_provider: Some(OneOf::Left(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -422,33 +571,73 @@ impl<T: LspContext> Backend<T> {
         last_valid_parse.get(uri).duped()
     }
 
+    /// Like `get_ast`, but only if the parse matches what the client currently has, so
+    /// that positions in it can be sent back as edits.
+    fn get_current_ast(&self, uri: &LspUrl) -> Option<Arc<LspModule>> {
+        if self.is_outdated(uri) {
+            return None;
+        }
+        self.get_ast(uri)
+    }
+
+    fn is_outdated(&self, uri: &LspUrl) -> bool {
+        self.outdated_parse.read().unwrap().contains(uri)
+    }
+
     pub(crate) fn get_ast_or_load_from_disk(
         &self,
         uri: &LspUrl,
     ) -> anyhow::Result<Option<Arc<LspModule>>> {
-        let module = match self.get_ast(uri) {
-            Some(result) => Some(result),
-            None => self
-                .context
-                .parse_file(uri)?
-                .and_then(|eval_result| eval_result.ast.map(|ast| Arc::new(LspModule::new(ast)))),
+        if let Some(module) = self.get_ast(uri) {
+            return Ok(Some(module));
+        }
+
+        let modified = match uri {
+            LspUrl::File(path) => std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            _ => None,
         };
+        if let Some(parse) = self.disk_parses.read().unwrap().get(uri) {
+            if parse.modified == modified {
+                return Ok(parse.module.dupe());
+            }
+        }
+
+        let module = self
+            .context
+            .parse_file(uri)?
+            .and_then(|eval_result| eval_result.ast.map(|ast| Arc::new(LspModule::new(ast))));
+        self.disk_parses.write().unwrap().insert(
+            uri.clone(),
+            DiskParse {
+                modified,
+                module: module.dupe(),
+            },
+        );
         Ok(module)
     }
 
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
         let lsp_url = uri.clone().try_into()?;
+        self.disk_parses.write().unwrap().remove(&lsp_url);
         let eval_result = self.context.parse_file_with_contents(&lsp_url, text);
+        let mut diagnostics = eval_result.diagnostics;
         if let Some(ast) = eval_result.ast {
+            diagnostics.extend(check_module(&ast, &self.context.get_checks(&lsp_url)));
             let module = Arc::new(LspModule::new(ast));
+            self.outdated_parse.write().unwrap().remove(&lsp_url);
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
             last_valid_parse.insert(lsp_url, module);
+        } else {
+            self.outdated_parse.write().unwrap().insert(lsp_url);
         }
-        self.publish_diagnostics(uri, eval_result.diagnostics, version);
+        self.publish_diagnostics(uri, diagnostics, version);
         Ok(())
     }
 
     fn did_open(&self, params: DidOpenTextDocumentParams) -> anyhow::Result<()> {
+        self.workspace_files.write().unwrap().clear();
         self.validate(
             params.text_document.uri,
             Some(params.text_document.version as i64),
@@ -466,10 +655,19 @@ impl<T: LspContext> Backend<T> {
         )
     }
 
+    fn did_save(&self, params: DidSaveTextDocumentParams) -> anyhow::Result<()> {
+        let uri = params.text_document.uri.try_into()?;
+        self.disk_parses.write().unwrap().remove(&uri);
+        self.workspace_files.write().unwrap().clear();
+        Ok(())
+    }
+
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
+            let uri = params.text_document.uri.clone().try_into()?;
+            self.outdated_parse.write().unwrap().remove(&uri);
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            last_valid_parse.remove(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
@@ -511,6 +709,69 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.hover_info(params, initialize_params)));
     }
 
+    /// Finds all references to the symbol at the current cursor, including in other
+    /// files that load it.
+    fn references(
+        &self,
+        id: RequestId,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.find_references(params, initialize_params),
+        ));
+    }
+
+    /// Renames the symbol at the current cursor, including in other files that load it.
+    fn rename(&self, id: RequestId, params: RenameParams, initialize_params: &InitializeParams) {
+        self.send_response(new_response(
+            id,
+            self.rename_symbol(params, initialize_params),
+        ));
+    }
+
+    /// Offers an outline of the symbols defined in the current file.
+    fn document_symbols(&self, id: RequestId, params: DocumentSymbolParams) {
+        self.send_response(new_response(id, self.document_symbol_outline(params)));
+    }
+
+    /// Finds the symbols exported by files in the workspace whose names match a query.
+    fn workspace_symbols(
+        &self,
+        id: RequestId,
+        params: WorkspaceSymbolParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.find_workspace_symbols(params, initialize_params),
+        ));
+    }
+
+    /// Offers the signature of the function being called at the current cursor.
+    fn signature_help(
+        &self,
+        id: RequestId,
+        params: SignatureHelpParams,
+        initialize_params: &InitializeParams,
+    ) {
+        self.send_response(new_response(
+            id,
+            self.signature_help_info(params, initialize_params),
+        ));
+    }
+
+    /// Offers fixes for the diagnostics at the current cursor.
+    fn code_action(&self, id: RequestId, params: CodeActionParams) {
+        self.send_response(new_response(id, self.quick_fixes(params)));
+    }
+
+    /// Reformat a whole file.
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        self.send_response(new_response(id, self.format_document(params)));
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -725,6 +986,371 @@ impl<T: LspContext> Backend<T> {
         Ok(GotoDefinitionResponse::Link(response))
     }
 
+    /// Find every reference to the symbol at the given location, grouped by the file
+    /// they were found in.
+    ///
+    /// Symbols that are exported from a module are searched for in all of the files
+    /// that `load()` them, whether the location was in the defining module or in
+    /// a module that loads the symbol.
+    ///
+    /// Files whose current contents don't parse are left out, as their last valid parse
+    /// doesn't match what the client has.
+    fn collect_references(
+        &self,
+        uri: &LspUrl,
+        line: u32,
+        character: u32,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<CollectedReferences> {
+        let mut result = CollectedReferences::default();
+        let Some(module) = self.get_current_ast(uri) else {
+            return Ok(result);
+        };
+        let (definition_uri, name) = match module.find_reference_target(line, character) {
+            None => return Ok(result),
+            Some(ReferenceTarget::Local { binding }) => {
+                result.add(uri.clone(), module.find_local_references(binding), false);
+                return Ok(result);
+            }
+            Some(ReferenceTarget::Exported { name }) => (uri.clone(), name),
+            Some(ReferenceTarget::Loaded { path, name }) => {
+                (self.resolve_load_path(&path, uri, workspace_root)?, name)
+            }
+        };
+
+        if let Some(module) = self.get_ast_or_load_from_disk(&definition_uri)? {
+            result.add(
+                definition_uri.clone(),
+                module.find_exported_references(&name),
+                self.is_outdated(&definition_uri),
+            );
+        }
+
+        for file in self.known_files(workspace_root)? {
+            if file == definition_uri {
+                continue;
+            }
+            // Files that can't be loaded or parsed can't reference anything.
+            let Ok(Some(module)) = self.get_ast_or_load_from_disk(&file) else {
+                continue;
+            };
+            let references = module.find_loaded_references(&name, |path| {
+                matches!(
+                    self.resolve_load_path(path, &file, workspace_root),
+                    Ok(load_uri) if load_uri == definition_uri
+                )
+            });
+            let outdated = self.is_outdated(&file);
+            result.add(file, references, outdated);
+        }
+        Ok(result)
+    }
+
+    /// All of the files that are open, plus those the context reports as being in the
+    /// workspace, without duplicates.
+    fn known_files(&self, workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        let open_files: Vec<LspUrl> = self
+            .last_valid_parse
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let cached_files = self
+            .workspace_files
+            .read()
+            .unwrap()
+            .get(&workspace_root.map(Path::to_owned))
+            .cloned();
+        let workspace_files = match cached_files {
+            Some(files) => files,
+            None => {
+                let files = self.context.get_workspace_files(workspace_root)?;
+                self.workspace_files
+                    .write()
+                    .unwrap()
+                    .insert(workspace_root.map(Path::to_owned), files.clone());
+                files
+            }
+        };
+        let mut seen = HashSet::new();
+        Ok(open_files
+            .into_iter()
+            .chain(workspace_files)
+            .filter(|file| seen.insert(file.clone()))
+            .collect())
+    }
+
+    fn find_references(
+        &self,
+        params: ReferenceParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<Vec<Location>> {
+        let uri = params.text_document_position.text_document.uri.try_into()?;
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+        let include_declaration = params.context.include_declaration;
+
+        // References in files that don't currently parse can't be located, so are omitted.
+        let references =
+            self.collect_references(&uri, line, character, workspace_root.as_deref())?;
+        let mut locations = Vec::new();
+        for (file, references) in references.references {
+            let url = Url::try_from(&file)?;
+            locations.extend(
+                references
+                    .into_iter()
+                    .filter(|reference| include_declaration || !reference.declaration)
+                    .map(|reference| Location::new(url.clone(), reference.span.into())),
+            );
+        }
+        Ok(locations)
+    }
+
+    fn rename_symbol(
+        &self,
+        params: RenameParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<WorkspaceEdit> {
+        let uri = params.text_document_position.text_document.uri.try_into()?;
+        let line = params.text_document_position.position.line;
+        let character = params.text_document_position.position.character;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        let new_name = params.new_name;
+        if lex_exactly_one_identifier(&new_name).as_deref() != Some(new_name.as_str()) {
+            return Err(RenameError::InvalidName(new_name).into());
+        }
+
+        let references =
+            self.collect_references(&uri, line, character, workspace_root.as_deref())?;
+        // Renaming in only some of the files would leave the others broken.
+        if let Some(file) = references.outdated.into_iter().next() {
+            return Err(RenameError::OutdatedFile(file).into());
+        }
+        if references.references.is_empty() {
+            return Err(RenameError::NoSymbol.into());
+        }
+
+        let mut changes = HashMap::new();
+        for (file, references) in references.references {
+            let edits = references
+                .into_iter()
+                .map(|reference| {
+                    TextEdit::new(
+                        reference.span.into(),
+                        match reference.kind {
+                            ReferenceKind::Identifier => new_name.clone(),
+                            ReferenceKind::LoadString => format!("\"{new_name}\""),
+                        },
+                    )
+                })
+                .collect();
+            changes.insert(Url::try_from(&file)?, edits);
+        }
+        Ok(WorkspaceEdit::new(changes))
+    }
+
+    fn quick_fixes(&self, params: CodeActionParams) -> anyhow::Result<CodeActionResponse> {
+        let url = params.text_document.uri;
+        let Some(module) = self.get_current_ast(&url.clone().try_into()?) else {
+            return Ok(Vec::new());
+        };
+        Ok(params
+            .context
+            .diagnostics
+            .into_iter()
+            .filter_map(|diagnostic| {
+                let QuickFix { title, edit } = module.quick_fix(&diagnostic)?;
+                Some(CodeActionOrCommand::CodeAction(CodeAction {
+                    title,
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic]),
+                    edit: Some(WorkspaceEdit::new(HashMap::from([(
+                        url.clone(),
+                        vec![edit],
+                    )]))),
+                    is_preferred: Some(true),
+                    ..CodeAction::default()
+                }))
+            })
+            .collect())
+    }
+
+    /// Produce an edit replacing the whole file with its formatted contents. Files that do
+    /// not currently parse are left alone.
+    fn format_document(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri.try_into()?;
+        let Some(module) = self.get_current_ast(&uri) else {
+            return Ok(None);
+        };
+        let codemap = module.ast.codemap();
+        let formatted = module.ast.format();
+        if formatted == codemap.source() {
+            return Ok(Some(Vec::new()));
+        }
+        let range = codemap.resolve_span(codemap.full_span()).into();
+        Ok(Some(vec![TextEdit::new(range, formatted)]))
+    }
+
+    fn document_symbol_outline(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> anyhow::Result<DocumentSymbolResponse> {
+        let uri = params.text_document.uri.try_into()?;
+        let symbols = match self.get_ast(&uri) {
+            Some(document) => {
+                find_document_symbols(document.ast.codemap(), document.ast.statement())
+            }
+            None => Vec::new(),
+        };
+        Ok(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn find_workspace_symbols(
+        &self,
+        params: WorkspaceSymbolParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<WorkspaceSymbolResponse> {
+        let query = params.query.to_lowercase();
+        let workspace_roots: Vec<Option<PathBuf>> = match &initialize_params.workspace_folders {
+            Some(folders) if !folders.is_empty() => folders
+                .iter()
+                .map(|folder| folder.uri.to_file_path().ok())
+                .collect(),
+            _ => vec![None],
+        };
+
+        let mut seen = HashSet::new();
+        let mut symbols = Vec::new();
+        for workspace_root in workspace_roots {
+            for file in self.known_files(workspace_root.as_deref())? {
+                if !seen.insert(file.clone()) {
+                    continue;
+                }
+                // Files that can't be loaded or parsed don't define anything.
+                let Ok(Some(module)) = self.get_ast_or_load_from_disk(&file) else {
+                    continue;
+                };
+                let url = Url::try_from(&file)?;
+                symbols.extend(
+                    module
+                        .ast
+                        .exported_symbols()
+                        .into_iter()
+                        .filter(|symbol| symbol.name.to_lowercase().contains(&query))
+                        .map(|symbol| {
+                            #[allow(deprecated)]
+                            SymbolInformation {
+                                name: symbol.name,
+                                kind: symbol.kind.into(),
+                                tags: None,
+                                deprecated: None,
+                                location: Location::new(
+                                    url.clone(),
+                                    symbol.span.resolve_span().into(),
+                                ),
+                                container_name: None,
+                            }
+                        }),
+                );
+            }
+        }
+        Ok(WorkspaceSymbolResponse::Flat(symbols))
+    }
+
+    fn signature_help_info(
+        &self,
+        params: SignatureHelpParams,
+        initialize_params: &InitializeParams,
+    ) -> anyhow::Result<Option<SignatureHelp>> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .try_into()?;
+        let line = params.text_document_position_params.position.line;
+        let character = params.text_document_position_params.position.character;
+        let workspace_root =
+            Self::get_workspace_root(initialize_params.workspace_folders.as_ref(), &uri);
+
+        let Some(document) = self.get_current_ast(&uri) else {
+            return Ok(None);
+        };
+        let Some(call) = document.find_call_at_location(line, character) else {
+            return Ok(None);
+        };
+        let function = match document.find_definition_at_location(
+            call.function_span.begin.line as u32,
+            call.function_span.begin.column as u32,
+        ) {
+            Definition::Identifier(identifier) => self.get_function_for_identifier_definition(
+                identifier,
+                &document,
+                &uri,
+                workspace_root.as_deref(),
+            )?,
+            // Members of values can't be resolved to a function yet.
+            Definition::Dotted(_) => None,
+        };
+
+        Ok(function.map(|(name, function)| {
+            let signature = signature_information(&name, &function, call.active_argument.as_ref());
+            SignatureHelp {
+                active_signature: Some(0),
+                active_parameter: signature.active_parameter,
+                signatures: vec![signature],
+            }
+        }))
+    }
+
+    /// Find the function that an identifier refers to, along with its name.
+    fn get_function_for_identifier_definition(
+        &self,
+        identifier_definition: IdentifierDefinition,
+        document: &LspModule,
+        document_uri: &LspUrl,
+        workspace_root: Option<&Path>,
+    ) -> anyhow::Result<Option<(String, DocFunction)>> {
+        Ok(match identifier_definition {
+            IdentifierDefinition::Location {
+                destination, name, ..
+            } => document
+                .find_def_signature(destination)
+                .map(|function| (name, function)),
+            IdentifierDefinition::LoadedLocation { path, name, .. } => {
+                let load_uri = self.resolve_load_path(&path, document_uri, workspace_root)?;
+                self.get_ast_or_load_from_disk(&load_uri)?
+                    .and_then(|ast| {
+                        let symbol = ast.find_exported_symbol(&name)?;
+                        ast.find_def_signature(symbol.span.resolve_span())
+                    })
+                    .map(|function| (name, function))
+            }
+            IdentifierDefinition::Unresolved { name, .. } => {
+                // Maybe it's a global symbol.
+                self.context
+                    .get_environment(document_uri)
+                    .members
+                    .into_iter()
+                    .find(|symbol| symbol.0 == name)
+                    .and_then(|symbol| match symbol.1 {
+                        DocItem::Member(DocMember::Function(function)) => Some((name, function)),
+                        _ => None,
+                    })
+            }
+            IdentifierDefinition::LoadPath { .. }
+            | IdentifierDefinition::StringLiteral { .. }
+            | IdentifierDefinition::NotFound => None,
+        })
+    }
+
     fn completion_options(
         &self,
         params: CompletionParams,
@@ -1228,6 +1854,20 @@ impl<T: LspContext> Backend<T> {
                         self.completion(req.id, params, &initialize_params);
                     } else if let Some(params) = as_request::<HoverRequest>(&req) {
                         self.hover(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<References>(&req) {
+                        self.references(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<Rename>(&req) {
+                        self.rename(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<CodeActionRequest>(&req) {
+                        self.code_action(req.id, params);
+                    } else if let Some(params) = as_request::<Formatting>(&req) {
+                        self.formatting(req.id, params);
+                    } else if let Some(params) = as_request::<DocumentSymbolRequest>(&req) {
+                        self.document_symbols(req.id, params);
+                    } else if let Some(params) = as_request::<WorkspaceSymbolRequest>(&req) {
+                        self.workspace_symbols(req.id, params, &initialize_params);
+                    } else if let Some(params) = as_request::<SignatureHelpRequest>(&req) {
+                        self.signature_help(req.id, params, &initialize_params);
                     } else if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
@@ -1238,6 +1878,8 @@ impl<T: LspContext> Backend<T> {
                         self.did_open(params)?;
                     } else if let Some(params) = as_notification::<DidChangeTextDocument>(&x) {
                         self.did_change(params)?;
+                    } else if let Some(params) = as_notification::<DidSaveTextDocument>(&x) {
+                        self.did_save(params)?;
                     } else if let Some(params) = as_notification::<DidCloseTextDocument>(&x) {
                         self.did_close(params)?;
                     }
@@ -1291,6 +1933,9 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        outdated_parse: RwLock::default(),
+        disk_parses: RwLock::default(),
+        workspace_files: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -1368,21 +2013,48 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::path::Path;
     use std::path::PathBuf;
 
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::request::CodeActionRequest;
+    use lsp_types::request::Formatting;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::request::References;
+    use lsp_types::request::Rename;
+    use lsp_types::request::SignatureHelpRequest;
+    use lsp_types::request::WorkspaceSymbolRequest;
+    use lsp_types::CodeActionContext;
+    use lsp_types::CodeActionOrCommand;
+    use lsp_types::CodeActionParams;
+    use lsp_types::CodeActionResponse;
+    use lsp_types::DiagnosticSeverity;
+    use lsp_types::DocumentFormattingParams;
+    use lsp_types::FormattingOptions;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
+    use lsp_types::Location;
     use lsp_types::LocationLink;
+    use lsp_types::NumberOrString;
     use lsp_types::Position;
     use lsp_types::Range;
+    use lsp_types::ReferenceContext;
+    use lsp_types::ReferenceParams;
+    use lsp_types::RenameParams;
+    use lsp_types::SignatureHelp;
+    use lsp_types::SignatureHelpParams;
+    use lsp_types::SymbolInformation;
+    use lsp_types::SymbolKind;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
+    use lsp_types::TextEdit;
     use lsp_types::Url;
+    use lsp_types::WorkspaceEdit;
+    use lsp_types::WorkspaceSymbolParams;
+    use lsp_types::WorkspaceSymbolResponse;
     use starlark::codemap::ResolvedSpan;
     use starlark::wasm::is_wasm;
     use textwrap::dedent;
@@ -1669,7 +2341,7 @@ mod tests {
     }
 
     #[test]
-    fn passes_cwd_for_relative_loads() -> anyhow::Result<()> {
+    fn reparses_closed_loaded_file_when_saved() -> anyhow::Result<()> {
         if is_wasm() {
             return Ok(());
         }
@@ -1679,21 +2351,18 @@ mod tests {
 
         let foo_contents = dedent(
             r#"
-            load("bar.star", "baz")
+            load("{load}", "baz")
             <baz_click><baz>b</baz>az</baz_click>()
             "#,
         )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
         .trim()
         .to_owned();
         let bar_contents = "def <baz>baz</baz>():\n    pass";
+        let saved_bar_contents = "x = 1\n\ndef <baz>baz</baz>():\n    pass";
         let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
         let bar = FixtureWithRanges::from_fixture(bar_uri.path(), bar_contents)?;
-
-        let expected_location = expected_location_link_from_spans(
-            bar_uri.clone(),
-            foo.resolved_span("baz_click"),
-            bar.resolved_span("baz"),
-        );
+        let saved_bar = FixtureWithRanges::from_fixture(bar_uri.path(), saved_bar_contents)?;
 
         let mut server = TestServer::new()?;
         server.open_file(foo_uri.clone(), foo.program())?;
@@ -1701,40 +2370,111 @@ mod tests {
 
         let goto_definition = goto_definition_request(
             &mut server,
-            foo_uri,
+            foo_uri.clone(),
             foo.begin_line("baz"),
             foo.begin_column("baz"),
         );
-
         let request_id = server.send_request(goto_definition)?;
         let location = goto_definition_response_location(&mut server, request_id)?;
+        assert_eq!(
+            expected_location_link_from_spans(
+                bar_uri.clone(),
+                foo.resolved_span("baz_click"),
+                bar.resolved_span("baz"),
+            ),
+            location
+        );
 
-        assert_eq!(expected_location, location);
+        // The parse of `bar.star` is kept until the client says it was saved.
+        server.set_file_contents(&bar_uri, saved_bar.program())?;
+        server.save_file(bar_uri.clone())?;
+
+        let goto_definition = goto_definition_request(
+            &mut server,
+            foo_uri,
+            foo.begin_line("baz"),
+            foo.begin_column("baz"),
+        );
+        let request_id = server.send_request(goto_definition)?;
+        let location = goto_definition_response_location(&mut server, request_id)?;
+        assert_eq!(
+            expected_location_link_from_spans(
+                bar_uri,
+                foo.resolved_span("baz_click"),
+                saved_bar.resolved_span("baz"),
+            ),
+            location
+        );
         Ok(())
     }
 
     #[test]
-    fn does_not_jump_to_definition_if_invalid_file() -> anyhow::Result<()> {
+    fn passes_cwd_for_relative_loads() -> anyhow::Result<()> {
         if is_wasm() {
             return Ok(());
         }
 
         let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
 
         let foo_contents = dedent(
             r#"
-            load("{load}", <baz_loc>"baz"</baz_loc>)
+            load("bar.star", "baz")
             <baz_click><baz>b</baz>az</baz_click>()
             "#,
         )
-        .replace("{load}", foo_uri.path())
         .trim()
         .to_owned();
+        let bar_contents = "def <baz>baz</baz>():\n    pass";
         let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), bar_contents)?;
+
         let expected_location = expected_location_link_from_spans(
-            foo_uri.clone(),
+            bar_uri.clone(),
             foo.resolved_span("baz_click"),
-            foo.resolved_span("baz_loc"),
+            bar.resolved_span("baz"),
+        );
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar.program())?;
+
+        let goto_definition = goto_definition_request(
+            &mut server,
+            foo_uri,
+            foo.begin_line("baz"),
+            foo.begin_column("baz"),
+        );
+
+        let request_id = server.send_request(goto_definition)?;
+        let location = goto_definition_response_location(&mut server, request_id)?;
+
+        assert_eq!(expected_location, location);
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_jump_to_definition_if_invalid_file() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <baz_loc>"baz"</baz_loc>)
+            <baz_click><baz>b</baz>az</baz_click>()
+            "#,
+        )
+        .replace("{load}", foo_uri.path())
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let expected_location = expected_location_link_from_spans(
+            foo_uri.clone(),
+            foo.resolved_span("baz_click"),
+            foo.resolved_span("baz_loc"),
         );
 
         let mut server = TestServer::new()?;
@@ -2508,4 +3248,584 @@ mod tests {
         }
         Ok(())
     }
+
+    fn references_request(
+        server: &mut TestServer,
+        uri: Url,
+        line: u32,
+        character: u32,
+        include_declaration: bool,
+    ) -> Request {
+        server.new_request::<References>(ReferenceParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration,
+            },
+        })
+    }
+
+    fn rename_request(
+        server: &mut TestServer,
+        uri: Url,
+        line: u32,
+        character: u32,
+        new_name: &str,
+    ) -> Request {
+        server.new_request::<Rename>(RenameParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position: Position { line, character },
+            },
+            new_name: new_name.to_owned(),
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    fn sorted_locations(mut locations: Vec<Location>) -> Vec<Location> {
+        locations.sort_by_key(|l| (l.uri.to_string(), l.range.start));
+        locations
+    }
+
+    #[test]
+    fn finds_references_across_loads() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+        let baz_uri = temp_file_uri("baz.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <load>"baz"</load>)
+            <use1>b<click>a</click>z</use1>()
+            x = <use2>baz</use2>
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <dest>baz</dest>():
+                pass
+            def _other(baz):
+                return baz
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let baz_contents = dedent(
+            r#"
+            load("{load}", other = <load>"baz"</load>)
+            other()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+        let baz = FixtureWithRanges::from_fixture(baz_uri.path(), &baz_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar.program())?;
+        server.set_file_contents(&baz_uri, baz.program())?;
+
+        let expected = sorted_locations(vec![
+            Location::new(bar_uri.clone(), bar.resolved_span("dest").into()),
+            Location::new(foo_uri.clone(), foo.resolved_span("load").into()),
+            Location::new(foo_uri.clone(), foo.resolved_span("use1").into()),
+            Location::new(foo_uri.clone(), foo.resolved_span("use2").into()),
+            Location::new(baz_uri.clone(), baz.resolved_span("load").into()),
+        ]);
+
+        let request = references_request(
+            &mut server,
+            foo_uri.clone(),
+            foo.begin_line("click"),
+            foo.begin_column("click"),
+            true,
+        );
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+        assert_eq!(expected, sorted_locations(response));
+
+        let request = references_request(
+            &mut server,
+            foo_uri,
+            foo.begin_line("click"),
+            foo.begin_column("click"),
+            false,
+        );
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+        assert_eq!(
+            expected
+                .into_iter()
+                .filter(|l| l.uri != bar_uri)
+                .collect::<Vec<_>>(),
+            sorted_locations(response)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn renames_across_loads() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <load>"baz"</load>)
+            <use>baz</use>()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <dest>b<click>a</click>z</dest>():
+                pass
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+
+        let request = rename_request(
+            &mut server,
+            bar_uri.clone(),
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            "quz",
+        );
+        let request_id = server.send_request(request)?;
+        let mut response = server.get_response::<WorkspaceEdit>(request_id)?;
+        let mut changes = response.changes.take().unwrap_or_default();
+
+        assert_eq!(
+            Some(vec![TextEdit::new(
+                bar.resolved_span("dest").into(),
+                "quz".to_owned()
+            )]),
+            changes.remove(&bar_uri)
+        );
+        let mut foo_edits = changes.remove(&foo_uri).unwrap_or_default();
+        foo_edits.sort_by_key(|edit| edit.range.start);
+        assert_eq!(
+            vec![
+                TextEdit::new(foo.resolved_span("load").into(), "\"quz\"".to_owned()),
+                TextEdit::new(foo.resolved_span("use").into(), "quz".to_owned()),
+            ],
+            foo_edits
+        );
+        assert!(changes.is_empty());
+
+        let request = rename_request(
+            &mut server,
+            bar_uri,
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            "not valid",
+        );
+        let request_id = server.send_request(request)?;
+        assert!(server.get_response::<WorkspaceEdit>(request_id).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_rename_in_outdated_files() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", "baz")
+            baz()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <dest>b<click>a</click>z</dest>():
+                pass
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo_contents.clone())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+        // The last valid parse of foo.star no longer matches what the client has.
+        server.change_file(foo_uri.clone(), format!("{foo_contents}\nx = (\n"))?;
+
+        let request = rename_request(
+            &mut server,
+            bar_uri.clone(),
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            "quz",
+        );
+        let request_id = server.send_request(request)?;
+        assert!(server.get_response::<WorkspaceEdit>(request_id).is_err());
+
+        let request = references_request(
+            &mut server,
+            bar_uri.clone(),
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            true,
+        );
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+        assert_eq!(
+            vec![Location::new(bar_uri, bar.resolved_span("dest").into())],
+            response
+        );
+
+        // Nothing can be found in the outdated file itself.
+        let request = references_request(&mut server, foo_uri, 1, 0, true);
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+        assert_eq!(Vec::<Location>::new(), response);
+
+        Ok(())
+    }
+
+    #[test]
+    fn renames_in_unopened_loaders() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", <load>"baz"</load>)
+            <use>baz</use>()
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let bar_contents = dedent(
+            r#"
+            def <dest>b<click>a</click>z</dest>():
+                pass
+            "#,
+        )
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar = FixtureWithRanges::from_fixture(bar_uri.path(), &bar_contents)?;
+
+        // Only the definition is open, so the loader is only found through the workspace files.
+        let mut server = TestServer::new()?;
+        server.set_file_contents(&foo_uri, foo.program())?;
+        server.open_file(bar_uri.clone(), bar.program())?;
+
+        let request = references_request(
+            &mut server,
+            bar_uri.clone(),
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            false,
+        );
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Vec<Location>>(request_id)?;
+        assert_eq!(
+            sorted_locations(vec![
+                Location::new(foo_uri.clone(), foo.resolved_span("load").into()),
+                Location::new(foo_uri.clone(), foo.resolved_span("use").into()),
+            ]),
+            sorted_locations(response)
+        );
+
+        let request = rename_request(
+            &mut server,
+            bar_uri.clone(),
+            bar.begin_line("click"),
+            bar.begin_column("click"),
+            "quz",
+        );
+        let request_id = server.send_request(request)?;
+        let mut response = server.get_response::<WorkspaceEdit>(request_id)?;
+        let mut changes = response.changes.take().unwrap_or_default();
+
+        assert_eq!(
+            Some(vec![TextEdit::new(
+                bar.resolved_span("dest").into(),
+                "quz".to_owned()
+            )]),
+            changes.remove(&bar_uri)
+        );
+        let mut foo_edits = changes.remove(&foo_uri).unwrap_or_default();
+        foo_edits.sort_by_key(|edit| edit.range.start);
+        assert_eq!(
+            vec![
+                TextEdit::new(foo.resolved_span("load").into(), "\"quz\"".to_owned()),
+                TextEdit::new(foo.resolved_span("use").into(), "quz".to_owned()),
+            ],
+            foo_edits
+        );
+        assert!(changes.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_workspace_symbols() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo = FixtureWithRanges::from_fixture(
+            foo_uri.path(),
+            &dedent(
+                r#"
+                <foo>FOO_BAR</foo> = 1
+                other = 2
+                "#,
+            ),
+        )?;
+        let bar = FixtureWithRanges::from_fixture(
+            bar_uri.path(),
+            &dedent(
+                r#"
+                def <bar>bar_rule</bar>(name):
+                    pass
+                def _private_bar():
+                    pass
+                "#,
+            ),
+        )?;
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar.program())?;
+
+        let request = server.new_request::<WorkspaceSymbolRequest>(WorkspaceSymbolParams {
+            query: "bAr".to_owned(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<WorkspaceSymbolResponse>(request_id)?;
+        let WorkspaceSymbolResponse::Flat(mut symbols) = response else {
+            panic!("Expected flat symbols, got {:?}", response);
+        };
+        symbols.sort_by_key(|s| s.name.clone());
+
+        #[allow(deprecated)]
+        let expected = vec![
+            SymbolInformation {
+                name: "FOO_BAR".to_owned(),
+                kind: SymbolKind::CONSTANT,
+                tags: None,
+                deprecated: None,
+                location: Location::new(foo_uri, foo.resolved_span("foo").into()),
+                container_name: None,
+            },
+            SymbolInformation {
+                name: "bar_rule".to_owned(),
+                kind: SymbolKind::FUNCTION,
+                tags: None,
+                deprecated: None,
+                location: Location::new(bar_uri, bar.resolved_span("bar").into()),
+                container_name: None,
+            },
+        ];
+        assert_eq!(expected, symbols);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shows_signature_of_loaded_function() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let bar_uri = temp_file_uri("bar.star");
+
+        let foo_contents = dedent(
+            r#"
+            load("{load}", "rule")
+            rule("x", <click></click>)
+            "#,
+        )
+        .replace("{load}", &uri_to_load_string(&bar_uri))
+        .trim()
+        .to_owned();
+        let foo = FixtureWithRanges::from_fixture(foo_uri.path(), &foo_contents)?;
+        let bar_contents = dedent(
+            r#"
+            def rule(name, srcs = [], *, deps = []):
+                pass
+            "#,
+        );
+
+        let mut server = TestServer::new()?;
+        server.open_file(foo_uri.clone(), foo.program())?;
+        server.set_file_contents(&bar_uri, bar_contents)?;
+
+        let request = server.new_request::<SignatureHelpRequest>(SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: foo_uri },
+                position: Position {
+                    line: foo.begin_line("click"),
+                    character: foo.begin_column("click"),
+                },
+            },
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<Option<SignatureHelp>>(request_id)?;
+        let response = response.expect("Expected a signature");
+
+        assert_eq!(1, response.signatures.len());
+        assert_eq!("rule(name, srcs, *, deps)", response.signatures[0].label);
+        assert_eq!(Some(1), response.active_parameter);
+
+        Ok(())
+    }
+
+    #[test]
+    fn publishes_lints_with_quick_fixes() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let foo_contents = dedent(
+            r#"
+            load("bar.star", "x", "y")
+            print(y)
+            "#,
+        )
+        .trim_start()
+        .to_owned();
+
+        let mut server = TestServer::new()?;
+        let diagnostics = server.open_file_with_diagnostics(foo_uri.clone(), foo_contents)?;
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            Some(NumberOrString::String("unused-load".to_owned())),
+            diagnostics[0].code
+        );
+        assert_eq!(
+            Some(DiagnosticSeverity::INFORMATION),
+            diagnostics[0].severity
+        );
+
+        let request = server.new_request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier {
+                uri: foo_uri.clone(),
+            },
+            range: diagnostics[0].range,
+            context: CodeActionContext {
+                diagnostics: diagnostics.clone(),
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        let response = server.get_response::<CodeActionResponse>(request_id)?;
+
+        let [CodeActionOrCommand::CodeAction(action)] = response.as_slice() else {
+            panic!("Expected a single code action, got {:?}", response);
+        };
+        assert_eq!("Remove unused load of `x`", action.title);
+        let expected_edit = WorkspaceEdit::new(HashMap::from([(
+            foo_uri,
+            vec![TextEdit::new(
+                Range::new(Position::new(0, 17), Position::new(0, 22)),
+                String::new(),
+            )],
+        )]));
+        assert_eq!(Some(&expected_edit), action.edit.as_ref());
+
+        Ok(())
+    }
+
+    fn format_request(server: &mut TestServer, uri: &Url) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let request = server.new_request::<Formatting>(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: true,
+                ..FormattingOptions::default()
+            },
+            work_done_progress_params: Default::default(),
+        });
+        let request_id = server.send_request(request)?;
+        server.get_response::<Option<Vec<TextEdit>>>(request_id)
+    }
+
+    #[test]
+    fn formats_document() -> anyhow::Result<()> {
+        if is_wasm() {
+            return Ok(());
+        }
+
+        let foo_uri = temp_file_uri("foo.star");
+        let mut server = TestServer::new()?;
+        server.open_file(
+            foo_uri.clone(),
+            "x = {'a':1}\n\ndef f( a ):\n  return a\n".to_owned(),
+        )?;
+
+        let expected = TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(4, 0)),
+            "x = {\"a\": 1}\n\ndef f(a):\n    return a\n".to_owned(),
+        );
+        assert_eq!(Some(vec![expected]), format_request(&mut server, &foo_uri)?);
+
+        // Already formatted, so nothing to change.
+        server.change_file(foo_uri.clone(), "x = 1\n".to_owned())?;
+        assert_eq!(Some(Vec::new()), format_request(&mut server, &foo_uri)?);
+
+        // The last successful parse is out of date, so must not be used.
+        server.change_file(foo_uri.clone(), "x = (\n".to_owned())?;
+        assert_eq!(None, format_request(&mut server, &foo_uri)?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,403 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Parameter hints for the function call at the cursor, for "signature help" support.
+
+use lsp_types::Documentation;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
+use lsp_types::ParameterInformation;
+use lsp_types::ParameterLabel;
+use lsp_types::SignatureInformation;
+use starlark::codemap::CodeMap;
+use starlark::codemap::Pos;
+use starlark::codemap::ResolvedSpan;
+use starlark::codemap::Span;
+use starlark::docs::DocFunction;
+use starlark::docs::DocParam;
+use starlark::docs::DocString;
+use starlark::docs::FmtParam;
+use starlark::typing::Ty;
+use starlark_syntax::syntax::ast::ArgumentP;
+use starlark_syntax::syntax::ast::AstArgumentP;
+use starlark_syntax::syntax::ast::AstNoPayload;
+use starlark_syntax::syntax::ast::ExprP;
+use starlark_syntax::syntax::ast::StmtP;
+use starlark_syntax::syntax::module::AstModuleFields;
+use starlark_syntax::syntax::uniplate::Visit;
+
+use crate::definition::LspModule;
+use crate::docs::get_signature_for_def;
+
+/// The argument of a call that the cursor is in.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum ActiveArgument {
+    /// The n-th positional argument, counting from zero.
+    Positional(usize),
+    /// A named argument.
+    Named(String),
+}
+
+/// A function call that encloses the cursor. See [`LspModule::find_call_at_location`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct CallSite {
+    /// The span of the expression being called. For `foo.bar(x)`, this is `bar`.
+    pub(crate) function_span: ResolvedSpan,
+    /// The argument the cursor is in, if it can be determined.
+    pub(crate) active_argument: Option<ActiveArgument>,
+}
+
+impl LspModule {
+    /// Find the innermost function call whose parentheses enclose the given location.
+    ///
+    /// `line` and `col` are zero based.
+    pub(crate) fn find_call_at_location(&self, line: u32, col: u32) -> Option<CallSite> {
+        fn walk(
+            codemap: &CodeMap,
+            pos: Pos,
+            node: Visit<AstNoPayload>,
+            res: &mut Option<CallSite>,
+        ) {
+            let span = match &node {
+                Visit::Stmt(stmt) => stmt.span,
+                Visit::Expr(expr) => expr.span,
+            };
+            // The end is inclusive, so that `foo(x|)` is treated as being in the call.
+            if pos < span.begin() || pos > span.end() {
+                return;
+            }
+            if let Visit::Expr(expr) = &node {
+                if let ExprP::Call(function, args) = &expr.node {
+                    // Only in the call if after the opening and before the closing paren.
+                    if pos > function.span.end() && pos < expr.span.end() {
+                        let function_span = match &function.node {
+                            ExprP::Dot(_, name) => name.span,
+                            _ => function.span,
+                        };
+                        *res = Some(CallSite {
+                            function_span: codemap.resolve_span(function_span),
+                            active_argument: active_argument(codemap, pos, &args.args),
+                        });
+                    }
+                }
+            }
+            node.visit_children(|child| walk(codemap, pos, child, res));
+        }
+
+        let pos = self.pos_at_location(line, col)?;
+        let mut res = None;
+        walk(
+            self.ast.codemap(),
+            pos,
+            Visit::Stmt(self.ast.statement()),
+            &mut res,
+        );
+        res
+    }
+
+    /// Find the signature of the `def` whose name is at `name_span`.
+    pub(crate) fn find_def_signature(&self, name_span: ResolvedSpan) -> Option<DocFunction> {
+        fn walk(
+            codemap: &CodeMap,
+            name_span: ResolvedSpan,
+            node: Visit<AstNoPayload>,
+            res: &mut Option<DocFunction>,
+        ) {
+            if res.is_some() {
+                return;
+            }
+            if let Visit::Stmt(stmt) = &node {
+                if let StmtP::Def(def) = &stmt.node {
+                    if codemap.resolve_span(def.name.span) == name_span {
+                        *res = get_signature_for_def(def, codemap);
+                        return;
+                    }
+                }
+            }
+            node.visit_children(|child| walk(codemap, name_span, child, res));
+        }
+
+        let mut res = None;
+        walk(
+            self.ast.codemap(),
+            name_span,
+            Visit::Stmt(self.ast.statement()),
+            &mut res,
+        );
+        res
+    }
+}
+
+/// Work out which argument `pos` is in. `pos` is known to be inside the parentheses.
+fn active_argument(
+    codemap: &CodeMap,
+    pos: Pos,
+    args: &[AstArgumentP<AstNoPayload>],
+) -> Option<ActiveArgument> {
+    let mut positional = 0;
+    for arg in args {
+        if pos < arg.span.begin() {
+            break;
+        }
+        // The cursor is still in an argument until there is a comma after it.
+        let in_arg = pos <= arg.span.end()
+            || !codemap
+                .source_span(Span::new(arg.span.end(), pos))
+                .contains(',');
+        match &arg.node {
+            ArgumentP::Positional(_) if in_arg => {
+                return Some(ActiveArgument::Positional(positional));
+            }
+            ArgumentP::Positional(_) => positional += 1,
+            ArgumentP::Named(name, _) if in_arg => {
+                return Some(ActiveArgument::Named(name.node.clone()));
+            }
+            ArgumentP::Args(_) | ArgumentP::KwArgs(_) if in_arg => return None,
+            _ => {}
+        }
+    }
+    Some(ActiveArgument::Positional(positional))
+}
+
+/// Render the signature of `function`, called `name`, for display while typing a call.
+/// The parameter that receives `active_argument` is marked as the active one.
+pub(crate) fn signature_information(
+    name: &str,
+    function: &DocFunction,
+    active_argument: Option<&ActiveArgument>,
+) -> SignatureInformation {
+    let mut parts = Vec::new();
+    let mut parameters = Vec::new();
+    for param in function.params.fmt_params() {
+        let label = match param {
+            FmtParam::Regular(p) => render_param("", p),
+            FmtParam::Args(p) => render_param("*", p),
+            FmtParam::Kwargs(p) => render_param("**", p),
+            FmtParam::Slash => "/".to_owned(),
+            FmtParam::Star => "*".to_owned(),
+        };
+        if let FmtParam::Regular(p) | FmtParam::Args(p) | FmtParam::Kwargs(p) = param {
+            parameters.push(ParameterInformation {
+                label: ParameterLabel::Simple(label.clone()),
+                documentation: p.docs.as_ref().map(markdown),
+            });
+        }
+        parts.push(label);
+    }
+
+    let mut label = format!("{}({})", name, parts.join(", "));
+    if function.ret.typ != Ty::any() {
+        label.push_str(&format!(" -> {}", function.ret.typ));
+    }
+
+    SignatureInformation {
+        label,
+        documentation: function.docs.as_ref().map(markdown),
+        parameters: Some(parameters),
+        active_parameter: active_argument
+            .and_then(|arg| active_parameter(function, arg))
+            .map(|i| i as u32),
+    }
+}
+
+fn render_param(prefix: &str, param: &DocParam) -> String {
+    let mut res = format!("{}{}", prefix, param.name);
+    if param.typ != Ty::any() {
+        res.push_str(&format!(": {}", param.typ));
+    }
+    if let Some(default_value) = &param.default_value {
+        res.push_str(&format!(" = {}", default_value));
+    }
+    res
+}
+
+fn markdown(docs: &DocString) -> Documentation {
+    let mut value = docs.summary.clone();
+    if let Some(details) = &docs.details {
+        value.push_str("\n\n");
+        value.push_str(details);
+    }
+    Documentation::MarkupContent(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+/// Find the index of the parameter that receives `arg`, in the order parameters are
+/// listed by [`signature_information`].
+fn active_parameter(function: &DocFunction, arg: &ActiveArgument) -> Option<usize> {
+    let params = &function.params;
+    let positional = params.pos_only.len() + params.pos_or_named.len();
+    let args = params.args.as_ref().map(|_| positional);
+    let named_only_start = positional + usize::from(args.is_some());
+    let kwargs = params
+        .kwargs
+        .as_ref()
+        .map(|_| named_only_start + params.named_only.len());
+    match arg {
+        ActiveArgument::Positional(i) if *i < positional => Some(*i),
+        ActiveArgument::Positional(_) => args,
+        ActiveArgument::Named(name) => params
+            .pos_or_named
+            .iter()
+            .position(|p| &p.name == name)
+            .map(|i| params.pos_only.len() + i)
+            .or_else(|| {
+                params
+                    .named_only
+                    .iter()
+                    .position(|p| &p.name == name)
+                    .map(|i| named_only_start + i)
+            })
+            .or(kwargs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+    use crate::definition::helpers::FixtureWithRanges;
+
+    fn module(fixture: &str) -> starlark::Result<(FixtureWithRanges, LspModule)> {
+        let parsed = FixtureWithRanges::from_fixture("foo.star", dedent(fixture).trim())?;
+        let module = parsed.module()?;
+        Ok((parsed, module))
+    }
+
+    #[test]
+    fn finds_innermost_call() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            def f(a, b, *args, c, **kwargs):
+                pass
+            <f>f</f>(1, <g>g</g>(<x>x</x>), <y>y</y>, c = <z>z</z>)
+            "#,
+        )?;
+
+        let call_at =
+            |id: &str| module.find_call_at_location(parsed.begin_line(id), parsed.begin_column(id));
+        assert_eq!(
+            Some(CallSite {
+                function_span: parsed.resolved_span("g"),
+                active_argument: Some(ActiveArgument::Positional(0)),
+            }),
+            call_at("x")
+        );
+        assert_eq!(
+            Some(CallSite {
+                function_span: parsed.resolved_span("f"),
+                active_argument: Some(ActiveArgument::Positional(2)),
+            }),
+            call_at("y")
+        );
+        assert_eq!(
+            Some(CallSite {
+                function_span: parsed.resolved_span("f"),
+                active_argument: Some(ActiveArgument::Named("c".to_owned())),
+            }),
+            call_at("z")
+        );
+        assert_eq!(None, call_at("f"));
+        Ok(())
+    }
+
+    #[test]
+    fn counts_arguments_after_commas() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            <f>f</f>(1, <a></a>)
+            f(1<b></b>)
+            f(<c></c>)
+            "#,
+        )?;
+
+        let argument_at = |id: &str| {
+            module
+                .find_call_at_location(parsed.begin_line(id), parsed.begin_column(id))
+                .and_then(|call| call.active_argument)
+        };
+        assert_eq!(Some(ActiveArgument::Positional(1)), argument_at("a"));
+        assert_eq!(Some(ActiveArgument::Positional(0)), argument_at("b"));
+        assert_eq!(Some(ActiveArgument::Positional(0)), argument_at("c"));
+        Ok(())
+    }
+
+    #[test]
+    fn renders_signature_with_active_parameter() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            def <f>f</f>(a, b, *args, c, **kwargs):
+                """Does things.
+
+                Args:
+                    a: The first one.
+                """
+                pass
+            "#,
+        )?;
+
+        let function = module
+            .find_def_signature(parsed.resolved_span("f"))
+            .unwrap();
+        let render = |arg: ActiveArgument| signature_information("f", &function, Some(&arg));
+
+        let signature = render(ActiveArgument::Positional(1));
+        assert_eq!("f(a, b, *args, c, **kwargs)", signature.label);
+        assert_eq!(Some(1), signature.active_parameter);
+        assert_eq!(
+            Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: "The first one.".to_owned(),
+            })),
+            signature.parameters.unwrap()[0].documentation
+        );
+
+        assert_eq!(
+            Some(2),
+            render(ActiveArgument::Positional(5)).active_parameter
+        );
+        assert_eq!(
+            Some(3),
+            render(ActiveArgument::Named("c".to_owned())).active_parameter
+        );
+        assert_eq!(
+            Some(4),
+            render(ActiveArgument::Named("d".to_owned())).active_parameter
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn finds_signature_without_docstring() -> starlark::Result<()> {
+        let (parsed, module) = module(
+            r#"
+            def _outer():
+                def <f>f</f>(x, y):
+                    pass
+            "#,
+        )?;
+
+        let function = module.find_def_signature(parsed.resolved_span("f"));
+        assert_eq!(
+            "f(x, y)",
+            signature_information("f", &function.unwrap(), None).label
+        );
+        Ok(())
+    }
+}
@@ -33,6 +33,7 @@ use lsp_server::Response;
 use lsp_server::ResponseError;
 use lsp_types::notification::DidChangeTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
+use lsp_types::notification::DidSaveTextDocument;
 use lsp_types::notification::Exit;
 use lsp_types::notification::Initialized;
 use lsp_types::notification::Notification;
@@ -41,34 +42,37 @@ use lsp_types::request::Initialize;
 use lsp_types::request::Request;
 use lsp_types::request::Shutdown;
 use lsp_types::ClientCapabilities;
+use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DidSaveTextDocumentParams;
 use lsp_types::GotoCapability;
 use lsp_types::InitializeParams;
 use lsp_types::InitializeResult;
 use lsp_types::InitializedParams;
 use lsp_types::TextDocumentClientCapabilities;
 use lsp_types::TextDocumentContentChangeEvent;
+use lsp_types::TextDocumentIdentifier;
 use lsp_types::TextDocumentItem;
 use lsp_types::Url;
 use lsp_types::VersionedTextDocumentIdentifier;
 use maplit::hashmap;
 use serde::de::DeserializeOwned;
-use starlark::analysis::AstModuleLint;
 use starlark::codemap::Pos;
 use starlark::codemap::Span;
 use starlark::docs::DocFunction;
 use starlark::docs::DocItem;
 use starlark::docs::DocMember;
 use starlark::docs::DocModule;
+use starlark::environment::Globals;
 use starlark::errors::EvalMessage;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
-use starlark_syntax::slice_vec_ext::VecExt;
 
 use crate::error::eval_message_to_lsp_diagnostic;
 use crate::server::new_notification;
 use crate::server::server_with_connection;
+use crate::server::LspChecks;
 use crate::server::LspContext;
 use crate::server::LspEvalResult;
 use crate::server::LspServerSettings;
@@ -131,15 +135,10 @@ impl LspContext for TestServerContext {
                     content,
                     &Dialect::AllOptionsInternal,
                 ) {
-                    Ok(ast) => {
-                        let diagnostics = ast
-                            .lint(None)
-                            .into_map(|l| eval_message_to_lsp_diagnostic(EvalMessage::from(l)));
-                        LspEvalResult {
-                            diagnostics,
-                            ast: Some(ast),
-                        }
-                    }
+                    Ok(ast) => LspEvalResult {
+                        diagnostics: Vec::new(),
+                        ast: Some(ast),
+                    },
                     Err(e) => {
                         let diagnostics = vec![eval_message_to_lsp_diagnostic(
                             EvalMessage::from_error(path, &e),
@@ -282,6 +281,24 @@ impl LspContext for TestServerContext {
         Ok(self.builtin_symbols.get(symbol).cloned())
     }
 
+    fn get_checks(&self, _uri: &LspUrl) -> LspChecks {
+        LspChecks {
+            lint: true,
+            lint_globals: None,
+            typecheck_globals: Some(Globals::extended_internal()),
+        }
+    }
+
+    fn get_workspace_files(&self, _workspace_root: Option<&Path>) -> anyhow::Result<Vec<LspUrl>> {
+        Ok(self
+            .file_contents
+            .read()
+            .unwrap()
+            .keys()
+            .map(|path| LspUrl::File(path.clone()))
+            .collect())
+    }
+
     fn get_environment(&self, _uri: &LspUrl) -> DocModule {
         DocModule {
             docs: None,
@@ -608,6 +625,25 @@ impl TestServer {
     ///
     /// This will return an error if there were any diagnostic messages.
     pub fn open_file(&mut self, uri: Url, contents: String) -> anyhow::Result<()> {
+        let diagnostics = self.open_file_with_diagnostics(uri.clone(), contents)?;
+        if !diagnostics.is_empty() {
+            Err(anyhow::anyhow!(
+                "Got unexpected diagnostic messages when opening {}, got {:?}",
+                uri,
+                diagnostics
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Send a notification saying that a file was opened with the given contents, and
+    /// return the diagnostics that were published for it.
+    pub fn open_file_with_diagnostics(
+        &mut self,
+        uri: Url,
+        contents: String,
+    ) -> anyhow::Result<Vec<Diagnostic>> {
         let open_params = DidOpenTextDocumentParams {
             text_document: TextDocumentItem {
                 uri: uri.clone(),
@@ -625,14 +661,8 @@ impl TestServer {
                 notification.uri,
                 uri
             ))
-        } else if !notification.diagnostics.is_empty() {
-            Err(anyhow::anyhow!(
-                "Got unexpected diagnostic messages when opening {}, got {:?}",
-                uri,
-                notification.diagnostics
-            ))
         } else {
-            Ok(())
+            Ok(notification.diagnostics)
         }
     }
 
@@ -654,6 +684,17 @@ impl TestServer {
         Ok(())
     }
 
+    /// Send a notification saying that a file was saved.
+    pub fn save_file(&mut self, uri: Url) -> anyhow::Result<()> {
+        let save_params = DidSaveTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+            text: None,
+        };
+        let save_notification = new_notification::<DidSaveTextDocument>(save_params);
+        self.send_notification(save_notification)?;
+        Ok(())
+    }
+
     /// Set the file contents that `get_load_contents()` will return.
     pub fn set_file_contents(&self, file_uri: &Url, contents: String) -> anyhow::Result<()> {
         let path = file_uri
@@ -64,6 +64,12 @@ pub struct Dialect {
     ///
     /// [Starlark spec proposal](https://github.com/bazelbuild/starlark/issues/91).
     pub enable_f_strings: bool,
+    /// Are `b"bytes"` literals supported?
+    /// The `bytes()` function is provided by `LibraryExtension::BytesType`.
+    /// Disabled by default.
+    ///
+    /// [Starlark spec](https://github.com/bazelbuild/starlark/blob/master/spec.md#bytes).
+    pub enable_bytes: bool,
     /// Like `#[non_exhaustive]`, but allows struct expression.
     ///
     /// [Explanation](https://github.com/rust-lang/rust-clippy/issues/6559).
@@ -93,6 +99,7 @@ impl Dialect {
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
         enable_f_strings: false,
+        enable_bytes: false,
         _non_exhaustive: (),
     };
 
@@ -108,6 +115,7 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: false,
+        enable_bytes: false,
         _non_exhaustive: (),
     };
 
@@ -123,6 +131,7 @@ impl Dialect {
         enable_load_reexport: true,
         enable_top_level_stmt: true,
         enable_f_strings: true,
+        enable_bytes: true,
         _non_exhaustive: (),
     };
 }
@@ -188,6 +188,9 @@ pub enum ErrorKind {
     Fail(anyhow::Error),
     /// Starlark call stack overflow.
     StackOverflow(anyhow::Error),
+    /// The evaluation ran out of one of the budgets set on the evaluator (instructions, heap
+    /// size or time).
+    BudgetExceeded(anyhow::Error),
     /// An error approximately associated with a value.
     ///
     /// Includes unsupported operations, missing attributes, things of that sort.
@@ -219,6 +222,7 @@ impl ErrorKind {
         match self {
             Self::Fail(_) => None,
             Self::StackOverflow(_) => None,
+            Self::BudgetExceeded(_) => None,
             Self::Value(_) => None,
             Self::Function(_) => None,
             Self::Scope(_) => None,
@@ -241,6 +245,7 @@ impl ErrorKind {
             | ErrorKind::Freeze(e)
             | ErrorKind::Parser(e)
             | ErrorKind::StackOverflow(e)
+            | ErrorKind::BudgetExceeded(e)
             | ErrorKind::Native(e)
             | ErrorKind::Other(e) => ErrorKind::Internal(e),
         }
@@ -253,6 +258,7 @@ impl fmt::Debug for ErrorKind {
             Self::Fail(s) => write!(f, "fail:{}", s),
             Self::Value(e) => fmt::Debug::fmt(e, f),
             Self::StackOverflow(e) => fmt::Debug::fmt(e, f),
+            Self::BudgetExceeded(e) => fmt::Debug::fmt(e, f),
             Self::Function(e) => fmt::Debug::fmt(e, f),
             Self::Scope(e) => fmt::Debug::fmt(e, f),
             Self::Freeze(e) => fmt::Debug::fmt(e, f),
@@ -269,6 +275,7 @@ impl fmt::Display for ErrorKind {
         match self {
             Self::Fail(s) => write!(f, "fail:{}", s),
             Self::StackOverflow(e) => fmt::Display::fmt(e, f),
+            Self::BudgetExceeded(e) => fmt::Display::fmt(e, f),
             Self::Value(e) => fmt::Display::fmt(e, f),
             Self::Function(e) => fmt::Display::fmt(e, f),
             Self::Scope(e) => fmt::Display::fmt(e, f),
@@ -311,6 +311,63 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    /// Decode the escape sequences in the contents of a bytes literal.
+    /// Unlike in strings, `\xNN` and octal escapes denote bytes rather than code points.
+    /// On error, returns the position of the invalid escape sequence.
+    fn bytes_escape(s: &str) -> Result<Vec<u8>, (usize, usize)> {
+        let mut res = Vec::with_capacity(s.len());
+        let mut it = CursorChars::new_offset(s, 0);
+        while let Some(c) = it.next() {
+            if c != '\\' {
+                res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                continue;
+            }
+            let start = it.pos() - 1;
+            let byte = match it.next() {
+                Some('x') => Self::escape_char(&mut it, 2, 2, 16).map(|c| c as u32),
+                Some(c @ '0'..='7') => {
+                    it.unnext(c);
+                    Self::escape_char(&mut it, 1, 3, 8).map(|c| c as u32)
+                }
+                Some(c) => {
+                    it.unnext(c);
+                    let mut escaped = String::new();
+                    Self::escape(&mut it, &mut escaped).map_err(|()| (start, it.pos()))?;
+                    res.extend_from_slice(escaped.as_bytes());
+                    continue;
+                }
+                None => Err(()),
+            };
+            match byte.ok().and_then(|b| u8::try_from(b).ok()) {
+                Some(b) => res.push(b),
+                None => return Err((start, it.pos())),
+            }
+        }
+        Ok(res)
+    }
+
+    fn bytes(&mut self, raw: bool, double_quoted: bool) -> Option<Lexeme> {
+        // Parse as a raw string, and decode the escapes as bytes.
+        let lexeme = if double_quoted {
+            self.parse_double_quoted_string(true)?
+        } else {
+            self.parse_single_quoted_string(true)?
+        };
+        Some(lexeme.and_then(|(start, (contents, _offset), end)| {
+            if raw {
+                return Ok((start, Token::Bytes(contents.into_bytes()), end));
+            }
+            match Self::bytes_escape(&contents) {
+                Ok(bytes) => Ok((start, Token::Bytes(bytes), end)),
+                Err((bad_start, bad_end)) => self.err_span(
+                    LexemeError::InvalidEscapeSequence(contents[bad_start..bad_end].to_owned()),
+                    start,
+                    end,
+                ),
+            }
+        }))
+    }
+
     /// Parse a String. Return the String, and the offset where it starts.
     // String parsing is a hot-spot, so parameterise by a `stop` function which gets
     // specialised for each variant
@@ -539,6 +596,17 @@ impl<'a> Lexer<'a> {
                         Token::FString(_) => {
                             unreachable!("The lexer does not produce FString")
                         }
+                        Token::RawBytesDoubleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            self.bytes(raw, true)
+                        }
+                        Token::RawBytesSingleQuote => {
+                            let raw = self.lexer.span().len() == 3;
+                            self.bytes(raw, false)
+                        }
+                        Token::Bytes(_) => {
+                            unreachable!("The lexer does not produce Bytes")
+                        }
                         Token::OpeningCurly | Token::OpeningRound | Token::OpeningSquare => {
                             self.parens += 1;
                             self.wrap(token)
@@ -658,6 +726,17 @@ pub enum Token {
     #[token("fr\"")]
     RawFStringDoubleQuote,
 
+    /// The start of a single-quoted bytes literal.
+    #[token("b'")]
+    #[token("rb'")]
+    #[token("br'")]
+    RawBytesSingleQuote,
+    /// The start of a double-quoted bytes literal.
+    #[token("b\"")]
+    #[token("rb\"")]
+    #[token("br\"")]
+    RawBytesDoubleQuote,
+
     #[regex(
         "as|\
         assert|\
@@ -704,6 +783,7 @@ pub enum Token {
     String(String), // A string literal
     /// The raw text of a f-string
     FString(TokenFString),
+    Bytes(Vec<u8>), // A bytes literal
 
     // Keywords
     #[token("and")]
@@ -842,6 +922,7 @@ impl Token {
                 // Reuse the StarlarkValue implementation since it's close to hand.
                 serde_json::to_string(x).unwrap()
             }
+            Token::Bytes(x) => format!("b\"{}\"", x.escape_ascii()),
             Token::FString(x) => {
                 let mut buff = Vec::new();
                 write!(&mut buff, "f").unwrap();
@@ -943,6 +1024,9 @@ impl Display for Token {
             Token::RawFStringDoubleQuote => write!(f, "starting f'"),
             Token::RawFStringSingleQuote => write!(f, "starting f\""),
             Token::FString(s) => write!(f, "f-string {:?}", &s.content),
+            Token::RawBytesDoubleQuote => write!(f, "starting b\""),
+            Token::RawBytesSingleQuote => write!(f, "starting b'"),
+            Token::Bytes(b) => write!(f, "bytes literal b\"{}\"", b.escape_ascii()),
             Token::Comment(c) => write!(f, "comment '{}'", c),
             Token::Tabs => Ok(()),
         }
@@ -425,3 +425,18 @@ fr'' fr"" fr'\'' fr"\"" fr'"' fr"'" fr'\n'
 "#,
     );
 }
+
+#[test]
+fn test_bytes() {
+    lexer_golden_test(
+        "bytes",
+        r#"
+b"abc" b'abc' b"" b'\'"'
+b"\x00\xff\377\n" b"\u00ff" b"\U0001F600" b"世界"
+rb"\x00\n" br'\''
+b'''triple
+quoted'''
+"#,
+    );
+    lexer_fail_golden_test("bytes", &["b'\\xZZ'", "b'\\400'", "b'unfinished"]);
+}
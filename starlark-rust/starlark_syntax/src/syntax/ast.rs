@@ -87,6 +87,7 @@ pub type AstAssignIdent = AstAssignIdentP<AstNoPayload>;
 pub type AstIdent = AstIdentP<AstNoPayload>;
 pub type AstArgument = AstArgumentP<AstNoPayload>;
 pub type AstString = Spanned<String>;
+pub type AstBytes = Spanned<Vec<u8>>;
 pub type AstParameter = AstParameterP<AstNoPayload>;
 pub type AstInt = Spanned<TokenInt>;
 pub type AstFloat = Spanned<f64>;
@@ -147,6 +148,7 @@ pub enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
+    Bytes(AstBytes),
     Ellipsis,
 }
 
@@ -512,6 +514,7 @@ impl Display for AstLiteral {
             AstLiteral::Int(i) => write!(f, "{}", &i.node),
             AstLiteral::Float(n) => write!(f, "{}", &n.node),
             AstLiteral::String(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::Bytes(b) => write!(f, "b\"{}\"", b.node.escape_ascii()),
             AstLiteral::Ellipsis => f.write_str("..."),
         }
     }
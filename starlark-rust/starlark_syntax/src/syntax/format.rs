@@ -0,0 +1,1013 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Pretty printer producing the canonical layout of a module, preserving comments.
+//!
+//! The layout rules are deliberately simple:
+//!
+//! * Blocks are indented with four spaces, and statements separated by `;` are split
+//!   onto their own lines.
+//! * Runs of blank lines are collapsed to a single blank line.
+//! * A bracketed list of items (call arguments, parameters, list and dict literals,
+//!   and so on) is written on one line if it fits and was written on one line in the
+//!   source. Otherwise it is written with one item per line and a trailing comma.
+//! * Parentheses written in the source are kept, everything else is normalized.
+
+use dupe::Dupe;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AssignTargetP;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssignTarget;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstNoPayload;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::AstString;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::ClauseP;
+use crate::syntax::ast::DefP;
+use crate::syntax::ast::ExprP;
+use crate::syntax::ast::ForClauseP;
+use crate::syntax::ast::ForP;
+use crate::syntax::ast::LambdaP;
+use crate::syntax::ast::LoadArgP;
+use crate::syntax::ast::ParameterP;
+use crate::syntax::ast::StmtP;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+/// Indentation for each nested block or bracket.
+const INDENT: &str = "    ";
+
+/// Lines longer than this have their brackets split over several lines.
+const MAX_WIDTH: usize = 100;
+
+impl AstModule {
+    /// Format the module in the canonical layout, keeping all comments.
+    ///
+    /// The result parses to the same AST as the original, and formatting it again
+    /// produces the same text.
+    pub fn format(&self) -> String {
+        let comments = comments(&self.codemap, &self.dialect);
+        let mut printer = Printer::new(&self.codemap, &comments, false);
+        printer.module(&self.statement);
+        printer.out
+    }
+}
+
+/// A comment in the source, with the span including the leading `#`.
+struct Comment {
+    begin: usize,
+    end: usize,
+    text: String,
+}
+
+fn comments(codemap: &CodeMap, dialect: &Dialect) -> Vec<Comment> {
+    // Comments are dropped by the parser, so lex the file again to find them.
+    Lexer::new(codemap.source(), dialect, codemap.dupe())
+        .filter_map(|token| match token {
+            Ok((begin, Token::Comment(text), end)) => Some(Comment {
+                begin,
+                end,
+                text: format!("#{}", text.trim_end()),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Binding strength of an expression, from loosest to tightest.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Prec {
+    Tuple,
+    Lambda,
+    If,
+    Or,
+    And,
+    Not,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Arith,
+    Product,
+    Unary,
+    Primary,
+}
+
+impl Prec {
+    fn of(expr: &AstExpr) -> Prec {
+        match &expr.node {
+            ExprP::Tuple(xs) if !xs.is_empty() => Prec::Tuple,
+            ExprP::Lambda(_) => Prec::Lambda,
+            ExprP::If(_) => Prec::If,
+            ExprP::Op(_, op, _) => Prec::of_op(*op),
+            ExprP::Not(_) => Prec::Not,
+            ExprP::Minus(_) | ExprP::Plus(_) | ExprP::BitNot(_) => Prec::Unary,
+            _ => Prec::Primary,
+        }
+    }
+
+    fn of_op(op: BinOp) -> Prec {
+        match op {
+            BinOp::Or => Prec::Or,
+            BinOp::And => Prec::And,
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Less
+            | BinOp::Greater
+            | BinOp::LessOrEqual
+            | BinOp::GreaterOrEqual
+            | BinOp::In
+            | BinOp::NotIn => Prec::Comparison,
+            BinOp::BitOr => Prec::BitOr,
+            BinOp::BitXor => Prec::BitXor,
+            BinOp::BitAnd => Prec::BitAnd,
+            BinOp::LeftShift | BinOp::RightShift => Prec::Shift,
+            BinOp::Add | BinOp::Subtract => Prec::Arith,
+            BinOp::Multiply | BinOp::Percent | BinOp::Divide | BinOp::FloorDivide => Prec::Product,
+        }
+    }
+
+    /// The precedence of the operands of a binary operator with this precedence.
+    /// Operators are left associative, except comparisons which don't chain.
+    fn operands(self) -> (Prec, Prec) {
+        let tighter = match self {
+            Prec::Tuple => Prec::Lambda,
+            Prec::Lambda => Prec::If,
+            Prec::If => Prec::Or,
+            Prec::Or => Prec::And,
+            Prec::And => Prec::Not,
+            Prec::Not => Prec::Comparison,
+            Prec::Comparison => Prec::BitOr,
+            Prec::BitOr => Prec::BitXor,
+            Prec::BitXor => Prec::BitAnd,
+            Prec::BitAnd => Prec::Shift,
+            Prec::Shift => Prec::Arith,
+            Prec::Arith => Prec::Product,
+            Prec::Product => Prec::Unary,
+            Prec::Unary | Prec::Primary => Prec::Primary,
+        };
+        if self == Prec::Comparison {
+            (tighter, tighter)
+        } else {
+            (self, tighter)
+        }
+    }
+}
+
+/// Something that appears between brackets, separated by commas.
+#[derive(Copy, Clone)]
+enum Item<'b> {
+    Expr(&'b AstExpr),
+    /// A call argument, and whether it is the only one.
+    Arg(&'b AstArgument, bool),
+    Param(&'b AstParameter),
+    DictEntry(&'b (AstExpr, AstExpr)),
+    Target(&'b AstAssignTarget),
+    LoadModule(&'b AstString),
+    LoadArg(&'b LoadArgP<AstNoPayload>),
+}
+
+impl Item<'_> {
+    fn span(self) -> Span {
+        match self {
+            Item::Expr(x) => x.span,
+            Item::Arg(x, _) => x.span,
+            Item::Param(x) => x.span,
+            Item::DictEntry((k, v)) => k.span.merge(v.span),
+            Item::Target(x) => x.span,
+            Item::LoadModule(x) => x.span,
+            Item::LoadArg(x) => x.span(),
+        }
+    }
+}
+
+fn first_stmt_begin(stmt: &AstStmt) -> Pos {
+    match &stmt.node {
+        StmtP::Statements(xs) if !xs.is_empty() => first_stmt_begin(&xs[0]),
+        _ => stmt.span.begin(),
+    }
+}
+
+struct Printer<'a> {
+    codemap: &'a CodeMap,
+    /// All comments in the file, in order.
+    comments: &'a [Comment],
+    /// The index of the first comment which has not been written yet.
+    next_comment: usize,
+    /// Write everything on one line and skip comments, used to measure things.
+    flat: bool,
+    /// The lines finished so far.
+    out: String,
+    /// The line being written.
+    line: String,
+    indent: usize,
+    /// The end of the last thing written, as an offset in the source.
+    last_pos: usize,
+    /// Whether nothing has been written yet in the current block or brackets,
+    /// in which case blank lines are dropped.
+    block_start: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(codemap: &'a CodeMap, comments: &'a [Comment], flat: bool) -> Self {
+        Printer {
+            codemap,
+            comments,
+            next_comment: 0,
+            flat,
+            out: String::new(),
+            line: String::new(),
+            indent: 0,
+            last_pos: 0,
+            block_start: true,
+        }
+    }
+
+    fn module(&mut self, stmt: &AstStmt) {
+        self.stmts(stmt);
+        self.comments_before(usize::MAX);
+        self.newline();
+    }
+
+    // Layout primitives.
+
+    fn write(&mut self, s: &str) {
+        self.line.push_str(s);
+    }
+
+    fn newline(&mut self) {
+        if !self.line.trim().is_empty() {
+            self.out.push_str(self.line.trim_end());
+            self.out.push('\n');
+        }
+        self.line.clear();
+    }
+
+    fn start_line(&mut self) {
+        self.newline();
+        self.line.push_str(&INDENT.repeat(self.indent));
+    }
+
+    /// Start a line for a statement or bracketed item that begins at `begin`,
+    /// writing any comments and blank line that come before it.
+    fn item_start(&mut self, begin: usize) {
+        self.comments_before(begin);
+        if !self.block_start && self.blank_line_between(self.last_pos, begin) {
+            self.newline();
+            self.out.push('\n');
+        }
+        self.start_line();
+        self.block_start = false;
+    }
+
+    fn comments_before(&mut self, pos: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.begin >= pos {
+                break;
+            }
+            self.comment();
+        }
+    }
+
+    /// Write the comments after the end of a block that are indented at least as far
+    /// as its statements, so belong to it rather than whatever follows.
+    fn block_end_comments(&mut self, column: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let only_space = self.source(self.last_pos, comment.begin).trim().is_empty();
+            if !(self.same_line(self.last_pos, comment.begin)
+                || (only_space && self.column(comment.begin) >= column))
+            {
+                break;
+            }
+            self.comment();
+        }
+    }
+
+    fn comment(&mut self) {
+        let comment = &self.comments[self.next_comment];
+        self.next_comment += 1;
+        if self.flat {
+            return;
+        }
+        if self.same_line(self.last_pos, comment.begin) && !self.line.trim().is_empty() {
+            self.write("  ");
+        } else {
+            if !self.block_start && self.blank_line_between(self.last_pos, comment.begin) {
+                self.newline();
+                self.out.push('\n');
+            }
+            self.start_line();
+        }
+        self.write(&comment.text);
+        self.newline();
+        self.last_pos = comment.end;
+        self.block_start = false;
+    }
+
+    // Looking at the source.
+
+    fn source(&self, begin: usize, end: usize) -> &'a str {
+        let source = self.codemap.source();
+        &source[begin.min(source.len())..end.min(source.len())]
+    }
+
+    fn line_of(&self, pos: usize) -> usize {
+        self.codemap
+            .find_line(Pos::new(pos.min(self.codemap.source().len()) as u32))
+    }
+
+    fn same_line(&self, a: usize, b: usize) -> bool {
+        self.line_of(a) == self.line_of(b)
+    }
+
+    fn column(&self, pos: usize) -> usize {
+        pos - self.codemap.line_span(self.line_of(pos)).begin().get() as usize
+    }
+
+    fn blank_line_between(&self, begin: usize, end: usize) -> bool {
+        let lines: Vec<&str> = self.source(begin, end).split('\n').collect();
+        lines.len() > 2
+            && lines[1..lines.len() - 1]
+                .iter()
+                .any(|x| x.trim().is_empty())
+    }
+
+    fn has_comments(&self, begin: usize, end: usize) -> bool {
+        self.comments[self.next_comment..]
+            .iter()
+            .any(|c| begin <= c.begin && c.begin < end)
+    }
+
+    /// Move backwards from `pos` over whitespace and comments.
+    fn skip_trivia_back(&self, mut pos: usize) -> usize {
+        let source = self.codemap.source().as_bytes();
+        loop {
+            while pos > 0 && source[pos - 1].is_ascii_whitespace() {
+                pos -= 1;
+            }
+            match self.comments.binary_search_by_key(&pos, |c| c.end) {
+                Ok(i) => pos = self.comments[i].begin,
+                Err(_) => return pos,
+            }
+        }
+    }
+
+    /// Move forwards from `pos` over whitespace and comments.
+    fn skip_trivia(&self, mut pos: usize) -> usize {
+        let source = self.codemap.source().as_bytes();
+        loop {
+            while pos < source.len() && source[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            match self.comments.binary_search_by_key(&pos, |c| c.begin) {
+                Ok(i) => pos = self.comments[i].end,
+                Err(_) => return pos,
+            }
+        }
+    }
+
+    /// The position of the first `byte` at or after `pos`, ignoring comments.
+    fn find(&self, mut pos: usize, byte: u8) -> usize {
+        let source = self.codemap.source().as_bytes();
+        loop {
+            pos = self.skip_trivia(pos);
+            if pos >= source.len() || source[pos] == byte {
+                return pos;
+            }
+            pos += 1;
+        }
+    }
+
+    /// The parentheses written around `span` in the source, as the number of pairs
+    /// and the span of the innermost pair.
+    fn source_parens(&self, span: Span) -> (usize, Option<(usize, usize)>) {
+        let source = self.codemap.source().as_bytes();
+        let mut count = 0;
+        let mut innermost = None;
+        let (mut begin, mut end) = (span.begin().get() as usize, span.end().get() as usize);
+        loop {
+            let before = self.skip_trivia_back(begin);
+            let after = self.skip_trivia(end);
+            if before == 0
+                || source[before - 1] != b'('
+                || after >= source.len()
+                || source[after] != b')'
+            {
+                return (count, innermost);
+            }
+            begin = before - 1;
+            end = after + 1;
+            count += 1;
+            innermost.get_or_insert((begin, end));
+        }
+    }
+
+    /// How wide `f` would be if written on one line.
+    fn measure(&self, f: impl FnOnce(&mut Printer<'a>)) -> usize {
+        let mut printer = Printer::new(self.codemap, self.comments, true);
+        f(&mut printer);
+        printer
+            .line
+            .split('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+    }
+
+    fn fits(&self, width: usize) -> bool {
+        let current = self.line.rsplit('\n').next().unwrap_or_default();
+        current.chars().count() + width <= MAX_WIDTH
+    }
+
+    // Statements.
+
+    fn stmts(&mut self, stmt: &AstStmt) {
+        match &stmt.node {
+            StmtP::Statements(xs) => {
+                for x in xs {
+                    self.stmts(x);
+                }
+            }
+            _ => {
+                self.item_start(stmt.span.begin().get() as usize);
+                self.stmt(stmt);
+                // The span of a compound statement can extend over the comments after
+                // its body, so rely on the body to have set the position.
+                if !matches!(
+                    stmt.node,
+                    StmtP::If(..) | StmtP::IfElse(..) | StmtP::For(..) | StmtP::Def(..)
+                ) {
+                    self.last_pos = stmt.span.end().get() as usize;
+                }
+            }
+        }
+    }
+
+    fn stmt(&mut self, stmt: &AstStmt) {
+        match &stmt.node {
+            StmtP::Break => self.write("break"),
+            StmtP::Continue => self.write("continue"),
+            StmtP::Pass => self.write("pass"),
+            StmtP::Return(None) => self.write("return"),
+            StmtP::Return(Some(e)) => {
+                self.write("return ");
+                self.expr(e, Prec::Tuple);
+            }
+            StmtP::Expression(e) => self.expr(e, Prec::Lambda),
+            StmtP::Assign(AssignP { lhs, ty, rhs }) => {
+                self.target(lhs, true);
+                if let Some(ty) = ty {
+                    self.write(": ");
+                    self.expr(&ty.node.expr, Prec::Lambda);
+                }
+                self.write(" = ");
+                self.expr(rhs, Prec::Tuple);
+            }
+            StmtP::AssignModify(lhs, op, rhs) => {
+                self.target(lhs, true);
+                self.write(&op.to_string());
+                self.expr(rhs, Prec::Tuple);
+            }
+            StmtP::Statements(_) => self.stmts(stmt),
+            StmtP::If(cond, body) => {
+                self.write("if ");
+                self.expr(cond, Prec::Lambda);
+                self.block(body);
+            }
+            StmtP::IfElse(cond, then_else) => {
+                let (then_body, else_body) = &**then_else;
+                self.write("if ");
+                self.expr(cond, Prec::Lambda);
+                self.block(then_body);
+                self.else_branch(else_body);
+            }
+            StmtP::For(ForP { var, over, body }) => {
+                self.write("for ");
+                self.target(var, true);
+                self.write(" in ");
+                self.expr(over, Prec::Lambda);
+                self.block(body);
+            }
+            StmtP::Def(DefP {
+                name,
+                params,
+                return_type,
+                body,
+                payload: _,
+            }) => {
+                self.write("def ");
+                self.write(&name.ident);
+                let open = self.find(name.span.end().get() as usize, b'(');
+                let close = match params.last() {
+                    Some(x) => self.find(x.span.end().get() as usize, b')'),
+                    None => self.find(open + 1, b')'),
+                };
+                let items: Vec<_> = params.iter().map(Item::Param).collect();
+                self.bracketed("(", ")", open, close + 1, &items, false);
+                if let Some(return_type) = return_type {
+                    self.write(" -> ");
+                    self.expr(&return_type.node.expr, Prec::Lambda);
+                }
+                self.block(body);
+            }
+            StmtP::Load(load) => {
+                self.write("load");
+                let open = self.find(stmt.span.begin().get() as usize, b'(');
+                let items: Vec<_> = [Item::LoadModule(&load.module)]
+                    .into_iter()
+                    .chain(load.args.iter().map(Item::LoadArg))
+                    .collect();
+                self.bracketed(
+                    "(",
+                    ")",
+                    open,
+                    stmt.span.end().get() as usize,
+                    &items,
+                    false,
+                );
+            }
+        }
+    }
+
+    fn block(&mut self, body: &AstStmt) {
+        let first = first_stmt_begin(body).get() as usize;
+        self.write(":");
+        self.last_pos = self.skip_trivia_back(first);
+        self.indent += 1;
+        self.block_start = true;
+        self.stmts(body);
+        self.block_end_comments(self.column(first));
+        self.indent -= 1;
+    }
+
+    fn else_branch(&mut self, stmt: &AstStmt) {
+        let first = first_stmt_begin(stmt).get() as usize;
+        let is_elif = matches!(stmt.node, StmtP::If(..) | StmtP::IfElse(..))
+            && self
+                .source(0, self.skip_trivia_back(stmt.span.begin().get() as usize))
+                .ends_with("elif");
+        if is_elif {
+            self.comments_before(stmt.span.begin().get() as usize);
+            self.start_line();
+            self.write("el");
+            self.stmt(stmt);
+        } else {
+            self.comments_before(self.skip_trivia_back(first));
+            self.start_line();
+            self.write("else");
+            self.block(stmt);
+        }
+    }
+
+    fn target(&mut self, target: &AstAssignTarget, top: bool) {
+        match &target.node {
+            AssignTargetP::Tuple(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::Target).collect();
+                let begin = target.span.begin().get() as usize;
+                let end = target.span.end().get() as usize;
+                if self.source(begin, begin + 1) == "[" {
+                    self.bracketed("[", "]", begin, end, &items, false);
+                } else if let (_, Some((begin, end))) = self.source_parens(target.span) {
+                    self.bracketed("(", ")", begin, end, &items, true);
+                } else if top {
+                    self.flat_items(&items, true);
+                } else {
+                    self.bracketed("(", ")", begin, end, &items, true);
+                }
+            }
+            AssignTargetP::Index(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, Prec::Primary);
+                self.write("[");
+                self.expr(index, Prec::Lambda);
+                self.write("]");
+            }
+            AssignTargetP::Dot(object, field) => {
+                self.expr(object, Prec::Primary);
+                self.write(".");
+                self.write(&field.node);
+            }
+            AssignTargetP::Identifier(x) => self.write(&x.ident),
+        }
+    }
+
+    // Expressions.
+
+    /// Write an expression in a position which needs at least `min` precedence,
+    /// keeping any parentheses written around it.
+    fn expr(&mut self, expr: &AstExpr, min: Prec) {
+        let (_, parens) = self.source_parens(expr.span);
+        self.expr_with_parens(expr, min, parens);
+    }
+
+    fn expr_with_parens(&mut self, expr: &AstExpr, min: Prec, parens: Option<(usize, usize)>) {
+        if parens.is_none() && Prec::of(expr) >= min {
+            self.expr_bare(expr);
+            return;
+        }
+        let (begin, end) = parens.unwrap_or((
+            expr.span.begin().get() as usize,
+            expr.span.end().get() as usize,
+        ));
+        if let ExprP::Tuple(xs) = &expr.node {
+            let items: Vec<_> = xs.iter().map(Item::Expr).collect();
+            self.bracketed("(", ")", begin, end, &items, true);
+            return;
+        }
+
+        let multiline = !self.flat
+            && (!self.same_line(begin, expr.span.begin().get() as usize)
+                || self.has_comments(begin, end)
+                || !self.fits(self.measure(|p| p.expr_bare(expr)) + 2));
+        if !multiline {
+            self.write("(");
+            self.expr_bare(expr);
+            self.write(")");
+            return;
+        }
+
+        self.write("(");
+        self.last_pos = begin + 1;
+        self.indent += 1;
+        self.block_start = true;
+        match &expr.node {
+            ExprP::Op(_, op, _) if Prec::of_op(*op) != Prec::Comparison => {
+                // Put each operand of a chain of the same operator on its own line.
+                let mut operands = Vec::new();
+                let mut x = expr;
+                while let ExprP::Op(lhs, op2, rhs) = &x.node {
+                    if Prec::of_op(*op2) != Prec::of_op(*op)
+                        || (!std::ptr::eq(x, expr) && self.source_parens(x.span).0 > 0)
+                    {
+                        break;
+                    }
+                    operands.push((Some(*op2), &**rhs));
+                    x = lhs;
+                }
+                operands.push((None, x));
+                operands.reverse();
+                let (lhs_prec, rhs_prec) = Prec::of_op(*op).operands();
+                for (i, (_, operand)) in operands.iter().enumerate() {
+                    self.item_start(operand.span.begin().get() as usize);
+                    self.expr(operand, if i == 0 { lhs_prec } else { rhs_prec });
+                    if let Some((Some(op), _)) = operands.get(i + 1) {
+                        self.write(op.to_string().trim_end());
+                    }
+                    self.last_pos = operand.span.end().get() as usize;
+                }
+            }
+            _ => {
+                self.item_start(expr.span.begin().get() as usize);
+                self.expr_bare(expr);
+                self.last_pos = expr.span.end().get() as usize;
+            }
+        }
+        self.comments_before(end - 1);
+        self.indent -= 1;
+        self.start_line();
+        self.write(")");
+        self.last_pos = end;
+    }
+
+    /// Write an expression without any parentheses around it.
+    fn expr_bare(&mut self, expr: &AstExpr) {
+        let span_begin = expr.span.begin().get() as usize;
+        let span_end = expr.span.end().get() as usize;
+        match &expr.node {
+            ExprP::Tuple(xs) if xs.is_empty() => self.write("()"),
+            ExprP::Tuple(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::Expr).collect();
+                self.flat_items(&items, true);
+            }
+            ExprP::Dot(object, field) => {
+                self.expr(object, Prec::Primary);
+                self.write(".");
+                self.write(&field.node);
+            }
+            ExprP::Call(function, args) => {
+                self.expr(function, Prec::Primary);
+                let open = self.find(function.span.end().get() as usize, b'(');
+                let only = args.args.len() == 1;
+                let items: Vec<_> = args.args.iter().map(|x| Item::Arg(x, only)).collect();
+                self.bracketed("(", ")", open, span_end, &items, false);
+            }
+            ExprP::Index(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, Prec::Primary);
+                self.write("[");
+                self.expr(index, Prec::Lambda);
+                self.write("]");
+            }
+            ExprP::Index2(array_indices) => {
+                let (array, index0, index1) = &**array_indices;
+                self.expr(array, Prec::Primary);
+                self.write("[");
+                self.expr(index0, Prec::Lambda);
+                self.write(", ");
+                self.expr(index1, Prec::Lambda);
+                self.write("]");
+            }
+            ExprP::Slice(array, start, stop, step) => {
+                self.expr(array, Prec::Primary);
+                self.write("[");
+                if let Some(start) = start {
+                    self.expr(start, Prec::Lambda);
+                }
+                self.write(":");
+                if let Some(stop) = stop {
+                    self.expr(stop, Prec::Lambda);
+                }
+                if let Some(step) = step {
+                    self.write(":");
+                    self.expr(step, Prec::Lambda);
+                }
+                self.write("]");
+            }
+            ExprP::Identifier(x) => self.write(&x.node.ident),
+            ExprP::Lambda(LambdaP {
+                params,
+                body,
+                payload: _,
+            }) => {
+                self.write("lambda");
+                if !params.is_empty() {
+                    self.write(" ");
+                    let items: Vec<_> = params.iter().map(Item::Param).collect();
+                    self.flat_items(&items, false);
+                }
+                self.write(": ");
+                self.expr(body, Prec::Lambda);
+            }
+            ExprP::Literal(AstLiteral::String(x)) => self.string(x.span),
+            ExprP::Literal(AstLiteral::Ellipsis) => self.write("..."),
+            ExprP::Literal(AstLiteral::Int(_) | AstLiteral::Float(_) | AstLiteral::Bytes(_))
+            | ExprP::FString(_) => self.write(self.source(span_begin, span_end)),
+            ExprP::Not(x) => {
+                self.write("not ");
+                self.expr(x, Prec::Not);
+            }
+            ExprP::Minus(x) => {
+                self.write("-");
+                self.expr(x, Prec::Unary);
+            }
+            ExprP::Plus(x) => {
+                self.write("+");
+                self.expr(x, Prec::Unary);
+            }
+            ExprP::BitNot(x) => {
+                self.write("~");
+                self.expr(x, Prec::Unary);
+            }
+            ExprP::Op(lhs, op, rhs) => {
+                let (lhs_prec, rhs_prec) = Prec::of_op(*op).operands();
+                self.expr(lhs, lhs_prec);
+                self.write(&op.to_string());
+                self.expr(rhs, rhs_prec);
+            }
+            ExprP::If(cond_then_else) => {
+                let (cond, then_expr, else_expr) = &**cond_then_else;
+                self.expr(then_expr, Prec::Or);
+                self.write(" if ");
+                self.expr(cond, Prec::Or);
+                self.write(" else ");
+                self.expr(else_expr, Prec::Lambda);
+            }
+            ExprP::List(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::Expr).collect();
+                self.bracketed("[", "]", span_begin, span_end, &items, false);
+            }
+            ExprP::Dict(xs) => {
+                let items: Vec<_> = xs.iter().map(Item::DictEntry).collect();
+                self.bracketed("{", "}", span_begin, span_end, &items, false);
+            }
+            ExprP::ListComprehension(x, for_clause, clauses) => self.comprehension(
+                ("[", "]"),
+                (span_begin, span_end),
+                Item::Expr(x),
+                for_clause,
+                clauses,
+            ),
+            ExprP::DictComprehension(k_v, for_clause, clauses) => self.comprehension(
+                ("{", "}"),
+                (span_begin, span_end),
+                Item::DictEntry(k_v),
+                for_clause,
+                clauses,
+            ),
+        }
+    }
+
+    /// Write a string literal, preferring double quotes where that doesn't require
+    /// changing the contents.
+    fn string(&mut self, span: Span) {
+        let text = self.source(span.begin().get() as usize, span.end().get() as usize);
+        match text.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')) {
+            Some(contents) if !text.starts_with("'''") && !contents.contains(['"', '\\']) => {
+                self.write("\"");
+                self.write(contents);
+                self.write("\"");
+            }
+            _ => self.write(text),
+        }
+    }
+
+    // Bracketed lists.
+
+    fn item(&mut self, item: Item) {
+        match item {
+            Item::Expr(x) => self.expr(x, Prec::Lambda),
+            Item::Arg(arg, only) => match &arg.node {
+                ArgumentP::Positional(x) => {
+                    // The parentheses of the call look like they surround the only argument.
+                    let (count, parens) = self.source_parens(x.span);
+                    let parens = if only && count < 2 { None } else { parens };
+                    self.expr_with_parens(x, Prec::Lambda, parens);
+                }
+                ArgumentP::Named(name, x) => {
+                    self.write(&name.node);
+                    self.write(" = ");
+                    self.expr(x, Prec::Lambda);
+                }
+                ArgumentP::Args(x) => {
+                    self.write("*");
+                    self.expr(x, Prec::Lambda);
+                }
+                ArgumentP::KwArgs(x) => {
+                    self.write("**");
+                    self.expr(x, Prec::Lambda);
+                }
+            },
+            Item::Param(param) => {
+                let (prefix, name, ty, default) = match &param.node {
+                    ParameterP::Slash => return self.write("/"),
+                    ParameterP::NoArgs => return self.write("*"),
+                    ParameterP::Normal(name, ty, default) => ("", name, ty, default.as_ref()),
+                    ParameterP::Args(name, ty) => ("*", name, ty, None),
+                    ParameterP::KwArgs(name, ty) => ("**", name, ty, None),
+                };
+                self.write(prefix);
+                self.write(&name.ident);
+                if let Some(ty) = ty {
+                    self.write(": ");
+                    self.expr(&ty.node.expr, Prec::Lambda);
+                }
+                if let Some(default) = default {
+                    self.write(" = ");
+                    self.expr(default, Prec::Lambda);
+                }
+            }
+            Item::DictEntry((k, v)) => {
+                self.expr(k, Prec::Lambda);
+                self.write(": ");
+                self.expr(v, Prec::Lambda);
+            }
+            Item::Target(x) => self.target(x, false),
+            Item::LoadModule(x) => self.string(x.span),
+            Item::LoadArg(x) => {
+                if x.local.span != x.their.span {
+                    self.write(&x.local.ident);
+                    self.write(" = ");
+                }
+                self.string(x.their.span);
+            }
+        }
+    }
+
+    /// Write items separated by commas, with a trailing comma if there is a single
+    /// item and `tuple` is set.
+    fn flat_items(&mut self, items: &[Item], tuple: bool) {
+        for (i, item) in items.iter().enumerate() {
+            if i != 0 {
+                self.write(", ");
+            }
+            self.item(*item);
+        }
+        if tuple && items.len() == 1 {
+            self.write(",");
+        }
+    }
+
+    /// Write `items` in brackets, either on one line or one per line. `begin` and `end`
+    /// are the positions of the brackets in the source.
+    fn bracketed(
+        &mut self,
+        open: &str,
+        close: &str,
+        begin: usize,
+        end: usize,
+        items: &[Item],
+        tuple: bool,
+    ) {
+        let multiline = !self.flat
+            && (self.has_comments(begin, end)
+                || items.first().is_some_and(|x| {
+                    !self.same_line(begin, x.span().begin().get() as usize)
+                        || !self.fits(self.measure(|p| {
+                            p.write(open);
+                            p.flat_items(items, tuple);
+                            p.write(close);
+                        }))
+                }));
+        self.write(open);
+        if multiline {
+            self.last_pos = begin + 1;
+            self.indent += 1;
+            self.block_start = true;
+            for item in items {
+                self.item_start(item.span().begin().get() as usize);
+                self.item(*item);
+                self.write(",");
+                self.last_pos = item.span().end().get() as usize;
+            }
+            self.comments_before(end.saturating_sub(1));
+            self.indent -= 1;
+            self.start_line();
+            self.last_pos = end;
+        } else {
+            self.flat_items(items, tuple);
+        }
+        self.write(close);
+    }
+
+    fn comprehension(
+        &mut self,
+        (open, close): (&str, &str),
+        (begin, end): (usize, usize),
+        head: Item,
+        for_clause: &ForClauseP<AstNoPayload>,
+        clauses: &[ClauseP<AstNoPayload>],
+    ) {
+        let multiline = !self.flat
+            && (self.has_comments(begin, end)
+                || !self.same_line(begin, head.span().begin().get() as usize)
+                || !self.fits(self.measure(|p| {
+                    p.comprehension((open, close), (begin, end), head, for_clause, clauses)
+                })));
+        self.write(open);
+        if multiline {
+            self.last_pos = begin + 1;
+            self.indent += 1;
+            self.block_start = true;
+            self.item_start(head.span().begin().get() as usize);
+        }
+        self.item(head);
+        self.last_pos = head.span().end().get() as usize;
+        let for_clauses = [(for_clause.var.span.begin(), Some(for_clause), None)];
+        let clauses = clauses.iter().map(|clause| match clause {
+            ClauseP::For(x) => (x.var.span.begin(), Some(x), None),
+            ClauseP::If(x) => (x.span.begin(), None, Some(x)),
+        });
+        for (begin, for_clause, if_clause) in for_clauses.into_iter().chain(clauses) {
+            if multiline {
+                self.item_start(begin.get() as usize);
+            } else {
+                self.write(" ");
+            }
+            if let Some(x) = for_clause {
+                self.write("for ");
+                self.target(&x.var, true);
+                self.write(" in ");
+                self.expr(&x.over, Prec::Or);
+                self.last_pos = x.over.span.end().get() as usize;
+            }
+            if let Some(x) = if_clause {
+                self.write("if ");
+                self.expr(x, Prec::Or);
+                self.last_pos = x.span.end().get() as usize;
+            }
+        }
+        if multiline {
+            self.comments_before(end - 1);
+            self.indent -= 1;
+            self.start_line();
+            self.last_pos = end;
+        }
+        self.write(close);
+    }
+}
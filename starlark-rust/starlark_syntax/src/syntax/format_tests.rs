@@ -0,0 +1,202 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::golden_test_template::golden_test_template;
+use crate::lexer::Lexer;
+use crate::lexer::Token;
+use crate::syntax::testcases::TESTCASE_FILES;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+fn parse(program: &str) -> AstModule {
+    AstModule::parse(
+        "format.star",
+        program.to_owned(),
+        &Dialect::AllOptionsInternal,
+    )
+    .unwrap()
+}
+
+fn comments(program: &str) -> Vec<String> {
+    let ast = parse(program);
+    Lexer::new(program, &Dialect::AllOptionsInternal, ast.codemap.clone())
+        .filter_map(|token| match token.unwrap() {
+            (_, Token::Comment(x), _) => Some(x.trim().to_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Format `program`, checking that the result means the same thing, keeps the comments
+/// and is already formatted.
+fn format(program: &str) -> String {
+    let formatted = parse(program).format();
+    assert_eq!(
+        parse(program).statement.to_string(),
+        parse(&formatted).statement.to_string(),
+        "Formatting changed the meaning of:\n{program}\nto:\n{formatted}"
+    );
+    assert_eq!(comments(program), comments(&formatted));
+    assert_eq!(formatted, parse(&formatted).format(), "Not idempotent");
+    formatted
+}
+
+fn format_golden(name: &str, program: &str) {
+    let program = program.trim_start();
+    let formatted = format(program);
+    golden_test_template(
+        &format!("src/syntax/format_tests/{name}.golden"),
+        &format!("Program:\n{program}\nFormatted:\n{formatted}"),
+    );
+}
+
+#[test]
+fn test_format_statements() {
+    format_golden(
+        "statements",
+        r#"
+load('a.star', 'x', y='z')
+def f(a,b:int=1,*args,**kwargs)->str:
+  if a: return 'x'
+  elif b:
+      pass
+  else:
+    for x,y in z: a+=1; b-=1
+  return a,b
+x,y=1,2
+[a, b] = (1, 2)
+z = lambda x,y=2: x+y
+w = -x if not y else ~z
+v = {k:v for k,v in d.items() if k}
+u = [x for x in y]
+t = a[1:2], a[::2], a[:], a[1, 2], a.b.c(d)[e]
+s = ()
+r = (1,)
+q = f"{x}"
+"#,
+    );
+}
+
+#[test]
+fn test_format_comments() {
+    format_golden(
+        "comments",
+        r#"
+# A comment at the top.
+
+x = 1  # Trailing.
+
+
+# Before a function.
+def f():  # On the header.
+    # In the body.
+    return 1
+    # At the end of the body.
+# After the body.
+
+srcs = [
+    # Before an item.
+    "a.c",  # After an item.
+
+    "b.c",
+    # At the end.
+]
+# At the end of the file.
+"#,
+    );
+}
+
+#[test]
+fn test_format_brackets() {
+    format_golden(
+        "brackets",
+        r#"
+cc_library(name = "short", srcs = ["a.c"],
+           deps = [":b"])
+cc_library(name = "long", srcs = ["a_long_file_name.c", "another_long_file_name.c"], deps = [":b", ":c"])
+cc_library(
+    name = "kept",
+)
+x = {"a": 1,
+     "b": 2}
+load("a.star", "aaaaaaaaaaaaaaaaaaaa", "bbbbbbbbbbbbbbbbbbbb", "cccccccccccccccccccc", "dddddddddddddddd")
+def f(a, b, c): pass
+def g(
+    a, b):
+    pass
+y = [
+    x
+    for x in y
+    if x
+]
+"#,
+    );
+}
+
+#[test]
+fn test_format_parens() {
+    format_golden(
+        "parens",
+        r#"
+x = (a + b) * c
+x = a + (b * c)
+x = (a)
+x = f((a))
+x = f((a), b)
+x = (a - (b - c)) - d
+x = not (a and b)
+x = (lambda: 1)()
+x = (
+    "aaa" +
+    "bbb" +  # A comment.
+    "ccc"
+)
+if (a or
+    b):
+    pass
+"#,
+    );
+}
+
+#[test]
+fn test_format_quotes() {
+    assert_eq!("x = \"a\"\n", format("x = 'a'"));
+    assert_eq!("x = 'a\"'\n", format("x = 'a\"'"));
+    assert_eq!("x = '\\n'\n", format("x = '\\n'"));
+    assert_eq!("x = r'a'\n", format("x = r'a'"));
+    assert_eq!("x = '''a'''\n", format("x = '''a'''"));
+}
+
+#[test]
+fn test_format_empty() {
+    assert_eq!("", format(""));
+    assert_eq!("", format("\n\n"));
+    assert_eq!("# Only a comment.\n", format("\n# Only a comment.\n\n"));
+}
+
+#[test]
+fn test_format_testcases() {
+    for (name, content) in TESTCASE_FILES {
+        let formatted = parse(content).format();
+        assert_eq!(
+            formatted,
+            format(&formatted),
+            "Formatting {name} is not idempotent"
+        );
+        format(content);
+    }
+}
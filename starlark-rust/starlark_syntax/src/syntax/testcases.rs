@@ -34,7 +34,7 @@ macro_rules! testcases_parse {
     }
 }
 
-const TESTCASE_FILES: &[(&str, &str)] = testcases_parse!(
+pub(crate) const TESTCASE_FILES: &[(&str, &str)] = testcases_parse!(
     // A list of all files from testcases/parse, minus README.md
     // If you add additional parse tests, make sure to update this list.
     // If Rust adds list_directory! as a macro, remove this list.
@@ -156,6 +156,11 @@ pub(crate) fn validate_module(stmt: &AstStmt, parser_state: &mut ParserState) {
                     parser_state.error(x.span, "`...` is not allowed in this dialect");
                 }
             }
+            Expr::Literal(AstLiteral::Bytes(_)) => {
+                if !parser_state.dialect.enable_bytes {
+                    parser_state.error(x.span, "bytes literals are not allowed in this dialect");
+                }
+            }
             Expr::Lambda(LambdaP { params, .. }) => {
                 if !parser_state.dialect.enable_lambda {
                     parser_state.error(x.span, "`lambda` is not allowed in this dialect");
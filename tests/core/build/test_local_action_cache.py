@@ -0,0 +1,83 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+import typing
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.buck_workspace import buck_test
+from buck2.tests.e2e_util.helper.utils import expect_exec_count, filter_events
+
+ACTION_EXECUTION_KIND_LOCAL = 1
+ACTION_EXECUTION_KIND_LOCAL_ACTION_CACHE = 10
+
+
+async def execution_kinds(buck: Buck) -> typing.List[int]:
+    return await filter_events(
+        buck,
+        "Event",
+        "data",
+        "SpanEnd",
+        "data",
+        "ActionExecution",
+        "execution_kind",
+    )
+
+
+@buck_test()
+async def test_local_action_cache_survives_clean(buck: Buck) -> None:
+    result = await buck.build("//:test", "--show-full-simple-output")
+    output = result.stdout.strip()
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL]
+
+    await buck.clean()
+
+    result = await buck.build("//:test", "--show-full-simple-output")
+    assert result.stdout.strip() == output
+    await expect_exec_count(buck, 0)
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL_ACTION_CACHE]
+    with open(output) as f:
+        assert f.read() == "hello\n"
+
+
+@buck_test()
+async def test_local_action_cache_miss(buck: Buck) -> None:
+    await buck.build("//:test")
+    await buck.clean()
+
+    # A different command line is a different action digest.
+    await buck.build("//:test", "-c", "test.text=world")
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL]
+
+
+@buck_test()
+async def test_local_action_cache_no_remote_cache(buck: Buck) -> None:
+    await buck.build("//:test")
+    await buck.clean()
+
+    await buck.build("//:test", "--no-remote-cache")
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL]
+
+
+@buck_test()
+async def test_local_action_cache_skips_local_only(buck: Buck) -> None:
+    await buck.build("//:local_only")
+    await buck.clean()
+
+    await buck.build("//:local_only")
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL]
+
+
+@buck_test()
+async def test_local_action_cache_local_only_opt_in(buck: Buck) -> None:
+    opt_in = "buck2.local_action_cache_local_only=true"
+    await buck.build("//:local_only", "-c", opt_in)
+    await buck.clean()
+
+    await buck.build("//:local_only", "-c", opt_in)
+    assert await execution_kinds(buck) == [ACTION_EXECUTION_KIND_LOCAL_ACTION_CACHE]
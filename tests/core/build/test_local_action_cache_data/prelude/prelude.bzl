@@ -0,0 +1,23 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _echo_impl(ctx):
+    out = ctx.actions.declare_output("out.txt")
+    ctx.actions.run(
+        ["sh", "-c", 'echo "$1" > "$2"', "--", ctx.attrs.text, out.as_output()],
+        category = "echo",
+        local_only = ctx.attrs.local_only,
+    )
+    return [DefaultInfo(default_output = out)]
+
+echo = rule(
+    impl = _echo_impl,
+    attrs = {
+        "local_only": attrs.bool(default = False),
+        "text": attrs.string(),
+    },
+)
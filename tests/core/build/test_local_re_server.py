@@ -0,0 +1,74 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+import contextlib
+import re
+from asyncio import wait_for
+from typing import AsyncIterator
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.buck_workspace import buck_test
+from buck2.tests.e2e_util.helper.utils import read_what_ran
+
+
+@contextlib.asynccontextmanager
+async def local_re_server(buck: Buck) -> AsyncIterator[str]:
+    """Runs `buck2 debug local-re-server`, and yields the address it listens on."""
+    server = await buck.debug("local-re-server", "--workers", "2").start()
+    try:
+        assert server.stdout is not None
+        line = await wait_for(server.stdout.readline(), timeout=60)
+        address = re.search(r"grpc://[0-9.]+:[0-9]+", line.decode())
+        assert address, f"The server didn't print its address: `{line}`"
+        yield address.group(0)
+    finally:
+        server.kill()
+        await server.wait()
+
+
+def use_local_re_server(buck: Buck, address: str) -> None:
+    (buck.cwd / ".buckconfig.local").write_text(
+        "[buck2_re_client]\n"
+        f"engine_address = {address}\n"
+        f"action_cache_address = {address}\n"
+        f"cas_address = {address}\n"
+        "tls = false\n"
+        "[buck2]\n"
+        "digest_algorithms = SHA256\n"
+    )
+
+
+async def build_executors(buck: Buck) -> list[str]:
+    return [
+        entry["reproducer"]["executor"]
+        for entry in await read_what_ran(buck)
+        if entry["reason"] == "build"
+    ]
+
+
+@buck_test()
+async def test_build_on_local_re_server(buck: Buck) -> None:
+    async with local_re_server(buck) as address:
+        use_local_re_server(buck, address)
+
+        result = await buck.build("root//:upper", "--remote-only")
+        out = result.get_build_report().output_for_target("root//:upper").read_text()
+        assert out == "HELLO\n"
+        assert "Re" in await build_executors(buck)
+
+        # The server keeps the result in its action cache, so it is a cache hit once
+        # buck-out and the daemon's state are gone.
+        await buck.clean()
+        result = await buck.build("root//:upper", "--remote-only")
+        out = result.get_build_report().output_for_target("root//:upper").read_text()
+        assert out == "HELLO\n"
+        executors = await build_executors(buck)
+        assert "Cache" in executors
+        assert "Re" not in executors
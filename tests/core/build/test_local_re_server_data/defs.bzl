@@ -0,0 +1,22 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _copy_upper(ctx):
+    output = ctx.actions.declare_output("output.txt")
+    ctx.actions.run(
+        cmd_args([
+            "python3",
+            "-c",
+            "import sys; open(sys.argv[2], 'w').write(open(sys.argv[1]).read().upper())",
+            ctx.attrs.src,
+            output.as_output(),
+        ]),
+        category = "copy_upper",
+    )
+    return [DefaultInfo(default_output = output)]
+
+copy_upper = rule(impl = _copy_upper, attrs = {"src": attrs.source()})
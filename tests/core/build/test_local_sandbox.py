@@ -0,0 +1,108 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+from pathlib import Path
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.asserts import expect_failure
+from buck2.tests.e2e_util.buck_workspace import buck_test
+from buck2.tests.e2e_util.helper.utils import read_invocation_record
+
+SANDBOX = ["-c", "buck2.sandbox_local_actions=true"]
+LOG_DENIALS = ["-c", "buck2.sandbox_log_denials=true"]
+RERUN = ["-c", "buck2.sandbox_rerun_failed_actions=true"]
+
+
+@buck_test()
+async def test_sandbox_declared_input(buck: Buck) -> None:
+    result = await buck.build("//:declared", "--show-full-simple-output", *SANDBOX)
+    with open(result.stdout.strip()) as f:
+        assert f.read() == "declared\n"
+
+
+@buck_test()
+async def test_no_sandbox_undeclared_input(buck: Buck) -> None:
+    result = await buck.build("//:undeclared", "--show-full-simple-output")
+    with open(result.stdout.strip()) as f:
+        assert f.read() == "undeclared\n"
+
+
+@buck_test()
+async def test_sandbox_undeclared_input(buck: Buck, tmp_path: Path) -> None:
+    record_path = tmp_path / "record.json"
+    await expect_failure(
+        buck.build(
+            "//:undeclared",
+            "--unstable-write-invocation-record",
+            str(record_path),
+            *SANDBOX,
+            *LOG_DENIALS,
+        ),
+        stderr_regex="(?s)which denied it:.*undeclared input `[^`]*/undeclared.txt`.*"
+        "undeclared.txt: No such file or directory",
+    )
+    record = read_invocation_record(record_path)
+    errors = record["errors"]
+    assert len(errors) == 1
+    assert "ACTION_SANDBOX_VIOLATION" in errors[0]["tags"]
+
+
+@buck_test()
+async def test_sandbox_undeclared_input_unlogged(buck: Buck, tmp_path: Path) -> None:
+    record_path = tmp_path / "record.json"
+    await expect_failure(
+        buck.build(
+            "//:undeclared",
+            "--unstable-write-invocation-record",
+            str(record_path),
+            *SANDBOX,
+        ),
+        stderr_regex="(?s)undeclared.txt: No such file or directory.*sandbox_log_denials",
+    )
+    record = read_invocation_record(record_path)
+    errors = record["errors"]
+    assert len(errors) == 1
+    assert "ACTION_SANDBOX_VIOLATION" not in errors[0]["tags"]
+
+
+@buck_test()
+async def test_sandbox_undeclared_write(buck: Buck, tmp_path: Path) -> None:
+    record_path = tmp_path / "record.json"
+    await expect_failure(
+        buck.build(
+            "//:undeclared_write",
+            "--unstable-write-invocation-record",
+            str(record_path),
+            *SANDBOX,
+        ),
+        stderr_regex="(?s)written.txt.*sandbox_rerun_failed_actions",
+    )
+    record = read_invocation_record(record_path)
+    errors = record["errors"]
+    assert len(errors) == 1
+    assert "ACTION_SANDBOX_VIOLATION" not in errors[0]["tags"]
+
+
+@buck_test()
+async def test_sandbox_undeclared_write_rerun(buck: Buck, tmp_path: Path) -> None:
+    record_path = tmp_path / "record.json"
+    await expect_failure(
+        buck.build(
+            "//:undeclared_write",
+            "--unstable-write-invocation-record",
+            str(record_path),
+            *SANDBOX,
+            *RERUN,
+        ),
+        stderr_regex="succeeded outside of it",
+    )
+    record = read_invocation_record(record_path)
+    errors = record["errors"]
+    assert len(errors) == 1
+    assert "ACTION_SANDBOX_VIOLATION" in errors[0]["tags"]
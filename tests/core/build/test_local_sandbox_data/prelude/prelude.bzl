@@ -0,0 +1,42 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _cat_file_impl(ctx):
+    out = ctx.actions.declare_output("out.txt")
+
+    # Either a declared input, or a path the action reads without declaring it.
+    src = ctx.attrs.src if ctx.attrs.src else ctx.attrs.path
+    ctx.actions.run(
+        ["sh", "-c", 'cat "$1" > "$2"', "--", src, out.as_output()],
+        category = "cat",
+    )
+    return [DefaultInfo(default_output = out)]
+
+cat_file = rule(
+    impl = _cat_file_impl,
+    attrs = {
+        "path": attrs.string(default = ""),
+        "src": attrs.option(attrs.source(), default = None),
+    },
+)
+
+def _write_file_impl(ctx):
+    out = ctx.actions.declare_output("out.txt")
+
+    # Writes to a path which is not one of its outputs first.
+    ctx.actions.run(
+        ["sh", "-c", 'echo written > "$1" && cp "$1" "$2"', "--", ctx.attrs.path, out.as_output()],
+        category = "write",
+    )
+    return [DefaultInfo(default_output = out)]
+
+write_file = rule(
+    impl = _write_file_impl,
+    attrs = {
+        "path": attrs.string(),
+    },
+)
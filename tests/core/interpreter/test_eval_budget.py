@@ -0,0 +1,49 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.asserts import expect_failure
+from buck2.tests.e2e_util.buck_workspace import buck_test
+
+
+@buck_test()
+async def test_max_instructions(buck: Buck) -> None:
+    await expect_failure(
+        buck.uquery("instructions//:"),
+        stderr_regex="exceeded the budget of 1000000 bytecode instructions",
+    )
+
+
+@buck_test()
+async def test_max_heap_bytes(buck: Buck) -> None:
+    await expect_failure(
+        buck.uquery("heap//:"),
+        stderr_regex="exceeded the budget of 50000000 heap bytes",
+    )
+
+
+@buck_test()
+async def test_timeout(buck: Buck) -> None:
+    await expect_failure(
+        buck.uquery(
+            "instructions//:",
+            "-c",
+            "buck2.starlark_max_instructions=1000000000000",
+            "-c",
+            "buck2.starlark_timeout_ms=100",
+        ),
+        stderr_regex="exceeded its time budget",
+    )
+
+
+@buck_test()
+async def test_within_budget(buck: Buck) -> None:
+    output = await buck.uquery("good//:")
+    assert "TEST PASSED" in output.stderr
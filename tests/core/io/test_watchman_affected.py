@@ -0,0 +1,30 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+import subprocess
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.buck_workspace import buck_test
+
+
+@buck_test(setup_eden=False)
+async def test_list_affected_by_mergebase(buck: Buck) -> None:
+    subprocess.run(["sl", "init"], cwd=buck.cwd, check=True)
+    subprocess.run(
+        ["sl", "commit", "--addremove", "-m", "temp"], cwd=buck.cwd, check=True
+    )
+    subprocess.run(["sl", "bookmark", "main"], cwd=buck.cwd, check=True)
+
+    result = await buck.test("--list-affected", "--affected-by-mergebase", "//...")
+    assert result.stdout.splitlines() == []
+
+    (buck.cwd / "lib.txt").write_text("changed")
+    result = await buck.test("--list-affected", "--affected-by-mergebase", "//...")
+    assert result.stdout.splitlines() == ["root//:lib_test"]
@@ -58,6 +58,18 @@ async def test_action_divergence_command(buck: Buck) -> None:
     )
 
 
+@buck_test()
+async def test_actions_diff_command(buck: Buck) -> None:
+    await buck.build("//:non_det", *with_buck2_output("foo"))
+    await buck.build("//:non_det", *with_buck2_output("bar"))
+    out = await buck.log("diff", "actions", "--recent1", "0", "--recent2", "1")
+
+    assert (
+        "Changed: prelude//:non_det (<unspecified>) (write foo.txt)\n  Tiny output digest(s):"
+        in out.stdout
+    )
+
+
 @buck_test()
 async def test_no_config_diff_command(buck: Buck) -> None:
     await buck.build("//:simple", *with_buck2_output("foo"))
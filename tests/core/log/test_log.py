@@ -115,3 +115,24 @@ async def test_what_buck(buck: Buck, tmp_path: Path) -> None:
 
     out = await buck.log("what-cmd", "--expand")
     assert "uquery //: -c" in out.stdout
+
+
+@buck_test()
+async def test_log_export_otlp_json(buck: Buck, tmp_path: Path) -> None:
+    await buck.cquery("//:EEE")
+
+    output = tmp_path / "trace.json"
+    await buck.log("export", "--format", "otlp-json", "--output", str(output))
+
+    trace = json.loads(output.read_text())
+    [resource_spans] = trace["resourceSpans"]
+    attributes = {
+        attr["key"]: attr["value"] for attr in resource_spans["resource"]["attributes"]
+    }
+    assert attributes["service.name"] == {"stringValue": "buck2"}
+    spans = resource_spans["scopeSpans"][0]["spans"]
+    assert len(spans) > 0
+    for span in spans:
+        assert len(span["traceId"]) == 32
+        assert len(span["spanId"]) == 16
+        assert int(span["startTimeUnixNano"]) <= int(span["endTimeUnixNano"])
@@ -257,3 +257,52 @@ async def test_disabling_of_execution_platforms(buck: Buck) -> None:
         query = "deps(set(tests/...))"
         await buck.cquery(query)
         await buck.cquery(query, "-c", "build.execution_platforms=")
+
+
+def _labels(stdout: str) -> list[str]:
+    """The sorted labels of the targets cquery printed, without their configurations."""
+    return sorted(re.sub(r" \(.*\)$", "", line) for line in stdout.splitlines())
+
+
+@buck_test(data_dir="query_functions")
+async def test_siblings(buck: Buck) -> None:
+    out = await buck.cquery(
+        "siblings(//pkg:lib)", "--target-universe", "//..."
+    )
+    assert _labels(out.stdout) == [
+        "root//pkg:lib",
+        "root//pkg:private",
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
+
+    # Siblings are looked up in the target universe, which defaults to the targets in
+    # the query and their deps.
+    out = await buck.cquery("siblings(//pkg:user)")
+    assert _labels(out.stdout) == ["root//pkg:lib", "root//pkg:user"]
+
+
+@buck_test(data_dir="query_functions")
+async def test_same_pkg_direct_rdeps(buck: Buck) -> None:
+    out = await buck.cquery(
+        "same_pkg_direct_rdeps(//pkg:lib)", "--target-universe", "//..."
+    )
+    assert _labels(out.stdout) == ["root//pkg:private_user", "root//pkg:user"]
+
+
+@buck_test(data_dir="query_functions")
+async def test_visible(buck: Buck) -> None:
+    out = await buck.cquery(
+        "visible(//app:bin, //pkg:)", "--target-universe", "//..."
+    )
+    assert _labels(out.stdout) == ["root//pkg:lib"]
+
+    out = await buck.cquery(
+        "visible(//pkg:user, //pkg:)", "--target-universe", "//..."
+    )
+    assert _labels(out.stdout) == [
+        "root//pkg:lib",
+        "root//pkg:private",
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
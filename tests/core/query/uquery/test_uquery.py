@@ -356,6 +356,46 @@ async def test_dot_compact(buck: Buck) -> None:
     )
 
 
+@buck_test(data_dir="bxl_simple")
+async def test_ndjson(buck: Buck) -> None:
+    out = await buck.uquery(
+        "--output-format=ndjson", "set(root//bin:the_binary //lib:file1)"
+    )
+    assert [
+        {"buck.label": "root//bin:the_binary"},
+        {"buck.label": "root//lib:file1"},
+    ] == [json.loads(line) for line in out.stdout.splitlines()]
+
+    out = await buck.uquery(
+        "--output-format=ndjson",
+        "--output-attribute=buck.type",
+        "set(root//bin:the_binary //lib:file1)",
+    )
+    assert [
+        {"buck.label": "root//bin:the_binary", "buck.type": "_foo_binary"},
+        {"buck.label": "root//lib:file1", "buck.type": "_foo_genrule"},
+    ] == [json.loads(line) for line in out.stdout.splitlines()]
+
+
+@buck_test(data_dir="bxl_simple")
+async def test_graphml(buck: Buck) -> None:
+    out = await buck.uquery(
+        "--output-format=graphml",
+        "--output-attribute=buck.type",
+        "set(root//bin:the_binary //lib:lib1)",
+    )
+    assert '<node id="root//bin:the_binary">' in out.stdout
+    assert '<data key="buck_buck.type">_foo_binary</data>' in out.stdout
+    assert (
+        '<edge source="root//bin:the_binary" target="root//lib:lib1"/>' in out.stdout
+    )
+
+    await expect_failure(
+        buck.uquery("--output-format=graphml", "buildfile(root//bin:the_binary)"),
+        stderr_regex="cannot be output as a graph in graphml format",
+    )
+
+
 # Tests for "%Ss" uses
 @buck_test(data_dir="bxl_simple")
 async def test_args_as_set(buck: Buck) -> None:
@@ -532,3 +572,55 @@ async def test_uquery_rdeps(buck: Buck) -> None:
 
     result = await buck.query("""rdeps(root//bin:the_binary, //lib:file1, 100)""")
     assert result.stdout == "root//bin:the_binary\nroot//lib:lib1\nroot//lib:file1\n"
+
+
+@buck_test(data_dir="query_functions")
+async def test_uquery_siblings(buck: Buck) -> None:
+    result = await buck.uquery("siblings(//pkg:lib)")
+    assert sorted(result.stdout.splitlines()) == [
+        "root//pkg:lib",
+        "root//pkg:private",
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
+
+    result = await buck.uquery("siblings(set(//pkg:user //app:bin))")
+    assert sorted(result.stdout.splitlines()) == [
+        "root//app:bin",
+        "root//pkg:lib",
+        "root//pkg:private",
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
+
+
+@buck_test(data_dir="query_functions")
+async def test_uquery_same_pkg_direct_rdeps(buck: Buck) -> None:
+    # `//app:bin` depends on `//pkg:lib` too, but is in another package.
+    result = await buck.uquery("same_pkg_direct_rdeps(//pkg:lib)")
+    assert sorted(result.stdout.splitlines()) == [
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
+
+    result = await buck.uquery("same_pkg_direct_rdeps(//pkg:user)")
+    assert result.stdout == ""
+
+
+@buck_test(data_dir="query_functions")
+async def test_uquery_visible(buck: Buck) -> None:
+    result = await buck.uquery("visible(//app:bin, //pkg:)")
+    assert result.stdout == "root//pkg:lib\n"
+
+    # Targets are always visible to targets in the same package.
+    result = await buck.uquery("visible(//pkg:user, //pkg:)")
+    assert sorted(result.stdout.splitlines()) == [
+        "root//pkg:lib",
+        "root//pkg:private",
+        "root//pkg:private_user",
+        "root//pkg:user",
+    ]
+
+    # Only the targets visible to every target in the scope are returned.
+    result = await buck.uquery("visible(set(//app:bin //pkg:user), //pkg:)")
+    assert result.stdout == "root//pkg:lib\n"
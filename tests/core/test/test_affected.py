@@ -0,0 +1,111 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+from pathlib import Path
+from typing import Optional
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.asserts import expect_failure
+from buck2.tests.e2e_util.buck_workspace import buck_test
+
+
+async def list_affected(
+    buck: Buck, *argv: str, rel_cwd: Optional[Path] = None
+) -> list[str]:
+    result = await buck.test("--list-affected", *argv, rel_cwd=rel_cwd)
+    return result.stdout.splitlines()
+
+
+@buck_test()
+async def test_list_affected(buck: Buck) -> None:
+    assert await list_affected(buck, "--affected-by", "lib.txt", "//...") == [
+        "root//:lib_test",
+        "root//:lib_tests_attribute",
+    ]
+    assert await list_affected(buck, "--affected-by", "other.txt", "//...") == [
+        "root//:other_test",
+    ]
+    assert await list_affected(buck, "--affected-by", "defs.bzl", "//...") == []
+
+
+@buck_test()
+async def test_list_affected_by_quoted_file(buck: Buck) -> None:
+    assert await list_affected(buck, "--affected-by", "quote's.txt", "//...") == [
+        "root//:quote_test",
+    ]
+
+
+@buck_test()
+async def test_list_affected_repeated(buck: Buck) -> None:
+    assert await list_affected(
+        buck, "--affected-by", "lib.txt", "--affected-by", "other.txt", "//..."
+    ) == [
+        "root//:lib_test",
+        "root//:lib_tests_attribute",
+        "root//:other_test",
+    ]
+
+
+@buck_test()
+async def test_list_affected_only_matched_by_patterns(buck: Buck) -> None:
+    # The patterns after `--affected-by FILE` are not taken as more changed files.
+    assert await list_affected(
+        buck, "--affected-by", "lib.txt", "//:lib_test", "//:other_test"
+    ) == ["root//:lib_test"]
+
+
+@buck_test()
+async def test_list_affected_relative_to_cwd(buck: Buck) -> None:
+    assert await list_affected(
+        buck, "--affected-by", "sub.txt", ":sub_test", rel_cwd=Path("sub")
+    ) == ["root//sub:sub_test"]
+
+
+@buck_test()
+async def test_affected_by_file(buck: Buck) -> None:
+    (buck.cwd / "changed_files.txt").write_text("lib.txt\nsub/sub.txt\n")
+    assert await list_affected(
+        buck, "--affected-by-file", "changed_files.txt", "//..."
+    ) == [
+        "root//:lib_test",
+        "root//:lib_tests_attribute",
+        "root//sub:sub_test",
+    ]
+
+    (buck.cwd / "changed_files.txt").write_text("")
+    assert (
+        await list_affected(buck, "--affected-by-file", "changed_files.txt", "//...")
+        == []
+    )
+
+
+@buck_test()
+async def test_affected_runs_only_affected_tests(buck: Buck) -> None:
+    await expect_failure(
+        buck.test("//..."), stderr_regex="Fail: root//:unaffected_fail"
+    )
+    # `unaffected_fail` is not affected, so it is not run.
+    await buck.test("--affected-by", "lib.txt", "//...")
+
+
+@buck_test()
+async def test_list_affected_requires_affected_by(buck: Buck) -> None:
+    await expect_failure(
+        buck.test("--list-affected", "//..."),
+        stderr_regex="`--list-affected` requires `--affected-by`",
+    )
+
+
+@buck_test()
+async def test_affected_by_mergebase_without_file_watcher_mergebase(buck: Buck) -> None:
+    await expect_failure(
+        buck.test("--list-affected", "--affected-by-mergebase", "//..."),
+        stderr_regex="did not report a mergebase",
+    )
@@ -0,0 +1,46 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _impl_ok(_ctx):
+    return [
+        DefaultInfo(),
+        ExternalRunnerTestInfo(
+            command = ["python3", "-c", "import sys; sys.exit(0)"],
+            type = "custom",
+        ),
+    ]
+
+ok_test = rule(
+    attrs = {
+        "deps": attrs.list(attrs.dep(), default = []),
+        "srcs": attrs.list(attrs.source(), default = []),
+    },
+    impl = _impl_ok,
+)
+
+def _impl_fail(_ctx):
+    return [
+        DefaultInfo(),
+        ExternalRunnerTestInfo(
+            command = ["python3", "-c", "import sys; sys.exit(1)"],
+            type = "custom",
+        ),
+    ]
+
+fail_test = rule(attrs = {}, impl = _impl_fail)
+
+def _impl_library(_ctx):
+    return [
+        DefaultInfo(),
+    ]
+
+library = rule(
+    attrs = {
+        "srcs": attrs.list(attrs.source(), default = []),
+    },
+    impl = _impl_library,
+)
@@ -0,0 +1,56 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.asserts import expect_failure
+from buck2.tests.e2e_util.buck_workspace import buck_test, env
+from buck2.tests.e2e_util.helper.utils import filter_events
+
+# Empty test executor forces internal test executor to be used.
+INTERNAL_TEST_EXECUTOR = ""
+
+
+async def cached_results(buck: Buck) -> dict[str, bool]:
+    """Whether the result of each test the last command ran was replayed from the cache."""
+    results = await filter_events(
+        buck, "Event", "data", "Instant", "data", "TestResult"
+    )
+    return {result["name"]: result.get("cached", False) for result in results}
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_hermetic_pass_is_cached(buck: Buck) -> None:
+    targets = ["//:hermetic_pass", "//:not_hermetic"]
+
+    await buck.test(*targets, test_executor=INTERNAL_TEST_EXECUTOR)
+    assert await cached_results(buck) == {
+        "root//:hermetic_pass": False,
+        "root//:not_hermetic": False,
+    }
+
+    result = await buck.test(*targets, test_executor=INTERNAL_TEST_EXECUTOR)
+    assert "Pass: root//:hermetic_pass (cached)" in result.stderr
+    # Tests which are not hermetic always run again.
+    assert await cached_results(buck) == {
+        "root//:hermetic_pass": True,
+        "root//:not_hermetic": False,
+    }
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_hermetic_fail_is_not_cached(buck: Buck) -> None:
+    for _ in range(2):
+        await expect_failure(
+            buck.test("//:hermetic_fail", test_executor=INTERNAL_TEST_EXECUTOR),
+            stderr_regex="Fail: root//:hermetic_fail",
+        )
+        assert await cached_results(buck) == {"root//:hermetic_fail": False}
@@ -0,0 +1,24 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+def _impl(ctx):
+    return [
+        DefaultInfo(),
+        ExternalRunnerTestInfo(
+            command = ["python3", "-c", ctx.attrs.script],
+            type = "custom",
+            hermetic = ctx.attrs.hermetic,
+        ),
+    ]
+
+python_test = rule(
+    impl = _impl,
+    attrs = {
+        "hermetic": attrs.bool(default = False),
+        "script": attrs.string(),
+    },
+)
@@ -52,3 +52,24 @@ async def test_internal_test_executor_timeout(buck: Buck) -> None:
         ),
         stderr_regex="Timeout: ",
     )
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_internal_test_executor_retries(buck: Buck) -> None:
+    attempts = buck.cwd / "attempts"
+    args = [":flaky", ":trivial_pass", "--", "--env", f"ATTEMPTS={attempts}"]
+
+    # Without retries, the first attempt's failure fails the run.
+    await expect_failure(
+        buck.test(*args, test_executor=INTERNAL_TEST_EXECUTOR),
+        stderr_regex=r"Pass 1\. Fail 1\.",
+    )
+
+    attempts.unlink()
+    result = await buck.test(
+        *args, "--retries", "1", test_executor=INTERNAL_TEST_EXECUTOR
+    )
+    # The flaky test is counted separately from the tests that passed the first time.
+    assert "Pass 1. Flaky 1. Fail 0." in result.stderr
+    assert "1 TESTS FLAKY" in result.stderr
@@ -0,0 +1,83 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+import re
+import xml.etree.ElementTree as ET
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.asserts import expect_failure
+from buck2.tests.e2e_util.buck_workspace import buck_test, env
+
+# Empty test executor forces internal test executor to be used.
+INTERNAL_TEST_EXECUTOR = ""
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_reports(buck: Buck) -> None:
+    await expect_failure(
+        buck.test(
+            "//:pass",
+            "//:fail",
+            "//:skipped",
+            "--report",
+            "junit=results.xml",
+            "--report",
+            "tap=results.tap",
+            test_executor=INTERNAL_TEST_EXECUTOR,
+        ),
+        stderr_regex="Fail: root//:fail",
+    )
+
+    junit = ET.parse(buck.cwd / "results.xml").getroot()
+    assert junit.tag == "testsuites"
+    assert junit.attrib["tests"] == "3"
+    assert junit.attrib["failures"] == "1"
+    assert junit.attrib["errors"] == "0"
+    assert junit.attrib["skipped"] == "1"
+    testcases = {
+        testcase.attrib["name"]: testcase for testcase in junit.iter("testcase")
+    }
+    assert set(testcases) == {"root//:pass", "root//:fail", "root//:skipped"}
+    for suite in junit.iter("testsuite"):
+        assert suite.attrib["tests"] == "1"
+
+    passed = testcases["root//:pass"]
+    assert passed.find("failure") is None
+    assert passed.find("skipped") is None
+    assert passed.findtext("system-out") == "passing\n"
+
+    failed = testcases["root//:fail"]
+    failure = failed.find("failure")
+    assert failure is not None
+    assert failure.attrib["type"] == "FAIL"
+    assert failed.findtext("system-out") == "failing\n"
+
+    skipped = testcases["root//:skipped"].find("skipped")
+    assert skipped is not None
+    assert skipped.attrib["message"] == "Skipped by the `skip` label"
+
+    tap = (buck.cwd / "results.tap").read_text().splitlines()
+    assert tap[:2] == ["TAP version 13", "1..3"]
+    # Results are in the order the tests finished, so match them by the test's name.
+    tap_results = {
+        m.group(2): (m.group(1), m.group(3))
+        for m in (
+            re.match(r"^(ok|not ok) \d+ - .*? (\S+)(?: # SKIP (.*))?$", line)
+            for line in tap
+        )
+        if m
+    }
+    assert tap_results == {
+        "root//:pass": ("ok", None),
+        "root//:fail": ("not ok", None),
+        "root//:skipped": ("ok", "Skipped by the `skip` label"),
+    }
+    assert "  status: FAIL" in tap
@@ -0,0 +1,45 @@
+# Copyright (c) Meta Platforms, Inc. and affiliates.
+#
+# This source code is licensed under both the MIT license found in the
+# LICENSE-MIT file in the root directory of this source tree and the Apache
+# License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+# of this source tree.
+
+# pyre-strict
+
+
+from buck2.tests.e2e_util.api.buck import Buck
+from buck2.tests.e2e_util.buck_workspace import buck_test, env
+from buck2.tests.e2e_util.helper.utils import read_what_ran
+
+# Empty test executor forces internal test executor to be used.
+INTERNAL_TEST_EXECUTOR = ""
+
+
+async def tests_run(buck: Buck) -> list[str]:
+    return sorted(
+        entry["identity"]
+        for entry in await read_what_ran(buck)
+        if entry["reason"] == "test.run"
+    )
+
+
+@buck_test()
+@env("BUCK2_ALLOW_INTERNAL_TEST_RUNNER_DO_NOT_USE", "1")
+async def test_shards_partition_targets(buck: Buck) -> None:
+    whole = []
+    for index in range(2):
+        # The tests check the environment they are run with.
+        await buck.test(
+            "//...",
+            "--shard-count",
+            "2",
+            "--shard-index",
+            str(index),
+            test_executor=INTERNAL_TEST_EXECUTOR,
+        )
+        run = await tests_run(buck)
+        # A target that shards its test cases is run by every shard.
+        assert "cases" in run
+        whole.extend(name for name in run if name != "cases")
+    assert sorted(whole) == ["whole_a", "whole_b", "whole_c", "whole_d"]
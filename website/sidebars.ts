@@ -125,6 +125,11 @@ export const sidebars: SidebarsConfig = {
             'users/advanced/deferred_materialization',
             'users/advanced/restarter',
             'users/advanced/in_memory_cache',
+            'users/advanced/local_action_cache',
+            'users/advanced/starlark_budgets',
+            'users/advanced/local_sandbox',
+            'users/advanced/action_cgroups',
+            'users/advanced/local_re_server',
             'users/advanced/external_cells',
             isInternal() ? 'users/advanced/offline_build_archives' : null,
             isInternal() ? 'users/advanced/vpnless' : null,